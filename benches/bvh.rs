@@ -0,0 +1,174 @@
+//! Compares BVHs built by the surface area heuristic against the older median
+//! split builder, and the flattened BVH against the tree of nodes, timing the
+//! build and tracing the same rays through each. Refitting the flattened BVH is
+//! timed too.
+//!
+//! Run with `cargo bench --bench bvh`.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use ray_tracer::geometry::point::Point3;
+use ray_tracer::geometry::ray::Ray;
+use ray_tracer::graphics::colour::Colour;
+use ray_tracer::render::element::Element;
+use ray_tracer::render::elements::bvh::Bvh;
+use ray_tracer::render::elements::bvh_node::BvhNode;
+use ray_tracer::render::elements::sphere::Sphere;
+use ray_tracer::render::elements::triangle::Triangle;
+use ray_tracer::render::material::Material;
+use ray_tracer::render::materials::lambertian::Lambertian;
+use ray_tracer::util::sampler::Sampler;
+
+const RAYS: usize = 200_000;
+
+type CreateScene = fn(&mut Sampler) -> Vec<Arc<dyn Element>>;
+
+fn material() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new_with_colour(Colour::new(0.5, 0.5, 0.5)))
+}
+
+/// Small spheres spread evenly through a cube.
+fn uniform_spheres(sampler: &mut Sampler) -> Vec<Arc<dyn Element>> {
+    let material = material();
+
+    (0..100_000)
+        .map(|_| {
+            let centre = Point3::new(
+                sampler.gen_range(-50.0..50.0),
+                sampler.gen_range(-50.0..50.0),
+                sampler.gen_range(-50.0..50.0),
+            );
+            Arc::new(Sphere::new(centre, 0.2, material.clone())) as Arc<dyn Element>
+        })
+        .collect()
+}
+
+/// Dense clusters of spheres in a lot of empty space, above one huge ground
+/// sphere, where median splits do worst.
+fn clustered_spheres(sampler: &mut Sampler) -> Vec<Arc<dyn Element>> {
+    let material = material();
+    let mut elements: Vec<Arc<dyn Element>> = Vec::new();
+    elements.push(Arc::new(Sphere::new(Point3::new(0.0, -10_000.0, 0.0), 9_950.0, material.clone())));
+
+    for _ in 0..20 {
+        let centre = Point3::new(
+            sampler.gen_range(-50.0..50.0),
+            sampler.gen_range(-50.0..50.0),
+            sampler.gen_range(-50.0..50.0),
+        );
+        for _ in 0..5_000 {
+            let offset = Point3::new(
+                sampler.gen_range(-2.0..2.0),
+                sampler.gen_range(-2.0..2.0),
+                sampler.gen_range(-2.0..2.0),
+            );
+            let position = Point3::new(centre.x + offset.x, centre.y + offset.y, centre.z + offset.z);
+            elements.push(Arc::new(Sphere::new(position, 0.05, material.clone())));
+        }
+    }
+
+    elements
+}
+
+/// A bumpy terrain of separate triangles, long thin ones included.
+fn terrain(_sampler: &mut Sampler) -> Vec<Arc<dyn Element>> {
+    let material = material();
+    let n = 250;
+    let height = |i: usize, j: usize| ((i as f32) * 0.3).sin() * ((j as f32) * 0.05).cos() * 5.0;
+    let point = |i: usize, j: usize| Point3::new((i as f32) * 0.4 - 50.0, height(i, j), (j as f32) - 125.0);
+
+    let mut elements: Vec<Arc<dyn Element>> = Vec::new();
+    for i in 0..n {
+        for j in 0..n {
+            let (a, b, c, d) = (point(i, j), point(i + 1, j), point(i, j + 1), point(i + 1, j + 1));
+            elements.push(Arc::new(Triangle::new([a, b, c], material.clone())));
+            elements.push(Arc::new(Triangle::new([b, d, c], material.clone())));
+        }
+    }
+
+    elements
+}
+
+/// Rays from points around the scene towards points inside it.
+fn rays(sampler: &mut Sampler) -> Vec<Ray> {
+    (0..RAYS)
+        .map(|_| {
+            let origin = Point3::new(
+                sampler.gen_range(-80.0..80.0),
+                sampler.gen_range(20.0..80.0),
+                sampler.gen_range(-80.0..80.0),
+            );
+            let target = Point3::new(
+                sampler.gen_range(-50.0..50.0),
+                sampler.gen_range(-50.0..50.0),
+                sampler.gen_range(-50.0..50.0),
+            );
+            Ray::new(origin, target - origin)
+        })
+        .collect()
+}
+
+/// The time taken to trace every ray, and how many hit. The counts can differ
+/// between builders by a ray or two grazing a box edge.
+fn trace(world: &dyn Element, rays: &[Ray]) -> (Duration, usize) {
+    // The scenes hold no media, so nothing is drawn from the sampler.
+    let mut sampler = Sampler::new(0);
+
+    let start = Instant::now();
+    let hits = rays.iter().filter(|ray| world.hit(ray, 0.001, f32::INFINITY, &mut sampler).is_some()).count();
+
+    (start.elapsed(), hits)
+}
+
+fn report(builder: &str, build: Duration, tracing: Duration, hits: usize) {
+    println!(
+        "  {:<8} build {:>8.1} ms   trace {:>8.1} ms   {:>7.0} ns/ray   {} hits",
+        builder,
+        build.as_secs_f64() * 1e3,
+        tracing.as_secs_f64() * 1e3,
+        tracing.as_secs_f64() * 1e9 / (RAYS as f64),
+        hits,
+    );
+}
+
+fn main() {
+    let scenes: [(&str, CreateScene); 3] = [
+        ("uniform spheres", uniform_spheres),
+        ("clustered spheres", clustered_spheres),
+        ("terrain", terrain),
+    ];
+
+    for &(name, create) in scenes.iter() {
+        let elements = create(&mut Sampler::new(1));
+        let rays = rays(&mut Sampler::new(2));
+        println!("{} ({} elements, {} rays)", name, elements.len(), RAYS);
+
+        let start = Instant::now();
+        let median = BvhNode::from_elements_median(elements.clone(), 0.0, 1.0, &mut Sampler::new(3));
+        let build = start.elapsed();
+        let (tracing, hits) = trace(&median, &rays);
+        report("median", build, tracing, hits);
+
+        let start = Instant::now();
+        let sah = BvhNode::from_elements(elements.clone(), 0.0, 1.0);
+        let build = start.elapsed();
+        let (tracing, hits) = trace(&sah, &rays);
+        report("sah", build, tracing, hits);
+
+        let start = Instant::now();
+        let mut linear = Bvh::new(elements.clone(), 0.0, 1.0);
+        let build = start.elapsed();
+        let (tracing, hits) = trace(&linear, &rays);
+        report("linear", build, tracing, hits);
+
+        // As for the next frame of an animation where nothing moved.
+        let start = Instant::now();
+        linear.refit(elements, 0.0, 1.0);
+        let build = start.elapsed();
+        let (tracing, hits) = trace(&linear, &rays);
+        report("refit", build, tracing, hits);
+    }
+}
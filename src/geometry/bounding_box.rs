@@ -19,38 +19,77 @@ impl BoundingBox {
         }
     }
 
-    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
-        // Times of the two hits
-        let t_a_x = (self.min.x - ray.origin.x) / ray.direction.x;
-        let t_b_x = (self.max.x - ray.origin.x) / ray.direction.x;
+    /// The smallest box containing all the points, which must not be empty.
+    pub fn from_points(points: &[Point3]) -> Self {
+        let mut min = points[0];
+        let mut max = points[0];
+
+        for p in points[1..].iter() {
+            min = Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+        }
+
+        BoundingBox::new(min, max)
+    }
 
-        let t_a_y = (self.min.y - ray.origin.y) / ray.direction.y;
-        let t_b_y = (self.max.y - ray.origin.y) / ray.direction.y;
+    /// Widens any side thinner than size, keeping it centred. A box with no
+    /// thickness, say around a flat triangle, is never hit otherwise.
+    pub fn padded(&self, size: f32) -> Self {
+        let pad = |min: f32, max: f32| {
+            if max - min >= size {
+                (min, max)
+            } else {
+                let centre = (min + max) / 2.0;
+                (centre - (size / 2.0), centre + (size / 2.0))
+            }
+        };
+
+        let (min_x, max_x) = pad(self.min.x, self.max.x);
+        let (min_y, max_y) = pad(self.min.y, self.max.y);
+        let (min_z, max_z) = pad(self.min.z, self.max.z);
+
+        BoundingBox {
+            min: Point3::new(min_x, min_y, min_z),
+            max: Point3::new(max_x, max_y, max_z),
+        }
+    }
 
-        let t_a_z = (self.min.z - ray.origin.z) / ray.direction.z;
-        let t_b_z = (self.max.z - ray.origin.z) / ray.direction.z;
-        
-        // Ordered so t_0 <= t_1
-        let t_0_x = t_a_x.min(t_b_x);
-        let t_1_x = t_a_x.max(t_b_x);
+    /// Whether the ray passes through the box for some t in [t_min, t_max]. We
+    /// narrow the range of t to where the ray is between each pair of planes in
+    /// turn, and miss if it becomes empty.
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        let slabs = [
+            (self.min.x, self.max.x, ray.origin.x, ray.direction.x),
+            (self.min.y, self.max.y, ray.origin.y, ray.direction.y),
+            (self.min.z, self.max.z, ray.origin.z, ray.direction.z),
+        ];
 
-        let t_0_y = t_a_y.min(t_b_y);
-        let t_1_y = t_a_y.max(t_b_y);
+        let mut t_0 = t_min;
+        let mut t_1 = t_max;
 
-        let t_0_z = t_a_z.min(t_b_z);
-        let t_1_z = t_a_z.max(t_b_z);
+        for &(min, max, origin, direction) in slabs.iter() {
+            // A ray parallel to the planes is between them everywhere or nowhere.
+            if direction == 0.0 {
+                if origin < min || origin > max {
+                    return false;
+                }
+                continue;
+            }
 
-        // Bounded by t_min and t_max
-        let t_0b_x = t_0_x.max(t_min);
-        let t_1b_x = t_1_x.min(t_max);
+            // Times of the two hits, ordered so t_a <= t_b
+            let t_a = (min - origin) / direction;
+            let t_b = (max - origin) / direction;
+            let (t_a, t_b) = if t_a <= t_b { (t_a, t_b) } else { (t_b, t_a) };
 
-        let t_0b_y = t_0_y.max(t_min);
-        let t_1b_y = t_1_y.min(t_max);
+            t_0 = t_0.max(t_a);
+            t_1 = t_1.min(t_b);
 
-        let t_0b_z = t_0_z.max(t_min);
-        let t_1b_z = t_1_z.min(t_max);
+            if t_1 < t_0 {
+                return false;
+            }
+        }
 
-        (t_1b_x > t_0b_x) && (t_1b_y > t_0b_y) && (t_1b_z > t_0b_z)
+        true
     }
 
     pub fn union(&self, other: &Self) -> Self {
@@ -70,9 +109,24 @@ impl BoundingBox {
             max: max,
         }
     }
+
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(
+            (self.min.x + self.max.x) / 2.0,
+            (self.min.y + self.max.y) / 2.0,
+            (self.min.z + self.max.z) / 2.0,
+        )
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+
+        2.0 * ((d.x * d.y) + (d.y * d.z) + (d.z * d.x))
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
     use super::super::vector::Vector3;
@@ -151,6 +205,72 @@ mod tests {
         );
     }
 
+    #[test]
+    fn bounding_box_from_points() {
+        assert_eq!(
+            BoundingBox::from_points(&[
+                Point3::new(1.0, -2.0, 0.0),
+                Point3::new(-1.0, 3.0, 0.5),
+                Point3::new(0.0, 0.0, -4.0),
+            ]),
+            BoundingBox::new(
+                Point3::new(-1.0, -2.0, -4.0),
+                Point3::new(1.0, 3.0, 0.5),
+            ),
+        );
+    }
+
+    #[test]
+    fn padded_bounding_box() {
+        let b = BoundingBox::new(
+            Point3::new(0.0, 0.0, 1.0),
+            Point3::new(2.0, 2.0, 1.0),
+        );
+
+        assert_eq!(
+            b.padded(0.5),
+            BoundingBox::new(
+                Point3::new(0.0, 0.0, 0.75),
+                Point3::new(2.0, 2.0, 1.25),
+            ),
+        );
+    }
+
+    #[test]
+    fn hit_bounding_box_needs_all_slabs_at_once() {
+        // Within the x and y slabs at different times, but never both.
+        let ray = Ray::new(
+            Point3::new(0.0, 0.0, 0.5),
+            Vector3::new(1.0, 1.0, 0.0),
+        );
+        let b = BoundingBox::new(
+            Point3::new(2.0, -1.0, 0.0),
+            Point3::new(3.0, 1.0, 1.0),
+        );
+
+        assert_eq!(
+            b.hit(&ray, 0.0, f32::INFINITY),
+            false,
+        );
+    }
+
+    #[test]
+    fn hit_bounding_box_parallel_on_face() {
+        let ray = Ray::new(
+            Point3::new(1.0, 5.0, 1.0),
+            Vector3::new(0.0, -1.0, 0.0),
+        );
+        let b = BoundingBox::new(
+            Point3::new(1.0, -1.0, 0.0),
+            Point3::new(2.0, 1.0, 1.0),
+        );
+
+        assert_eq!(
+            b.hit(&ray, 0.0, f32::INFINITY),
+            true,
+        );
+    }
+
     #[test]
     fn union_bounding_box() {
         let b_1 = BoundingBox::new(
@@ -171,4 +291,18 @@ mod tests {
             ),
         );
     }
+
+    #[test]
+    fn bounding_box_centroid() {
+        let b = BoundingBox::new(Point3::new(0.0, -2.0, 1.0), Point3::new(2.0, 2.0, 4.0));
+
+        assert_eq!(b.centroid(), Point3::new(1.0, 0.0, 2.5));
+    }
+
+    #[test]
+    fn bounding_box_surface_area() {
+        let b = BoundingBox::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 2.0, 3.0));
+
+        assert_eq!(b.surface_area(), 22.0);
+    }
 }
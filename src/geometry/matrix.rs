@@ -0,0 +1,304 @@
+use std::ops::Mul;
+
+use super::point::Point3;
+use super::vector::Vector3;
+
+/// A 4x4 matrix acting on points and vectors as columns, in homogeneous
+/// coordinates with w = 1 for points and w = 0 for vectors. Entries are
+/// m[row][column].
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Matrix4 {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Matrix4 {
+    pub fn new(m: [[f32; 4]; 4]) -> Self {
+        Matrix4 {
+            m: m,
+        }
+    }
+
+    pub fn identity() -> Self {
+        Matrix4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn translation(offset: Vector3) -> Self {
+        Matrix4::new([
+            [1.0, 0.0, 0.0, offset.x],
+            [0.0, 1.0, 0.0, offset.y],
+            [0.0, 0.0, 1.0, offset.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scaling(x: f32, y: f32, z: f32) -> Self {
+        Matrix4::new([
+            [x, 0.0, 0.0, 0.0],
+            [0.0, y, 0.0, 0.0],
+            [0.0, 0.0, z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// A rotation by the angle, in degrees, anticlockwise about the axis when
+    /// looking back along it.
+    pub fn rotation(axis: Vector3, degrees: f32) -> Self {
+        let a = axis.normalise();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let c = 1.0 - cos;
+
+        Matrix4::new([
+            [(a.x * a.x * c) + cos, (a.x * a.y * c) - (a.z * sin), (a.x * a.z * c) + (a.y * sin), 0.0],
+            [(a.y * a.x * c) + (a.z * sin), (a.y * a.y * c) + cos, (a.y * a.z * c) - (a.x * sin), 0.0],
+            [(a.z * a.x * c) - (a.y * sin), (a.z * a.y * c) + (a.x * sin), (a.z * a.z * c) + cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// The view matrix of a camera at eye looking at target, taking the world to
+    /// the camera's space, where it looks down -z with +y up.
+    pub fn look_at(eye: Point3, target: Point3, up: Vector3) -> Self {
+        let w = (eye - target).normalise();
+        let u = up.cross(&w).normalise();
+        let v = w.cross(&u);
+        let e = eye.as_vector3();
+
+        Matrix4::new([
+            [u.x, u.y, u.z, -u.dot(&e)],
+            [v.x, v.y, v.z, -v.dot(&e)],
+            [w.x, w.y, w.z, -w.dot(&e)],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// A perspective projection with the vertical field of view in degrees, taking
+    /// camera space, looking down -z, to clip space, where the view is from -1 to 1
+    /// on each axis. Depth runs from -1 at near to 1 at far.
+    pub fn perspective(vertical_fov: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        let f = (vertical_fov.to_radians() / 2.0).tan().recip();
+        let depth = near - far;
+
+        Matrix4::new([
+            [f / aspect_ratio, 0.0, 0.0, 0.0],
+            [0.0, f, 0.0, 0.0],
+            [0.0, 0.0, (far + near) / depth, (2.0 * far * near) / depth],
+            [0.0, 0.0, -1.0, 0.0],
+        ])
+    }
+
+    /// The determinant, by expanding along the bottom two rows in 2x2 minors.
+    pub fn determinant(&self) -> f32 {
+        let m = &self.m;
+        let minor = |a: usize, b: usize| (m[2][a] * m[3][b]) - (m[2][b] * m[3][a]);
+        let cofactor = |a: usize, b: usize| (m[0][a] * m[1][b]) - (m[0][b] * m[1][a]);
+
+        (cofactor(0, 1) * minor(2, 3)) - (cofactor(0, 2) * minor(1, 3)) + (cofactor(0, 3) * minor(1, 2))
+            + (cofactor(1, 2) * minor(0, 3)) - (cofactor(1, 3) * minor(0, 2)) + (cofactor(2, 3) * minor(0, 1))
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (row, values) in m.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().enumerate() {
+                *value = self.m[column][row];
+            }
+        }
+
+        Matrix4::new(m)
+    }
+
+    /// The inverse, found by Gauss-Jordan elimination with partial pivoting, or
+    /// None if the matrix is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inverse = Matrix4::identity().m;
+
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+                .unwrap();
+            if !a[pivot][column].is_finite() || a[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let scale = a[column][column].recip();
+            for k in 0..4 {
+                a[column][k] *= scale;
+                inverse[column][k] *= scale;
+            }
+
+            for row in 0..4 {
+                if row != column {
+                    let factor = a[row][column];
+                    for k in 0..4 {
+                        a[row][k] -= factor * a[column][k];
+                        inverse[row][k] -= factor * inverse[column][k];
+                    }
+                }
+            }
+        }
+
+        Some(Matrix4::new(inverse))
+    }
+
+    pub fn transform_point(&self, p: &Point3) -> Point3 {
+        let m = &self.m;
+        let x = (m[0][0] * p.x) + (m[0][1] * p.y) + (m[0][2] * p.z) + m[0][3];
+        let y = (m[1][0] * p.x) + (m[1][1] * p.y) + (m[1][2] * p.z) + m[1][3];
+        let z = (m[2][0] * p.x) + (m[2][1] * p.y) + (m[2][2] * p.z) + m[2][3];
+        let w = (m[3][0] * p.x) + (m[3][1] * p.y) + (m[3][2] * p.z) + m[3][3];
+
+        if w == 1.0 {
+            Point3::new(x, y, z)
+        } else {
+            Point3::new(x / w, y / w, z / w)
+        }
+    }
+
+    pub fn transform_vector(&self, v: &Vector3) -> Vector3 {
+        let m = &self.m;
+
+        Vector3::new(
+            (m[0][0] * v.x) + (m[0][1] * v.y) + (m[0][2] * v.z),
+            (m[1][0] * v.x) + (m[1][1] * v.y) + (m[1][2] * v.z),
+            (m[2][0] * v.x) + (m[2][1] * v.y) + (m[2][2] * v.z),
+        )
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (row, values) in m.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[row][k] * other.m[k][column]).sum();
+            }
+        }
+
+        Matrix4::new(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: &Matrix4, b: &Matrix4) {
+        for row in 0..4 {
+            for column in 0..4 {
+                assert!((a.m[row][column] - b.m[row][column]).abs() < 1e-5, "{:?} is not near {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn multiply_matrix4() {
+        let a = Matrix4::translation(Vector3::new(1.0, 2.0, 3.0));
+        let b = Matrix4::scaling(2.0, 2.0, 2.0);
+
+        // Scale first, then translate.
+        assert_eq!(
+            (a * b).transform_point(&Point3::new(1.0, 1.0, 1.0)),
+            Point3::new(3.0, 4.0, 5.0),
+        );
+        assert_eq!(a * Matrix4::identity(), a);
+    }
+
+    #[test]
+    fn transform_vector_ignores_translation() {
+        let m = Matrix4::translation(Vector3::new(1.0, 2.0, 3.0));
+
+        assert_eq!(
+            m.transform_vector(&Vector3::new(1.0, 0.0, 0.0)),
+            Vector3::new(1.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn rotation_matrix4() {
+        let m = Matrix4::rotation(Vector3::new(0.0, 1.0, 0.0), 90.0);
+        let v = m.transform_vector(&Vector3::new(1.0, 0.0, 0.0));
+
+        assert!((v + Vector3::new(0.0, 0.0, 1.0)).length() < 1e-6);
+    }
+
+    #[test]
+    fn transpose_matrix4() {
+        let m = Matrix4::translation(Vector3::new(1.0, 2.0, 3.0)).transpose();
+
+        assert_eq!(m.m[3], [1.0, 2.0, 3.0, 1.0]);
+    }
+
+    #[test]
+    fn inverse_matrix4() {
+        let m = Matrix4::translation(Vector3::new(1.0, -2.0, 3.0))
+            * Matrix4::rotation(Vector3::new(1.0, 1.0, 0.0), 30.0)
+            * Matrix4::scaling(2.0, 0.5, 4.0);
+
+        let inverse = m.inverse().unwrap();
+
+        assert_near(&(m * inverse), &Matrix4::identity());
+        assert_near(&(inverse * m), &Matrix4::identity());
+    }
+
+    #[test]
+    fn look_at_matrix4() {
+        let m = Matrix4::look_at(Point3::new(0.0, 0.0, 5.0), Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+
+        assert_eq!(m.transform_point(&Point3::new(0.0, 0.0, 5.0)), Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(m.transform_point(&Point3::new(0.0, 0.0, 0.0)), Point3::new(0.0, 0.0, -5.0));
+        assert_eq!(m.transform_point(&Point3::new(1.0, 2.0, 5.0)), Point3::new(1.0, 2.0, 0.0));
+
+        // Looking along +x, the camera's right is +z.
+        let m = Matrix4::look_at(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(m.transform_vector(&Vector3::new(0.0, 0.0, 1.0)), Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn perspective_matrix4() {
+        let m = Matrix4::perspective(90.0, 2.0, 1.0, 10.0);
+
+        let near_corner = m.transform_point(&Point3::new(2.0, 1.0, -1.0));
+        assert!((near_corner.x - 1.0).abs() < 1e-6);
+        assert!((near_corner.y - 1.0).abs() < 1e-6);
+        assert!((near_corner.z + 1.0).abs() < 1e-6);
+
+        let far_centre = m.transform_point(&Point3::new(0.0, 0.0, -10.0));
+        assert!((far_centre.z - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn determinant_matrix4() {
+        assert_eq!(Matrix4::identity().determinant(), 1.0);
+        assert_eq!(Matrix4::scaling(2.0, 3.0, -1.0).determinant(), -6.0);
+        assert_eq!((Matrix4::translation(Vector3::new(1.0, 2.0, 3.0)) * Matrix4::scaling(2.0, 2.0, 2.0)).determinant(), 8.0);
+
+        let m = Matrix4::new([
+            [1.0, 2.0, 3.0, 4.0],
+            [0.0, 1.0, 0.0, 2.0],
+            [2.0, 0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0, 1.0],
+        ]);
+        assert_eq!(m.determinant(), 5.0);
+    }
+
+    #[test]
+    fn singular_matrix4_has_no_inverse() {
+        assert_eq!(Matrix4::scaling(1.0, 0.0, 1.0).inverse(), None);
+    }
+
+    #[test]
+    fn matrix4_with_nan_has_no_inverse() {
+        assert_eq!(Matrix4::scaling(1.0, f32::NAN, 1.0).inverse(), None);
+        assert_eq!(Matrix4::scaling(1.0, 1.0, f32::INFINITY).inverse(), None);
+    }
+}
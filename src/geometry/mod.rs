@@ -1,4 +1,9 @@
 pub mod bounding_box;
+pub mod matrix;
+pub mod normal;
+pub mod orthonormal_basis;
 pub mod point;
+pub mod quaternion;
 pub mod ray;
+pub mod transform;
 pub mod vector;
@@ -0,0 +1,133 @@
+use std::ops::{Add, Mul, Neg};
+
+use super::vector::Vector3;
+
+/// A surface normal. It is kept apart from Vector3 because it transforms
+/// differently: by the inverse transpose, so it stays at right angles to the
+/// surface when the surface is stretched.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Normal3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Normal3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Normal3 {
+            x: x,
+            y: y,
+            z: z,
+        }
+    }
+
+    pub fn length_squared(&self) -> f32 {
+        (self.x * self.x) + (self.y * self.y) + (self.z * self.z)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    pub fn normalise(&self) -> Self {
+        let length = self.length();
+
+        Normal3 {
+            x: self.x / length,
+            y: self.y / length,
+            z: self.z / length,
+        }
+    }
+
+    pub fn dot(&self, v: &Vector3) -> f32 {
+        (self.x * v.x) + (self.y * v.y) + (self.z * v.z)
+    }
+
+    /// The normal, turned round if need be to lie on the same side as v.
+    pub fn face_forward(&self, v: &Vector3) -> Self {
+        if self.dot(v) < 0.0 { -*self } else { *self }
+    }
+
+    pub fn as_vector3(self) -> Vector3 {
+        Vector3 {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+        }
+    }
+}
+
+impl Add for Normal3 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self { x: self.x + other.x, y: self.y + other.y, z: self.z + other.z }
+    }
+}
+
+impl Neg for Normal3 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self { x: -self.x, y: -self.y, z: -self.z }
+    }
+}
+
+impl Mul<f32> for Normal3 {
+    type Output = Self;
+
+    fn mul(self, scalar: f32) -> Self {
+        Self { x: self.x * scalar, y: self.y * scalar, z: self.z * scalar }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_normal3() {
+        assert_eq!(
+            Normal3::new(1.0, 2.0, 3.0),
+            Normal3 { x: 1.0, y: 2.0, z: 3.0 },
+        );
+    }
+
+    #[test]
+    fn normalise_normal3() {
+        assert_eq!(
+            Normal3::new(0.0, 3.0, 4.0).normalise(),
+            Normal3::new(0.0, 0.6, 0.8),
+        );
+    }
+
+    #[test]
+    fn dot_normal3() {
+        assert_eq!(Normal3::new(1.0, 2.0, 3.0).dot(&Vector3::new(-1.0, 0.5, 2.0)), 6.0);
+    }
+
+    #[test]
+    fn face_forward_normal3() {
+        let n = Normal3::new(0.0, 1.0, 0.0);
+
+        assert_eq!(n.face_forward(&Vector3::new(1.0, 1.0, 0.0)), n);
+        assert_eq!(n.face_forward(&Vector3::new(1.0, -1.0, 0.0)), Normal3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn operators_normal3() {
+        let n = Normal3::new(1.0, 2.0, 3.0);
+
+        assert_eq!(-n, Normal3::new(-1.0, -2.0, -3.0));
+        assert_eq!(n * 2.0, Normal3::new(2.0, 4.0, 6.0));
+        assert_eq!(n + n, Normal3::new(2.0, 4.0, 6.0));
+    }
+
+    #[test]
+    fn normal3_as_vector3() {
+        assert_eq!(
+            Normal3::new(1.0, 2.0, 3.0).as_vector3(),
+            Vector3::new(1.0, 2.0, 3.0),
+        )
+    }
+}
@@ -0,0 +1,73 @@
+use super::vector::Vector3;
+
+/// Three unit vectors at right angles, with w along a given direction, so that
+/// directions sampled about the z axis can be turned to lie about w.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct OrthonormalBasis {
+    pub u: Vector3,
+    pub v: Vector3,
+    pub w: Vector3,
+}
+
+impl OrthonormalBasis {
+    /// A basis with w along the direction, which needn't be normalised. The
+    /// choice of u and v about it is arbitrary.
+    pub fn new_from_w(direction: &Vector3) -> Self {
+        let w = direction.normalise();
+
+        // Any vector not parallel to w will do to start from.
+        let a = if w.x.abs() > 0.9 {
+            Vector3::new(0.0, 1.0, 0.0)
+        } else {
+            Vector3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(&a).normalise();
+        let u = v.cross(&w);
+
+        OrthonormalBasis {
+            u: u,
+            v: v,
+            w: w,
+        }
+    }
+
+    /// The vector with the given coordinates in this basis.
+    pub fn local(&self, a: &Vector3) -> Vector3 {
+        (self.u * a.x) + (self.v * a.y) + (self.w * a.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-6, "{} is not near {}", a, b);
+    }
+
+    #[test]
+    fn basis_is_orthonormal() {
+        for direction in [Vector3::new(0.0, 0.0, 2.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(-1.0, 3.0, 0.5)].iter() {
+            let basis = OrthonormalBasis::new_from_w(direction);
+
+            assert_near(basis.u.length(), 1.0);
+            assert_near(basis.v.length(), 1.0);
+            assert_near(basis.u.dot(&basis.v), 0.0);
+            assert_near(basis.u.dot(&basis.w), 0.0);
+            assert_near(basis.v.dot(&basis.w), 0.0);
+            assert_near(basis.w.dot(&direction.normalise()), 1.0);
+            // Right handed, so u cross v is w.
+            assert_near(basis.u.cross(&basis.v).dot(&basis.w), 1.0);
+        }
+    }
+
+    #[test]
+    fn local_z_is_w() {
+        let basis = OrthonormalBasis::new_from_w(&Vector3::new(1.0, 2.0, 3.0));
+
+        assert_eq!(
+            basis.local(&Vector3::new(0.0, 0.0, 1.0)),
+            basis.w,
+        );
+    }
+}
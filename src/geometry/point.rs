@@ -1,4 +1,4 @@
-use std::ops::{Add, Sub, Mul};
+use std::ops::{Add, Index, Mul, Sub};
 
 use super::vector::Vector3;
 
@@ -67,6 +67,20 @@ impl Mul<f32> for Point3 {
     }
 }
 
+/// The coordinate along an axis: 0 for x, 1 for y and 2 for z.
+impl Index<usize> for Point3 {
+    type Output = f32;
+
+    fn index(&self, axis: usize) -> &f32 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("axis {} out of range", axis),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -126,4 +140,11 @@ mod tests {
             Point3::new(2.0, 4.0, 6.0),
         )
     }
+
+    #[test]
+    fn index_point3() {
+        let p = Point3::new(1.0, 2.0, 3.0);
+
+        assert_eq!([p[0], p[1], p[2]], [1.0, 2.0, 3.0]);
+    }
 }
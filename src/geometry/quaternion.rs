@@ -0,0 +1,241 @@
+use std::ops::Mul;
+
+use super::matrix::Matrix4;
+use super::vector::Vector3;
+
+/// A quaternion w + xi + yj + zk. Unit quaternions are rotations, and can be
+/// interpolated smoothly with slerp.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Quaternion {
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Quaternion {
+            w: w,
+            x: x,
+            y: y,
+            z: z,
+        }
+    }
+
+    pub fn identity() -> Self {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    /// The rotation by the angle, in degrees, anticlockwise about the axis when
+    /// looking back along it, as Matrix4::rotation.
+    pub fn from_axis_angle(axis: Vector3, degrees: f32) -> Self {
+        let a = axis.normalise();
+        let (sin, cos) = (degrees.to_radians() / 2.0).sin_cos();
+
+        Quaternion::new(cos, a.x * sin, a.y * sin, a.z * sin)
+    }
+
+    pub fn dot(&self, other: &Self) -> f32 {
+        (self.w * other.w) + (self.x * other.x) + (self.y * other.y) + (self.z * other.z)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalise(&self) -> Self {
+        self.scaled(self.length().recip())
+    }
+
+    /// The inverse rotation, for a unit quaternion.
+    pub fn conjugate(&self) -> Self {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    /// Rotates the vector, for a unit quaternion.
+    pub fn rotate(&self, v: &Vector3) -> Vector3 {
+        let p = Quaternion::new(0.0, v.x, v.y, v.z);
+        let r = *self * p * self.conjugate();
+
+        Vector3::new(r.x, r.y, r.z)
+    }
+
+    /// Spherical linear interpolation between unit quaternions, from self at t = 0
+    /// to other at t = 1, turning at a constant rate the short way round.
+    pub fn slerp(&self, other: &Self, t: f32) -> Self {
+        // q and -q are the same rotation, so pick the nearer.
+        let mut cos_theta = self.dot(other);
+        let other = if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            other.scaled(-1.0)
+        } else {
+            *other
+        };
+
+        // Nearly the same, where the sine below vanishes, so interpolate straight.
+        if cos_theta > 0.9995 {
+            return self.scaled(1.0 - t).add(&other.scaled(t)).normalise();
+        }
+
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin_theta;
+        let b = (t * theta).sin() / sin_theta;
+
+        self.scaled(a).add(&other.scaled(b))
+    }
+
+    /// The rotation matrix, for a unit quaternion.
+    pub fn to_matrix(&self) -> Matrix4 {
+        let Quaternion { w, x, y, z } = *self;
+
+        Matrix4::new([
+            [1.0 - (2.0 * ((y * y) + (z * z))), 2.0 * ((x * y) - (w * z)), 2.0 * ((x * z) + (w * y)), 0.0],
+            [2.0 * ((x * y) + (w * z)), 1.0 - (2.0 * ((x * x) + (z * z))), 2.0 * ((y * z) - (w * x)), 0.0],
+            [2.0 * ((x * z) - (w * y)), 2.0 * ((y * z) + (w * x)), 1.0 - (2.0 * ((x * x) + (y * y))), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    fn scaled(&self, s: f32) -> Self {
+        Quaternion::new(self.w * s, self.x * s, self.y * s, self.z * s)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Quaternion::new(self.w + other.w, self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+/// The Hamilton product. As rotations, a * b is b followed by a.
+impl Mul for Quaternion {
+    type Output = Self;
+
+    fn mul(self, o: Self) -> Self {
+        Quaternion::new(
+            (self.w * o.w) - (self.x * o.x) - (self.y * o.y) - (self.z * o.z),
+            (self.w * o.x) + (self.x * o.w) + (self.y * o.z) - (self.z * o.y),
+            (self.w * o.y) - (self.x * o.z) + (self.y * o.w) + (self.z * o.x),
+            (self.w * o.z) + (self.x * o.y) - (self.y * o.x) + (self.z * o.w),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: &Vector3, b: &Vector3) {
+        assert!((*a - *b).length() < 1e-5, "{:?} is not near {:?}", a, b);
+    }
+
+    #[test]
+    fn new_quaternion() {
+        assert_eq!(
+            Quaternion::new(1.0, 2.0, 3.0, 4.0),
+            Quaternion { w: 1.0, x: 2.0, y: 3.0, z: 4.0 },
+        );
+    }
+
+    #[test]
+    fn multiply_quaternion() {
+        let i = Quaternion::new(0.0, 1.0, 0.0, 0.0);
+        let j = Quaternion::new(0.0, 0.0, 1.0, 0.0);
+        let k = Quaternion::new(0.0, 0.0, 0.0, 1.0);
+
+        assert_eq!(i * j, k);
+        assert_eq!(j * i, k * Quaternion::new(-1.0, 0.0, 0.0, 0.0));
+        assert_eq!(i * i, Quaternion::new(-1.0, 0.0, 0.0, 0.0));
+        assert_eq!(Quaternion::identity() * k, k);
+    }
+
+    #[test]
+    fn length_quaternion() {
+        let q = Quaternion::new(1.0, 1.0, 1.0, 1.0);
+
+        assert_eq!(q.length(), 2.0);
+        assert_eq!(q.normalise(), Quaternion::new(0.5, 0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn rotate_vector() {
+        let q = Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), 90.0);
+
+        assert_near(&q.rotate(&Vector3::new(1.0, 0.0, 0.0)), &Vector3::new(0.0, 1.0, 0.0));
+        assert_near(&q.conjugate().rotate(&Vector3::new(0.0, 1.0, 0.0)), &Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn compose_rotations() {
+        let a = Quaternion::from_axis_angle(Vector3::new(0.0, 1.0, 0.0), 90.0);
+        let b = Quaternion::from_axis_angle(Vector3::new(1.0, 0.0, 0.0), 90.0);
+        let v = Vector3::new(0.0, 0.0, 1.0);
+
+        assert_near(&(a * b).rotate(&v), &a.rotate(&b.rotate(&v)));
+    }
+
+    #[test]
+    fn to_matrix_matches_rotation() {
+        let axis = Vector3::new(1.0, 2.0, -0.5);
+        let q = Quaternion::from_axis_angle(axis, 70.0);
+        let m = Matrix4::rotation(axis, 70.0);
+
+        for row in 0..4 {
+            for column in 0..4 {
+                assert!((q.to_matrix().m[row][column] - m.m[row][column]).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn slerp_ends_and_middle() {
+        let axis = Vector3::new(0.0, 1.0, 0.0);
+        let a = Quaternion::identity();
+        let b = Quaternion::from_axis_angle(axis, 90.0);
+
+        assert_eq!(a.slerp(&b, 0.0), a);
+        assert!((a.slerp(&b, 1.0).dot(&b) - 1.0).abs() < 1e-6);
+
+        let middle = a.slerp(&b, 0.5);
+        let expected = Quaternion::from_axis_angle(axis, 45.0);
+        assert!((middle.dot(&expected) - 1.0).abs() < 1e-6);
+        assert!((middle.length() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn slerp_turns_constant_rate() {
+        let axis = Vector3::new(0.0, 0.0, 1.0);
+        let a = Quaternion::from_axis_angle(axis, 0.0);
+        let b = Quaternion::from_axis_angle(axis, 120.0);
+        let v = Vector3::new(1.0, 0.0, 0.0);
+
+        let turned = a.slerp(&b, 0.25).rotate(&v);
+        let angle = turned.y.atan2(turned.x).to_degrees();
+
+        assert!((angle - 30.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn slerp_takes_short_way() {
+        let axis = Vector3::new(0.0, 0.0, 1.0);
+        let a = Quaternion::from_axis_angle(axis, 10.0);
+        // The same rotation as 350 degrees, but with the opposite sign.
+        let b = Quaternion::from_axis_angle(axis, -10.0).scaled(-1.0);
+
+        let middle = a.slerp(&b, 0.5);
+
+        assert_near(&middle.rotate(&Vector3::new(1.0, 0.0, 0.0)), &Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn slerp_nearly_equal() {
+        let axis = Vector3::new(0.0, 0.0, 1.0);
+        let a = Quaternion::from_axis_angle(axis, 10.0);
+        let b = Quaternion::from_axis_angle(axis, 10.01);
+
+        let middle = a.slerp(&b, 0.5);
+
+        assert!((middle.length() - 1.0).abs() < 1e-6);
+        assert!(middle.dot(&a) > 0.9999);
+    }
+}
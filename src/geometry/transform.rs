@@ -0,0 +1,300 @@
+use std::ops::Mul;
+
+use super::bounding_box::BoundingBox;
+use super::matrix::Matrix4;
+use super::normal::Normal3;
+use super::point::Point3;
+use super::quaternion::Quaternion;
+use super::ray::Ray;
+use super::vector::Vector3;
+
+/// An invertible transform, kept with its inverse so neither has to be worked
+/// out again. As with matrices, a * b applies b first, then a.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Transform {
+    matrix: Matrix4,
+    inverse: Matrix4,
+}
+
+impl Transform {
+    /// The transform by the matrix, or None if the matrix is singular.
+    pub fn new(matrix: Matrix4) -> Option<Self> {
+        Some(Transform {
+            matrix: matrix,
+            inverse: matrix.inverse()?,
+        })
+    }
+
+    pub fn identity() -> Self {
+        Transform {
+            matrix: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+        }
+    }
+
+    pub fn translate(offset: Vector3) -> Self {
+        Transform {
+            matrix: Matrix4::translation(offset),
+            inverse: Matrix4::translation(-offset),
+        }
+    }
+
+    /// Scales along each axis. None of the factors may be zero.
+    pub fn scale(x: f32, y: f32, z: f32) -> Self {
+        Transform {
+            matrix: Matrix4::scaling(x, y, z),
+            inverse: Matrix4::scaling(x.recip(), y.recip(), z.recip()),
+        }
+    }
+
+    /// A rotation by the angle, in degrees, anticlockwise about the axis when
+    /// looking back along it.
+    pub fn rotate(axis: Vector3, degrees: f32) -> Self {
+        let matrix = Matrix4::rotation(axis, degrees);
+
+        Transform {
+            matrix: matrix,
+            inverse: matrix.transpose(),
+        }
+    }
+
+    /// The rotation of a unit quaternion.
+    pub fn rotate_quaternion(rotation: &Quaternion) -> Self {
+        let matrix = rotation.to_matrix();
+
+        Transform {
+            matrix: matrix,
+            inverse: matrix.transpose(),
+        }
+    }
+
+    /// The view transform of a camera at eye looking at target, from the world to
+    /// the camera's space. Its inverse places the camera in the world.
+    pub fn look_at(eye: Point3, target: Point3, up: Vector3) -> Self {
+        let matrix = Matrix4::look_at(eye, target, up);
+
+        // The view is a rotation after a translation, so its inverse is the
+        // rotation's transpose before the opposite translation.
+        let mut rotation = matrix;
+        for row in rotation.m[..3].iter_mut() {
+            row[3] = 0.0;
+        }
+
+        Transform {
+            matrix: matrix,
+            inverse: Matrix4::translation(eye.as_vector3()) * rotation.transpose(),
+        }
+    }
+
+    /// A perspective projection, as Matrix4::perspective.
+    pub fn perspective(vertical_fov: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        let matrix = Matrix4::perspective(vertical_fov, aspect_ratio, near, far);
+
+        Transform {
+            matrix: matrix,
+            inverse: matrix.inverse().unwrap(),
+        }
+    }
+
+    pub fn matrix(&self) -> &Matrix4 {
+        &self.matrix
+    }
+
+    pub fn inverse_matrix(&self) -> &Matrix4 {
+        &self.inverse
+    }
+
+    pub fn inverse(&self) -> Self {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    /// This transform followed by the next.
+    pub fn then(&self, next: &Self) -> Self {
+        *next * *self
+    }
+
+    /// Whether the transform mirrors space, turning the winding of triangles
+    /// around.
+    pub fn swaps_handedness(&self) -> bool {
+        self.matrix.determinant() < 0.0
+    }
+
+    pub fn point(&self, p: &Point3) -> Point3 {
+        self.matrix.transform_point(p)
+    }
+
+    pub fn vector(&self, v: &Vector3) -> Vector3 {
+        self.matrix.transform_vector(v)
+    }
+
+    /// Transforms a surface normal by the inverse transpose, so it stays at right
+    /// angles to the transformed surface. The result is normalised.
+    pub fn normal(&self, n: &Normal3) -> Normal3 {
+        self.inverse.transpose().transform_vector(&n.as_vector3()).as_normal3().normalise()
+    }
+
+    /// Transforms the ray without normalising its direction, so a point at t on
+    /// the ray goes to the point at t on the transformed ray.
+    pub fn ray(&self, ray: &Ray) -> Ray {
+        Ray::new_at_time(self.point(&ray.origin), self.vector(&ray.direction), ray.time)
+    }
+
+    /// The box around the eight transformed corners of the box.
+    pub fn bounding_box(&self, b: &BoundingBox) -> BoundingBox {
+        let mut corners = Vec::with_capacity(8);
+        for &x in [b.min.x, b.max.x].iter() {
+            for &y in [b.min.y, b.max.y].iter() {
+                for &z in [b.min.z, b.max.z].iter() {
+                    corners.push(self.point(&Point3::new(x, y, z)));
+                }
+            }
+        }
+
+        BoundingBox::from_points(&corners)
+    }
+}
+
+impl Mul for Transform {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Transform {
+            matrix: self.matrix * other.matrix,
+            inverse: other.inverse * self.inverse,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: &Point3, b: &Point3) {
+        assert!((*a - *b).length() < 1e-5, "{:?} is not near {:?}", a, b);
+    }
+
+    fn assert_near_vector(a: &Vector3, b: &Vector3) {
+        assert!((*a - *b).length() < 1e-5, "{:?} is not near {:?}", a, b);
+    }
+
+    fn assert_inverse(t: &Transform) {
+        let product = *t.matrix() * *t.inverse_matrix();
+        for row in 0..4 {
+            for column in 0..4 {
+                let expected = if row == column { 1.0 } else { 0.0 };
+                assert!((product.m[row][column] - expected).abs() < 1e-5, "{:?}", t);
+            }
+        }
+    }
+
+    #[test]
+    fn inverses_match() {
+        let axis = Vector3::new(1.0, -1.0, 2.0);
+
+        assert_inverse(&Transform::translate(Vector3::new(1.0, 2.0, 3.0)));
+        assert_inverse(&Transform::scale(2.0, -3.0, 0.5));
+        assert_inverse(&Transform::rotate(axis, 33.0));
+        assert_inverse(&Transform::rotate_quaternion(&Quaternion::from_axis_angle(axis, 33.0)));
+        assert_inverse(&Transform::look_at(Point3::new(1.0, 2.0, 3.0), Point3::new(-1.0, 0.0, 2.0), Vector3::new(0.0, 1.0, 0.0)));
+        assert_inverse(&Transform::perspective(60.0, 1.5, 0.1, 100.0));
+        assert_inverse(&(Transform::translate(Vector3::new(1.0, 0.0, 0.0)) * Transform::rotate(axis, 10.0)));
+    }
+
+    #[test]
+    fn new_transform() {
+        let m = Matrix4::scaling(2.0, 4.0, 8.0);
+
+        assert_eq!(Transform::new(m), Some(Transform::scale(2.0, 4.0, 8.0)));
+        assert_eq!(Transform::new(Matrix4::scaling(0.0, 1.0, 1.0)), None);
+    }
+
+    #[test]
+    fn compose_transforms() {
+        let scale = Transform::scale(2.0, 2.0, 2.0);
+        let translate = Transform::translate(Vector3::new(1.0, 0.0, 0.0));
+        let p = Point3::new(1.0, 1.0, 1.0);
+
+        assert_eq!((translate * scale).point(&p), Point3::new(3.0, 2.0, 2.0));
+        assert_eq!(scale.then(&translate).point(&p), Point3::new(3.0, 2.0, 2.0));
+        assert_eq!(translate.then(&scale).point(&p), Point3::new(4.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn inverse_transform() {
+        let t = Transform::translate(Vector3::new(1.0, 2.0, 3.0)) * Transform::rotate(Vector3::new(0.0, 1.0, 0.0), 45.0);
+        let p = Point3::new(4.0, 5.0, 6.0);
+
+        assert_near(&t.inverse().point(&t.point(&p)), &p);
+        assert_eq!(t.inverse().inverse(), t);
+    }
+
+    #[test]
+    fn transform_vector_and_point() {
+        let t = Transform::translate(Vector3::new(1.0, 2.0, 3.0));
+
+        assert_eq!(t.point(&Point3::new(0.0, 0.0, 0.0)), Point3::new(1.0, 2.0, 3.0));
+        assert_eq!(t.vector(&Vector3::new(1.0, 0.0, 0.0)), Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn transform_normal() {
+        // The plane x + y = 0 has normal (1, 1, 0). Stretching x by 2 takes it to
+        // x / 2 + y = 0, with normal (1, 2, 0).
+        let t = Transform::scale(2.0, 1.0, 1.0);
+
+        assert_near_vector(
+            &t.normal(&Normal3::new(1.0, 1.0, 0.0)).as_vector3(),
+            &Vector3::new(1.0, 2.0, 0.0).normalise(),
+        );
+
+        // Unlike the normal, a vector along the plane stays along it.
+        let along = t.vector(&Vector3::new(1.0, -1.0, 0.0));
+        assert!(t.normal(&Normal3::new(1.0, 1.0, 0.0)).dot(&along).abs() < 1e-6);
+
+        // A rotation turns normals like any other vector.
+        let r = Transform::rotate(Vector3::new(0.0, 0.0, 1.0), 90.0);
+        assert_near_vector(&r.normal(&Normal3::new(1.0, 0.0, 0.0)).as_vector3(), &Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn transform_ray() {
+        let t = Transform::scale(2.0, 2.0, 2.0);
+        let ray = Ray::new_at_time(Point3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), 0.5);
+
+        let transformed = t.ray(&ray);
+
+        assert_eq!(transformed, Ray::new_at_time(Point3::new(2.0, 0.0, 0.0), Vector3::new(0.0, 2.0, 0.0), 0.5));
+        assert_eq!(transformed.at(3.0), t.point(&ray.at(3.0)));
+    }
+
+    #[test]
+    fn transform_bounding_box() {
+        let b = BoundingBox::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+        let t = Transform::rotate(Vector3::new(0.0, 1.0, 0.0), 45.0);
+
+        let transformed = t.bounding_box(&b);
+        let s = 0.5_f32.sqrt();
+
+        assert_near(&transformed.min, &Point3::new(0.0, 0.0, -s));
+        assert_near(&transformed.max, &Point3::new(2.0 * s, 1.0, s));
+    }
+
+    #[test]
+    fn look_at_places_camera() {
+        let eye = Point3::new(1.0, 2.0, 3.0);
+        let target = Point3::new(1.0, 2.0, -3.0);
+        let t = Transform::look_at(eye, target, Vector3::new(0.0, 1.0, 0.0));
+
+        assert_near(&t.point(&eye), &Point3::new(0.0, 0.0, 0.0));
+        assert_near(&t.inverse().point(&Point3::new(0.0, 0.0, -6.0)), &target);
+    }
+
+    #[test]
+    fn swaps_handedness() {
+        assert!(Transform::scale(-1.0, 1.0, 1.0).swaps_handedness());
+        assert!(!Transform::rotate(Vector3::new(0.0, 1.0, 0.0), 120.0).swaps_handedness());
+    }
+}
@@ -1,5 +1,6 @@
-use std::ops::{Add, Mul, Div};
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
 
+use super::normal::Normal3;
 use super::point::Point3;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -64,6 +65,52 @@ impl Vector3 {
         }
     }
 
+    pub fn min_component(&self) -> f32 {
+        self.x.min(self.y).min(self.z)
+    }
+
+    pub fn max_component(&self) -> f32 {
+        self.x.max(self.y).max(self.z)
+    }
+
+    /// The axis, 0 to 2, of the largest component.
+    pub fn max_dimension(&self) -> usize {
+        if self.x > self.y {
+            if self.x > self.z { 0 } else { 2 }
+        } else if self.y > self.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// The smaller of each pair of components.
+    pub fn min(&self, other: &Self) -> Self {
+        Vector3::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z))
+    }
+
+    /// The larger of each pair of components.
+    pub fn max(&self, other: &Self) -> Self {
+        Vector3::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
+    }
+
+    /// The mirror image of the vector in the surface with unit normal n.
+    pub fn reflect(&self, n: &Normal3) -> Self {
+        *self - (n.as_vector3() * (2.0 * n.dot(self)))
+    }
+
+    /// The unit vector refracted through the surface with unit normal n, facing
+    /// against it, by Snell's law with the ratio of the refractive indices. The
+    /// caller checks for total internal reflection first.
+    pub fn refract(&self, n: &Normal3, eta_i_over_eta_t: f32) -> Self {
+        let n = n.as_vector3();
+        let cos_theta = (-*self).dot(&n).min(1.0);
+        let r_perpendicular = (*self + (n * cos_theta)) * eta_i_over_eta_t;
+        let r_parallel = n * -(1.0 - r_perpendicular.length_squared()).abs().sqrt();
+
+        r_perpendicular + r_parallel
+    }
+
     pub fn as_point3(self) -> Point3 {
         Point3 {
             x: self.x,
@@ -71,6 +118,14 @@ impl Vector3 {
             z: self.z,
         }
     }
+
+    pub fn as_normal3(self) -> Normal3 {
+        Normal3 {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+        }
+    }
 }
 
 impl Add for Vector3 {
@@ -81,6 +136,43 @@ impl Add for Vector3 {
     }
 }
 
+impl AddAssign for Vector3 {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vector3 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self { x: self.x - other.x, y: self.y - other.y, z: self.z - other.z }
+    }
+}
+
+impl SubAssign for Vector3 {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Vector3 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self { x: -self.x, y: -self.y, z: -self.z }
+    }
+}
+
+/// Multiplies component by component.
+impl Mul for Vector3 {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self { x: self.x * other.x, y: self.y * other.y, z: self.z * other.z }
+    }
+}
+
 impl Mul<f32> for Vector3 {
     type Output = Self;
 
@@ -100,6 +192,43 @@ impl Div<f32> for Vector3 {
     }
 }
 
+impl MulAssign<f32> for Vector3 {
+    fn mul_assign(&mut self, scalar: f32) {
+        *self = *self * scalar;
+    }
+}
+
+impl DivAssign<f32> for Vector3 {
+    fn div_assign(&mut self, scalar: f32) {
+        *self = *self / scalar;
+    }
+}
+
+/// The component along an axis: 0 for x, 1 for y and 2 for z.
+impl Index<usize> for Vector3 {
+    type Output = f32;
+
+    fn index(&self, axis: usize) -> &f32 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("axis {} out of range", axis),
+        }
+    }
+}
+
+impl IndexMut<usize> for Vector3 {
+    fn index_mut(&mut self, axis: usize) -> &mut f32 {
+        match axis {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("axis {} out of range", axis),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,4 +332,101 @@ mod tests {
             Vector3::new(0.5, 1.0, 1.5),
         )
     }
+
+    #[test]
+    fn subtract_vector3() {
+        let v = Vector3 { x: 1.0, y: 2.0, z: 3.0 };
+        let w = Vector3 { x: 2.0, y: 4.0, z: 1.0 };
+
+        assert_eq!(v - w, Vector3 { x: -1.0, y: -2.0, z: 2.0 });
+    }
+
+    #[test]
+    fn negate_vector3() {
+        assert_eq!(
+            -Vector3::new(1.0, -2.0, 3.0),
+            Vector3::new(-1.0, 2.0, -3.0),
+        )
+    }
+
+    #[test]
+    fn assign_operators_vector3() {
+        let mut v = Vector3::new(1.0, 2.0, 3.0);
+
+        v += Vector3::new(1.0, 1.0, 1.0);
+        assert_eq!(v, Vector3::new(2.0, 3.0, 4.0));
+        v -= Vector3::new(0.0, 1.0, 2.0);
+        assert_eq!(v, Vector3::new(2.0, 2.0, 2.0));
+        v *= 3.0;
+        assert_eq!(v, Vector3::new(6.0, 6.0, 6.0));
+        v /= 2.0;
+        assert_eq!(v, Vector3::new(3.0, 3.0, 3.0));
+    }
+
+    #[test]
+    fn multiply_components_vector3() {
+        assert_eq!(
+            Vector3::new(1.0, 2.0, 3.0) * Vector3::new(4.0, -1.0, 0.5),
+            Vector3::new(4.0, -2.0, 1.5),
+        )
+    }
+
+    #[test]
+    fn index_vector3() {
+        let mut v = Vector3::new(1.0, 2.0, 3.0);
+        v[1] = 5.0;
+
+        assert_eq!([v[0], v[1], v[2]], [1.0, 5.0, 3.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "axis 3 out of range")]
+    fn index_vector3_out_of_range() {
+        let _ = Vector3::zero()[3];
+    }
+
+    #[test]
+    fn components_vector3() {
+        let v = Vector3::new(2.0, -1.0, 3.0);
+        let w = Vector3::new(1.0, 4.0, 3.5);
+
+        assert_eq!(v.min_component(), -1.0);
+        assert_eq!(v.max_component(), 3.0);
+        assert_eq!(v.max_dimension(), 2);
+        assert_eq!(Vector3::new(1.0, 5.0, 2.0).max_dimension(), 1);
+        assert_eq!(v.min(&w), Vector3::new(1.0, -1.0, 3.0));
+        assert_eq!(v.max(&w), Vector3::new(2.0, 4.0, 3.5));
+    }
+
+    #[test]
+    fn reflect_vector3() {
+        let v = Vector3::new(1.0, -1.0, 0.0);
+        let n = Normal3::new(0.0, 1.0, 0.0);
+
+        assert_eq!(v.reflect(&n), Vector3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn refract_vector3() {
+        let v = Vector3::new(1.0, -1.0, 0.0).normalise();
+        let n = Normal3::new(0.0, 1.0, 0.0);
+
+        // Straight through with equal indices.
+        let same = v.refract(&n, 1.0);
+        assert!((same - v).length() < 1e-6);
+
+        // Bent towards the normal going into a denser medium, by Snell's law.
+        let bent = v.refract(&n, 1.0 / 1.5);
+        assert!((bent.length() - 1.0).abs() < 1e-6);
+        assert!((bent.x - (v.x / 1.5)).abs() < 1e-6);
+        assert!(bent.y < v.y);
+    }
+
+    #[test]
+    fn vector3_as_normal3() {
+        assert_eq!(
+            Vector3::new(1.0, 2.0, 3.0).as_normal3(),
+            Normal3::new(1.0, 2.0, 3.0),
+        )
+    }
 }
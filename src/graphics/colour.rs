@@ -15,6 +15,11 @@ impl Colour {
             b: b,
         }
     }
+
+    /// The brightest of the three channels.
+    pub fn max_component(&self) -> f32 {
+        self.r.max(self.g).max(self.b)
+    }
 }
 
 impl Add for Colour {
@@ -67,6 +72,11 @@ mod tests {
         );
     }
 
+    #[test]
+    fn max_component() {
+        assert_eq!(Colour::new(0.1, 0.7, 0.3).max_component(), 0.7);
+    }
+
     #[test]
     fn add_colour() {
         let out = Colour::new(0.1, 0.2, 0.3) + Colour::new(0.2, 0.4, 0.6);
@@ -0,0 +1,108 @@
+use num::clamp;
+
+use super::colour::Colour;
+
+/// Linear, unclamped colours for a whole image, row by row from the top.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Colour>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer {
+            width: width,
+            height: height,
+            pixels: vec![Colour::new(0.0, 0.0, 0.0); width * height],
+        }
+    }
+
+    /// Builds a framebuffer from pixels holding the sum of samples_per_pixel samples.
+    pub fn from_sums(width: usize, height: usize, sums: Vec<Colour>, samples_per_pixel: usize) -> Self {
+        debug_assert!(sums.len() == width * height);
+        let scale = 1.0 / (samples_per_pixel as f32);
+
+        Framebuffer {
+            width: width,
+            height: height,
+            pixels: sums.into_iter().map(|c| c * scale).collect(),
+        }
+    }
+
+    /// The colour at column x of row y, counting rows from the top.
+    pub fn get(&self, x: usize, y: usize) -> Colour {
+        self.pixels[(y * self.width) + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, colour: Colour) {
+        self.pixels[(y * self.width) + x] = colour;
+    }
+
+    /// The pixels gamma corrected and quantised to 8 bits per channel, as
+    /// consecutive red, green and blue bytes.
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 3);
+        for colour in self.pixels.iter() {
+            bytes.extend_from_slice(&to_rgb8(colour));
+        }
+
+        bytes
+    }
+}
+
+/// Gamma corrects with gamma = 2.0, then clamps and scales to a byte.
+pub fn to_rgb8(colour: &Colour) -> [u8; 3] {
+    let quantise = |x: f32| (256.0 * clamp(x.max(0.0).sqrt(), 0.0, 0.999)) as u8;
+
+    [quantise(colour.r), quantise(colour.g), quantise(colour.b)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_framebuffer() {
+        assert_eq!(
+            Framebuffer::new(2, 1),
+            Framebuffer {
+                width: 2,
+                height: 1,
+                pixels: vec![Colour::new(0.0, 0.0, 0.0), Colour::new(0.0, 0.0, 0.0)],
+            },
+        );
+    }
+
+    #[test]
+    fn framebuffer_from_sums() {
+        let framebuffer = Framebuffer::from_sums(
+            1,
+            2,
+            vec![Colour::new(4.0, 2.0, 0.0), Colour::new(1.0, 1.0, 1.0)],
+            4,
+        );
+
+        assert_eq!(framebuffer.get(0, 0), Colour::new(1.0, 0.5, 0.0));
+        assert_eq!(framebuffer.get(0, 1), Colour::new(0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn framebuffer_set() {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        framebuffer.set(2, 1, Colour::new(0.1, 0.2, 0.3));
+
+        assert_eq!(framebuffer.pixels[5], Colour::new(0.1, 0.2, 0.3));
+    }
+
+    #[test]
+    fn to_rgb8_gamma_and_clamp() {
+        assert_eq!(to_rgb8(&Colour::new(0.0, 0.25, 4.0)), [0, 128, 255]);
+    }
+
+    #[test]
+    fn to_rgb8_negative() {
+        assert_eq!(to_rgb8(&Colour::new(-1.0, 0.0, 0.0)), [0, 0, 0]);
+    }
+}
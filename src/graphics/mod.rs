@@ -1 +1,3 @@
 pub mod colour;
+pub mod framebuffer;
+pub mod output;
@@ -0,0 +1,436 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use exr::prelude::{
+    AnyChannel, AnyChannels, Encoding, FlatSamples, Image, ImageAttributes, IntegerBounds, Layer,
+    LayerAttributes, Vec2, WritableImage,
+};
+use image::{ColorType, ImageError, Rgb};
+use image::codecs::hdr::HdrEncoder;
+use image::codecs::png::PngEncoder;
+
+use super::colour::Colour;
+use super::framebuffer::Framebuffer;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ImageFormat {
+    /// 8-bit gamma corrected PNG.
+    Png,
+    /// Binary (P6) PPM, 8-bit gamma corrected.
+    Ppm,
+    /// ASCII (P3) PPM, 8-bit gamma corrected, one pixel per line.
+    PpmAscii,
+    /// Radiance RGBE, linear and unclamped.
+    Hdr,
+    /// OpenEXR with 32-bit float channels, linear and unclamped.
+    Exr,
+}
+
+impl ImageFormat {
+    /// The format implied by the extension of path. A .ppm file is binary.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "ppm" => Some(ImageFormat::Ppm),
+            "hdr" => Some(ImageFormat::Hdr),
+            "exr" => Some(ImageFormat::Exr),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum OutputError {
+    UnknownFormat(PathBuf),
+    Io(io::Error),
+    Image(ImageError),
+    Exr(exr::error::Error),
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputError::UnknownFormat(path) => write!(
+                f,
+                "can't tell the image format of {}, use .png, .ppm, .hdr or .exr",
+                path.display(),
+            ),
+            OutputError::Io(e) => write!(f, "{}", e),
+            OutputError::Image(e) => write!(f, "{}", e),
+            OutputError::Exr(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for OutputError {}
+
+impl From<io::Error> for OutputError {
+    fn from(e: io::Error) -> Self {
+        OutputError::Io(e)
+    }
+}
+
+impl From<ImageError> for OutputError {
+    fn from(e: ImageError) -> Self {
+        OutputError::Image(e)
+    }
+}
+
+impl From<exr::error::Error> for OutputError {
+    fn from(e: exr::error::Error) -> Self {
+        OutputError::Exr(e)
+    }
+}
+
+/// Writes the framebuffer to path, in the given format or else the one implied
+/// by the extension.
+pub fn write_image(framebuffer: &Framebuffer, path: &Path, format: Option<ImageFormat>) -> Result<(), OutputError> {
+    let format = match format.or_else(|| ImageFormat::from_path(path)) {
+        Some(format) => format,
+        None => return Err(OutputError::UnknownFormat(path.to_path_buf())),
+    };
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_image_to(framebuffer, &mut writer, format)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Writes the layers to path as a single OpenEXR file.
+pub fn write_exr_layers(layers: &[ExrLayer], path: &Path) -> Result<(), OutputError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_exr(layers, &mut writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+pub fn write_image_to(
+    framebuffer: &Framebuffer,
+    writer: &mut (impl Write + Seek),
+    format: ImageFormat,
+) -> Result<(), OutputError> {
+    match format {
+        ImageFormat::Png => write_png(framebuffer, writer),
+        ImageFormat::Ppm => Ok(write_ppm(framebuffer, writer)?),
+        ImageFormat::PpmAscii => Ok(write_ppm_ascii(framebuffer, writer)?),
+        ImageFormat::Hdr => write_hdr(framebuffer, writer),
+        ImageFormat::Exr => write_exr(&[ExrLayer::rgb("beauty", framebuffer)], writer),
+    }
+}
+
+pub fn write_png(framebuffer: &Framebuffer, writer: &mut impl Write) -> Result<(), OutputError> {
+    PngEncoder::new(writer).encode(
+        &framebuffer.to_rgb8(),
+        framebuffer.width as u32,
+        framebuffer.height as u32,
+        ColorType::Rgb8,
+    )?;
+
+    Ok(())
+}
+
+pub fn write_ppm(framebuffer: &Framebuffer, writer: &mut impl Write) -> io::Result<()> {
+    writer.write_all(format!("P6\n{} {}\n255\n", framebuffer.width, framebuffer.height).as_bytes())?;
+    writer.write_all(&framebuffer.to_rgb8())
+}
+
+pub fn write_ppm_ascii(framebuffer: &Framebuffer, writer: &mut impl Write) -> io::Result<()> {
+    writer.write_all(format!("P3\n{} {}\n255\n", framebuffer.width, framebuffer.height).as_bytes())?;
+
+    for rgb in framebuffer.to_rgb8().chunks(3) {
+        writer.write_all(format!("{} {} {}\n", rgb[0], rgb[1], rgb[2]).as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Writes the linear colours as Radiance RGBE. Negative values can't be stored
+/// so are clamped to zero.
+pub fn write_hdr(framebuffer: &Framebuffer, writer: &mut impl Write) -> Result<(), OutputError> {
+    let pixels: Vec<Rgb<f32>> = framebuffer.pixels
+        .iter()
+        .map(|c| Rgb([c.r.max(0.0), c.g.max(0.0), c.b.max(0.0)]))
+        .collect();
+
+    HdrEncoder::new(writer).encode(&pixels, framebuffer.width, framebuffer.height)?;
+
+    Ok(())
+}
+
+/// How a framebuffer is stored in an EXR layer.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ExrChannels {
+    /// R, G and B channels.
+    Rgb,
+    /// A single Z channel taken from the red component, for depth.
+    Z,
+}
+
+/// A framebuffer written as one named layer of an OpenEXR file.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ExrLayer<'a> {
+    pub name: &'a str,
+    pub framebuffer: &'a Framebuffer,
+    pub channels: ExrChannels,
+}
+
+impl<'a> ExrLayer<'a> {
+    pub fn rgb(name: &'a str, framebuffer: &'a Framebuffer) -> Self {
+        ExrLayer {
+            name: name,
+            framebuffer: framebuffer,
+            channels: ExrChannels::Rgb,
+        }
+    }
+
+    pub fn z(name: &'a str, framebuffer: &'a Framebuffer) -> Self {
+        ExrLayer {
+            name: name,
+            framebuffer: framebuffer,
+            channels: ExrChannels::Z,
+        }
+    }
+}
+
+/// Writes each layer as a separate named part of one OpenEXR file, with 32-bit
+/// float channels and lossless compression. The layers must all be the same size.
+pub fn write_exr(layers: &[ExrLayer], writer: &mut (impl Write + Seek)) -> Result<(), OutputError> {
+    let (width, height) = match layers.first() {
+        Some(layer) => (layer.framebuffer.width, layer.framebuffer.height),
+        None => return Err(OutputError::Exr(exr::error::Error::Invalid("an EXR image needs at least one layer".into()))),
+    };
+
+    let mut exr_layers = Vec::with_capacity(layers.len());
+
+    for layer in layers {
+        let framebuffer = layer.framebuffer;
+        if (framebuffer.width, framebuffer.height) != (width, height) {
+            return Err(OutputError::Exr(exr::error::Error::Invalid("EXR layers must all be the same size".into())));
+        }
+
+        let channel = |name: &str, component: fn(&Colour) -> f32| AnyChannel::new(
+            name,
+            FlatSamples::F32(framebuffer.pixels.iter().map(component).collect()),
+        );
+
+        let channels = match layer.channels {
+            ExrChannels::Rgb => vec![
+                channel("R", |c| c.r),
+                channel("G", |c| c.g),
+                channel("B", |c| c.b),
+            ],
+            ExrChannels::Z => vec![channel("Z", |c| c.r)],
+        };
+
+        exr_layers.push(Layer::new(
+            Vec2(width, height),
+            LayerAttributes::named(layer.name),
+            Encoding::SMALL_LOSSLESS,
+            AnyChannels::sort(channels.into()),
+        ));
+    }
+
+    let image = Image::from_layers(
+        ImageAttributes::new(IntegerBounds::from_dimensions(Vec2(width, height))),
+        exr_layers,
+    );
+    image.write().to_buffered(writer)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::codecs::hdr::HdrDecoder;
+
+    use exr::prelude::{FlatSamples, ReadChannels, ReadLayers};
+
+    use super::*;
+
+    fn framebuffer() -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(2, 2);
+        framebuffer.set(0, 0, Colour::new(1.0, 0.0, 0.0));
+        framebuffer.set(1, 0, Colour::new(0.0, 0.25, 0.0));
+        framebuffer.set(0, 1, Colour::new(0.0, 0.0, 4.0));
+        framebuffer.set(1, 1, Colour::new(0.5, 2.0, 8.0));
+
+        framebuffer
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(ImageFormat::from_path(Path::new("a.png")), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_path(Path::new("a/b.PPM")), Some(ImageFormat::Ppm));
+        assert_eq!(ImageFormat::from_path(Path::new("c.hdr")), Some(ImageFormat::Hdr));
+        assert_eq!(ImageFormat::from_path(Path::new("c.exr")), Some(ImageFormat::Exr));
+        assert_eq!(ImageFormat::from_path(Path::new("d.jpg")), None);
+        assert_eq!(ImageFormat::from_path(Path::new("e")), None);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let mut bytes = Vec::new();
+        write_png(&framebuffer(), &mut bytes).unwrap();
+
+        let image = image::load_from_memory(&bytes).unwrap().into_rgb8();
+
+        assert_eq!(image.dimensions(), (2, 2));
+        assert_eq!(image.into_raw(), framebuffer().to_rgb8());
+    }
+
+    #[test]
+    fn ppm_binary() {
+        let mut bytes = Vec::new();
+        write_ppm(&framebuffer(), &mut bytes).unwrap();
+
+        let header = b"P6\n2 2\n255\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(&bytes[header.len()..], &framebuffer().to_rgb8()[..]);
+    }
+
+    #[test]
+    fn ppm_binary_round_trip() {
+        let mut bytes = Vec::new();
+        write_ppm(&framebuffer(), &mut bytes).unwrap();
+
+        let image = image::load_from_memory(&bytes).unwrap().into_rgb8();
+
+        assert_eq!(image.into_raw(), framebuffer().to_rgb8());
+    }
+
+    #[test]
+    fn ppm_ascii() {
+        let mut bytes = Vec::new();
+        write_ppm_ascii(&framebuffer(), &mut bytes).unwrap();
+
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            "P3\n2 2\n255\n255 0 0\n0 128 0\n0 0 255\n181 255 255\n",
+        );
+    }
+
+    #[test]
+    fn hdr_round_trip() {
+        let mut bytes = Vec::new();
+        write_hdr(&framebuffer(), &mut bytes).unwrap();
+
+        let decoder = HdrDecoder::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(decoder.metadata().width, 2);
+        assert_eq!(decoder.metadata().height, 2);
+
+        // RGBE shares one exponent between the channels, so small channels
+        // lose precision next to large ones.
+        let pixels = decoder.read_image_hdr().unwrap();
+        for (pixel, colour) in pixels.iter().zip(framebuffer().pixels.iter()) {
+            let tolerance = 0.01 * colour.r.max(colour.g).max(colour.b);
+            assert!((pixel[0] - colour.r).abs() <= tolerance);
+            assert!((pixel[1] - colour.g).abs() <= tolerance);
+            assert!((pixel[2] - colour.b).abs() <= tolerance);
+        }
+    }
+
+    /// A layer name with its channels' names and samples.
+    type ExrLayerData = (String, Vec<(String, Vec<f32>)>);
+
+    fn read_exr(bytes: Vec<u8>) -> Vec<ExrLayerData> {
+        let image = exr::prelude::read()
+            .no_deep_data()
+            .largest_resolution_level()
+            .all_channels()
+            .all_layers()
+            .all_attributes()
+            .from_buffered(Cursor::new(bytes))
+            .unwrap();
+
+        assert_eq!(image.attributes.display_window.size, Vec2(2, 2));
+
+        image.layer_data
+            .iter()
+            .map(|layer| {
+                let channels = layer.channel_data.list
+                    .iter()
+                    .map(|channel| match channel.sample_data {
+                        FlatSamples::F32(ref samples) => (channel.name.to_string(), samples.clone()),
+                        _ => panic!("expected f32 samples"),
+                    })
+                    .collect();
+
+                (layer.attributes.layer_name.as_ref().unwrap().to_string(), channels)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exr_round_trip() {
+        let mut bytes = Cursor::new(Vec::new());
+        write_image_to(&framebuffer(), &mut bytes, ImageFormat::Exr).unwrap();
+
+        let layers = read_exr(bytes.into_inner());
+
+        assert_eq!(
+            layers,
+            vec![(
+                String::from("beauty"),
+                vec![
+                    (String::from("B"), vec![0.0, 0.0, 4.0, 8.0]),
+                    (String::from("G"), vec![0.0, 0.25, 0.0, 2.0]),
+                    (String::from("R"), vec![1.0, 0.0, 0.0, 0.5]),
+                ],
+            )],
+        );
+    }
+
+    #[test]
+    fn exr_named_layers() {
+        let mut depth = Framebuffer::new(2, 2);
+        depth.set(1, 0, Colour::new(2.5, 0.0, 0.0));
+        depth.set(0, 1, Colour::new(f32::INFINITY, 0.0, 0.0));
+
+        let mut bytes = Cursor::new(Vec::new());
+        write_exr(
+            &[ExrLayer::rgb("beauty", &framebuffer()), ExrLayer::z("depth", &depth)],
+            &mut bytes,
+        ).unwrap();
+
+        let layers = read_exr(bytes.into_inner());
+
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].0, "beauty");
+        assert_eq!(layers[0].1.len(), 3);
+        assert_eq!(
+            layers[1],
+            (
+                String::from("depth"),
+                vec![(String::from("Z"), vec![0.0, 2.5, f32::INFINITY, 0.0])],
+            ),
+        );
+    }
+
+    #[test]
+    fn exr_layers_must_match() {
+        let mut bytes = Cursor::new(Vec::new());
+        let result = write_exr(
+            &[ExrLayer::rgb("beauty", &framebuffer()), ExrLayer::rgb("normal", &Framebuffer::new(3, 2))],
+            &mut bytes,
+        );
+
+        assert!(matches!(result, Err(OutputError::Exr(_))));
+    }
+
+    #[test]
+    fn write_image_unknown_extension() {
+        match write_image(&framebuffer(), Path::new("render.xyz"), None) {
+            Err(OutputError::UnknownFormat(path)) => assert_eq!(path, Path::new("render.xyz")),
+            _ => panic!("expected an unknown format error"),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+// Struct fields are always spelled out in full, as in `point: point`.
+#![allow(clippy::redundant_field_names)]
+
+pub mod geometry;
+pub mod graphics;
+pub mod loaders;
+pub mod render;
+pub mod scenes;
+pub mod util;
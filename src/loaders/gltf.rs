@@ -0,0 +1,616 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use base64::Engine;
+use gltf::camera::Projection;
+use gltf::image::Source as ImageSource;
+use gltf::mesh::Mode;
+use gltf::{Document, Gltf, Node, Primitive};
+
+use crate::geometry::matrix::Matrix4;
+use crate::geometry::point::Point3;
+use crate::geometry::transform::Transform;
+use crate::geometry::vector::Vector3;
+use crate::graphics::colour::Colour;
+use crate::render::camera::CameraSettings;
+use crate::render::element::Element;
+use crate::render::elements::bvh::Bvh;
+use crate::render::elements::instance::Instance;
+use crate::render::elements::triangle_mesh::TriangleMesh;
+use crate::render::lights::Lights;
+use crate::render::material::Material;
+use crate::render::materials::dielectric::Dielectric;
+use crate::render::materials::diffuse_light::DiffuseLight;
+use crate::render::materials::lambertian::Lambertian;
+use crate::render::materials::metal::Metal;
+use crate::render::texture::Texture;
+use crate::render::textures::image_texture::ImageTexture;
+use crate::render::textures::solid_colour::SolidColour;
+use crate::render::textures::vertex_colour::VertexColour;
+use crate::scenes::Scene;
+
+/// A problem with a glTF file. The line is only known for errors in the JSON.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GltfError {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl Error for GltfError {}
+
+/// The background of a scene made from a glTF file alone, which has no sky of
+/// its own.
+const BACKGROUND: Colour = Colour { r: 0.7, g: 0.8, b: 1.0 };
+
+/// The meshes of a glTF scene, placed by their nodes, and the cameras in it.
+///
+/// Each glTF mesh is built once, in its own space, and placed by an Instance for
+/// every node using it, so a mesh used many times has only one BVH.
+#[derive(Debug)]
+pub struct GltfModel {
+    pub elements: Vec<Arc<dyn Element>>,
+    pub cameras: Vec<CameraSettings>,
+}
+
+/// Reads the glTF file at path, either JSON with separate or embedded buffers or
+/// binary glb, and builds a mesh for each primitive of each node in the default
+/// scene. Primitives with no material get default_material, or the glTF default
+/// material if it is None.
+pub fn load_gltf(path: &Path, default_material: Option<Arc<dyn Material>>) -> Result<GltfModel, GltfError> {
+    let bytes = fs::read(path).map_err(|e| GltfError {
+        path: path.to_path_buf(),
+        line: None,
+        message: format!("couldn't read file: {}", e),
+    })?;
+
+    parse_gltf(&bytes, path, default_material)
+}
+
+/// A scene of everything in the glTF file at path, seen from its first camera,
+/// under a plain sky.
+pub fn load_gltf_scene(path: &Path) -> Result<Scene, GltfError> {
+    let model = load_gltf(path, None)?;
+
+    let camera = match model.cameras.first() {
+        Some(camera) => *camera,
+        None => return Err(GltfError {
+            path: path.to_path_buf(),
+            line: None,
+            message: "no perspective camera".to_string(),
+        }),
+    };
+
+    let world = Bvh::new(model.elements, camera.time_0, camera.time_1);
+
+    Ok(Scene {
+        world: Box::new(world),
+        lights: Lights::none(),
+        camera: camera,
+        background: BACKGROUND,
+    })
+}
+
+/// Builds the model in a glTF or glb file. Buffers and images outside the file
+/// are found relative to path, and may also be base64 data URIs.
+pub fn parse_gltf(bytes: &[u8], path: &Path, default_material: Option<Arc<dyn Material>>) -> Result<GltfModel, GltfError> {
+    let error = |line: Option<usize>, message: String| GltfError {
+        path: path.to_path_buf(),
+        line: line,
+        message: message,
+    };
+
+    let Gltf { document, blob } = Gltf::from_slice(bytes).map_err(|e| match e {
+        gltf::Error::Deserialize(e) => error(Some(e.line()), e.to_string()),
+        e => error(None, e.to_string()),
+    })?;
+
+    let mut loader = Loader {
+        path: path,
+        buffers: Vec::new(),
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        default_material: default_material,
+        meshes: HashMap::new(),
+        elements: Vec::new(),
+        cameras: Vec::new(),
+    };
+    loader.load_buffers(&document, blob)?;
+
+    let scene = match document.default_scene().or_else(|| document.scenes().next()) {
+        Some(scene) => scene,
+        None => return Err(error(None, "no scenes".to_string())),
+    };
+    for node in scene.nodes() {
+        loader.node(&node, &Transform::identity())?;
+    }
+
+    if loader.elements.is_empty() {
+        return Err(error(None, "no triangles in scene".to_string()));
+    }
+
+    Ok(GltfModel {
+        elements: loader.elements,
+        cameras: loader.cameras,
+    })
+}
+
+struct Loader<'a> {
+    path: &'a Path,
+    buffers: Vec<Vec<u8>>,
+    /// Textures by glTF image index.
+    textures: HashMap<usize, Arc<dyn Texture>>,
+    /// Materials by glTF material index, and whether they use vertex colours.
+    materials: HashMap<(Option<usize>, bool), Arc<dyn Material>>,
+    default_material: Option<Arc<dyn Material>>,
+    /// The elements of each primitive by glTF mesh index, in the mesh's space.
+    meshes: HashMap<usize, Vec<Arc<dyn Element>>>,
+    elements: Vec<Arc<dyn Element>>,
+    cameras: Vec<CameraSettings>,
+}
+
+impl<'a> Loader<'a> {
+    fn error(&self, message: String) -> GltfError {
+        GltfError {
+            path: self.path.to_path_buf(),
+            line: None,
+            message: message,
+        }
+    }
+
+    /// Reads the data at a URI, either a base64 data URI or a path relative to
+    /// the glTF file.
+    fn read_uri(&self, uri: &str) -> Result<Vec<u8>, GltfError> {
+        if uri.starts_with("data:") {
+            let data = match uri.find(";base64,") {
+                Some(start) => &uri[start + ";base64,".len()..],
+                None => return Err(self.error("data URIs must be base64".to_string())),
+            };
+
+            return base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .map_err(|e| self.error(format!("invalid data URI: {}", e)));
+        }
+
+        let file_path = self.path.parent().unwrap_or_else(|| Path::new("")).join(uri);
+        fs::read(&file_path).map_err(|e| self.error(format!("couldn't read {}: {}", file_path.display(), e)))
+    }
+
+    fn load_buffers(&mut self, document: &Document, mut blob: Option<Vec<u8>>) -> Result<(), GltfError> {
+        for buffer in document.buffers() {
+            let data = match buffer.source() {
+                gltf::buffer::Source::Bin => match blob.take() {
+                    Some(data) => data,
+                    None => return Err(self.error("buffer refers to a missing BIN chunk".to_string())),
+                },
+                gltf::buffer::Source::Uri(uri) => self.read_uri(uri)?,
+            };
+
+            if data.len() < buffer.length() {
+                return Err(self.error(format!(
+                    "buffer {} has {} bytes, not {}",
+                    buffer.index(),
+                    data.len(),
+                    buffer.length(),
+                )));
+            }
+
+            self.buffers.push(data);
+        }
+
+        Ok(())
+    }
+
+    /// Adds the meshes and cameras of the node and its children. The transform
+    /// places the node's parent in the world. Nodes scaled to nothing can't be
+    /// seen, so are left out with their children.
+    fn node(&mut self, node: &Node, parent: &Transform) -> Result<(), GltfError> {
+        // glTF matrices are column major, and ours are row major.
+        let local = match Transform::new(Matrix4::new(node.transform().matrix()).transpose()) {
+            Some(local) => local,
+            None => return Ok(()),
+        };
+        let transform = *parent * local;
+
+        if let Some(mesh) = node.mesh() {
+            for element in self.mesh(&mesh)? {
+                if transform == Transform::identity() {
+                    self.elements.push(element);
+                } else {
+                    self.elements.push(Arc::new(Instance::new(element, transform)));
+                }
+            }
+        }
+
+        if let Some(camera) = node.camera() {
+            // Orthographic cameras have no equivalent, so are left out.
+            if let Projection::Perspective(perspective) = camera.projection() {
+                // glTF cameras look down -z, with +y up.
+                let look_from = transform.point(&Point3::new(0.0, 0.0, 0.0));
+                let forward = transform.vector(&Vector3::new(0.0, 0.0, -1.0)).normalise();
+
+                let mut settings = CameraSettings::new(
+                    look_from,
+                    look_from + forward,
+                    perspective.yfov().to_degrees(),
+                );
+                settings.v_up = transform.vector(&Vector3::new(0.0, 1.0, 0.0)).normalise();
+                settings.aperture = 0.0;
+
+                self.cameras.push(settings);
+            }
+        }
+
+        for child in node.children() {
+            self.node(&child, &transform)?;
+        }
+
+        Ok(())
+    }
+
+    /// The elements of the mesh's primitives, building them on first use.
+    fn mesh(&mut self, mesh: &gltf::Mesh) -> Result<Vec<Arc<dyn Element>>, GltfError> {
+        if let Some(elements) = self.meshes.get(&mesh.index()) {
+            return Ok(elements.clone());
+        }
+
+        let mut elements = Vec::new();
+        for primitive in mesh.primitives() {
+            if let Some(element) = self.primitive(&primitive)? {
+                elements.push(element);
+            }
+        }
+        self.meshes.insert(mesh.index(), elements.clone());
+
+        Ok(elements)
+    }
+
+    /// A mesh of the primitive, in the space of its glTF mesh, or None for
+    /// primitives of points or lines.
+    fn primitive(&mut self, primitive: &Primitive) -> Result<Option<Arc<dyn Element>>, GltfError> {
+        let buffers = &self.buffers;
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| data.as_slice()));
+        let mesh_name = || format!("primitive {}", primitive.index());
+
+        let positions: Vec<Point3> = match reader.read_positions() {
+            Some(positions) => positions.map(|p| Point3::new(p[0], p[1], p[2])).collect(),
+            None => return Err(self.error(format!("{} has no positions", mesh_name()))),
+        };
+
+        let normals: Option<Vec<Vector3>> = reader
+            .read_normals()
+            .map(|normals| normals.map(|n| Vector3::new(n[0], n[1], n[2])).collect());
+
+        // glTF puts the texture origin top left, but ours is bottom left.
+        let material = primitive.material();
+        let uv_set = material.pbr_metallic_roughness().base_color_texture().map_or(0, |info| info.tex_coord());
+        let uvs: Option<Vec<(f32, f32)>> = reader
+            .read_tex_coords(uv_set)
+            .map(|uvs| uvs.into_f32().map(|uv| (uv[0], 1.0 - uv[1])).collect());
+
+        let colours: Option<Vec<Colour>> = reader
+            .read_colors(0)
+            .map(|colours| colours.into_rgb_f32().map(|c| Colour::new(c[0], c[1], c[2])).collect());
+
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        if let Some(i) = indices.iter().find(|i| **i >= positions.len()) {
+            return Err(self.error(format!("{} has index {} but {} vertices", mesh_name(), i, positions.len())));
+        }
+
+        let triangles: Vec<[usize; 3]> = match primitive.mode() {
+            Mode::Triangles => indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect(),
+            // Every other triangle of a strip is wound the other way.
+            Mode::TriangleStrip => (2..indices.len())
+                .map(|i| if i % 2 == 0 {
+                    [indices[i - 2], indices[i - 1], indices[i]]
+                } else {
+                    [indices[i - 1], indices[i - 2], indices[i]]
+                })
+                .collect(),
+            Mode::TriangleFan => (2..indices.len()).map(|i| [indices[0], indices[i - 1], indices[i]]).collect(),
+            _ => return Ok(None),
+        };
+        if triangles.is_empty() {
+            return Ok(None);
+        }
+
+        let material = self.material(&material, colours.is_some())?;
+        let mut mesh = TriangleMesh::new_with_attributes(positions, normals, uvs, triangles, material);
+        if let Some(colours) = colours {
+            mesh = mesh.with_colours(colours);
+        }
+
+        Ok(Some(Arc::new(mesh)))
+    }
+
+    /// Picks the closest of our materials to a metallic-roughness material. In
+    /// order:
+    ///  - any emission gives a DiffuseLight,
+    ///  - any transmission gives a Dielectric with the material's index,
+    ///  - mostly metallic gives a Metal, as fuzzy as the roughness,
+    ///  - and otherwise a Lambertian, with the base colour texture if there is one,
+    ///    or the vertex colours if vertex_colours.
+    ///
+    /// Only factors are used for metal and light. The base colour factor is
+    /// applied to vertex colours, but not to textures.
+    fn material(&mut self, material: &gltf::Material, vertex_colours: bool) -> Result<Arc<dyn Material>, GltfError> {
+        if material.index().is_none() {
+            if let Some(ref default_material) = self.default_material {
+                return Ok(default_material.clone());
+            }
+        }
+
+        let key = (material.index(), vertex_colours);
+        if let Some(material) = self.materials.get(&key) {
+            return Ok(material.clone());
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, _] = pbr.base_color_factor();
+        let base_colour = Colour::new(r, g, b);
+
+        let [r, g, b] = material.emissive_factor();
+        let strength = material.emissive_strength().unwrap_or(1.0);
+        let emission = Colour::new(r * strength, g * strength, b * strength);
+
+        let transmission = material.transmission().map_or(0.0, |t| t.transmission_factor());
+
+        let built: Arc<dyn Material> = if emission.r.max(emission.g).max(emission.b) > 0.0 {
+            Arc::new(DiffuseLight::new(Arc::new(SolidColour::new(emission))))
+        } else if transmission > 0.0 {
+            Arc::new(Dielectric::new(material.ior().unwrap_or(1.5)))
+        } else if pbr.metallic_factor() >= 0.5 {
+            Arc::new(Metal::new_with_fuzz(base_colour, pbr.roughness_factor()))
+        } else {
+            let texture: Arc<dyn Texture> = match pbr.base_color_texture() {
+                Some(info) => self.texture(&info.texture().source())?,
+                None if vertex_colours => Arc::new(VertexColour::new(base_colour)),
+                None => Arc::new(SolidColour::new(base_colour)),
+            };
+
+            Arc::new(Lambertian::new(texture))
+        };
+
+        self.materials.insert(key, built.clone());
+
+        Ok(built)
+    }
+
+    fn texture(&mut self, image: &gltf::Image) -> Result<Arc<dyn Texture>, GltfError> {
+        if let Some(texture) = self.textures.get(&image.index()) {
+            return Ok(texture.clone());
+        }
+
+        let data = match image.source() {
+            ImageSource::View { view, .. } => {
+                let buffer = &self.buffers[view.buffer().index()];
+                buffer[view.offset()..view.offset() + view.length()].to_vec()
+            },
+            ImageSource::Uri { uri, .. } => self.read_uri(uri)?,
+        };
+
+        let decoded = image::load_from_memory(&data)
+            .map_err(|e| self.error(format!("couldn't load image {}: {}", image.index(), e)))?;
+        let texture: Arc<dyn Texture> = Arc::new(ImageTexture::new(decoded.into_rgb8()));
+
+        self.textures.insert(image.index(), texture.clone());
+
+        Ok(texture)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::bounding_box::BoundingBox;
+    use crate::geometry::ray::Ray;
+    use crate::util::sampler::Sampler;
+
+    use super::*;
+
+    fn models() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes").join("models")
+    }
+
+    fn bounding_boxes(model: &GltfModel) -> Vec<BoundingBox> {
+        model.elements.iter().map(|e| e.bounding_box(0.0, 1.0).unwrap()).collect()
+    }
+
+    fn assert_near(a: &Point3, b: &Point3) {
+        assert!((*a - *b).length() < 1e-3, "{:?} is not near {:?}", a, b);
+    }
+
+    /// A single triangle with positions and indices in a data URI buffer.
+    fn triangle_gltf(node: &str, mode: u32) -> String {
+        let mut data = Vec::new();
+        for value in [0.0_f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].iter() {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        for index in [0_u16, 1, 2, 0].iter() {
+            data.extend_from_slice(&index.to_le_bytes());
+        }
+
+        format!(r#"{{
+  "asset": {{ "version": "2.0" }},
+  "scenes": [{{ "nodes": [0] }}],
+  "nodes": [{node}],
+  "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1, "mode": {mode} }}] }}],
+  "accessors": [
+    {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] }},
+    {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+  ],
+  "bufferViews": [
+    {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+    {{ "buffer": 0, "byteOffset": 36, "byteLength": 8 }}
+  ],
+  "buffers": [{{ "byteLength": 44, "uri": "data:application/octet-stream;base64,{data}" }}]
+}}"#,
+            node = node,
+            mode = mode,
+            data = base64::engine::general_purpose::STANDARD.encode(&data),
+        )
+    }
+
+    fn parse(source: &str) -> Result<GltfModel, GltfError> {
+        parse_gltf(source.as_bytes(), Path::new("test.gltf"), None)
+    }
+
+    #[test]
+    fn load_gltf_with_separate_buffer() {
+        let model = load_gltf(&models().join("gallery.gltf"), None).unwrap();
+
+        assert_eq!(model.elements.len(), 4);
+        assert_eq!(model.cameras.len(), 1);
+
+        // The glass cube is scaled by 0.8 and moved to (1, 0.5, 0) by its node and
+        // the stand above it.
+        let glass = bounding_boxes(&model)[2];
+        assert_near(&glass.min, &Point3::new(0.6, 0.1, -0.4));
+        assert_near(&glass.max, &Point3::new(1.4, 0.9, 0.4));
+
+        // The gold cube is turned 45 degrees about y.
+        let gold = bounding_boxes(&model)[1];
+        let half_diagonal = 0.5 * 2.0_f32.sqrt();
+        assert_near(&gold.min, &Point3::new(-1.0 - half_diagonal, 0.0, -half_diagonal));
+        assert_near(&gold.max, &Point3::new(-1.0 + half_diagonal, 1.0, half_diagonal));
+
+        let camera = model.cameras[0];
+        assert_eq!(camera.look_from, Point3::new(0.0, 2.0, 6.0));
+        assert!((camera.vertical_fov - 40.0).abs() < 1e-3);
+        assert_eq!(camera.aperture, 0.0);
+        // Tilted 15 degrees down.
+        let forward = camera.look_at - camera.look_from;
+        assert!((forward.y + 15.0_f32.to_radians().sin()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn load_glb_matches_gltf() {
+        let gltf = load_gltf(&models().join("gallery.gltf"), None).unwrap();
+        let glb = load_gltf(&models().join("gallery.glb"), None).unwrap();
+
+        assert_eq!(bounding_boxes(&glb), bounding_boxes(&gltf));
+        assert_eq!(glb.cameras, gltf.cameras);
+    }
+
+    #[test]
+    fn load_gltf_scene_uses_camera() {
+        let scene = load_gltf_scene(&models().join("gallery.glb")).unwrap();
+
+        assert_eq!(scene.camera.look_from, Point3::new(0.0, 2.0, 6.0));
+        assert_eq!(scene.background, BACKGROUND);
+    }
+
+    #[test]
+    fn parse_data_uri_buffer() {
+        let model = parse(&triangle_gltf(r#"{ "mesh": 0, "translation": [0, 0, 2] }"#, 4)).unwrap();
+
+        let bounding_box = bounding_boxes(&model)[0];
+        assert_near(&bounding_box.min, &Point3::new(0.0, 0.0, 2.0));
+        assert_near(&bounding_box.max, &Point3::new(1.0, 1.0, 2.0));
+        assert!(model.cameras.is_empty());
+    }
+
+    #[test]
+    fn nodes_share_meshes() {
+        let source = triangle_gltf(r#"{ "mesh": 0 }, { "mesh": 0, "translation": [0, 0, 2] }"#, 4)
+            .replace(r#""nodes": [0] }"#, r#""nodes": [0, 1] }"#);
+
+        let model = parse(&source).unwrap();
+
+        // The first node isn't moved, so needs no instance.
+        assert_eq!(model.elements.len(), 2);
+        let instance = model.elements[1].as_any().downcast_ref::<Instance>().unwrap();
+        assert!(Arc::ptr_eq(&instance.element, &model.elements[0]));
+        assert_near(&bounding_boxes(&model)[1].min, &Point3::new(0.0, 0.0, 2.0));
+    }
+
+    #[test]
+    fn mirrored_node_keeps_front_face() {
+        // Mirrored in x, the triangle is wound clockwise seen from +z, which glTF
+        // takes as its front.
+        let model = parse(&triangle_gltf(r#"{ "mesh": 0, "scale": [-1, 1, 1] }"#, 4)).unwrap();
+        let ray = Ray::new(Point3::new(-0.25, 0.25, 5.0), Vector3::new(0.0, 0.0, -1.0));
+
+        let hit = model.elements[0].hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+
+        assert_near(&hit.point, &Point3::new(-0.25, 0.25, 0.0));
+        assert!((hit.normal.z - 1.0).abs() < 1e-5, "{:?}", hit.normal);
+        assert!(hit.front_face);
+    }
+
+    #[test]
+    fn points_are_not_meshes() {
+        assert_eq!(
+            parse(&triangle_gltf(r#"{ "mesh": 0 }"#, 0)).unwrap_err().message,
+            "no triangles in scene",
+        );
+    }
+
+    #[test]
+    fn map_to_materials() {
+        let source = r#"{
+  "asset": { "version": "2.0" },
+  "materials": [
+    { "pbrMetallicRoughness": { "baseColorFactor": [0.5, 0.5, 0.5, 1.0], "metallicFactor": 0.0 } },
+    { "pbrMetallicRoughness": { "baseColorFactor": [0.9, 0.8, 0.7, 1.0], "roughnessFactor": 0.25 } },
+    { "emissiveFactor": [1.0, 1.0, 1.0], "extensions": { "KHR_materials_emissive_strength": { "emissiveStrength": 4.0 } } },
+    { "extensions": { "KHR_materials_transmission": { "transmissionFactor": 1.0 }, "KHR_materials_ior": { "ior": 1.33 } } }
+  ]
+}"#;
+        let gltf = Gltf::from_slice(source.as_bytes()).unwrap();
+        let mut loader = Loader {
+            path: Path::new("test.gltf"),
+            buffers: Vec::new(),
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            default_material: None,
+            meshes: HashMap::new(),
+            elements: Vec::new(),
+            cameras: Vec::new(),
+        };
+        let mut material = |index: usize, vertex_colours: bool| {
+            loader.material(&gltf.materials().nth(index).unwrap(), vertex_colours).unwrap()
+        };
+
+        let grey = Colour::new(0.5, 0.5, 0.5);
+        assert!(Material::eq(material(0, false).as_ref(), &Lambertian::new_with_colour(grey)));
+        assert!(Material::eq(material(0, true).as_ref(), &Lambertian::new(Arc::new(VertexColour::new(grey)))));
+        assert!(Material::eq(material(1, false).as_ref(), &Metal::new_with_fuzz(Colour::new(0.9, 0.8, 0.7), 0.25)));
+        assert!(
+            Material::eq(material(2, false).as_ref(), &DiffuseLight::new(Arc::new(SolidColour::new(Colour::new(4.0, 4.0, 4.0)))))
+        );
+        assert!(Material::eq(material(3, false).as_ref(), &Dielectric::new(1.33)));
+    }
+
+    #[test]
+    fn missing_buffer_is_an_error() {
+        let source = triangle_gltf(r#"{ "mesh": 0 }"#, 4);
+        let source = source.replace(&source[source.find("data:").unwrap()..source.rfind('"').unwrap()], "missing.bin");
+
+        let error = parse(&source).unwrap_err();
+
+        assert!(error.message.starts_with("couldn't read missing.bin"), "{}", error.message);
+    }
+
+    #[test]
+    fn invalid_json_reports_line() {
+        let error = parse("{\n  \"asset\": { \"version\": \"2.0\" },\n  \"nodes\": [,]\n}").unwrap_err();
+
+        assert_eq!(error.line, Some(3));
+    }
+}
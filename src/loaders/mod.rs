@@ -0,0 +1,6 @@
+pub mod gltf;
+pub mod mtl;
+pub mod obj;
+pub mod ply;
+pub mod scene_file;
+pub mod stl;
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::graphics::colour::Colour;
+use crate::render::material::Material;
+use crate::render::materials::dielectric::Dielectric;
+use crate::render::materials::diffuse_light::DiffuseLight;
+use crate::render::materials::lambertian::Lambertian;
+use crate::render::materials::metal::Metal;
+use crate::render::texture::Texture;
+use crate::render::textures::image_texture::ImageTexture;
+use crate::render::textures::solid_colour::SolidColour;
+
+use super::obj::ObjError;
+
+/// The statements of one newmtl block that we understand. Anything else in the
+/// file, such as Ka or illum, is ignored.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MtlMaterial {
+    pub name: String,
+    /// The line of the newmtl statement.
+    pub line: usize,
+    pub diffuse: Colour,
+    pub specular: Colour,
+    pub specular_exponent: f32,
+    pub index_of_refraction: f32,
+    pub emission: Colour,
+    pub dissolve: f32,
+    pub diffuse_map: Option<PathBuf>,
+}
+
+impl MtlMaterial {
+    fn new(name: &str, line: usize) -> Self {
+        MtlMaterial {
+            name: name.to_string(),
+            line: line,
+            diffuse: Colour::new(0.8, 0.8, 0.8),
+            specular: Colour::new(0.0, 0.0, 0.0),
+            specular_exponent: 0.0,
+            index_of_refraction: 1.5,
+            emission: Colour::new(0.0, 0.0, 0.0),
+            dissolve: 1.0,
+            diffuse_map: None,
+        }
+    }
+
+    /// Picks the closest of our materials. In order:
+    ///  - any emission, Ke, gives a DiffuseLight,
+    ///  - any transparency, d below 1, gives a Dielectric with index Ni,
+    ///  - specular, Ks, brighter than diffuse, Kd, gives a Metal, with fuzz
+    ///    falling as the exponent Ns rises,
+    ///  - and otherwise a Lambertian, textured by map_Kd if there is one.
+    ///
+    /// The path of map_Kd is relative to directory. Path is the MTL file, for errors.
+    pub fn material(&self, directory: &Path, path: &Path) -> Result<Arc<dyn Material>, ObjError> {
+        if brightest(&self.emission) > 0.0 {
+            return Ok(Arc::new(DiffuseLight::new(Arc::new(SolidColour::new(self.emission)))));
+        }
+
+        if self.dissolve < 1.0 {
+            return Ok(Arc::new(Dielectric::new(self.index_of_refraction)));
+        }
+
+        if self.diffuse_map.is_none() && brightest(&self.specular) > brightest(&self.diffuse) {
+            let fuzz = (2.0 / (self.specular_exponent.max(0.0) + 2.0)).sqrt();
+            return Ok(Arc::new(Metal::new_with_fuzz(self.specular, fuzz)));
+        }
+
+        let texture: Arc<dyn Texture> = match self.diffuse_map {
+            Some(ref map) => {
+                let image_path = directory.join(map);
+                let image = image::open(&image_path).map_err(|e| ObjError {
+                    path: path.to_path_buf(),
+                    line: Some(self.line),
+                    message: format!("couldn't load image {}: {}", image_path.display(), e),
+                })?;
+
+                Arc::new(ImageTexture::new(image.into_rgb8()))
+            },
+            None => Arc::new(SolidColour::new(self.diffuse)),
+        };
+
+        Ok(Arc::new(Lambertian::new(texture)))
+    }
+}
+
+/// Parses the materials of an MTL file, keyed by name. The path is only used for
+/// error messages.
+pub fn parse_mtl(source: &str, path: &Path) -> Result<HashMap<String, MtlMaterial>, ObjError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<MtlMaterial> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: String| ObjError {
+            path: path.to_path_buf(),
+            line: Some(line_number),
+            message: message,
+        };
+
+        let line = line.split('#').next().unwrap().trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if arguments.len() != 1 {
+                return Err(error("newmtl needs a single name".to_string()));
+            }
+            if let Some(material) = current.take() {
+                materials.insert(material.name.clone(), material);
+            }
+            current = Some(MtlMaterial::new(arguments[0], line_number));
+            continue;
+        }
+
+        let material = match current {
+            Some(ref mut material) => material,
+            None => return Err(error(format!("`{}` before any newmtl", keyword))),
+        };
+
+        match keyword {
+            "Kd" => material.diffuse = parse_colour(&arguments).map_err(error)?,
+            "Ks" => material.specular = parse_colour(&arguments).map_err(error)?,
+            "Ke" => material.emission = parse_colour(&arguments).map_err(error)?,
+            "Ns" => material.specular_exponent = parse_single(keyword, &arguments).map_err(error)?,
+            "Ni" => material.index_of_refraction = parse_single(keyword, &arguments).map_err(error)?,
+            "d" => material.dissolve = parse_single(keyword, &arguments).map_err(error)?,
+            "Tr" => material.dissolve = 1.0 - parse_single(keyword, &arguments).map_err(error)?,
+            "map_Kd" => match arguments.last() {
+                // Any options come before the file name.
+                Some(file) => material.diffuse_map = Some(PathBuf::from(file)),
+                None => return Err(error("map_Kd needs a file name".to_string())),
+            },
+            _ => (),
+        }
+    }
+
+    if let Some(material) = current {
+        materials.insert(material.name.clone(), material);
+    }
+
+    Ok(materials)
+}
+
+/// An r g b colour, or a single value for all three.
+fn parse_colour(arguments: &[&str]) -> Result<Colour, String> {
+    let values = arguments
+        .iter()
+        .map(|a| a.parse::<f32>().map_err(|_| format!("invalid number `{}`", a)))
+        .collect::<Result<Vec<f32>, String>>()?;
+
+    match values[..] {
+        [v] => Ok(Colour::new(v, v, v)),
+        [r, g, b] => Ok(Colour::new(r, g, b)),
+        _ => Err(format!("expected a colour, not `{}`", arguments.join(" "))),
+    }
+}
+
+fn parse_single(keyword: &str, arguments: &[&str]) -> Result<f32, String> {
+    match arguments {
+        [value] => value.parse().map_err(|_| format!("invalid number `{}`", value)),
+        _ => Err(format!("{} needs a single number", keyword)),
+    }
+}
+
+fn brightest(colour: &Colour) -> f32 {
+    colour.r.max(colour.g).max(colour.b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> HashMap<String, MtlMaterial> {
+        parse_mtl(source, Path::new("test.mtl")).unwrap()
+    }
+
+    fn material(source: &str, name: &str) -> Arc<dyn Material> {
+        parse(source)[name].material(Path::new(""), Path::new("test.mtl")).unwrap()
+    }
+
+    #[test]
+    fn parse_materials() {
+        let materials = parse("
+# Two materials
+newmtl red
+Kd 0.8 0.1 0.1
+Ka 0.0 0.0 0.0
+illum 2
+
+newmtl shiny
+Kd 0 0 0
+Ks 0.9
+Ns 250
+map_Kd -s 1 1 1 textures/shiny.png
+");
+
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials["red"].diffuse, Colour::new(0.8, 0.1, 0.1));
+        assert_eq!(materials["red"].line, 3);
+        assert_eq!(materials["shiny"].specular, Colour::new(0.9, 0.9, 0.9));
+        assert_eq!(materials["shiny"].specular_exponent, 250.0);
+        assert_eq!(materials["shiny"].diffuse_map, Some(PathBuf::from("textures/shiny.png")));
+    }
+
+    #[test]
+    fn map_to_materials() {
+        let source = "
+newmtl matte
+Kd 0.5 0.5 0.5
+
+newmtl mirror
+Kd 0.0 0.0 0.0
+Ks 0.9 0.9 0.9
+Ns 1000000
+
+newmtl glass
+Ni 1.33
+d 0.2
+
+newmtl lamp
+Ke 4 4 4
+";
+
+        assert!(Material::eq(material(source, "matte").as_ref(), &Lambertian::new_with_colour(Colour::new(0.5, 0.5, 0.5))));
+        assert!(Material::eq(material(source, "glass").as_ref(), &Dielectric::new(1.33)));
+        assert!(
+            Material::eq(material(source, "lamp").as_ref(), &DiffuseLight::new(Arc::new(SolidColour::new(Colour::new(4.0, 4.0, 4.0)))))
+        );
+
+        let mirror = material(source, "mirror");
+        let mirror = mirror.as_any().downcast_ref::<Metal>().unwrap();
+        assert!(mirror.fuzz < 0.01);
+    }
+
+    #[test]
+    fn statement_before_newmtl_is_an_error() {
+        assert_eq!(
+            parse_mtl("Kd 1 1 1\n", Path::new("test.mtl")),
+            Err(ObjError {
+                path: PathBuf::from("test.mtl"),
+                line: Some(1),
+                message: "`Kd` before any newmtl".to_string(),
+            }),
+        );
+    }
+
+    #[test]
+    fn bad_number_is_an_error() {
+        let error = parse_mtl("newmtl a\nKd 1 x 1\n", Path::new("test.mtl")).unwrap_err();
+
+        assert_eq!(error.line, Some(2));
+        assert_eq!(error.message, "invalid number `x`");
+    }
+
+    #[test]
+    fn missing_texture_is_an_error() {
+        let materials = parse("newmtl a\nmap_Kd missing.png\n");
+        let error = materials["a"].material(Path::new("nowhere"), Path::new("test.mtl")).err().unwrap();
+
+        assert_eq!(error.line, Some(1));
+        assert!(error.message.starts_with("couldn't load image"));
+    }
+}
@@ -0,0 +1,516 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::geometry::point::Point3;
+use crate::geometry::vector::Vector3;
+use crate::render::element::Element;
+use crate::render::elements::triangle_mesh::TriangleMesh;
+use crate::render::material::Material;
+
+use super::mtl::{parse_mtl, MtlMaterial};
+
+/// A problem with an OBJ or MTL file, with the line at fault when we know it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ObjError {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl Error for ObjError {}
+
+/// A corner of a face, as 0-based indices into the positions, texture
+/// coordinates and normals of the model.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct FaceVertex {
+    pub position: usize,
+    pub uv: Option<usize>,
+    pub normal: Option<usize>,
+}
+
+/// The faces of one group using one material, triangulated.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Option<String>,
+    /// The line of the usemtl statement, or of the first face if there is none.
+    pub line: usize,
+    pub triangles: Vec<[FaceVertex; 3]>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ObjModel {
+    pub positions: Vec<Point3>,
+    pub uvs: Vec<(f32, f32)>,
+    pub normals: Vec<Vector3>,
+    pub groups: Vec<ObjGroup>,
+    /// The mtllib files named, with the line naming each.
+    pub material_libraries: Vec<(PathBuf, usize)>,
+}
+
+impl ObjModel {
+    /// A mesh of the triangles of the group. The mesh has normals, or texture
+    /// coordinates, only if every corner of every triangle in the group does.
+    pub fn mesh(&self, group: &ObjGroup, material: Arc<dyn Material>) -> TriangleMesh {
+        let corners = || group.triangles.iter().flatten();
+        let has_uvs = corners().all(|v| v.uv.is_some());
+        let has_normals = corners().all(|v| v.normal.is_some());
+
+        // Corners with the same indices become one shared mesh vertex.
+        let mut indices: HashMap<FaceVertex, usize> = HashMap::new();
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        let mut normals = Vec::new();
+
+        let mut triangles = Vec::with_capacity(group.triangles.len());
+        for triangle in group.triangles.iter() {
+            let mut mesh_triangle = [0; 3];
+            for (corner, vertex) in triangle.iter().enumerate() {
+                mesh_triangle[corner] = *indices.entry(*vertex).or_insert_with(|| {
+                    positions.push(self.positions[vertex.position]);
+                    if has_uvs {
+                        uvs.push(self.uvs[vertex.uv.unwrap()]);
+                    }
+                    if has_normals {
+                        normals.push(self.normals[vertex.normal.unwrap()]);
+                    }
+                    positions.len() - 1
+                });
+            }
+            triangles.push(mesh_triangle);
+        }
+
+        TriangleMesh::new_with_attributes(
+            positions,
+            if has_normals { Some(normals) } else { None },
+            if has_uvs { Some(uvs) } else { None },
+            triangles,
+            material,
+        )
+    }
+}
+
+/// Reads the OBJ file at path, with the MTL files it names, and builds a mesh for
+/// each group and material. Faces with no usemtl get default_material, and it is
+/// an error if there are any and it is None.
+pub fn load_obj(path: &Path, default_material: Option<Arc<dyn Material>>) -> Result<Vec<Arc<dyn Element>>, ObjError> {
+    let model = parse_obj(&read(path)?, path)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut descriptions: HashMap<String, (MtlMaterial, PathBuf)> = HashMap::new();
+    for (library, line) in model.material_libraries.iter() {
+        let library_path = directory.join(library);
+        let source = fs::read_to_string(&library_path).map_err(|e| ObjError {
+            path: path.to_path_buf(),
+            line: Some(*line),
+            message: format!("couldn't read material library {}: {}", library_path.display(), e),
+        })?;
+
+        for (name, material) in parse_mtl(&source, &library_path)? {
+            descriptions.insert(name, (material, library_path.clone()));
+        }
+    }
+
+    let mut materials: HashMap<&str, Arc<dyn Material>> = HashMap::new();
+    let mut elements: Vec<Arc<dyn Element>> = Vec::new();
+
+    for group in model.groups.iter() {
+        let error = |message: String| ObjError {
+            path: path.to_path_buf(),
+            line: Some(group.line),
+            message: message,
+        };
+
+        let material = match group.material {
+            Some(ref name) => match materials.get(name.as_str()) {
+                Some(material) => material.clone(),
+                None => {
+                    let (description, library_path) = match descriptions.get(name) {
+                        Some(description) => description,
+                        None => return Err(error(format!("unknown material `{}`", name))),
+                    };
+                    let library_directory = library_path.parent().unwrap_or_else(|| Path::new(""));
+                    let material = description.material(library_directory, library_path)?;
+
+                    materials.insert(name, material.clone());
+                    material
+                },
+            },
+            None => match default_material {
+                Some(ref material) => material.clone(),
+                None => return Err(error(format!("faces in group `{}` have no material", group.name))),
+            },
+        };
+
+        elements.push(Arc::new(model.mesh(group, material)));
+    }
+
+    if elements.is_empty() {
+        return Err(ObjError {
+            path: path.to_path_buf(),
+            line: None,
+            message: "no faces".to_string(),
+        });
+    }
+
+    Ok(elements)
+}
+
+/// Parses OBJ source. We read vertices (v, vt, vn), faces (f), groups (g and o),
+/// usemtl and mtllib, and ignore other statements. Faces with more than three
+/// corners are split into a fan of triangles. The path is only used for errors.
+pub fn parse_obj(source: &str, path: &Path) -> Result<ObjModel, ObjError> {
+    let mut model = ObjModel {
+        positions: Vec::new(),
+        uvs: Vec::new(),
+        normals: Vec::new(),
+        groups: Vec::new(),
+        material_libraries: Vec::new(),
+    };
+
+    let mut group_name = "default".to_string();
+    let mut material: Option<(String, usize)> = None;
+    // Index into model.groups of each group and material seen so far.
+    let mut group_indices: HashMap<(String, Option<String>), usize> = HashMap::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: String| ObjError {
+            path: path.to_path_buf(),
+            line: Some(line_number),
+            message: message,
+        };
+
+        let line = line.split('#').next().unwrap().trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                // An optional w, or vertex colours after x y z, are ignored.
+                let [x, y, z] = parse_numbers::<3>(keyword, &arguments, 3).map_err(error)?;
+                model.positions.push(Point3::new(x, y, z));
+            },
+            "vt" => {
+                let [u, v] = parse_numbers::<2>(keyword, &arguments, 1).map_err(error)?;
+                model.uvs.push((u, v));
+            },
+            "vn" => {
+                let [x, y, z] = parse_numbers::<3>(keyword, &arguments, 3).map_err(error)?;
+                model.normals.push(Vector3::new(x, y, z));
+            },
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(error(format!("a face needs at least 3 vertices, not {}", arguments.len())));
+                }
+                let corners = arguments
+                    .iter()
+                    .map(|a| parse_face_vertex(a, &model))
+                    .collect::<Result<Vec<FaceVertex>, String>>()
+                    .map_err(error)?;
+
+                let key = (group_name.clone(), material.as_ref().map(|m| m.0.clone()));
+                let group_index = *group_indices.entry(key).or_insert_with(|| {
+                    model.groups.push(ObjGroup {
+                        name: group_name.clone(),
+                        material: material.as_ref().map(|m| m.0.clone()),
+                        line: material.as_ref().map_or(line_number, |m| m.1),
+                        triangles: Vec::new(),
+                    });
+                    model.groups.len() - 1
+                });
+
+                let group = &mut model.groups[group_index];
+                for i in 1..(corners.len() - 1) {
+                    group.triangles.push([corners[0], corners[i], corners[i + 1]]);
+                }
+            },
+            "g" | "o" => {
+                group_name = if arguments.is_empty() { "default".to_string() } else { arguments.join(" ") };
+            },
+            "usemtl" => match arguments[..] {
+                [name] => material = Some((name.to_string(), line_number)),
+                _ => return Err(error("usemtl needs a single name".to_string())),
+            },
+            "mtllib" => {
+                if arguments.is_empty() {
+                    return Err(error("mtllib needs a file name".to_string()));
+                }
+                for library in arguments {
+                    model.material_libraries.push((PathBuf::from(library), line_number));
+                }
+            },
+            _ => (),
+        }
+    }
+
+    Ok(model)
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|e| ObjError {
+        path: path.to_path_buf(),
+        line: None,
+        message: format!("couldn't read file: {}", e),
+    })
+}
+
+/// Parses the first N arguments as numbers, of which at least required must be
+/// present. Missing ones are zero.
+fn parse_numbers<const N: usize>(keyword: &str, arguments: &[&str], required: usize) -> Result<[f32; N], String> {
+    if arguments.len() < required {
+        return Err(format!("{} needs at least {} numbers", keyword, required));
+    }
+
+    let mut values = [0.0; N];
+    for (value, argument) in values.iter_mut().zip(arguments.iter()) {
+        *value = argument.parse().map_err(|_| format!("invalid number `{}`", argument))?;
+    }
+
+    Ok(values)
+}
+
+/// Parses v, v/vt, v//vn or v/vt/vn, where each index counts from 1, or back from
+/// the last one read if negative.
+fn parse_face_vertex(argument: &str, model: &ObjModel) -> Result<FaceVertex, String> {
+    let mut parts = argument.split('/');
+
+    let position = match parts.next() {
+        Some(index) => resolve_index(index, model.positions.len(), "vertex")?,
+        None => return Err(format!("invalid face vertex `{}`", argument)),
+    };
+    let uv = match parts.next() {
+        Some("") | None => None,
+        Some(index) => Some(resolve_index(index, model.uvs.len(), "texture coordinate")?),
+    };
+    let normal = match parts.next() {
+        Some("") | None => None,
+        Some(index) => Some(resolve_index(index, model.normals.len(), "normal")?),
+    };
+
+    if parts.next().is_some() {
+        return Err(format!("invalid face vertex `{}`", argument));
+    }
+
+    Ok(FaceVertex {
+        position: position,
+        uv: uv,
+        normal: normal,
+    })
+}
+
+fn resolve_index(index: &str, count: usize, what: &str) -> Result<usize, String> {
+    let value: i64 = index.parse().map_err(|_| format!("invalid {} index `{}`", what, index))?;
+
+    let resolved = if value > 0 {
+        value - 1
+    } else {
+        (count as i64) + value
+    };
+
+    if value == 0 || resolved < 0 || resolved >= (count as i64) {
+        return Err(format!("{} index {} out of range, there are {}", what, value, count));
+    }
+
+    Ok(resolved as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::normal::Normal3;
+    use crate::geometry::ray::Ray;
+    use crate::graphics::colour::Colour;
+    use crate::render::materials::lambertian::Lambertian;
+    use crate::util::sampler::Sampler;
+
+    use super::*;
+
+    fn parse(source: &str) -> ObjModel {
+        parse_obj(source, Path::new("test.obj")).unwrap()
+    }
+
+    fn parse_error(source: &str) -> ObjError {
+        parse_obj(source, Path::new("test.obj")).unwrap_err()
+    }
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new_with_colour(Colour::new(0.5, 0.5, 0.5)))
+    }
+
+    const QUAD: &str = "
+# A unit quad facing +z
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+g quad
+f 1/1/1 2/2/1 3/3/1 4/4/1
+";
+
+    #[test]
+    fn parse_quad() {
+        let model = parse(QUAD);
+
+        assert_eq!(model.positions.len(), 4);
+        assert_eq!(model.uvs[2], (1.0, 1.0));
+        assert_eq!(model.normals, vec![Vector3::new(0.0, 0.0, 1.0)]);
+        assert_eq!(model.groups.len(), 1);
+        assert_eq!(model.groups[0].name, "quad");
+        assert_eq!(model.groups[0].line, 13);
+
+        let corner = |i| FaceVertex { position: i, uv: Some(i), normal: Some(0) };
+        assert_eq!(
+            model.groups[0].triangles,
+            vec![
+                [corner(0), corner(1), corner(2)],
+                [corner(0), corner(2), corner(3)],
+            ],
+        );
+    }
+
+    #[test]
+    fn parse_face_formats() {
+        let model = parse("
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vn 0 0 1
+f 1 2 3
+f 1/1 2/1 3/1
+f 1//1 2//1 3//1
+f -3/-1/-1 -2/-1/-1 -1/-1/-1
+");
+
+        let triangles = &model.groups[0].triangles;
+        assert_eq!(triangles.len(), 4);
+        assert_eq!(triangles[0][2], FaceVertex { position: 2, uv: None, normal: None });
+        assert_eq!(triangles[1][2], FaceVertex { position: 2, uv: Some(0), normal: None });
+        assert_eq!(triangles[2][2], FaceVertex { position: 2, uv: None, normal: Some(0) });
+        assert_eq!(triangles[3][0], FaceVertex { position: 0, uv: Some(0), normal: Some(0) });
+    }
+
+    #[test]
+    fn groups_and_materials() {
+        let model = parse("
+mtllib a.mtl b.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+o first
+usemtl red
+f 1 2 3
+usemtl blue
+f 1 2 3
+g second
+f 1 2 3
+g first
+usemtl red
+f 3 2 1
+");
+
+        assert_eq!(
+            model.material_libraries,
+            vec![(PathBuf::from("a.mtl"), 2), (PathBuf::from("b.mtl"), 2)],
+        );
+        assert_eq!(
+            model.groups
+                .iter()
+                .map(|g| (g.name.as_str(), g.material.as_deref(), g.triangles.len()))
+                .collect::<Vec<_>>(),
+            vec![("first", Some("red"), 2), ("first", Some("blue"), 1), ("second", Some("blue"), 1)],
+        );
+    }
+
+    #[test]
+    fn index_out_of_range_is_an_error() {
+        assert_eq!(
+            parse_error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n"),
+            ObjError {
+                path: PathBuf::from("test.obj"),
+                line: Some(4),
+                message: "vertex index 4 out of range, there are 3".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        assert_eq!(parse_error("v 0 zero 0\n").message, "invalid number `zero`");
+        assert_eq!(parse_error("v 0 0\n").message, "v needs at least 3 numbers");
+        assert_eq!(parse_error("v 0 0 0\nv 1 0 0\nf 1 2\n").message, "a face needs at least 3 vertices, not 2");
+        assert_eq!(parse_error("v 0 0 0\nf 1 1 1/2/3/4\n").message, "texture coordinate index 2 out of range, there are 0");
+        assert_eq!(parse_error("v 0 0 0\nf 1 1 0\n").message, "vertex index 0 out of range, there are 1");
+    }
+
+    #[test]
+    fn mesh_shares_vertices() {
+        let model = parse(QUAD);
+        let mesh = model.mesh(&model.groups[0], material());
+
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.triangle_count(), 2);
+
+        let ray = Ray::new(Point3::new(0.75, 0.25, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let record = mesh.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+        assert_eq!((record.u, record.v), (0.75, 0.25));
+        assert_eq!(record.normal, Normal3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn load_example_model() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/models/lamp.obj");
+
+        let elements = load_obj(&path, None).unwrap();
+
+        // A shade and a bulb.
+        assert_eq!(elements.len(), 2);
+    }
+
+    #[test]
+    fn load_without_material_is_an_error() {
+        let directory = std::env::temp_dir().join("ray_tracer_obj_test");
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("no_material.obj");
+        fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+
+        assert_eq!(
+            load_obj(&path, None).err().unwrap().message,
+            "faces in group `default` have no material",
+        );
+        assert_eq!(load_obj(&path, Some(material())).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn obj_error_display() {
+        let error = ObjError {
+            path: PathBuf::from("models/a.obj"),
+            line: Some(12),
+            message: "unknown material `b`".to_string(),
+        };
+
+        assert_eq!(error.to_string(), "models/a.obj:12: unknown material `b`");
+    }
+}
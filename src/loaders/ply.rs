@@ -0,0 +1,651 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::SplitAsciiWhitespace;
+use std::sync::Arc;
+
+use crate::geometry::point::Point3;
+use crate::geometry::vector::Vector3;
+use crate::graphics::colour::Colour;
+use crate::render::elements::triangle_mesh::TriangleMesh;
+use crate::render::material::Material;
+
+/// A problem with a PLY file. The line is known for problems in the header and
+/// in ASCII bodies.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PlyError {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl Error for PlyError {}
+
+/// The geometry of a PLY file, with whichever vertex attributes it has.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PlyMesh {
+    pub positions: Vec<Point3>,
+    pub normals: Option<Vec<Vector3>>,
+    pub uvs: Option<Vec<(f32, f32)>>,
+    pub colours: Option<Vec<Colour>>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl PlyMesh {
+    pub fn mesh(self, material: Arc<dyn Material>) -> TriangleMesh {
+        let mesh = TriangleMesh::new_with_attributes(
+            self.positions,
+            self.normals,
+            self.uvs,
+            self.triangles,
+            material,
+        );
+
+        match self.colours {
+            Some(colours) => mesh.with_colours(colours),
+            None => mesh,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(Scalar::I8),
+            "uchar" | "uint8" => Some(Scalar::U8),
+            "short" | "int16" => Some(Scalar::I16),
+            "ushort" | "uint16" => Some(Scalar::U16),
+            "int" | "int32" => Some(Scalar::I32),
+            "uint" | "uint32" => Some(Scalar::U32),
+            "float" | "float32" => Some(Scalar::F32),
+            "double" | "float64" => Some(Scalar::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Property {
+    Scalar { name: String, scalar: Scalar },
+    List { name: String, count: Scalar, item: Scalar },
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar { name, .. } => name,
+            Property::List { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct ElementHeader {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Header<'a> {
+    format: Format,
+    elements: Vec<ElementHeader>,
+    /// Number of lines in the header, including end_header.
+    lines: usize,
+    body: &'a [u8],
+}
+
+/// Where the values of the body come from.
+enum Body<'a> {
+    Ascii {
+        lines: std::iter::Enumerate<std::str::Lines<'a>>,
+        tokens: SplitAsciiWhitespace<'a>,
+        line: usize,
+    },
+    Binary {
+        bytes: &'a [u8],
+        big_endian: bool,
+    },
+}
+
+impl<'a> Body<'a> {
+    /// Each element of an ASCII body is on its own line.
+    fn start_element(&mut self) -> Result<(), String> {
+        if let Body::Ascii { lines, tokens, line } = self {
+            if let Some(extra) = tokens.next() {
+                return Err(format!("unexpected `{}` at end of element", extra));
+            }
+            loop {
+                match lines.next() {
+                    Some((index, text)) if !text.trim().is_empty() => {
+                        *tokens = text.split_ascii_whitespace();
+                        *line = index;
+                        break;
+                    },
+                    Some(_) => continue,
+                    None => return Err("file ends before all elements are read".to_string()),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read(&mut self, scalar: Scalar) -> Result<f64, String> {
+        match self {
+            Body::Ascii { tokens, .. } => {
+                let token = tokens.next().ok_or_else(|| "too few values for element".to_string())?;
+                token.parse().map_err(|_| format!("invalid number `{}`", token))
+            },
+            Body::Binary { bytes, big_endian } => {
+                let size = scalar.size();
+                if bytes.len() < size {
+                    return Err("file ends before all elements are read".to_string());
+                }
+                let (value, rest) = bytes.split_at(size);
+                *bytes = rest;
+
+                let mut buffer = [0; 8];
+                buffer[..size].copy_from_slice(value);
+                if *big_endian {
+                    buffer[..size].reverse();
+                }
+
+                Ok(match scalar {
+                    Scalar::I8 => buffer[0] as i8 as f64,
+                    Scalar::U8 => buffer[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+                    Scalar::I32 => i32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+                    Scalar::U32 => u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+                    Scalar::F32 => f32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+                    Scalar::F64 => f64::from_le_bytes(buffer),
+                })
+            },
+        }
+    }
+
+    fn line(&self) -> Option<usize> {
+        match self {
+            Body::Ascii { line, .. } => Some(line + 1),
+            Body::Binary { .. } => None,
+        }
+    }
+}
+
+/// Reads the PLY file at path and builds a mesh with the material.
+pub fn load_ply(path: &Path, material: Arc<dyn Material>) -> Result<TriangleMesh, PlyError> {
+    let bytes = fs::read(path).map_err(|e| PlyError {
+        path: path.to_path_buf(),
+        line: None,
+        message: format!("couldn't read file: {}", e),
+    })?;
+
+    Ok(parse_ply(&bytes, path)?.mesh(material))
+}
+
+/// Parses an ASCII or binary PLY file. We read x, y and z, and when present nx,
+/// ny and nz, u and v (or s and t), and red, green and blue of the vertex
+/// element, and the vertex_indices (or vertex_index) list of the face element.
+/// Faces with more than three corners are split into a fan of triangles. Other
+/// elements and properties are skipped. The path is only used for errors.
+pub fn parse_ply(bytes: &[u8], path: &Path) -> Result<PlyMesh, PlyError> {
+    let error = |line: Option<usize>, message: String| PlyError {
+        path: path.to_path_buf(),
+        line: line,
+        message: message,
+    };
+
+    let header = parse_header(bytes).map_err(|(line, message)| error(Some(line), message))?;
+
+    let mut body = match header.format {
+        Format::Ascii => {
+            let text = std::str::from_utf8(header.body).map_err(|_| error(None, "ASCII body isn't text".to_string()))?;
+            Body::Ascii {
+                lines: text.lines().enumerate(),
+                tokens: "".split_ascii_whitespace(),
+                line: 0,
+            }
+        },
+        Format::BinaryLittleEndian => Body::Binary { bytes: header.body, big_endian: false },
+        Format::BinaryBigEndian => Body::Binary { bytes: header.body, big_endian: true },
+    };
+
+    let mut mesh = PlyMesh {
+        positions: Vec::new(),
+        normals: None,
+        uvs: None,
+        colours: None,
+        triangles: Vec::new(),
+    };
+    let mut vertex_count = None;
+
+    for element in header.elements.iter() {
+        let result = match element.name.as_str() {
+            "vertex" => {
+                vertex_count = Some(element.count);
+                read_vertices(element, &mut body, &mut mesh)
+            },
+            "face" => match vertex_count {
+                Some(count) => read_faces(element, &mut body, &mut mesh, count),
+                None => Err("faces come before vertices".to_string()),
+            },
+            _ => skip_element(element, &mut body),
+        };
+
+        // Body lines count from the end of the header.
+        result.map_err(|message| error(body.line().map(|line| line + header.lines), message))?;
+    }
+
+    if mesh.triangles.is_empty() {
+        return Err(error(None, "no faces".to_string()));
+    }
+
+    Ok(mesh)
+}
+
+/// Parses the header. Errors carry the line at fault.
+fn parse_header(bytes: &[u8]) -> Result<Header<'_>, (usize, String)> {
+    let mut format = None;
+    let mut elements: Vec<ElementHeader> = Vec::new();
+    let mut offset = 0;
+    let mut line_number = 0;
+
+    loop {
+        line_number += 1;
+
+        let end = match bytes[offset..].iter().position(|&b| b == b'\n') {
+            Some(end) => offset + end,
+            None => return Err((line_number, "header has no end_header".to_string())),
+        };
+        let line = std::str::from_utf8(&bytes[offset..end])
+            .map_err(|_| (line_number, "header isn't text".to_string()))?
+            .trim();
+        offset = end + 1;
+
+        let tokens: Vec<&str> = line.split_ascii_whitespace().collect();
+
+        if line_number == 1 {
+            if line != "ply" {
+                return Err((1, "not a PLY file".to_string()));
+            }
+            continue;
+        }
+
+        match tokens[..] {
+            ["format", name, "1.0"] => format = Some(match name {
+                "ascii" => Format::Ascii,
+                "binary_little_endian" => Format::BinaryLittleEndian,
+                "binary_big_endian" => Format::BinaryBigEndian,
+                _ => return Err((line_number, format!("unknown format `{}`", name))),
+            }),
+            ["element", name, count] => elements.push(ElementHeader {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| (line_number, format!("invalid element count `{}`", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", ..] => {
+                let property = parse_property(&tokens[1..]).map_err(|message| (line_number, message))?;
+                match elements.last_mut() {
+                    Some(element) => element.properties.push(property),
+                    None => return Err((line_number, "property before any element".to_string())),
+                }
+            },
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => (),
+            _ => return Err((line_number, format!("unexpected `{}` in header", line))),
+        }
+    }
+
+    match format {
+        Some(format) => Ok(Header {
+            format: format,
+            elements: elements,
+            lines: line_number,
+            body: &bytes[offset..],
+        }),
+        None => Err((line_number, "header has no format".to_string())),
+    }
+}
+
+fn parse_property(tokens: &[&str]) -> Result<Property, String> {
+    let scalar = |name: &str| Scalar::from_name(name).ok_or_else(|| format!("unknown property type `{}`", name));
+
+    match tokens[..] {
+        ["list", count, item, name] => Ok(Property::List {
+            name: name.to_string(),
+            count: scalar(count)?,
+            item: scalar(item)?,
+        }),
+        [kind, name] => Ok(Property::Scalar {
+            name: name.to_string(),
+            scalar: scalar(kind)?,
+        }),
+        _ => Err(format!("invalid property `{}`", tokens.join(" "))),
+    }
+}
+
+/// Reads every property of an element into the values and lists it has.
+fn read_element(element: &ElementHeader, body: &mut Body) -> Result<(Vec<f64>, Vec<Vec<f64>>), String> {
+    body.start_element()?;
+
+    let mut values = Vec::with_capacity(element.properties.len());
+    let mut lists = Vec::new();
+
+    for property in element.properties.iter() {
+        match *property {
+            Property::Scalar { scalar, .. } => values.push(body.read(scalar)?),
+            Property::List { count, item, .. } => {
+                let length = body.read(count)?;
+                if length < 0.0 || length.fract() != 0.0 {
+                    return Err(format!("invalid list length {}", length));
+                }
+                let list = (0..(length as usize)).map(|_| body.read(item)).collect::<Result<Vec<f64>, String>>()?;
+
+                values.push(0.0);
+                lists.push(list);
+            },
+        }
+    }
+
+    Ok((values, lists))
+}
+
+fn skip_element(element: &ElementHeader, body: &mut Body) -> Result<(), String> {
+    for _ in 0..element.count {
+        read_element(element, body)?;
+    }
+
+    Ok(())
+}
+
+fn read_vertices(element: &ElementHeader, body: &mut Body, mesh: &mut PlyMesh) -> Result<(), String> {
+    let index = |names: &[&str]| {
+        element.properties.iter().position(|p| names.contains(&p.name()) && matches!(p, Property::Scalar { .. }))
+    };
+    let all = |names: &[&[&str]]| names.iter().map(|n| index(n)).collect::<Option<Vec<usize>>>();
+
+    let position = match all(&[&["x"], &["y"], &["z"]]) {
+        Some(position) => position,
+        None => return Err("vertices need x, y and z".to_string()),
+    };
+    let normal = all(&[&["nx"], &["ny"], &["nz"]]);
+    let uv = all(&[&["u", "s", "texture_u"], &["v", "t", "texture_v"]]);
+    let colour = all(&[&["red", "r"], &["green", "g"], &["blue", "b"]]);
+
+    // Integer colours are 0 to the type's maximum, floating point ones 0 to 1.
+    let colour_scale = match colour {
+        Some(ref colour) => match element.properties[colour[0]] {
+            Property::Scalar { scalar: Scalar::U8, .. } => 1.0 / 255.0,
+            Property::Scalar { scalar: Scalar::U16, .. } => 1.0 / 65535.0,
+            _ => 1.0,
+        },
+        None => 1.0,
+    };
+
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut colours = Vec::new();
+
+    for _ in 0..element.count {
+        let (values, _) = read_element(element, body)?;
+        let value = |i: usize| values[i] as f32;
+
+        mesh.positions.push(Point3::new(value(position[0]), value(position[1]), value(position[2])));
+        if let Some(ref normal) = normal {
+            normals.push(Vector3::new(value(normal[0]), value(normal[1]), value(normal[2])));
+        }
+        if let Some(ref uv) = uv {
+            uvs.push((value(uv[0]), value(uv[1])));
+        }
+        if let Some(ref colour) = colour {
+            colours.push(Colour::new(value(colour[0]), value(colour[1]), value(colour[2])) * colour_scale);
+        }
+    }
+
+    mesh.normals = normal.map(|_| normals);
+    mesh.uvs = uv.map(|_| uvs);
+    mesh.colours = colour.map(|_| colours);
+
+    Ok(())
+}
+
+fn read_faces(element: &ElementHeader, body: &mut Body, mesh: &mut PlyMesh, vertex_count: usize) -> Result<(), String> {
+    let list_index = element.properties
+        .iter()
+        .filter(|p| matches!(p, Property::List { .. }))
+        .position(|p| p.name() == "vertex_indices" || p.name() == "vertex_index");
+    let list_index = match list_index {
+        Some(list_index) => list_index,
+        None => return Err("faces need a vertex_indices list".to_string()),
+    };
+
+    for _ in 0..element.count {
+        let (_, lists) = read_element(element, body)?;
+
+        let corners = lists[list_index]
+            .iter()
+            .map(|&i| if i >= 0.0 && (i as usize) < vertex_count {
+                Ok(i as usize)
+            } else {
+                Err(format!("vertex index {} out of range, there are {}", i, vertex_count))
+            })
+            .collect::<Result<Vec<usize>, String>>()?;
+
+        if corners.len() < 3 {
+            return Err(format!("a face needs at least 3 vertices, not {}", corners.len()));
+        }
+        for i in 1..(corners.len() - 1) {
+            mesh.triangles.push([corners[0], corners[i], corners[i + 1]]);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    fn parse(bytes: &[u8]) -> Result<PlyMesh, PlyError> {
+        parse_ply(bytes, Path::new("test.ply"))
+    }
+
+    fn expected() -> PlyMesh {
+        PlyMesh {
+            positions: vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            normals: None,
+            uvs: None,
+            colours: Some(vec![
+                Colour::new(1.0, 0.0, 0.0),
+                Colour::new(0.0, 1.0, 0.0),
+                Colour::new(0.0, 0.0, 1.0),
+                Colour::new(1.0, 1.0, 1.0),
+            ]),
+            triangles: vec![[0, 1, 2], [0, 2, 3]],
+        }
+    }
+
+    /// The expected quad in binary, with each value's bytes in the given order.
+    fn binary(format: &str, to_bytes: fn(f32) -> [u8; 4], int_to_bytes: fn(i32) -> [u8; 4]) -> Vec<u8> {
+        let mut bytes = format!("ply\nformat {} 1.0\n{}", format, HEADER).into_bytes();
+        let colours = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 255]];
+        for (position, colour) in expected().positions.iter().zip(colours.iter()) {
+            for value in [position.x, position.y, position.z].iter() {
+                bytes.extend_from_slice(&to_bytes(*value));
+            }
+            bytes.extend_from_slice(colour);
+        }
+        bytes.push(4);
+        for index in 0..4 {
+            bytes.extend_from_slice(&int_to_bytes(index));
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn parse_ascii() {
+        let source = format!("ply\nformat ascii 1.0\ncomment a quad\n{}\
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 3
+", HEADER);
+
+        assert_eq!(parse(source.as_bytes()), Ok(expected()));
+    }
+
+    #[test]
+    fn parse_binary_little_endian() {
+        let bytes = binary("binary_little_endian", f32::to_le_bytes, i32::to_le_bytes);
+
+        assert_eq!(parse(&bytes), Ok(expected()));
+    }
+
+    #[test]
+    fn parse_binary_big_endian() {
+        let bytes = binary("binary_big_endian", f32::to_be_bytes, i32::to_be_bytes);
+
+        assert_eq!(parse(&bytes), Ok(expected()));
+    }
+
+    #[test]
+    fn parse_normals_uvs_and_other_elements() {
+        let source = "ply
+format ascii 1.0
+element vertex 3
+property double x
+property double y
+property double z
+property float nx
+property float ny
+property float nz
+property float s
+property float t
+property float confidence
+element material 1
+property list uchar uchar name
+element face 1
+property uchar flags
+property list uchar uint vertex_index
+end_header
+0 0 0 0 0 1 0 0 0.5
+1 0 0 0 0 1 1 0 0.5
+0 1 0 0 0 1 0 1 0.5
+3 65 66 67
+7 3 0 1 2
+";
+
+        let mesh = parse(source.as_bytes()).unwrap();
+
+        assert_eq!(mesh.normals, Some(vec![Vector3::new(0.0, 0.0, 1.0); 3]));
+        assert_eq!(mesh.uvs, Some(vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]));
+        assert_eq!(mesh.colours, None);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn index_out_of_range_reports_line() {
+        let source = format!("ply\nformat ascii 1.0\n{}\
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 4
+", HEADER);
+
+        assert_eq!(
+            parse(source.as_bytes()),
+            Err(PlyError {
+                path: PathBuf::from("test.ply"),
+                line: Some(17),
+                message: "vertex index 4 out of range, there are 4".to_string(),
+            }),
+        );
+    }
+
+    #[test]
+    fn truncated_binary_is_an_error() {
+        let mut bytes = binary("binary_little_endian", f32::to_le_bytes, i32::to_le_bytes);
+        bytes.truncate(bytes.len() - 2);
+
+        assert_eq!(
+            parse(&bytes).unwrap_err().message,
+            "file ends before all elements are read",
+        );
+    }
+
+    #[test]
+    fn malformed_headers_are_errors() {
+        let error = |source: &str| parse(source.as_bytes()).unwrap_err();
+
+        assert_eq!(error("obj\n").message, "not a PLY file");
+        assert_eq!(error("ply\nformat ascii 1.0\nelement vertex 1\n").message, "header has no end_header");
+        assert_eq!(error("ply\nformat ascii 1.0\nproperty float x\nend_header\n").line, Some(3));
+        assert_eq!(
+            error("ply\nformat ascii 1.0\nelement vertex 1\nproperty quad x\nend_header\n").message,
+            "unknown property type `quad`",
+        );
+        assert_eq!(
+            error("ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nend_header\n0\n").message,
+            "vertices need x, y and z",
+        );
+    }
+}
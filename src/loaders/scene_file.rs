@@ -0,0 +1,1213 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::geometry::point::Point3;
+use crate::geometry::transform::Transform;
+use crate::geometry::vector::Vector3;
+use crate::graphics::colour::Colour;
+use crate::render::camera::CameraSettings;
+use crate::render::element::Element;
+use crate::render::elements::atmosphere::Atmosphere;
+use crate::render::elements::axis_aligned_box::AxisAlignedBox;
+use crate::render::elements::bvh::Bvh;
+use crate::render::elements::constant_medium::ConstantMedium;
+use crate::render::elements::instance::Instance;
+use crate::render::elements::moving_sphere::MovingSphere;
+use crate::render::elements::rect::{XyRect, XzRect, YzRect};
+use crate::render::elements::sphere::Sphere;
+use crate::render::lights::Lights;
+use crate::render::material::Material;
+use crate::render::materials::dielectric::Dielectric;
+use crate::render::materials::diffuse_light::DiffuseLight;
+use crate::render::materials::isotropic::Isotropic;
+use crate::render::materials::lambertian::Lambertian;
+use crate::render::materials::metal::Metal;
+use crate::render::texture::Texture;
+use crate::render::textures::checker::Checker;
+use crate::render::textures::image_texture::ImageTexture;
+use crate::render::textures::marble::Marble;
+use crate::render::textures::noise::Noise;
+use crate::render::textures::solid_colour::SolidColour;
+use crate::render::textures::vertex_colour::VertexColour;
+use crate::scenes::Scene;
+use crate::util::perlin::Perlin;
+use crate::util::sampler::Sampler;
+
+use super::gltf::load_gltf;
+use super::obj::load_obj;
+use super::ply::load_ply;
+use super::stl::load_stl;
+
+/// A problem with a scene file. The line is the line of the TOML table or value
+/// at fault, when we know it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SceneError {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    /// May be left out when a glTF element has a camera.
+    camera: Option<CameraDescription>,
+    #[serde(default)]
+    background: [f32; 3],
+    #[serde(default)]
+    textures: BTreeMap<String, Spanned<TextureDescription>>,
+    #[serde(default)]
+    materials: BTreeMap<String, Spanned<MaterialDescription>>,
+    #[serde(default)]
+    objects: BTreeMap<String, ObjectDescription>,
+    #[serde(default)]
+    elements: Vec<Spanned<ElementDescription>>,
+    atmosphere: Option<Spanned<AtmosphereDescription>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    look_from: [f32; 3],
+    look_at: [f32; 3],
+    vertical_fov: f32,
+    v_up: Option<[f32; 3]>,
+    aperture: Option<f32>,
+    focus_distance: Option<f32>,
+    time_0: Option<f32>,
+    time_1: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDescription {
+    Solid { colour: [f32; 3] },
+    Checker { odd: String, even: String },
+    Noise { scale: f32 },
+    Marble { scale: f32 },
+    Image { path: PathBuf },
+    /// The vertex colours of a mesh, or the fallback, grey by default, elsewhere.
+    VertexColour { fallback: Option<[f32; 3]> },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDescription {
+    Lambertian { albedo: Option<[f32; 3]>, texture: Option<String> },
+    Metal { albedo: [f32; 3], #[serde(default)] fuzz: f32 },
+    Dielectric { index_of_refraction: f32 },
+    DiffuseLight { colour: Option<[f32; 3]>, texture: Option<String> },
+    /// The phase function of a medium, scattering equally in every direction.
+    Isotropic { albedo: Option<[f32; 3]>, texture: Option<String> },
+}
+
+/// Fog of even density filling the space between the elements, scattering
+/// light with the named material, usually an isotropic one.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AtmosphereDescription {
+    density: f32,
+    material: String,
+}
+
+/// Elements built once into a BVH of their own, which instance elements can
+/// place any number of times.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjectDescription {
+    elements: Vec<Spanned<ElementDescription>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RotationDescription {
+    axis: [f32; 3],
+    degrees: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ElementDescription {
+    Sphere { centre: [f32; 3], radius: f32, material: String },
+    MovingSphere {
+        centre_0: [f32; 3],
+        centre_1: [f32; 3],
+        time_0: f32,
+        time_1: f32,
+        radius: f32,
+        material: String,
+    },
+    /// Rectangles in the plane where one coordinate is fixed, spanning a range of
+    /// each of the other two.
+    XyRect { x: [f32; 2], y: [f32; 2], z: f32, material: String },
+    XzRect { x: [f32; 2], z: [f32; 2], y: f32, material: String },
+    YzRect { y: [f32; 2], z: [f32; 2], x: f32, material: String },
+    /// A box with sides parallel to the axes.
+    Box { min: [f32; 3], max: [f32; 3], material: String },
+    /// A Wavefront OBJ model, relative to the scene file. The material is used
+    /// for faces the model's MTL files don't give one.
+    Obj { path: PathBuf, material: Option<String> },
+    /// A PLY mesh, relative to the scene file. Any vertex colours are available
+    /// to a vertex_colour texture.
+    Ply { path: PathBuf, material: String },
+    /// An STL mesh, relative to the scene file.
+    Stl { path: PathBuf, material: String },
+    /// A glTF or glb scene, relative to the scene file. The material is used
+    /// for primitives the file doesn't give one.
+    Gltf { path: PathBuf, material: Option<String> },
+    /// A named object, scaled, then turned about the axis, then moved.
+    Instance {
+        object: String,
+        scale: Option<[f32; 3]>,
+        rotate: Option<RotationDescription>,
+        translate: Option<[f32; 3]>,
+    },
+    /// Smoke or fog of even density filling the boundary, which must be convex,
+    /// and scattering light with the material, usually an isotropic one. The
+    /// boundary's own material is never seen.
+    Medium {
+        boundary: Box<ElementDescription>,
+        density: f32,
+        material: String,
+    },
+}
+
+/// Reads and builds the scene in the TOML file at path. Any textures loaded from
+/// images are found relative to the directory containing the scene file.
+pub fn load_scene(path: &Path, sampler: &mut Sampler) -> Result<Scene, SceneError> {
+    let source = fs::read_to_string(path).map_err(|e| SceneError {
+        path: path.to_path_buf(),
+        line: None,
+        message: format!("couldn't read scene file: {}", e),
+    })?;
+
+    parse_scene(&source, path, sampler)
+}
+
+/// Builds a scene from TOML source. The path is only used for error messages and
+/// to find image textures.
+///
+/// A scene file has a camera table, an optional background colour, tables of
+/// named textures and materials, and an array of elements. Materials refer to
+/// textures, and elements to materials, by name. The elements are put in a BVH.
+/// Without a camera table, the first camera of a glTF element is used.
+///
+/// A table of named objects holds groups of elements, each built into a BVH
+/// only once, however many instance elements place it. Objects may place other
+/// objects.
+///
+/// An atmosphere table fills the space between the elements with fog.
+pub fn parse_scene(source: &str, path: &Path, sampler: &mut Sampler) -> Result<Scene, SceneError> {
+    let description: SceneDescription = toml::from_str(source).map_err(|e| SceneError {
+        path: path.to_path_buf(),
+        line: e.span().map(|span| line_of(source, span.start)),
+        message: e.message().to_string(),
+    })?;
+
+    // Objects are built before we know whether a glTF element has the camera,
+    // and so which shutter times their BVHs must cover, but the defaults are
+    // those of a glTF camera.
+    let shutter = match &description.camera {
+        Some(camera_description) => {
+            let settings = camera_settings(camera_description);
+            (settings.time_0, settings.time_1)
+        },
+        None => (0.0, 1.0),
+    };
+
+    let mut loader = Loader {
+        path: path,
+        source: source,
+        texture_descriptions: &description.textures,
+        textures: HashMap::new(),
+        object_descriptions: &description.objects,
+        objects: HashMap::new(),
+        shutter: shutter,
+        cameras: Vec::new(),
+        sampler: sampler,
+    };
+
+    let mut materials: HashMap<&str, Arc<dyn Material>> = HashMap::new();
+    for (name, material_description) in description.materials.iter() {
+        materials.insert(name, loader.material(material_description)?);
+    }
+
+    let mut elements: Vec<Arc<dyn Element>> = Vec::new();
+    let mut lights: Vec<Arc<dyn Element>> = Vec::new();
+    for element_description in description.elements.iter() {
+        let span = element_description.span();
+        let new_elements = loader.elements(element_description.get_ref(), span, &materials, &mut Vec::new())?;
+        if is_light(element_description.get_ref(), &materials) {
+            lights.extend(new_elements.iter().cloned());
+        }
+        elements.extend(new_elements);
+    }
+
+    if elements.is_empty() {
+        return Err(loader.error(None, "scene has no elements".to_string()));
+    }
+
+    let camera = match (&description.camera, loader.cameras.first()) {
+        (Some(camera_description), _) => camera_settings(camera_description),
+        (None, Some(gltf_camera)) => *gltf_camera,
+        (None, None) => return Err(loader.error(
+            None,
+            "scene has no camera table, and no glTF element with a camera".to_string(),
+        )),
+    };
+
+    let mut world: Box<dyn Element> = Box::new(Bvh::new(elements, camera.time_0, camera.time_1));
+
+    if let Some(atmosphere) = &description.atmosphere {
+        let span = atmosphere.span();
+        let AtmosphereDescription { density, material } = atmosphere.get_ref();
+
+        let material = match materials.get(material.as_str()) {
+            Some(material) => material.clone(),
+            None => return Err(loader.error(Some(&span), format!("unknown material `{}`", material))),
+        };
+        if !(density.is_finite() && *density > 0.0) {
+            return Err(loader.error(Some(&span), format!("density must be positive, not {}", density)));
+        }
+
+        world = Box::new(Atmosphere::new(world, *density, material));
+    }
+
+    Ok(Scene {
+        world: world,
+        lights: Lights::new(lights),
+        camera: camera,
+        background: colour(description.background),
+    })
+}
+
+/// Whether the element is one that gives off light and can be sampled directly:
+/// a sphere or rectangle made of a diffuse light. Lights placed inside objects
+/// are only found by the rays that happen to hit them.
+#[allow(clippy::unnecessary_map_or)]
+fn is_light(description: &ElementDescription, materials: &HashMap<&str, Arc<dyn Material>>) -> bool {
+    let material = match description {
+        ElementDescription::Sphere { material, .. } |
+        ElementDescription::XyRect { material, .. } |
+        ElementDescription::XzRect { material, .. } |
+        ElementDescription::YzRect { material, .. } => material,
+        _ => return false,
+    };
+
+    materials.get(material.as_str()).map_or(false, |m| m.as_any().is::<DiffuseLight>())
+}
+
+struct Loader<'a> {
+    path: &'a Path,
+    source: &'a str,
+    texture_descriptions: &'a BTreeMap<String, Spanned<TextureDescription>>,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    object_descriptions: &'a BTreeMap<String, ObjectDescription>,
+    objects: HashMap<String, Arc<dyn Element>>,
+    /// The times the BVHs of objects cover.
+    shutter: (f32, f32),
+    /// The cameras of any glTF elements, in order.
+    cameras: Vec<CameraSettings>,
+    sampler: &'a mut Sampler,
+}
+
+impl<'a> Loader<'a> {
+    fn error(&self, span: Option<&Range<usize>>, message: String) -> SceneError {
+        SceneError {
+            path: self.path.to_path_buf(),
+            line: span.map(|span| line_of(self.source, span.start)),
+            message: message,
+        }
+    }
+
+    /// A path in the scene file, relative to the directory containing it.
+    fn relative_path(&self, path: &Path) -> PathBuf {
+        self.path.parent().unwrap_or_else(|| Path::new("")).join(path)
+    }
+
+    /// Looks up the named texture, building it and the textures it refers to on
+    /// first use. The span is where the reference was made, and visiting holds
+    /// the textures we are part way through building, to catch cycles.
+    fn texture(
+        &mut self,
+        name: &str,
+        span: &Range<usize>,
+        visiting: &mut Vec<String>,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        if let Some(texture) = self.textures.get(name) {
+            return Ok(texture.clone());
+        }
+
+        if visiting.iter().any(|v| v == name) {
+            return Err(self.error(
+                Some(span),
+                format!("texture `{}` refers to itself", name),
+            ));
+        }
+
+        let descriptions = self.texture_descriptions;
+        let description = match descriptions.get(name) {
+            Some(description) => description,
+            None => return Err(self.error(Some(span), format!("unknown texture `{}`", name))),
+        };
+        let texture_span = description.span();
+
+        visiting.push(name.to_string());
+
+        let texture: Arc<dyn Texture> = match description.get_ref() {
+            TextureDescription::Solid { colour: c } => Arc::new(SolidColour::new(colour(*c))),
+            TextureDescription::Checker { odd, even } => Arc::new(Checker::new(
+                self.texture(odd, &texture_span, visiting)?,
+                self.texture(even, &texture_span, visiting)?,
+            )),
+            TextureDescription::Noise { scale } => Arc::new(Noise::new(Perlin::new(self.sampler), *scale)),
+            TextureDescription::Marble { scale } => Arc::new(Marble::new(Perlin::new(self.sampler), *scale)),
+            TextureDescription::Image { path } => {
+                let image_path = self.relative_path(path);
+                let image = image::open(&image_path).map_err(|e| self.error(
+                    Some(&texture_span),
+                    format!("couldn't load image {}: {}", image_path.display(), e),
+                ))?;
+
+                Arc::new(ImageTexture::new(image.into_rgb8()))
+            },
+            TextureDescription::VertexColour { fallback } => {
+                Arc::new(VertexColour::new(colour(fallback.unwrap_or([0.5, 0.5, 0.5]))))
+            },
+        };
+
+        visiting.pop();
+        self.textures.insert(name.to_string(), texture.clone());
+
+        Ok(texture)
+    }
+
+    /// A texture given either by name or as a plain colour, but not both.
+    fn texture_or_colour(
+        &mut self,
+        texture: &Option<String>,
+        colour_value: &Option<[f32; 3]>,
+        span: &Range<usize>,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        match (texture, colour_value) {
+            (Some(name), None) => self.texture(name, span, &mut Vec::new()),
+            (None, Some(c)) => Ok(Arc::new(SolidColour::new(colour(*c)))),
+            _ => Err(self.error(
+                Some(span),
+                "material needs exactly one of a texture or a colour".to_string(),
+            )),
+        }
+    }
+
+    fn material(&mut self, description: &Spanned<MaterialDescription>) -> Result<Arc<dyn Material>, SceneError> {
+        let span = description.span();
+
+        let material: Arc<dyn Material> = match description.get_ref() {
+            MaterialDescription::Lambertian { albedo, texture } => {
+                Arc::new(Lambertian::new(self.texture_or_colour(texture, albedo, &span)?))
+            },
+            MaterialDescription::Metal { albedo, fuzz } => {
+                Arc::new(Metal::new_with_fuzz(colour(*albedo), *fuzz))
+            },
+            MaterialDescription::Dielectric { index_of_refraction } => {
+                Arc::new(Dielectric::new(*index_of_refraction))
+            },
+            MaterialDescription::DiffuseLight { colour: c, texture } => {
+                Arc::new(DiffuseLight::new(self.texture_or_colour(texture, c, &span)?))
+            },
+            MaterialDescription::Isotropic { albedo, texture } => {
+                Arc::new(Isotropic::new(self.texture_or_colour(texture, albedo, &span)?))
+            },
+        };
+
+        Ok(material)
+    }
+
+    /// Looks up the named object, building it and the objects it places on first
+    /// use. The span is where the reference was made, and visiting holds the
+    /// objects we are part way through building, to catch cycles.
+    fn object(
+        &mut self,
+        name: &str,
+        span: &Range<usize>,
+        materials: &HashMap<&str, Arc<dyn Material>>,
+        visiting: &mut Vec<String>,
+    ) -> Result<Arc<dyn Element>, SceneError> {
+        if let Some(object) = self.objects.get(name) {
+            return Ok(object.clone());
+        }
+
+        if visiting.iter().any(|v| v == name) {
+            return Err(self.error(
+                Some(span),
+                format!("object `{}` places itself", name),
+            ));
+        }
+
+        let descriptions = self.object_descriptions;
+        let description = match descriptions.get(name) {
+            Some(description) => description,
+            None => return Err(self.error(Some(span), format!("unknown object `{}`", name))),
+        };
+
+        visiting.push(name.to_string());
+
+        let mut elements: Vec<Arc<dyn Element>> = Vec::new();
+        for element_description in description.elements.iter() {
+            let element_span = element_description.span();
+            elements.extend(self.elements(element_description.get_ref(), element_span, materials, visiting)?);
+        }
+        if elements.is_empty() {
+            return Err(self.error(Some(span), format!("object `{}` has no elements", name)));
+        }
+        let object: Arc<dyn Element> = Arc::new(Bvh::new(elements, self.shutter.0, self.shutter.1));
+
+        visiting.pop();
+        self.objects.insert(name.to_string(), object.clone());
+
+        Ok(object)
+    }
+
+    /// The elements for one entry of an elements array. Most entries are a
+    /// single element, but a model gives one for each of its meshes. The span
+    /// is the entry's, or for a medium's boundary the medium's. Visiting holds
+    /// the objects we are part way through building.
+    fn elements(
+        &mut self,
+        description: &ElementDescription,
+        span: Range<usize>,
+        materials: &HashMap<&str, Arc<dyn Material>>,
+        visiting: &mut Vec<String>,
+    ) -> Result<Vec<Arc<dyn Element>>, SceneError> {
+        let lookup_material = |name: &str| match materials.get(name) {
+            Some(material) => Ok(material.clone()),
+            None => Err(self.error(Some(&span), format!("unknown material `{}`", name))),
+        };
+        let check_radius = |radius: f32| if radius > 0.0 {
+            Ok(radius)
+        } else {
+            Err(self.error(Some(&span), format!("radius must be positive, not {}", radius)))
+        };
+        let check_range = |name: &str, range: [f32; 2]| if range[0] < range[1] {
+            Ok(range)
+        } else {
+            Err(self.error(Some(&span), format!("{} must run from low to high, not {:?}", name, range)))
+        };
+
+        let element: Arc<dyn Element> = match description {
+            ElementDescription::Sphere { centre, radius, material } => Arc::new(Sphere::new(
+                point(*centre),
+                check_radius(*radius)?,
+                lookup_material(material)?,
+            )),
+            ElementDescription::MovingSphere { centre_0, centre_1, time_0, time_1, radius, material } => {
+                Arc::new(MovingSphere::new(
+                    point(*centre_0),
+                    point(*centre_1),
+                    *time_0,
+                    *time_1,
+                    check_radius(*radius)?,
+                    lookup_material(material)?,
+                ))
+            },
+            ElementDescription::XyRect { x, y, z, material } => {
+                let (x, y) = (check_range("x", *x)?, check_range("y", *y)?);
+                Arc::new(XyRect::new(x[0], x[1], y[0], y[1], *z, lookup_material(material)?))
+            },
+            ElementDescription::XzRect { x, z, y, material } => {
+                let (x, z) = (check_range("x", *x)?, check_range("z", *z)?);
+                Arc::new(XzRect::new(x[0], x[1], z[0], z[1], *y, lookup_material(material)?))
+            },
+            ElementDescription::YzRect { y, z, x, material } => {
+                let (y, z) = (check_range("y", *y)?, check_range("z", *z)?);
+                Arc::new(YzRect::new(y[0], y[1], z[0], z[1], *x, lookup_material(material)?))
+            },
+            ElementDescription::Box { min, max, material } => {
+                for (axis, i) in ["x", "y", "z"].iter().zip(0..3) {
+                    check_range(axis, [min[i], max[i]])?;
+                }
+                Arc::new(AxisAlignedBox::new(point(*min), point(*max), lookup_material(material)?))
+            },
+            ElementDescription::Obj { path, material } => {
+                let default_material = match material {
+                    Some(name) => Some(lookup_material(name)?),
+                    None => None,
+                };
+
+                return load_obj(&self.relative_path(path), default_material)
+                    .map_err(|e| self.error(Some(&span), e.to_string()));
+            },
+            ElementDescription::Ply { path, material } => Arc::new(
+                load_ply(&self.relative_path(path), lookup_material(material)?)
+                    .map_err(|e| self.error(Some(&span), e.to_string()))?
+            ),
+            ElementDescription::Stl { path, material } => Arc::new(
+                load_stl(&self.relative_path(path), lookup_material(material)?)
+                    .map_err(|e| self.error(Some(&span), e.to_string()))?
+            ),
+            ElementDescription::Gltf { path, material } => {
+                let default_material = match material {
+                    Some(name) => Some(lookup_material(name)?),
+                    None => None,
+                };
+
+                let model = load_gltf(&self.relative_path(path), default_material)
+                    .map_err(|e| self.error(Some(&span), e.to_string()))?;
+                self.cameras.extend(model.cameras);
+
+                return Ok(model.elements);
+            },
+            ElementDescription::Instance { object, scale, rotate, translate } => {
+                let mut transform = Transform::identity();
+                if let Some(scale) = scale {
+                    if scale.contains(&0.0) {
+                        return Err(self.error(Some(&span), format!("scale must not be zero, not {:?}", scale)));
+                    }
+                    transform = Transform::scale(scale[0], scale[1], scale[2]);
+                }
+                if let Some(rotate) = rotate {
+                    if rotate.axis == [0.0, 0.0, 0.0] {
+                        return Err(self.error(Some(&span), "rotation axis must not be zero".to_string()));
+                    }
+                    transform = Transform::rotate(vector(rotate.axis), rotate.degrees) * transform;
+                }
+                if let Some(translate) = translate {
+                    transform = Transform::translate(vector(*translate)) * transform;
+                }
+
+                Arc::new(Instance::new(self.object(object, &span, materials, visiting)?, transform))
+            },
+            ElementDescription::Medium { boundary, density, material } => {
+                let phase_function = lookup_material(material)?;
+                if !(density.is_finite() && *density > 0.0) {
+                    return Err(self.error(Some(&span), format!("density must be positive, not {}", density)));
+                }
+
+                let mut boundaries = self.elements(boundary, span.clone(), materials, visiting)?;
+                let boundary: Arc<dyn Element> = match boundaries.len() {
+                    0 => return Err(self.error(Some(&span), "medium boundary has no elements".to_string())),
+                    1 => boundaries.pop().unwrap(),
+                    _ => Arc::new(Bvh::new(boundaries, self.shutter.0, self.shutter.1)),
+                };
+
+                Arc::new(ConstantMedium::new(boundary, *density, phase_function))
+            },
+        };
+
+        Ok(vec![element])
+    }
+}
+
+fn camera_settings(description: &CameraDescription) -> CameraSettings {
+    let mut settings = CameraSettings::new(
+        point(description.look_from),
+        point(description.look_at),
+        description.vertical_fov,
+    );
+
+    if let Some(v_up) = description.v_up {
+        settings.v_up = vector(v_up);
+    }
+    if let Some(aperture) = description.aperture {
+        settings.aperture = aperture;
+    }
+    if let Some(focus_distance) = description.focus_distance {
+        settings.focus_distance = focus_distance;
+    }
+    if let Some(time_0) = description.time_0 {
+        settings.time_0 = time_0;
+    }
+    if let Some(time_1) = description.time_1 {
+        settings.time_1 = time_1;
+    }
+
+    settings
+}
+
+fn point(xyz: [f32; 3]) -> Point3 {
+    Point3::new(xyz[0], xyz[1], xyz[2])
+}
+
+fn vector(xyz: [f32; 3]) -> Vector3 {
+    Vector3::new(xyz[0], xyz[1], xyz[2])
+}
+
+fn colour(rgb: [f32; 3]) -> Colour {
+    Colour::new(rgb[0], rgb[1], rgb[2])
+}
+
+/// The 1-based line containing the byte offset.
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::bounding_box::BoundingBox;
+    use crate::render::elements::triangle::BOUNDING_BOX_PADDING;
+
+    use super::*;
+
+    const CAMERA: &str = "
+[camera]
+look_from = [13.0, 2.0, 3.0]
+look_at = [0.0, 0.0, 0.0]
+vertical_fov = 20.0
+";
+
+    fn parse(source: &str) -> Result<Scene, SceneError> {
+        parse_scene(source, Path::new("test.toml"), &mut Sampler::new(0))
+    }
+
+    fn parse_error(source: &str) -> SceneError {
+        match parse(source) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn parse_simple_scene() {
+        let source = format!("background = [0.7, 0.8, 1.0]
+{}
+[textures.dark]
+type = \"solid\"
+colour = [0.1, 0.1, 0.1]
+
+[textures.light]
+type = \"solid\"
+colour = [0.9, 0.9, 0.9]
+
+[textures.checker]
+type = \"checker\"
+odd = \"dark\"
+even = \"light\"
+
+[materials.ground]
+type = \"lambertian\"
+texture = \"checker\"
+
+[materials.glass]
+type = \"dielectric\"
+index_of_refraction = 1.5
+
+[[elements]]
+type = \"sphere\"
+centre = [0.0, -1000.0, 0.0]
+radius = 1000.0
+material = \"ground\"
+
+[[elements]]
+type = \"sphere\"
+centre = [0.0, 1.0, 0.0]
+radius = 1.0
+material = \"glass\"
+", CAMERA);
+
+        let scene = parse(&source).unwrap();
+
+        assert_eq!(scene.background, Colour::new(0.7, 0.8, 1.0));
+        assert_eq!(
+            scene.camera,
+            CameraSettings::new(
+                Point3::new(13.0, 2.0, 3.0),
+                Point3::new(0.0, 0.0, 0.0),
+                20.0,
+            ),
+        );
+        assert_eq!(
+            scene.world.bounding_box(0.0, 1.0),
+            Some(BoundingBox::new(
+                Point3::new(-1000.0, -2000.0, -1000.0),
+                Point3::new(1000.0, 2.0, 1000.0),
+            )),
+        );
+    }
+
+    #[test]
+    fn unknown_material_reports_line() {
+        let source = format!("{}
+[materials.red]
+type = \"lambertian\"
+albedo = [1.0, 0.0, 0.0]
+
+[[elements]]
+type = \"sphere\"
+centre = [0.0, 0.0, 0.0]
+radius = 1.0
+material = \"blue\"
+", CAMERA);
+
+        assert_eq!(
+            parse_error(&source),
+            SceneError {
+                path: PathBuf::from("test.toml"),
+                line: Some(11),
+                message: "unknown material `blue`".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn unknown_type_reports_line() {
+        let source = format!("{}
+[materials.red]
+type = \"lambertain\"
+albedo = [1.0, 0.0, 0.0]
+", CAMERA);
+
+        let error = parse_error(&source);
+
+        assert_eq!(error.line, Some(8));
+        assert!(error.message.contains("unknown variant `lambertain`"));
+    }
+
+    #[test]
+    fn texture_cycle_is_an_error() {
+        let source = format!("{}
+[textures.a]
+type = \"checker\"
+odd = \"b\"
+even = \"b\"
+
+[textures.b]
+type = \"checker\"
+odd = \"a\"
+even = \"a\"
+
+[materials.m]
+type = \"lambertian\"
+texture = \"a\"
+", CAMERA);
+
+        assert_eq!(
+            parse_error(&source).message,
+            "texture `a` refers to itself",
+        );
+    }
+
+    #[test]
+    fn material_needs_texture_or_colour() {
+        let source = format!("{}
+[materials.m]
+type = \"lambertian\"
+", CAMERA);
+
+        assert_eq!(
+            parse_error(&source).message,
+            "material needs exactly one of a texture or a colour",
+        );
+    }
+
+    #[test]
+    fn missing_camera_is_an_error() {
+        let source = "
+[materials.red]
+type = \"lambertian\"
+albedo = [1.0, 0.0, 0.0]
+
+[[elements]]
+type = \"sphere\"
+centre = [0.0, 0.0, 0.0]
+radius = 1.0
+material = \"red\"
+";
+
+        assert_eq!(
+            parse_error(source).message,
+            "scene has no camera table, and no glTF element with a camera",
+        );
+    }
+
+    #[test]
+    fn camera_from_gltf() {
+        let source = "
+[[elements]]
+type = \"gltf\"
+path = \"models/gallery.glb\"
+";
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes").join("gallery.toml");
+
+        let scene = parse_scene(source, &path, &mut Sampler::new(0)).unwrap();
+
+        assert_eq!(scene.camera.look_from, Point3::new(0.0, 2.0, 6.0));
+    }
+
+    #[test]
+    fn empty_scene_is_an_error() {
+        assert_eq!(
+            parse_error(CAMERA).message,
+            "scene has no elements",
+        );
+    }
+
+    #[test]
+    fn parse_rects_and_boxes() {
+        let source = format!("{}
+[materials.white]
+type = \"lambertian\"
+albedo = [0.7, 0.7, 0.7]
+
+[[elements]]
+type = \"xz_rect\"
+x = [-5.0, 5.0]
+z = [-5.0, 5.0]
+y = 0.0
+material = \"white\"
+
+[[elements]]
+type = \"box\"
+min = [-1.0, 0.0, -1.0]
+max = [1.0, 3.0, 1.0]
+material = \"white\"
+", CAMERA);
+
+        let scene = parse(&source).unwrap();
+
+        assert_eq!(
+            scene.world.bounding_box(0.0, 1.0),
+            Some(BoundingBox::new(
+                Point3::new(-5.0, -0.5 * BOUNDING_BOX_PADDING, -5.0),
+                Point3::new(5.0, 3.0, 5.0),
+            )),
+        );
+    }
+
+    #[test]
+    fn inside_out_rect_is_an_error() {
+        let source = format!("{}
+[materials.white]
+type = \"lambertian\"
+albedo = [0.7, 0.7, 0.7]
+
+[[elements]]
+type = \"box\"
+min = [0.0, 0.0, 0.0]
+max = [1.0, -1.0, 1.0]
+material = \"white\"
+", CAMERA);
+
+        let error = parse_error(&source);
+
+        assert_eq!(error.line, Some(11));
+        assert_eq!(error.message, "y must run from low to high, not [0.0, -1.0]");
+    }
+
+    #[test]
+    fn parse_instances() {
+        let source = format!("{}
+[materials.white]
+type = \"lambertian\"
+albedo = [0.7, 0.7, 0.7]
+
+[[objects.post.elements]]
+type = \"box\"
+min = [-0.5, 0.0, -0.5]
+max = [0.5, 2.0, 0.5]
+material = \"white\"
+
+[[objects.post.elements]]
+type = \"sphere\"
+centre = [0.0, 2.0, 0.0]
+radius = 0.5
+material = \"white\"
+
+[[elements]]
+type = \"instance\"
+object = \"post\"
+
+[[elements]]
+type = \"instance\"
+object = \"post\"
+scale = [1.0, 2.0, 1.0]
+rotate = {{ axis = [0.0, 1.0, 0.0], degrees = 90.0 }}
+translate = [10.0, 0.0, 0.0]
+", CAMERA);
+
+        let scene = parse(&source).unwrap();
+
+        // Both instances place the same object, built once.
+        let world = scene.world.as_any().downcast_ref::<Bvh>().unwrap();
+        let instances: Vec<&Instance> = world
+            .elements()
+            .iter()
+            .map(|e| e.as_any().downcast_ref::<Instance>().unwrap())
+            .collect();
+        assert_eq!(instances.len(), 2);
+        assert!(Arc::ptr_eq(&instances[0].element, &instances[1].element));
+
+        let world_box = world.bounding_box(0.0, 1.0).unwrap();
+        assert!((world_box.min - Point3::new(-0.5, 0.0, -0.5)).length() < 1e-3, "{:?}", world_box);
+        assert!((world_box.max - Point3::new(10.5, 5.0, 0.5)).length() < 1e-3, "{:?}", world_box);
+    }
+
+    #[test]
+    fn finds_lights() {
+        let source = format!("{}
+[materials.white]
+type = \"lambertian\"
+albedo = [0.7, 0.7, 0.7]
+
+[materials.lamp]
+type = \"diffuse_light\"
+colour = [4.0, 4.0, 4.0]
+
+[[elements]]
+type = \"sphere\"
+centre = [0.0, -1000.0, 0.0]
+radius = 1000.0
+material = \"white\"
+
+[[elements]]
+type = \"sphere\"
+centre = [0.0, 5.0, 0.0]
+radius = 1.0
+material = \"lamp\"
+
+[[elements]]
+type = \"xz_rect\"
+x = [-1.0, 1.0]
+z = [-1.0, 1.0]
+y = 8.0
+material = \"lamp\"
+", CAMERA);
+
+        let scene = parse(&source).unwrap();
+
+        let lamp = Arc::new(DiffuseLight::new(Arc::new(SolidColour::new(Colour::new(4.0, 4.0, 4.0)))));
+        assert_eq!(
+            scene.lights,
+            Lights::new(vec![
+                Arc::new(Sphere::new(Point3::new(0.0, 5.0, 0.0), 1.0, lamp.clone())),
+                Arc::new(XzRect::new(-1.0, 1.0, -1.0, 1.0, 8.0, lamp)),
+            ]),
+        );
+    }
+
+    #[test]
+    fn parse_media() {
+        let source = format!("{}
+[materials.white]
+type = \"lambertian\"
+albedo = [0.7, 0.7, 0.7]
+
+[materials.smoke]
+type = \"isotropic\"
+albedo = [0.2, 0.2, 0.2]
+
+[materials.haze]
+type = \"isotropic\"
+albedo = [1.0, 1.0, 1.0]
+
+[atmosphere]
+density = 0.01
+material = \"haze\"
+
+[[elements]]
+type = \"medium\"
+density = 0.5
+material = \"smoke\"
+boundary = {{ type = \"box\", min = [-1.0, 0.0, -1.0], max = [1.0, 3.0, 1.0], material = \"white\" }}
+", CAMERA);
+
+        let scene = parse(&source).unwrap();
+
+        let white = Arc::new(Lambertian::new_with_colour(Colour::new(0.7, 0.7, 0.7)));
+        let smoke = Arc::new(ConstantMedium::new(
+            Arc::new(AxisAlignedBox::new(Point3::new(-1.0, 0.0, -1.0), Point3::new(1.0, 3.0, 1.0), white)),
+            0.5,
+            Arc::new(Isotropic::new_with_colour(Colour::new(0.2, 0.2, 0.2))),
+        ));
+        let atmosphere = Atmosphere::new(
+            Box::new(Bvh::new(vec![smoke], 0.0, 1.0)),
+            0.01,
+            Arc::new(Isotropic::new_with_colour(Colour::new(1.0, 1.0, 1.0))),
+        );
+        assert_eq!(scene.world.as_ref(), &atmosphere as &dyn Element);
+    }
+
+    #[test]
+    fn medium_needs_positive_density() {
+        let source = format!("{}
+[materials.smoke]
+type = \"isotropic\"
+albedo = [0.2, 0.2, 0.2]
+
+[[elements]]
+type = \"medium\"
+density = 0.0
+material = \"smoke\"
+boundary = {{ type = \"sphere\", centre = [0.0, 0.0, 0.0], radius = 1.0, material = \"smoke\" }}
+", CAMERA);
+
+        let error = parse_error(&source);
+
+        assert_eq!(error.line, Some(11));
+        assert_eq!(error.message, "density must be positive, not 0");
+    }
+
+    #[test]
+    fn medium_density_must_be_a_number() {
+        let source = format!("{}
+[materials.smoke]
+type = \"isotropic\"
+albedo = [0.2, 0.2, 0.2]
+
+[[elements]]
+type = \"medium\"
+density = nan
+material = \"smoke\"
+boundary = {{ type = \"sphere\", centre = [0.0, 0.0, 0.0], radius = 1.0, material = \"smoke\" }}
+", CAMERA);
+
+        let error = parse_error(&source);
+
+        assert_eq!(error.line, Some(11));
+        assert_eq!(error.message, "density must be positive, not NaN");
+    }
+
+    #[test]
+    fn atmosphere_density_must_be_finite() {
+        let source = format!("{}
+[materials.haze]
+type = \"isotropic\"
+albedo = [0.8, 0.8, 0.8]
+
+[atmosphere]
+density = inf
+material = \"haze\"
+
+[[elements]]
+type = \"sphere\"
+centre = [0.0, 0.0, 0.0]
+radius = 1.0
+material = \"haze\"
+", CAMERA);
+
+        let error = parse_error(&source);
+
+        assert_eq!(error.line, Some(11));
+        assert_eq!(error.message, "density must be positive, not inf");
+    }
+
+    #[test]
+    fn atmosphere_needs_known_material() {
+        let source = format!("{}
+[materials.white]
+type = \"lambertian\"
+albedo = [0.7, 0.7, 0.7]
+
+[atmosphere]
+density = 0.01
+material = \"haze\"
+
+[[elements]]
+type = \"sphere\"
+centre = [0.0, 0.0, 0.0]
+radius = 1.0
+material = \"white\"
+", CAMERA);
+
+        let error = parse_error(&source);
+
+        assert_eq!(error.line, Some(11));
+        assert_eq!(error.message, "unknown material `haze`");
+    }
+
+    #[test]
+    fn object_placing_itself_is_an_error() {
+        let source = format!("{}
+[[objects.a.elements]]
+type = \"instance\"
+object = \"b\"
+
+[[objects.b.elements]]
+type = \"instance\"
+object = \"a\"
+
+[[elements]]
+type = \"instance\"
+object = \"a\"
+", CAMERA);
+
+        assert_eq!(
+            parse_error(&source).message,
+            "object `a` places itself",
+        );
+    }
+
+    #[test]
+    fn unknown_object_reports_line() {
+        let source = format!("{}
+[[elements]]
+type = \"instance\"
+object = \"tree\"
+", CAMERA);
+
+        let error = parse_error(&source);
+
+        assert_eq!(error.line, Some(7));
+        assert_eq!(error.message, "unknown object `tree`");
+    }
+
+    #[test]
+    fn missing_model_reports_line() {
+        let source = format!("{}
+[[elements]]
+type = \"obj\"
+path = \"missing.obj\"
+", CAMERA);
+
+        let error = parse_error(&source);
+
+        assert_eq!(error.line, Some(7));
+        assert!(error.message.starts_with("missing.obj: couldn't read file"));
+    }
+
+    #[test]
+    fn scene_error_display() {
+        let error = SceneError {
+            path: PathBuf::from("scenes/a.toml"),
+            line: Some(3),
+            message: "unknown material `b`".to_string(),
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "scenes/a.toml:3: unknown material `b`",
+        );
+    }
+
+    #[test]
+    fn load_example_scenes() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes");
+        for entry in fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() != Some("toml".as_ref()) {
+                continue;
+            }
+            if let Err(e) = load_scene(&path, &mut Sampler::new(0)) {
+                panic!("{}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::geometry::point::Point3;
+use crate::render::elements::triangle_mesh::TriangleMesh;
+use crate::render::material::Material;
+
+/// A problem with an STL file. The line is known for ASCII files.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StlError {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl Error for StlError {}
+
+/// Size of the header of a binary STL file, and of each of its facets.
+const BINARY_HEADER_SIZE: usize = 84;
+const BINARY_FACET_SIZE: usize = 50;
+
+/// Reads the STL file at path and builds a mesh with the material.
+pub fn load_stl(path: &Path, material: Arc<dyn Material>) -> Result<TriangleMesh, StlError> {
+    let bytes = fs::read(path).map_err(|e| StlError {
+        path: path.to_path_buf(),
+        line: None,
+        message: format!("couldn't read file: {}", e),
+    })?;
+
+    let (positions, triangles) = parse_stl(&bytes, path)?;
+
+    Ok(TriangleMesh::new(positions, triangles, material))
+}
+
+/// Parses an ASCII or binary STL file into positions and triangles. STL repeats
+/// each vertex for every facet using it, so identical positions are merged. The
+/// facet normals are ignored, as the winding of the vertices gives the same
+/// thing. The path is only used for errors.
+///
+/// Some binary files start with `solid` like ASCII ones, so a file is taken as
+/// binary whenever its size matches the facet count in the binary header.
+pub fn parse_stl(bytes: &[u8], path: &Path) -> Result<(Vec<Point3>, Vec<[usize; 3]>), StlError> {
+    let error = |line: Option<usize>, message: String| StlError {
+        path: path.to_path_buf(),
+        line: line,
+        message: message,
+    };
+
+    let facets = if is_binary(bytes) {
+        parse_binary(bytes)
+    } else if bytes.starts_with(b"solid") {
+        let text = std::str::from_utf8(bytes).map_err(|_| error(None, "ASCII file isn't text".to_string()))?;
+        parse_ascii(text).map_err(|(line, message)| error(Some(line), message))?
+    } else {
+        return Err(error(None, "not an STL file".to_string()));
+    };
+
+    if facets.is_empty() {
+        return Err(error(None, "no facets".to_string()));
+    }
+
+    // Merge vertices with exactly the same position.
+    let mut indices: HashMap<[u32; 3], usize> = HashMap::new();
+    let mut positions = Vec::new();
+    let mut triangles = Vec::with_capacity(facets.len());
+
+    for facet in facets.iter() {
+        let mut triangle = [0; 3];
+        for (corner, p) in facet.iter().enumerate() {
+            let key = [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+            triangle[corner] = *indices.entry(key).or_insert_with(|| {
+                positions.push(*p);
+                positions.len() - 1
+            });
+        }
+        triangles.push(triangle);
+    }
+
+    Ok((positions, triangles))
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.len() < BINARY_HEADER_SIZE {
+        return false;
+    }
+
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+
+    bytes.len() == BINARY_HEADER_SIZE + (count * BINARY_FACET_SIZE)
+}
+
+/// Each facet is a normal, three vertices and two bytes of attributes, all
+/// little endian.
+fn parse_binary(bytes: &[u8]) -> Vec<[Point3; 3]> {
+    bytes[BINARY_HEADER_SIZE..]
+        .chunks_exact(BINARY_FACET_SIZE)
+        .map(|facet| {
+            let value = |i: usize| {
+                let offset = 12 + (i * 4);
+                f32::from_le_bytes([facet[offset], facet[offset + 1], facet[offset + 2], facet[offset + 3]])
+            };
+            let vertex = |v: usize| Point3::new(value(v * 3), value((v * 3) + 1), value((v * 3) + 2));
+
+            [vertex(0), vertex(1), vertex(2)]
+        })
+        .collect()
+}
+
+/// Reads the vertices of each facet, checking the keywords around them are in
+/// the right order. Errors carry the line at fault.
+fn parse_ascii(text: &str) -> Result<Vec<[Point3; 3]>, (usize, String)> {
+    let mut facets = Vec::new();
+    let mut vertices: Vec<Point3> = Vec::with_capacity(3);
+    let mut expected = "solid";
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let tokens: Vec<&str> = line.split_ascii_whitespace().collect();
+        let keyword = match tokens.first() {
+            Some(keyword) => *keyword,
+            None => continue,
+        };
+
+        let unexpected = || (line_number, format!("expected `{}`, not `{}`", expected, keyword));
+
+        match keyword {
+            "solid" if expected == "solid" => expected = "facet",
+            "facet" if expected == "facet" => expected = "outer",
+            "endsolid" if expected == "facet" => return Ok(facets),
+            "outer" if expected == "outer" => expected = "vertex",
+            "vertex" if expected == "vertex" => {
+                let value = |i: usize| match tokens.get(i) {
+                    Some(token) => token.parse::<f32>().map_err(|_| (line_number, format!("invalid number `{}`", token))),
+                    None => Err((line_number, "vertex needs 3 numbers".to_string())),
+                };
+                vertices.push(Point3::new(value(1)?, value(2)?, value(3)?));
+                if vertices.len() == 3 {
+                    expected = "endloop";
+                }
+            },
+            "endloop" if expected == "endloop" => expected = "endfacet",
+            "endfacet" if expected == "endfacet" => {
+                facets.push([vertices[0], vertices[1], vertices[2]]);
+                vertices.clear();
+                expected = "facet";
+            },
+            _ => return Err(unexpected()),
+        }
+    }
+
+    Err((text.lines().count(), "file ends without `endsolid`".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TETRAHEDRON: [[f32; 3]; 4] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [0.0, 0.0, 1.0],
+    ];
+    const FACES: [[usize; 3]; 4] = [[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]];
+
+    fn ascii() -> String {
+        let mut text = "solid tetrahedron\n".to_string();
+        for face in FACES.iter() {
+            text.push_str("  facet normal 0 0 0\n    outer loop\n");
+            for &i in face.iter() {
+                let [x, y, z] = TETRAHEDRON[i];
+                text.push_str(&format!("      vertex {} {} {}\n", x, y, z));
+            }
+            text.push_str("    endloop\n  endfacet\n");
+        }
+        text.push_str("endsolid tetrahedron\n");
+
+        text
+    }
+
+    fn binary(header: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0; 80];
+        bytes[..header.len()].copy_from_slice(header);
+        bytes.extend_from_slice(&(FACES.len() as u32).to_le_bytes());
+        for face in FACES.iter() {
+            bytes.extend_from_slice(&[0; 12]);
+            for &i in face.iter() {
+                for value in TETRAHEDRON[i].iter() {
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            bytes.extend_from_slice(&[0; 2]);
+        }
+
+        bytes
+    }
+
+    fn expected() -> (Vec<Point3>, Vec<[usize; 3]>) {
+        // Vertices are numbered in the order they are first seen.
+        (
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, 1.0),
+            ],
+            vec![[0, 1, 2], [0, 2, 3], [0, 3, 1], [2, 1, 3]],
+        )
+    }
+
+    fn parse(bytes: &[u8]) -> Result<(Vec<Point3>, Vec<[usize; 3]>), StlError> {
+        parse_stl(bytes, Path::new("test.stl"))
+    }
+
+    #[test]
+    fn parse_ascii_stl() {
+        assert_eq!(parse(ascii().as_bytes()), Ok(expected()));
+    }
+
+    #[test]
+    fn parse_binary_stl() {
+        assert_eq!(parse(&binary(b"binary tetrahedron")), Ok(expected()));
+    }
+
+    #[test]
+    fn parse_binary_stl_starting_with_solid() {
+        assert_eq!(parse(&binary(b"solid tetrahedron")), Ok(expected()));
+    }
+
+    #[test]
+    fn ascii_errors_report_line() {
+        let text = ascii().replacen("endloop", "endlop", 1);
+
+        assert_eq!(
+            parse(text.as_bytes()),
+            Err(StlError {
+                path: PathBuf::from("test.stl"),
+                line: Some(7),
+                message: "expected `endloop`, not `endlop`".to_string(),
+            }),
+        );
+
+        let text = ascii().replacen("vertex 1 0 0", "vertex 1 0", 1);
+        assert_eq!(parse(text.as_bytes()).unwrap_err().message, "vertex needs 3 numbers");
+
+        let text = ascii().replace("endsolid tetrahedron\n", "");
+        assert_eq!(parse(text.as_bytes()).unwrap_err().message, "file ends without `endsolid`");
+    }
+
+    #[test]
+    fn other_files_are_errors() {
+        assert_eq!(parse(b"ply\n").unwrap_err().message, "not an STL file");
+        assert_eq!(parse(b"solid empty\nendsolid empty\n").unwrap_err().message, "no facets");
+    }
+}
@@ -1,153 +1,485 @@
-mod geometry;
-mod graphics;
-mod render;
-mod scenes;
-mod util;
-
-use std::fs::File;
-use std::io::Write;
-use std::path::Path;
-
-use num::clamp;
-use rand::{Rng, thread_rng};
-
-use geometry::point::Point3;
-use geometry::ray::Ray;
-use geometry::vector::Vector3;
-use graphics::colour::Colour;
-use render::camera::Camera;
-use render::element::Element;
-use scenes::{create_basic_spheres, create_noise_spheres, create_globe, create_lit_globe};
+#![allow(clippy::redundant_field_names)]
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use ray_tracer::graphics::output::{ExrLayer, ImageFormat, write_exr_layers, write_image};
+use ray_tracer::loaders::gltf::load_gltf_scene;
+use ray_tracer::loaders::scene_file::load_scene;
+use ray_tracer::render::integrator::IntegratorKind;
+use ray_tracer::render::renderer::{Progress, RenderSettings, Renderer};
+use ray_tracer::scenes::{Scene, BUILTIN_SCENES, builtin_scene};
+use ray_tracer::util::sampler::Sampler;
+
+#[derive(Debug, Parser)]
+#[command(name = "ray_tracer", version, about = "Renders scenes with a path tracer")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Render a scene to an image
+    Render(RenderArgs),
+    /// List the built in scenes
+    ListScenes,
+    /// Describe a scene without rendering it
+    Info(SceneArgs),
+}
+
+#[derive(Debug, Args)]
+struct SceneArgs {
+    /// Name of a built in scene, see list-scenes
+    #[arg(long, default_value = "lit-globe", conflicts_with = "scene_file")]
+    scene: String,
+
+    /// Path to a TOML scene file, or a glTF or glb file, used instead of a built
+    /// in scene
+    #[arg(long, value_name = "PATH")]
+    scene_file: Option<PathBuf>,
+
+    /// Seed for everything random, in building the scene and in rendering
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+#[derive(Debug, Args)]
+struct RenderArgs {
+    #[command(flatten)]
+    scene: SceneArgs,
+
+    /// Width of the image in pixels
+    #[arg(long, default_value_t = 400, value_parser = clap::value_parser!(u32).range(2..))]
+    width: u32,
+
+    /// Height of the image in pixels; if not given it follows from the aspect ratio
+    #[arg(long, value_parser = clap::value_parser!(u32).range(2..), conflicts_with = "aspect_ratio")]
+    height: Option<u32>,
+
+    /// Aspect ratio of the image, as a number or as width:height
+    #[arg(long, default_value = "16:9", value_parser = parse_aspect_ratio)]
+    aspect_ratio: f32,
+
+    /// Samples taken for each pixel
+    #[arg(long, default_value_t = 400, value_parser = clap::value_parser!(u32).range(1..))]
+    samples: u32,
+
+    /// Number of passes the samples are split between; photons are traced afresh for each
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    passes: u32,
+
+    /// How the light along each ray is worked out
+    #[arg(long, value_enum, default_value_t = IntegratorOption::Path)]
+    integrator: IntegratorOption,
+
+    /// Distance within which surfaces occlude each other, for ambient occlusion
+    #[arg(long, default_value_t = 1.0, value_parser = parse_positive)]
+    ao_distance: f32,
+
+    /// Number of photons traced from the lights in each pass, for photon mapping
+    #[arg(long, default_value_t = 100_000, value_parser = clap::value_parser!(u32).range(1..))]
+    photons: u32,
+
+    /// Radius photons are gathered from in the first pass, shrinking in later passes
+    #[arg(long, default_value_t = 0.05, value_parser = parse_positive)]
+    photon_radius: f32,
+
+    /// Maximum number of bounces for each ray
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..))]
+    max_depth: u32,
+
+    /// Maximum number of diffuse bounces for each ray
+    #[arg(long, default_value_t = 16)]
+    max_diffuse_depth: u32,
+
+    /// Maximum number of mirror reflections for each ray
+    #[arg(long, default_value_t = 50)]
+    max_specular_depth: u32,
+
+    /// Maximum number of refractions for each ray
+    #[arg(long, default_value_t = 50)]
+    max_transmission_depth: u32,
+
+    /// Number of bounces after which dim paths may be ended at random, by Russian roulette
+    #[arg(long, default_value_t = 3)]
+    roulette_depth: u32,
+
+    /// Number of worker threads; defaults to the number of cores
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    threads: Option<u32>,
+
+    /// Width and height of the tiles handed to each thread
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u32).range(1..))]
+    tile_size: u32,
+
+    /// Where to write the image; the extension picks the format unless --format is given
+    #[arg(long, short, default_value = "render.png")]
+    output: PathBuf,
+
+    /// Format of the image
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, ValueEnum)]
+enum IntegratorOption {
+    /// Path tracing, with light sampling
+    Path,
+    /// Direct lighting only, with no light bouncing between surfaces
+    Direct,
+    /// Ambient occlusion, within --ao-distance
+    Ao,
+    /// Whitted ray tracing, with lights sampled and mirrors and glass followed
+    Whitted,
+    /// Bidirectional path tracing, joining paths from the camera and the lights
+    Bidirectional,
+    /// Photon mapping, with --photons photons gathered from within --photon-radius
+    Photon,
+    /// Path tracing, with caustics gathered from photons as for photon mapping
+    PathCaustics,
+}
+
+impl IntegratorOption {
+    fn kind(self, args: &RenderArgs) -> IntegratorKind {
+        let (photons, radius) = (args.photons as usize, args.photon_radius);
+
+        match self {
+            IntegratorOption::Path => IntegratorKind::Path,
+            IntegratorOption::Direct => IntegratorKind::Direct,
+            IntegratorOption::Ao => IntegratorKind::AmbientOcclusion { distance: args.ao_distance },
+            IntegratorOption::Whitted => IntegratorKind::Whitted,
+            IntegratorOption::Bidirectional => IntegratorKind::Bidirectional,
+            IntegratorOption::Photon => IntegratorKind::Photon { photons: photons, radius: radius },
+            IntegratorOption::PathCaustics => IntegratorKind::PathWithCaustics { photons: photons, radius: radius },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, ValueEnum)]
+enum OutputFormat {
+    /// 8-bit PNG
+    Png,
+    /// 8-bit binary (P6) PPM
+    Ppm,
+    /// 8-bit ASCII (P3) PPM
+    PpmAscii,
+    /// Linear floating point Radiance HDR
+    Hdr,
+    /// Linear floating point OpenEXR, with beauty, normal and depth layers
+    Exr,
+}
+
+impl OutputFormat {
+    fn image_format(self) -> ImageFormat {
+        match self {
+            OutputFormat::Png => ImageFormat::Png,
+            OutputFormat::Ppm => ImageFormat::Ppm,
+            OutputFormat::PpmAscii => ImageFormat::PpmAscii,
+            OutputFormat::Hdr => ImageFormat::Hdr,
+            OutputFormat::Exr => ImageFormat::Exr,
+        }
+    }
+}
 
 fn main() {
-    let aspect_ratio = 16.0 / 9.0;
-    let image_width = 400;
-    let image_height = (image_width as f32 / aspect_ratio) as isize;
-
-    let samples_per_pixel = 400;
-    let max_depth = 40;
-
-    let world_choice = 3;
-    let (world, look_at, look_from, vfov, background) = match world_choice {
-        0 => {
-            let world = create_basic_spheres();
-            let look_at = Point3::new(0.0, 0.0, -1.0);
-            let look_from = Point3::new(-2.5, 1.5, 1.0);
-            let vfov = 45.0;
-            let background = Colour::new(0.7, 0.8, 1.0);
-
-            (world, look_at, look_from, vfov, background)
-        },
-        1 => {
-            let world = create_noise_spheres();
-            let look_at = Point3::new(0.0, 0.0, 0.0);
-            let look_from = Point3::new(13.0, 2.0, 3.0);
-            let vfov = 20.0;
-            let background = Colour::new(0.7, 0.8, 1.0);
-
-            (world, look_at, look_from, vfov, background)
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Render(args) => render(&args),
+        Command::ListScenes => {
+            list_scenes();
+            Ok(())
         },
-        2 => {
-            let world = create_globe();
-            let look_at = Point3::new(0.0, 0.0, 0.0);
-            let look_from = Point3::new(13.0, 2.0, 3.0);
-            let vfov = 20.0;
-            let background = Colour::new(0.7, 0.8, 1.0);
-
-            (world, look_at, look_from, vfov, background)
+        Command::Info(args) => info(&args),
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn render(args: &RenderArgs) -> Result<(), Box<dyn Error>> {
+    let image_width = args.width as usize;
+    let (image_height, aspect_ratio) = match args.height {
+        Some(height) => (height as usize, args.width as f32 / height as f32),
+        None => ((args.width as f32 / args.aspect_ratio) as usize, args.aspect_ratio),
+    };
+    if image_height < 2 {
+        return Err(format!("image height {} is too small", image_height).into());
+    }
+
+    let scene = create_scene(&args.scene)?;
+    let camera = scene.camera.camera(aspect_ratio);
+
+    let settings = RenderSettings {
+        image_width: image_width,
+        image_height: image_height,
+        samples_per_pixel: args.samples as usize,
+        passes: args.passes as usize,
+        integrator: args.integrator.kind(args),
+        max_depth: args.max_depth as usize,
+        max_diffuse_depth: args.max_diffuse_depth as usize,
+        max_specular_depth: args.max_specular_depth as usize,
+        max_transmission_depth: args.max_transmission_depth as usize,
+        roulette_depth: args.roulette_depth as usize,
+        tile_size: args.tile_size as usize,
+        threads: match args.threads {
+            Some(threads) => threads as usize,
+            None => thread::available_parallelism().map_or(1, |n| n.get()),
         },
-        3 => {
-            let world = create_lit_globe();
-            let look_at = Point3::new(0.0, 2.0, 0.0);
-            let look_from = Point3::new(26.0, 3.0, 6.0);
-            let vfov = 20.0;
-            let background = Colour::new(0.0, 0.0, 0.0);
-
-            (world, look_at, look_from, vfov, background)
-        }
-        _ => panic!("Invalid world choice"),
-    };    
-
-    let camera = Camera::new(
-        look_from,
-        look_at,
-        Vector3::new(0.0, 1.0, 0.0),
-        vfov,
-        aspect_ratio,
-        0.1,
-        (look_at - look_from).length(),
-        0.0,
-        1.0,
-    );
-
-    let path = Path::new("render.ppm");
-    let display = path.display();
-
-    let mut file = match File::create(&path) {
-        Ok(file) => file,
-        Err(why) => panic!("Couldn't create {}: {}", display, why),
+        seed: args.scene.seed,
+        progress: Some(print_progress),
     };
 
-    file.write_all("P3\n".as_bytes()).unwrap();
-    file.write_all(format!("{} {}\n", image_width, image_height).as_bytes()).unwrap();
-    file.write_all("255\n".as_bytes()).unwrap();
+    // Check the format before rendering so a bad output path fails straight away.
+    let format = match args.format.map(|f| f.image_format()).or_else(|| ImageFormat::from_path(&args.output)) {
+        Some(format) => format,
+        None => return Err(format!(
+            "can't tell the image format of {}, use --format or an extension of .png, .ppm, .hdr or .exr",
+            args.output.display(),
+        ).into()),
+    };
 
-    let mut rng = thread_rng();
+    let renderer = Renderer::new(settings);
 
-    // Our coordinates have the origin bottom left.
-    for j in (0..image_height).rev() {
-        println!("Line {} of {}", j + 1, image_height);
+    let result = match format {
+        ImageFormat::Exr => {
+            let passes = renderer.render_passes(&camera, scene.world.as_ref(), &scene.lights, &scene.background);
+            write_exr_layers(
+                &[
+                    ExrLayer::rgb("beauty", &passes.beauty),
+                    ExrLayer::rgb("normal", &passes.normal),
+                    ExrLayer::z("depth", &passes.depth),
+                ],
+                &args.output,
+            )
+        },
+        _ => {
+            let framebuffer = renderer.render(&camera, scene.world.as_ref(), &scene.lights, &scene.background);
+            write_image(&framebuffer, &args.output, Some(format))
+        },
+    };
+
+    result.map_err(|e| format!("couldn't write {}: {}", args.output.display(), e))?;
+
+    Ok(())
+}
 
-        for i in 0..image_width {
-            let mut pixel_colour = Colour::new(0.0, 0.0, 0.0);
+/// Reports progress on stderr, leaving stdout to the commands that print results.
+fn print_progress(progress: Progress) {
+    match progress {
+        Progress::Pass { pass, passes } => eprintln!("Pass {} of {}", pass, passes),
+        Progress::Tile { tile, tiles } => eprintln!("Tile {} of {}", tile, tiles),
+    }
+}
 
-            for _s in 0..samples_per_pixel {
-                let u_offset: f32 = rng.gen();
-                let v_offset: f32 = rng.gen();
+fn list_scenes() {
+    for scene in BUILTIN_SCENES.iter() {
+        println!("{:<16}{}", scene.name, scene.description);
+    }
+}
 
-                let u = ((i as f32) + u_offset) / ((image_width - 1) as f32);
-                let v = ((j as f32) + v_offset) / ((image_height - 1) as f32);
+fn info(args: &SceneArgs) -> Result<(), Box<dyn Error>> {
+    let scene = create_scene(args)?;
+    let camera = &scene.camera;
 
-                let ray = camera.get_ray(u, v);
+    println!("Camera");
+    println!("  look from:      {:?}", camera.look_from);
+    println!("  look at:        {:?}", camera.look_at);
+    println!("  up:             {:?}", camera.v_up);
+    println!("  vertical fov:   {}", camera.vertical_fov);
+    println!("  aperture:       {}", camera.aperture);
+    println!("  focus distance: {}", camera.focus_distance);
+    println!("  shutter:        {} to {}", camera.time_0, camera.time_1);
+    println!("Background:       {:?}", scene.background);
+    println!("Lights:           {}", scene.lights.elements.len());
 
-                pixel_colour += ray_colour(&ray, &background, &world, max_depth);
-            }
-            
-            write_colour(&mut file, &pixel_colour, samples_per_pixel);
-        }
+    match scene.world.bounding_box(camera.time_0, camera.time_1) {
+        Some(b) => println!("Bounds:           {:?} to {:?}", b.min, b.max),
+        None => println!("Bounds:           unbounded"),
     }
+
+    Ok(())
 }
 
-fn ray_colour(ray: &Ray, background: &Colour, world: &Box<dyn Element>, depth: isize) -> Colour {
-    if depth <= 0 {
-        return Colour::new(0.0, 0.0, 0.0);
+fn create_scene(args: &SceneArgs) -> Result<Scene, Box<dyn Error>> {
+    // Scene construction draws from its own sampler so the scene is the same
+    // whatever the render settings.
+    let mut sampler = Sampler::new(args.seed);
+
+    match args.scene_file {
+        Some(ref path) => match path.extension().and_then(|e| e.to_str()) {
+            Some("gltf") | Some("glb") => Ok(load_gltf_scene(path)?),
+            _ => Ok(load_scene(path, &mut sampler)?),
+        },
+        None => match builtin_scene(&args.scene) {
+            Some(scene) => Ok((scene.create)(&mut sampler)),
+            None => Err(format!(
+                "unknown scene `{}`, run list-scenes to see the built in scenes",
+                args.scene,
+            ).into()),
+        },
     }
-    
-    match world.hit(&ray, 0.001, f32::INFINITY) {
-        Some(hit_record) => {
-            let emitted = hit_record.material.emit(hit_record.u, hit_record.v, &hit_record.point);
-            match hit_record.material.scatter(&ray, &hit_record) {
-                Some((attenuation, scattered)) => {
-                    emitted + (ray_colour(&scattered, background, world, depth - 1) * attenuation)
-                },
-                None => emitted,
-            }
+}
+
+/// Accepts either a plain number or a ratio like 16:9.
+fn parse_aspect_ratio(s: &str) -> Result<f32, String> {
+    let ratio = match s.split_once(':') {
+        Some((width, height)) => {
+            let width: f32 = width.trim().parse().map_err(|_| format!("invalid width `{}`", width))?;
+            let height: f32 = height.trim().parse().map_err(|_| format!("invalid height `{}`", height))?;
+            width / height
         },
-        None => *background,
+        None => s.trim().parse().map_err(|_| format!("invalid aspect ratio `{}`", s))?,
+    };
+
+    if ratio.is_finite() && ratio > 0.0 {
+        Ok(ratio)
+    } else {
+        Err(format!("aspect ratio `{}` must be positive", s))
+    }
+}
+
+/// Accepts a finite number greater than zero.
+fn parse_positive(s: &str) -> Result<f32, String> {
+    let value: f32 = s.trim().parse().map_err(|_| format!("invalid number `{}`", s))?;
+
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err(format!("`{}` must be positive", s))
     }
 }
 
-fn write_colour(file: &mut File, colour: &Colour, samples_per_pixel: isize) {
-    let scale = 1.0 / (samples_per_pixel as f32);
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use clap::CommandFactory;
 
-    // Gamma correction with gamma = 2.0
-    let sr = (scale * colour.r).sqrt();
-    let sg = (scale * colour.g).sqrt();
-    let sb = (scale * colour.b).sqrt();
+    use super::*;
 
-    let ir = (256.0 * clamp(sr, 0.0, 0.999)) as isize;
-    let ig = (256.0 * clamp(sg, 0.0, 0.999)) as isize;
-    let ib = (256.0 * clamp(sb, 0.0, 0.999)) as isize;
+    #[test]
+    fn cli_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parse_aspect_ratio_number() {
+        assert_eq!(parse_aspect_ratio("1.5"), Ok(1.5));
+    }
 
-    file.write_all(format!("{} {} {}\n", ir, ig, ib).as_bytes()).unwrap();
+    #[test]
+    fn parse_aspect_ratio_ratio() {
+        assert_eq!(parse_aspect_ratio("4:2"), Ok(2.0));
+    }
+
+    #[test]
+    fn parse_aspect_ratio_invalid() {
+        assert!(parse_aspect_ratio("wide").is_err());
+        assert!(parse_aspect_ratio("16:0").is_err());
+        assert!(parse_aspect_ratio("-1").is_err());
+    }
+
+    #[test]
+    fn parse_render_defaults() {
+        let cli = Cli::try_parse_from(["ray_tracer", "render"]).unwrap();
+
+        match cli.command {
+            Command::Render(args) => {
+                assert_eq!(args.scene.scene, "lit-globe");
+                assert_eq!(args.width, 400);
+                assert_eq!(args.samples, 400);
+                assert_eq!(args.passes, 1);
+                assert_eq!(args.integrator, IntegratorOption::Path);
+                assert_eq!(args.max_depth, 100);
+                assert_eq!(args.max_diffuse_depth, 16);
+                assert_eq!(args.roulette_depth, 3);
+                assert_eq!(args.output, Path::new("render.png"));
+                assert_eq!(args.format, None);
+            },
+            _ => panic!("expected render"),
+        }
+    }
+
+    #[test]
+    fn parse_render_format() {
+        let cli = Cli::try_parse_from(["ray_tracer", "render", "--format", "ppm-ascii"]).unwrap();
+
+        match cli.command {
+            Command::Render(args) => assert_eq!(args.format, Some(OutputFormat::PpmAscii)),
+            _ => panic!("expected render"),
+        }
+    }
+
+    #[test]
+    fn parse_render_ambient_occlusion() {
+        let cli = Cli::try_parse_from([
+            "ray_tracer", "render", "--integrator", "ao", "--ao-distance", "50",
+        ]).unwrap();
+
+        match cli.command {
+            Command::Render(args) => assert_eq!(
+                args.integrator.kind(&args),
+                IntegratorKind::AmbientOcclusion { distance: 50.0 },
+            ),
+            _ => panic!("expected render"),
+        }
+    }
+
+    #[test]
+    fn parse_render_photon_mapping() {
+        let cli = Cli::try_parse_from([
+            "ray_tracer", "render", "--integrator", "path-caustics", "--photons", "5000", "--photon-radius", "2",
+            "--passes", "8",
+        ]).unwrap();
+
+        match cli.command {
+            Command::Render(args) => {
+                assert_eq!(
+                    args.integrator.kind(&args),
+                    IntegratorKind::PathWithCaustics { photons: 5000, radius: 2.0 },
+                );
+                assert_eq!(args.passes, 8);
+            },
+            _ => panic!("expected render"),
+        }
+    }
+
+    #[test]
+    fn parse_positive_invalid() {
+        assert_eq!(parse_positive("0.5"), Ok(0.5));
+        assert!(parse_positive("far").is_err());
+        assert!(parse_positive("0").is_err());
+        assert!(parse_positive("-1").is_err());
+        assert!(parse_positive("inf").is_err());
+    }
+
+    #[test]
+    fn ao_distance_must_be_positive() {
+        assert!(Cli::try_parse_from(["ray_tracer", "render", "--ao-distance", "0"]).is_err());
+        assert!(Cli::try_parse_from(["ray_tracer", "render", "--ao-distance=-2"]).is_err());
+    }
+
+    #[test]
+    fn photon_options_must_be_positive() {
+        assert!(Cli::try_parse_from(["ray_tracer", "render", "--photons", "0"]).is_err());
+        assert!(Cli::try_parse_from(["ray_tracer", "render", "--photon-radius", "0"]).is_err());
+        assert!(Cli::try_parse_from(["ray_tracer", "render", "--photon-radius=-0.1"]).is_err());
+    }
+
+    #[test]
+    fn scene_and_scene_file_conflict() {
+        assert!(
+            Cli::try_parse_from([
+                "ray_tracer", "render", "--scene", "globe", "--scene-file", "a.toml",
+            ]).is_err()
+        );
+    }
 }
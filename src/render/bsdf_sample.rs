@@ -0,0 +1,71 @@
+use crate::geometry::vector::Vector3;
+use crate::graphics::colour::Colour;
+
+/// The kind of scattering a sample comes from, each of which has its own limit
+/// on the number of bounces in a path.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Lobe {
+    /// Scattering over a range of directions with a density.
+    Diffuse,
+    /// Reflection in an exact direction, as by a mirror.
+    Specular,
+    /// Refraction through the surface in an exact direction, as by glass.
+    Transmission,
+}
+
+/// A direction picked by a material to scatter light along, and what it does
+/// to light coming back that way.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct BsdfSample {
+    pub direction: Vector3,
+    /// The BSDF times the cosine of the angle to the normal, divided by the
+    /// density: the factor applied to light arriving along the direction.
+    pub attenuation: Colour,
+    /// The density, per unit solid angle, with which the direction was picked.
+    /// Only meaningful when the sample isn't specular.
+    pub pdf: f32,
+    pub lobe: Lobe,
+    /// For transmission, the index of refraction on the far side of the surface
+    /// over that on the side the ray arrived from; otherwise 1. Radiance isn't
+    /// scaled by this squared on crossing the surface, so light traced the
+    /// other way, from the lights, has to be instead.
+    pub eta: f32,
+}
+
+impl BsdfSample {
+    pub fn new(direction: Vector3, attenuation: Colour, pdf: f32) -> Self {
+        BsdfSample {
+            direction: direction,
+            attenuation: attenuation,
+            pdf: pdf,
+            lobe: Lobe::Diffuse,
+            eta: 1.0,
+        }
+    }
+
+    pub fn new_specular(direction: Vector3, attenuation: Colour) -> Self {
+        BsdfSample {
+            direction: direction,
+            attenuation: attenuation,
+            pdf: 1.0,
+            lobe: Lobe::Specular,
+            eta: 1.0,
+        }
+    }
+
+    pub fn new_transmission(direction: Vector3, attenuation: Colour, eta: f32) -> Self {
+        BsdfSample {
+            direction: direction,
+            attenuation: attenuation,
+            pdf: 1.0,
+            lobe: Lobe::Transmission,
+            eta: eta,
+        }
+    }
+
+    /// Whether the direction was picked exactly, as by a mirror or glass, and
+    /// so can't be matched by sampling a light.
+    pub fn is_specular(&self) -> bool {
+        self.lobe != Lobe::Diffuse
+    }
+}
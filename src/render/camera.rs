@@ -1,9 +1,10 @@
-use rand::{Rng, thread_rng};
+use rand::Rng;
 
 use crate::geometry::point::Point3;
 use crate::geometry::ray::Ray;
 use crate::geometry::vector::Vector3;
 use crate::util::random::random_in_unit_disk;
+use crate::util::sampler::Sampler;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Camera {
@@ -20,6 +21,7 @@ pub struct Camera {
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         look_from: Point3,
         look_at: Point3,
@@ -61,19 +63,124 @@ impl Camera {
         }
     }
 
-    pub fn get_ray(&self, s: f32, t: f32) -> Ray {
-        let point_on_lens = random_in_unit_disk() * self.lens_radius;
+    pub fn get_ray(&self, s: f32, t: f32, sampler: &mut Sampler) -> Ray {
+        let point_on_lens = random_in_unit_disk(sampler) * self.lens_radius;
         let offset = (self.u * point_on_lens.x) + (self.v * point_on_lens.y);
 
-        let mut rng = thread_rng();
-
-        let ray = Ray::new_at_time(
+        Ray::new_at_time(
             self.origin + offset,
             self.lower_left_corner + (self.horizontal * s) + (self.vertical * t) - (self.origin + offset),
-            rng.gen_range(self.time_0..self.time_1),
-        );
+            self.time(sampler),
+        )
+    }
+
+    /// A random time while the shutter is open.
+    pub fn time(&self, sampler: &mut Sampler) -> f32 {
+        sampler.gen_range(self.time_0..self.time_1)
+    }
+
+    /// A point on the lens for light from point to reach the camera through, and
+    /// the coordinates (s, t) of the ray from there to point, as get_ray takes
+    /// them. None if point is behind the camera. The coordinates may be outside
+    /// the image.
+    pub fn connect(&self, point: &Point3, sampler: &mut Sampler) -> Option<(Point3, f32, f32)> {
+        let point_on_lens = random_in_unit_disk(sampler) * self.lens_radius;
+        let lens_point = self.origin + (self.u * point_on_lens.x) + (self.v * point_on_lens.y);
+
+        let direction = *point - lens_point;
+        let ahead = -direction.dot(&self.w);
+        if ahead <= 0.0 {
+            return None;
+        }
+
+        // Rays from every point on the lens cross the viewport at the focus
+        // distance.
+        let on_viewport = lens_point + (direction * (self.focus_distance() / ahead));
+        let offset = on_viewport - self.lower_left_corner;
+
+        let s = offset.dot(&self.horizontal) / self.horizontal.length_squared();
+        let t = offset.dot(&self.vertical) / self.vertical.length_squared();
+
+        Some((lens_point, s, t))
+    }
+
+    /// The density, per unit solid angle, with which get_ray sends rays in the
+    /// direction when s and t are uniform from 0 to 1. Light arriving at the
+    /// lens from the opposite direction is weighted by this, as the camera's
+    /// importance.
+    ///
+    /// The viewport has area A at distance 1 from the lens, and a direction at
+    /// angle theta to the view direction crosses it through area cos^3(theta)
+    /// times as large per unit solid angle, so the density is 1 / (A cos^3).
+    pub fn importance(&self, direction: &Vector3) -> f32 {
+        let cos_theta = -direction.normalise().dot(&self.w);
+        if cos_theta <= 0.0 {
+            return 0.0;
+        }
+
+        let focus_distance = self.focus_distance();
+        let area = (self.horizontal.length() * self.vertical.length()) / (focus_distance * focus_distance);
 
-        ray
+        1.0 / (area * cos_theta.powi(3))
+    }
+
+    fn focus_distance(&self) -> f32 {
+        -(self.lower_left_corner - self.origin).dot(&self.w)
+    }
+
+    /// A ray from the centre of the lens at the opening of the shutter, with no
+    /// randomness. Used for the auxiliary passes.
+    pub fn get_pinhole_ray(&self, s: f32, t: f32) -> Ray {
+        Ray::new_at_time(
+            self.origin,
+            self.lower_left_corner + (self.horizontal * s) + (self.vertical * t) - self.origin,
+            self.time_0,
+        )
+    }
+}
+
+/// Everything needed to build a Camera except the aspect ratio, which depends on
+/// the image being rendered rather than on the scene.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct CameraSettings {
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub v_up: Vector3,
+    pub vertical_fov: f32,
+    pub aperture: f32,
+    pub focus_distance: f32,
+    pub time_0: f32,
+    pub time_1: f32,
+}
+
+impl CameraSettings {
+    /// Settings looking from look_from to look_at, focused on look_at, with a small
+    /// aperture and the shutter open from time 0 to 1.
+    pub fn new(look_from: Point3, look_at: Point3, vertical_fov: f32) -> Self {
+        CameraSettings {
+            look_from: look_from,
+            look_at: look_at,
+            v_up: Vector3::new(0.0, 1.0, 0.0),
+            vertical_fov: vertical_fov,
+            aperture: 0.1,
+            focus_distance: (look_at - look_from).length(),
+            time_0: 0.0,
+            time_1: 1.0,
+        }
+    }
+
+    pub fn camera(&self, aspect_ratio: f32) -> Camera {
+        Camera::new(
+            self.look_from,
+            self.look_at,
+            self.v_up,
+            self.vertical_fov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_distance,
+            self.time_0,
+            self.time_1,
+        )
     }
 }
 
@@ -98,4 +205,106 @@ mod tests {
             Point3::new(0.0, 0.0, 0.0),
         );
     }
+
+    fn camera(aperture: f32) -> Camera {
+        Camera::new(
+            Point3::new(1.0, 2.0, 3.0),
+            Point3::new(-1.0, 0.0, -1.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            60.0,
+            3.0 / 2.0,
+            aperture,
+            2.0,
+            0.0,
+            1.0,
+        )
+    }
+
+    #[test]
+    fn connect_finds_ray_coordinates() {
+        let camera = camera(0.0);
+        let mut sampler = Sampler::new(0);
+
+        let ray = camera.get_ray(0.2, 0.7, &mut sampler);
+        let (lens_point, s, t) = camera.connect(&ray.at(3.5), &mut sampler).unwrap();
+
+        assert_eq!(lens_point, ray.origin);
+        assert!((s - 0.2).abs() < 1e-5, "{}", s);
+        assert!((t - 0.7).abs() < 1e-5, "{}", t);
+    }
+
+    #[test]
+    fn connect_through_lens_at_focus_distance() {
+        // Points in focus are seen at the same coordinates from all over the lens.
+        let camera = camera(0.5);
+        let mut sampler = Sampler::new(0);
+
+        for _ in 0..10 {
+            let focused = camera.lower_left_corner + (camera.horizontal * 0.9) + (camera.vertical * 0.1);
+            let (lens_point, s, t) = camera.connect(&focused, &mut sampler).unwrap();
+
+            assert!((lens_point - camera.origin).length() <= 0.25 + 1e-5);
+            assert!((s - 0.9).abs() < 1e-4, "{}", s);
+            assert!((t - 0.1).abs() < 1e-4, "{}", t);
+        }
+    }
+
+    #[test]
+    fn connect_behind_camera() {
+        let camera = camera(0.0);
+
+        assert!(camera.connect(&Point3::new(2.0, 3.0, 5.0), &mut Sampler::new(0)).is_none());
+    }
+
+    #[test]
+    fn importance_integrates_to_one_over_image() {
+        let camera = camera(0.0);
+        let mut sampler = Sampler::new(0);
+
+        // At the centre of a 60 degree view at aspect ratio 3 / 2, the viewport at
+        // distance 1 is 2 tan(30) high and 3 tan(30) wide.
+        let centre = camera.get_ray(0.5, 0.5, &mut sampler).direction;
+        let tan = 30.0f32.to_radians().tan();
+        assert!((camera.importance(&centre) * 6.0 * tan * tan - 1.0).abs() < 1e-5);
+
+        // The importance times the solid angle of each of a grid of small
+        // pieces of the viewport sums to one.
+        let n = 100;
+        let mut total = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let (s, t) = (((i as f32) + 0.5) / (n as f32), ((j as f32) + 0.5) / (n as f32));
+                let direction = camera.get_pinhole_ray(s, t).direction;
+                let piece = camera.horizontal.length() * camera.vertical.length() / ((n * n) as f32);
+                let cos_theta = -direction.normalise().dot(&camera.w);
+                let solid_angle = piece * cos_theta / direction.length_squared();
+
+                total += camera.importance(&direction) * solid_angle;
+            }
+        }
+        assert!((total - 1.0).abs() < 1e-3, "{}", total);
+
+        assert_eq!(camera.importance(&-centre), 0.0);
+    }
+
+    #[test]
+    fn new_camera_settings() {
+        assert_eq!(
+            CameraSettings::new(
+                Point3::new(0.0, 3.0, 4.0),
+                Point3::new(0.0, 0.0, 0.0),
+                20.0,
+            ),
+            CameraSettings {
+                look_from: Point3::new(0.0, 3.0, 4.0),
+                look_at: Point3::new(0.0, 0.0, 0.0),
+                v_up: Vector3::new(0.0, 1.0, 0.0),
+                vertical_fov: 20.0,
+                aperture: 0.1,
+                focus_distance: 5.0,
+                time_0: 0.0,
+                time_1: 1.0,
+            },
+        );
+    }
 }
@@ -2,14 +2,56 @@ use std::any::Any;
 use std::fmt::Debug;
 
 use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::normal::Normal3;
+use crate::geometry::point::Point3;
 use crate::geometry::ray::Ray;
+use crate::geometry::vector::Vector3;
+use crate::util::sampler::Sampler;
 
 use super::hit_record::HitRecord;
 
-pub trait Element : Debug {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+/// How far a point may be from an element's surface, relative to the element's
+/// size, and still count as on it, allowing for rounding in where rays hit.
+pub const SURFACE_TOLERANCE: f32 = 1e-4;
+
+pub trait Element : Debug + Send + Sync {
+    /// The nearest point along the ray between t_min and t_max where it meets the
+    /// element. Elements that scatter rays at random, like media, draw from the
+    /// sampler of the path the ray is part of. Geometric elements draw nothing
+    /// from it, and keep their intersection in a method without one for use
+    /// where no sampler is at hand, as in pdf_value.
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord>;
     fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<BoundingBox>;
 
+    /// The density, per unit solid angle, with which sample_direction picks the
+    /// direction from origin at the given time. Zero if the direction misses, or
+    /// the element can't be sampled.
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vector3, _time: f32) -> f32 {
+        0.0
+    }
+
+    /// A direction from origin towards a random point of the element at the
+    /// given time, or None if the element can't be sampled. Lights are sampled
+    /// this way to light surfaces directly.
+    fn sample_direction(&self, _origin: &Point3, _time: f32, _sampler: &mut Sampler) -> Option<Vector3> {
+        None
+    }
+
+    /// A point picked uniformly over the element's surface at the given time, as
+    /// a hit record with the outward normal, along with the density per unit
+    /// area with which it was picked. None if the element can't be sampled this
+    /// way. Light paths start from points on the lights picked like this.
+    fn sample_surface(&self, _time: f32, _sampler: &mut Sampler) -> Option<(HitRecord, f32)> {
+        None
+    }
+
+    /// The density, per unit area, with which sample_surface picks the point,
+    /// where the surface has the given normal. Zero if the point isn't on the
+    /// element, or the element can't be sampled.
+    fn surface_pdf(&self, _point: &Point3, _normal: &Normal3, _time: f32) -> f32 {
+        0.0
+    }
+
     fn eq(&self, other: &dyn Element) -> bool;
     fn as_any(&self) -> &dyn Any;
 }
@@ -0,0 +1,138 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::ray::Ray;
+use crate::util::sampler::Sampler;
+
+use super::super::element::Element;
+use super::super::hit_record::HitRecord;
+use super::super::material::Material;
+
+/// Haze or fog of even density filling the space between the surfaces of the
+/// world, scattering rays as a constant medium does. Rays that miss the world
+/// altogether are taken to leave the atmosphere at once, so the background is
+/// seen, and lights it, clear of the fog.
+#[derive(Debug)]
+pub struct Atmosphere {
+    pub world: Box<dyn Element>,
+    pub density: f32,
+    pub phase_function: Arc<dyn Material>,
+}
+
+impl Atmosphere {
+    pub fn new(world: Box<dyn Element>, density: f32, phase_function: Arc<dyn Material>) -> Self {
+        Atmosphere {
+            world: world,
+            density: density,
+            phase_function: phase_function,
+        }
+    }
+}
+
+impl Element for Atmosphere {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
+        let hit_record = self.world.hit(ray, t_min, t_max, sampler);
+        let t_end = hit_record.as_ref().map_or(t_max, |h| h.t);
+        if !t_end.is_finite() {
+            return hit_record;
+        }
+
+        let distance = -(1.0 - sampler.gen::<f32>()).ln() / self.density;
+        let t = t_min + distance / ray.direction.length();
+        if t >= t_end {
+            return hit_record;
+        }
+
+        Some(HitRecord::new_in_medium(ray.at(t), self.phase_function.clone(), t))
+    }
+
+    fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<BoundingBox> {
+        self.world.bounding_box(time_0, time_1)
+    }
+
+    fn eq(&self, other: &dyn Element) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PartialEq for Atmosphere {
+    fn eq(&self, other: &Self) -> bool {
+        (self.world.as_ref() == other.world.as_ref()) &&
+        (self.density == other.density) &&
+        (*self.phase_function == *other.phase_function)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::point::Point3;
+    use crate::geometry::vector::Vector3;
+    use crate::graphics::colour::Colour;
+    use crate::render::elements::rect::XyRect;
+    use crate::render::materials::isotropic::Isotropic;
+    use crate::render::materials::lambertian::Lambertian;
+
+    use super::*;
+
+    /// A wall across z = 2, for x and y from 0 to 1, in fog.
+    fn foggy_wall(density: f32) -> Atmosphere {
+        let white = Arc::new(Lambertian::new_with_colour(Colour::new(0.5, 0.5, 0.5)));
+
+        Atmosphere::new(
+            Box::new(XyRect::new(0.0, 1.0, 0.0, 1.0, 2.0, white)),
+            density,
+            Arc::new(Isotropic::new_with_colour(Colour::new(0.8, 0.8, 0.8))),
+        )
+    }
+
+    fn ray(i: usize, x: f32) -> Ray {
+        Ray::new(
+            Point3::new(x + (i % 100) as f32 * 0.001, 0.5 + (i / 100) as f32 * 0.001, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+        )
+    }
+
+    #[test]
+    fn fog_fills_space_before_surface() {
+        let atmosphere = foggy_wall(0.5);
+        let count = 20_000;
+        let mut sampler = Sampler::new(1);
+
+        let reached = (0..count)
+            .filter_map(|i| atmosphere.hit(&ray(i, 0.4), 0.0, f32::INFINITY, &mut sampler))
+            .filter(|hit_record| !hit_record.medium)
+            .count();
+
+        let transmittance = reached as f32 / count as f32;
+        let expected = (-1.0f32).exp();
+        assert!((transmittance - expected).abs() < 0.015, "{} is not near {}", transmittance, expected);
+    }
+
+    #[test]
+    fn fog_fills_range_to_t_max() {
+        let atmosphere = foggy_wall(1.0);
+        let count = 20_000;
+        let mut sampler = Sampler::new(2);
+
+        let through = (0..count).filter(|&i| atmosphere.hit(&ray(i, 2.0), 0.0, 0.5, &mut sampler).is_none()).count();
+
+        let transmittance = through as f32 / count as f32;
+        let expected = (-0.5f32).exp();
+        assert!((transmittance - expected).abs() < 0.015, "{} is not near {}", transmittance, expected);
+    }
+
+    #[test]
+    fn rays_escaping_world_are_clear() {
+        let atmosphere = foggy_wall(10.0);
+        let mut sampler = Sampler::new(3);
+
+        assert!((0..1_000).all(|i| atmosphere.hit(&ray(i, 2.0), 0.0, f32::INFINITY, &mut sampler).is_none()));
+    }
+}
@@ -0,0 +1,122 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::point::Point3;
+use crate::geometry::ray::Ray;
+use crate::util::sampler::Sampler;
+
+use super::super::element::Element;
+use super::super::hit_record::HitRecord;
+use super::super::material::Material;
+use super::element_list::ElementList;
+use super::flip_face::FlipFace;
+use super::rect::{XyRect, XzRect, YzRect};
+
+/// A box with sides parallel to the axes, from min to max, made of six
+/// rectangles facing outwards. Each face has its own texture coordinates, from 0
+/// to 1 across it.
+#[derive(Debug, PartialEq)]
+pub struct AxisAlignedBox {
+    pub min: Point3,
+    pub max: Point3,
+    faces: ElementList,
+}
+
+impl AxisAlignedBox {
+    pub fn new(min: Point3, max: Point3, material: Arc<dyn Material>) -> Self {
+        let mut faces = ElementList::new();
+
+        faces.add(Box::new(XyRect::new(min.x, max.x, min.y, max.y, max.z, material.clone())));
+        faces.add(Box::new(FlipFace::new(Box::new(XyRect::new(min.x, max.x, min.y, max.y, min.z, material.clone())))));
+
+        faces.add(Box::new(XzRect::new(min.x, max.x, min.z, max.z, max.y, material.clone())));
+        faces.add(Box::new(FlipFace::new(Box::new(XzRect::new(min.x, max.x, min.z, max.z, min.y, material.clone())))));
+
+        faces.add(Box::new(YzRect::new(min.y, max.y, min.z, max.z, max.x, material.clone())));
+        faces.add(Box::new(FlipFace::new(Box::new(YzRect::new(min.y, max.y, min.z, max.z, min.x, material)))));
+
+        AxisAlignedBox {
+            min: min,
+            max: max,
+            faces: faces,
+        }
+    }
+}
+
+impl Element for AxisAlignedBox {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
+        self.faces.hit(ray, t_min, t_max, sampler)
+    }
+
+    fn bounding_box(&self, _time_0: f32, _time_1: f32) -> Option<BoundingBox> {
+        Some(BoundingBox::new(self.min, self.max))
+    }
+
+    fn eq(&self, other: &dyn Element) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::normal::Normal3;
+    use crate::geometry::vector::Vector3;
+    use crate::graphics::colour::Colour;
+
+    use super::*;
+    use super::super::super::materials::lambertian::Lambertian;
+
+    fn unit_box() -> AxisAlignedBox {
+        AxisAlignedBox::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 2.0, 3.0),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+        )
+    }
+
+    #[test]
+    fn hit_box_nearest_face() {
+        let ray = Ray::new(Point3::new(0.5, 1.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+
+        let hit = unit_box().hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+
+        assert_eq!(hit.point, Point3::new(0.5, 1.0, 3.0));
+        assert_eq!(hit.normal, Normal3::new(0.0, 0.0, 1.0));
+        assert_eq!((hit.u, hit.v), (0.5, 0.5));
+        assert!(hit.front_face);
+    }
+
+    #[test]
+    fn hit_box_from_inside() {
+        let ray = Ray::new(Point3::new(0.5, 1.0, 1.5), Vector3::new(-1.0, 0.0, 0.0));
+
+        let hit = unit_box().hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+
+        assert_eq!(hit.point, Point3::new(0.0, 1.0, 1.5));
+        assert_eq!(hit.normal, Normal3::new(1.0, 0.0, 0.0));
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn miss_box() {
+        let ray = Ray::new(Point3::new(2.0, 1.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert!(unit_box().hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).is_none());
+    }
+
+    #[test]
+    fn box_bounding_box() {
+        assert_eq!(
+            unit_box().bounding_box(0.0, 1.0),
+            Some(BoundingBox::new(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 2.0, 3.0),
+            )),
+        );
+    }
+}
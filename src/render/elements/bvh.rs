@@ -0,0 +1,284 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::ray::Ray;
+use crate::util::sampler::Sampler;
+
+use super::super::element::Element;
+use super::super::hit_record::HitRecord;
+use super::linear_bvh::LinearBvh;
+
+/// Most elements in a leaf of the BVH.
+const MAX_LEAF_ELEMENTS: usize = 4;
+
+/// How much worse than when it was built, by the surface area heuristic, a
+/// refitted BVH may get before it is built again.
+const REBUILD_COST_RATIO: f32 = 1.5;
+
+/// The elements of a scene in a flattened BVH, built by the surface area
+/// heuristic. The elements are kept in the BVH's leaf order, so each leaf is a
+/// contiguous run of them.
+///
+/// For animation, the BVH can be refitted to moved elements each frame rather
+/// than built again, as long as the same number of elements are given in the
+/// same order.
+#[derive(Debug)]
+pub struct Bvh {
+    elements: Vec<Arc<dyn Element>>,
+    bvh: LinearBvh,
+    /// The index each element was given at in new or refit, in the leaf order.
+    order: Vec<usize>,
+    /// The cost of the tree when it was last built.
+    built_cost: f32,
+}
+
+impl Bvh {
+    /// Builds the BVH over the elements' boxes from time_0 to time_1. Panics if
+    /// there are no elements, or one has no box.
+    pub fn new(elements: Vec<Arc<dyn Element>>, time_0: f32, time_1: f32) -> Self {
+        let (bvh, order) = LinearBvh::build(&bounding_boxes(&elements, time_0, time_1), MAX_LEAF_ELEMENTS);
+
+        Bvh {
+            elements: order.iter().map(|&i| elements[i].clone()).collect(),
+            built_cost: bvh.cost(),
+            bvh: bvh,
+            order: order,
+        }
+    }
+
+    /// Replaces the elements with the same number of new ones, say the same
+    /// elements moved for the next frame, in the order first given. The tree is
+    /// refitted to their boxes from time_0 to time_1, or built again if that
+    /// leaves it much worse than when it was built.
+    ///
+    /// Returns whether the tree was built again. Panics if the number of
+    /// elements differs, or one has no box.
+    pub fn refit(&mut self, elements: Vec<Arc<dyn Element>>, time_0: f32, time_1: f32) -> bool {
+        if elements.len() != self.elements.len() {
+            panic!("refit with {} elements, not {}", elements.len(), self.elements.len());
+        }
+
+        self.elements = self.order.iter().map(|&i| elements[i].clone()).collect();
+        self.bvh.refit(&bounding_boxes(&self.elements, time_0, time_1));
+
+        if self.bvh.cost() > REBUILD_COST_RATIO * self.built_cost {
+            *self = Bvh::new(elements, time_0, time_1);
+            return true;
+        }
+
+        false
+    }
+
+    /// The elements, in the BVH's leaf order.
+    pub fn elements(&self) -> &[Arc<dyn Element>] {
+        &self.elements
+    }
+}
+
+fn bounding_boxes(elements: &[Arc<dyn Element>], time_0: f32, time_1: f32) -> Vec<BoundingBox> {
+    elements
+        .iter()
+        .map(|e| match e.bounding_box(time_0, time_1) {
+            Some(b) => b,
+            None => panic!("element in BVH has no bounding box"),
+        })
+        .collect()
+}
+
+impl Element for Bvh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
+        self.bvh.hit(ray, t_min, t_max, |i, ray, t_min, t_max| self.elements[i].hit(ray, t_min, t_max, sampler))
+    }
+
+    fn bounding_box(&self, _time_0: f32, _time_1: f32) -> Option<BoundingBox> {
+        Some(*self.bvh.bounding_box())
+    }
+
+    fn eq(&self, other: &dyn Element) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PartialEq for Bvh {
+    fn eq(&self, other: &Self) -> bool {
+        (self.elements.len() == other.elements.len()) &&
+        self.elements.iter().zip(other.elements.iter()).all(|(a, b)| **a == **b) &&
+        (self.bvh == other.bvh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+
+    use crate::geometry::point::Point3;
+    use crate::geometry::vector::Vector3;
+    use crate::graphics::colour::Colour;
+    use crate::render::materials::lambertian::Lambertian;
+    use crate::util::sampler::Sampler;
+
+    use super::*;
+    use super::super::element_list::ElementList;
+    use super::super::moving_sphere::MovingSphere;
+    use super::super::sphere::Sphere;
+
+    fn spheres() -> Vec<Arc<dyn Element>> {
+        moved_spheres(|_| Vector3::new(0.0, 0.0, 0.0))
+    }
+
+    fn centre(i: usize) -> Point3 {
+        Point3::new((i % 8) as f32, (i % 3) as f32, (i / 8) as f32)
+    }
+
+    /// The spheres of spheres(), each moved by its offset.
+    fn moved_spheres(offset: impl Fn(usize) -> Vector3) -> Vec<Arc<dyn Element>> {
+        let mut elements: Vec<Arc<dyn Element>> = Vec::new();
+        for i in 0..40 {
+            elements.push(Arc::new(Sphere::new(
+                centre(i) + offset(i),
+                0.3,
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            )));
+        }
+
+        elements
+    }
+
+    /// Checks the two BVHs hit the same things with rays through the spheres of
+    /// spheres(), wherever they have moved to.
+    fn assert_same_hits(a: &Bvh, b: &Bvh) {
+        let mut sampler = Sampler::new(7);
+        for _ in 0..500 {
+            let origin = Point3::new(sampler.gen_range(-5.0..15.0), sampler.gen_range(-5.0..10.0), -10.0);
+            let target = Point3::new(sampler.gen_range(-2.0..10.0), sampler.gen_range(-2.0..5.0), sampler.gen_range(-2.0..8.0));
+            let ray = Ray::new(origin, target - origin);
+
+            assert_eq!(
+                a.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)),
+                b.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)),
+            );
+        }
+    }
+
+    #[test]
+    fn bvh_hits_match_list() {
+        let mut list = ElementList::new();
+        for i in 0..40 {
+            list.add(Box::new(Sphere::new(
+                Point3::new((i % 8) as f32, (i % 3) as f32, (i / 8) as f32),
+                0.3,
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            )));
+        }
+        let bvh = Bvh::new(spheres(), 0.0, 1.0);
+
+        let mut sampler = Sampler::new(5);
+        for _ in 0..500 {
+            let origin = Point3::new(sampler.gen_range(-2.0..10.0), sampler.gen_range(-2.0..4.0), -3.0);
+            let target = Point3::new(sampler.gen_range(0.0..8.0), sampler.gen_range(0.0..3.0), sampler.gen_range(0.0..5.0));
+            let ray = Ray::new(origin, target - origin);
+
+            assert_eq!(
+                bvh.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)),
+                list.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)),
+            );
+        }
+    }
+
+    #[test]
+    fn bvh_bounding_box() {
+        let bvh = Bvh::new(spheres(), 0.0, 1.0);
+
+        assert_eq!(
+            bvh.bounding_box(0.0, 1.0),
+            Some(BoundingBox::new(Point3::new(-0.3, -0.3, -0.3), Point3::new(7.3, 2.3, 4.3))),
+        );
+        assert_eq!(bvh.elements().len(), 40);
+    }
+
+    #[test]
+    fn bvh_single_element() {
+        let bvh = Bvh::new(spheres()[..1].to_vec(), 0.0, 1.0);
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(bvh.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)).map(|h| h.t), Some(4.7));
+    }
+
+    #[test]
+    fn bvh_covers_motion() {
+        // The sphere moves from x = 0 to x = 10 over the shutter, so is only hit
+        // at x = 10 at the end of it.
+        let sphere: Arc<dyn Element> = Arc::new(MovingSphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(10.0, 0.0, 0.0),
+            0.0,
+            1.0,
+            1.0,
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+        ));
+        let bvh = Bvh::new(vec![sphere], 0.0, 1.0);
+        let ray = |time| Ray::new_at_time(Point3::new(10.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0), time);
+
+        assert!(bvh.hit(&ray(1.0), 0.001, f32::INFINITY, &mut Sampler::new(0)).is_some());
+        assert!(bvh.hit(&ray(0.0), 0.001, f32::INFINITY, &mut Sampler::new(0)).is_none());
+    }
+
+    #[test]
+    fn bvh_reproducible() {
+        assert_eq!(
+            Bvh::new(spheres(), 0.0, 1.0),
+            Bvh::new(spheres(), 0.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn refit_matches_rebuild() {
+        // Each sphere drifts a little, so the tree is still good enough to keep.
+        let moved = moved_spheres(|i| Vector3::new(0.1 * ((i % 5) as f32), 0.2, -0.05 * ((i % 7) as f32)));
+        let mut bvh = Bvh::new(spheres(), 0.0, 1.0);
+
+        assert!(!bvh.refit(moved.clone(), 0.0, 1.0));
+        assert_same_hits(&bvh, &Bvh::new(moved, 0.0, 1.0));
+    }
+
+    #[test]
+    fn refit_rebuilds_when_elements_cross() {
+        // The spheres are shuffled, so the nodes stretch across the grid.
+        let mut places: Vec<usize> = (0..40).collect();
+        places.shuffle(&mut Sampler::new(3));
+        let moved = moved_spheres(|i| centre(places[i]) - centre(i));
+        let mut bvh = Bvh::new(spheres(), 0.0, 1.0);
+
+        assert!(bvh.refit(moved.clone(), 0.0, 1.0));
+        assert_eq!(bvh, Bvh::new(moved.clone(), 0.0, 1.0));
+        assert_same_hits(&bvh, &Bvh::new(moved, 0.0, 1.0));
+    }
+
+    #[test]
+    fn refit_moves_back() {
+        let mut bvh = Bvh::new(spheres(), 0.0, 1.0);
+
+        bvh.refit(moved_spheres(|_| Vector3::new(0.0, 3.0, 0.0)), 0.0, 1.0);
+        bvh.refit(spheres(), 0.0, 1.0);
+
+        assert_eq!(bvh, Bvh::new(spheres(), 0.0, 1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "refit with 1 elements, not 40")]
+    fn refit_with_other_elements_panics() {
+        Bvh::new(spheres(), 0.0, 1.0).refit(spheres()[..1].to_vec(), 0.0, 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "BVH has nothing to hold")]
+    fn empty_bvh_panics() {
+        Bvh::new(Vec::new(), 0.0, 1.0);
+    }
+}
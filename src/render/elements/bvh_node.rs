@@ -1,24 +1,30 @@
 use std::any::Any;
 use std::cmp::Ordering;
-use std::rc::Rc;
+use std::sync::Arc;
 
-use rand::{Rng, thread_rng};
+use rand::Rng;
 
 use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::point::Point3;
 use crate::geometry::ray::Ray;
 use crate::render::hit_record::HitRecord;
+use crate::util::sampler::Sampler;
 
 use super::super::element::Element;
+use super::sah::{self, Split};
+
+/// Most elements in a leaf of the BVH.
+const MAX_LEAF_ELEMENTS: usize = 4;
 
 #[derive(Debug)]
 pub struct BvhNode {
-    left: Rc<dyn Element>,
-    right: Rc<dyn Element>,
+    left: Arc<dyn Element>,
+    right: Arc<dyn Element>,
     bounding_box: BoundingBox,
 }
 
 impl BvhNode {
-    pub fn new(left: Rc<dyn Element>, right: Rc<dyn Element>, bounding_box: BoundingBox) -> Self {
+    pub fn new(left: Arc<dyn Element>, right: Arc<dyn Element>, bounding_box: BoundingBox) -> Self {
         BvhNode {
             left: left,
             right: right,
@@ -26,7 +32,43 @@ impl BvhNode {
         }
     }
 
-    pub fn from_elements(mut elements: Vec<Rc<dyn Element>>, time_0: f32, time_1: f32) -> Self {
+    /// Builds a BVH over the elements, splitting by the surface area heuristic.
+    /// Small groups of elements share a leaf, and a single element sits in both
+    /// children of the root.
+    pub fn from_elements(elements: Vec<Arc<dyn Element>>, time_0: f32, time_1: f32) -> Self {
+        let boxes: Vec<BoundingBox> = elements
+            .iter()
+            .map(|e| e.bounding_box(time_0, time_1).unwrap())
+            .collect();
+        let centroids: Vec<Point3> = boxes.iter().map(|b| b.centroid()).collect();
+        let mut order: Vec<usize> = (0..elements.len()).collect();
+
+        if elements.len() == 1 {
+            return Self::new(elements[0].clone(), elements[0].clone(), boxes[0]);
+        }
+
+        let builder = Builder {
+            elements: &elements,
+            boxes: &boxes,
+            centroids: &centroids,
+        };
+
+        // The root is always split, as it has to be a node.
+        match sah::split(&mut order, &boxes, &centroids, 1, 0) {
+            Split::At { mid, .. } => builder.node(&mut order, mid, 0),
+            Split::Leaf => unreachable!(),
+        }
+    }
+
+    /// Builds a BVH the original way, sorting along a random axis and splitting
+    /// at the median, with one element in each leaf. It is kept to compare the
+    /// surface area heuristic against.
+    pub fn from_elements_median(
+        mut elements: Vec<Arc<dyn Element>>,
+        time_0: f32,
+        time_1: f32,
+        sampler: &mut Sampler,
+    ) -> Self {
         let length = elements.len();
 
         Self::from_elements_inner(
@@ -35,18 +77,19 @@ impl BvhNode {
             length,
             time_0,
             time_1,
+            sampler,
         )
     }
 
     fn from_elements_inner(
-        elements: &mut Vec<Rc<dyn Element>>,
+        elements: &mut Vec<Arc<dyn Element>>,
         start: usize,
         end: usize,
         time_0: f32,
         time_1: f32,
+        sampler: &mut Sampler,
     ) -> Self {
-        let mut rng = thread_rng();
-        let comparator = match rng.gen_range(0..3) {
+        let comparator = match sampler.gen_range(0..3) {
             0 => compare_bounding_box_x,
             1 => compare_bounding_box_y,
             2 => compare_bounding_box_z,
@@ -66,11 +109,11 @@ impl BvhNode {
                 elements[start..end].sort_unstable_by(comparator);
 
                 let mid = start + (span / 2);
-                let left_node: Rc<dyn Element> = Rc::new(
-                    Self::from_elements_inner(elements, start, mid, time_0, time_1)
+                let left_node: Arc<dyn Element> = Arc::new(
+                    Self::from_elements_inner(elements, start, mid, time_0, time_1, sampler)
                 );
-                let right_node: Rc<dyn Element> = Rc::new(
-                    Self::from_elements_inner(elements, mid, end, time_0, time_1)
+                let right_node: Arc<dyn Element> = Arc::new(
+                    Self::from_elements_inner(elements, mid, end, time_0, time_1, sampler)
                 );
 
                 (left_node, right_node)
@@ -88,18 +131,94 @@ impl BvhNode {
     }
 }
 
+/// The elements, and their boxes and centroids, for building the BVH.
+struct Builder<'a> {
+    elements: &'a [Arc<dyn Element>],
+    boxes: &'a [BoundingBox],
+    centroids: &'a [Point3],
+}
+
+impl<'a> Builder<'a> {
+    /// The node over order[..], split at mid.
+    fn node(&self, order: &mut [usize], mid: usize, depth: usize) -> BvhNode {
+        let bounding_box = order[1..].iter().fold(self.boxes[order[0]], |b, &i| b.union(&self.boxes[i]));
+
+        let (left, right) = order.split_at_mut(mid);
+
+        BvhNode::new(
+            self.child(left, depth + 1),
+            self.child(right, depth + 1),
+            bounding_box,
+        )
+    }
+
+    fn child(&self, order: &mut [usize], depth: usize) -> Arc<dyn Element> {
+        match sah::split(order, self.boxes, self.centroids, MAX_LEAF_ELEMENTS, depth) {
+            Split::Leaf if order.len() == 1 => self.elements[order[0]].clone(),
+            Split::Leaf => Arc::new(BvhLeaf {
+                elements: order.iter().map(|&i| self.elements[i].clone()).collect(),
+                bounding_box: order[1..].iter().fold(self.boxes[order[0]], |b, &i| b.union(&self.boxes[i])),
+            }),
+            Split::At { mid, .. } => Arc::new(self.node(order, mid, depth)),
+        }
+    }
+}
+
+/// A few elements at the bottom of the BVH, tested one after another.
+#[derive(Debug)]
+struct BvhLeaf {
+    elements: Vec<Arc<dyn Element>>,
+    bounding_box: BoundingBox,
+}
+
+impl Element for BvhLeaf {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
+        let mut closest = t_max;
+        let mut hit = None;
+
+        for element in self.elements.iter() {
+            if let Some(hit_record) = element.hit(ray, t_min, closest, sampler) {
+                closest = hit_record.t;
+                hit = Some(hit_record);
+            }
+        }
+
+        hit
+    }
+
+    fn bounding_box(&self, _time_0: f32, _time_1: f32) -> Option<BoundingBox> {
+        Some(self.bounding_box)
+    }
+
+    fn eq(&self, other: &dyn Element) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PartialEq for BvhLeaf {
+    fn eq(&self, other: &Self) -> bool {
+        (self.elements.len() == other.elements.len()) &&
+        self.elements.iter().zip(other.elements.iter()).all(|(a, b)| **a == **b) &&
+        (self.bounding_box == other.bounding_box)
+    }
+}
+
 impl Element for BvhNode {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
         if !self.bounding_box.hit(ray, t_min, t_max) {
             return None;
         }
 
-        let hit_left = self.left.hit(ray, t_min, t_max);
+        let hit_left = self.left.hit(ray, t_min, t_max, sampler);
         let t_max_right = match hit_left {
             Some(ref hit_record) => hit_record.t,
             None => t_max,
         };
-        let hit_right = self.right.hit(ray, t_min, t_max_right);
+        let hit_right = self.right.hit(ray, t_min, t_max_right, sampler);
 
         match hit_right {
             Some(ref _hit_record) => hit_right,
@@ -128,21 +247,21 @@ impl PartialEq for BvhNode {
     }
 }
 
-fn compare_bounding_box_x(a: &Rc<dyn Element>, b: &Rc<dyn Element>) -> Ordering {
+fn compare_bounding_box_x(a: &Arc<dyn Element>, b: &Arc<dyn Element>) -> Ordering {
     let box_a = a.bounding_box(0.0, 0.0).unwrap();
     let box_b = b.bounding_box(0.0, 0.0).unwrap();
 
     box_a.min.x.partial_cmp(&box_b.min.x).unwrap()
 }
 
-fn compare_bounding_box_y(a: &Rc<dyn Element>, b: &Rc<dyn Element>) -> Ordering {
+fn compare_bounding_box_y(a: &Arc<dyn Element>, b: &Arc<dyn Element>) -> Ordering {
     let box_a = a.bounding_box(0.0, 0.0).unwrap();
     let box_b = b.bounding_box(0.0, 0.0).unwrap();
 
     box_a.min.y.partial_cmp(&box_b.min.y).unwrap()
 }
 
-fn compare_bounding_box_z(a: &Rc<dyn Element>, b: &Rc<dyn Element>) -> Ordering {
+fn compare_bounding_box_z(a: &Arc<dyn Element>, b: &Arc<dyn Element>) -> Ordering {
     let box_a = a.bounding_box(0.0, 0.0).unwrap();
     let box_b = b.bounding_box(0.0, 0.0).unwrap();
 
@@ -150,28 +269,31 @@ fn compare_bounding_box_z(a: &Rc<dyn Element>, b: &Rc<dyn Element>) -> Ordering
 }
 
 #[cfg(test)]
+#[allow(clippy::vec_init_then_push)]
 mod tests {
-    use std::rc::Rc;
+    use std::sync::Arc;
 
+    use crate::geometry::normal::Normal3;
     use crate::geometry::point::Point3;
     use crate::geometry::vector::Vector3;
     use crate::graphics::colour::Colour;
     use crate::render::materials::lambertian::Lambertian;
 
     use super::*;
+    use super::super::element_list::ElementList;
     use super::super::sphere::Sphere;
 
     #[test]
     pub fn new_bvh_node() {
-        let sphere_1 = Rc::new(Sphere::new(
+        let sphere_1 = Arc::new(Sphere::new(
             Point3::new(1.0, 2.0, 3.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         ));
-        let sphere_2 = Rc::new(Sphere::new(
+        let sphere_2 = Arc::new(Sphere::new(
             Point3::new(1.0, 2.0, 3.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         ));
         let bounding_box = BoundingBox::new(
             Point3::new(1.0, 2.0, 3.0),
@@ -181,15 +303,15 @@ mod tests {
         assert_eq!(
             BvhNode::new(sphere_1, sphere_2, bounding_box),
             BvhNode {
-                left: Rc::new(Sphere::new(
+                left: Arc::new(Sphere::new(
                     Point3::new(1.0, 2.0, 3.0),
                     1.0,
-                    Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                    Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 )),
-                right: Rc::new(Sphere::new(
+                right: Arc::new(Sphere::new(
                     Point3::new(1.0, 2.0, 3.0),
                     1.0,
-                    Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                    Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 )),
                 bounding_box: bounding_box,
             },
@@ -198,15 +320,15 @@ mod tests {
 
     #[test]
     fn hit_bvh_node_left() {
-        let sphere_1 = Rc::new(Sphere::new(
+        let sphere_1 = Arc::new(Sphere::new(
             Point3::new(4.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         ));
-        let sphere_2 = Rc::new(Sphere::new(
+        let sphere_2 = Arc::new(Sphere::new(
             Point3::new(7.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         ));
         let bounding_box = BoundingBox::new(
             Point3::new(3.0, -1.0, -1.0),
@@ -219,14 +341,14 @@ mod tests {
             Vector3::new(1.0, 0.0, 0.0),
         );
 
-        let hit_record = bvh_node.hit(&ray, 0.0, 10.0);
+        let hit_record = bvh_node.hit(&ray, 0.0, 10.0, &mut Sampler::new(0));
 
         assert_eq!(
             hit_record,
             Some(HitRecord::new(
                 Point3::new(3.0, 0.0, 0.0),
-                Vector3::new(-1.0, 0.0, 0.0),
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Normal3::new(-1.0, 0.0, 0.0),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 3.0,
                 0.0,
                 0.5,
@@ -237,15 +359,15 @@ mod tests {
 
     #[test]
     fn hit_bvh_node_right() {
-        let sphere_1 = Rc::new(Sphere::new(
+        let sphere_1 = Arc::new(Sphere::new(
             Point3::new(7.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         ));
-        let sphere_2 = Rc::new(Sphere::new(
+        let sphere_2 = Arc::new(Sphere::new(
             Point3::new(4.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         ));
         let bounding_box = BoundingBox::new(
             Point3::new(3.0, -1.0, -1.0),
@@ -258,14 +380,14 @@ mod tests {
             Vector3::new(1.0, 0.0, 0.0),
         );
 
-        let hit_record = bvh_node.hit(&ray, 0.0, 10.0);
+        let hit_record = bvh_node.hit(&ray, 0.0, 10.0, &mut Sampler::new(0));
 
         assert_eq!(
             hit_record,
             Some(HitRecord::new(
                 Point3::new(3.0, 0.0, 0.0),
-                Vector3::new(-1.0, 0.0, 0.0),
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Normal3::new(-1.0, 0.0, 0.0),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 3.0,
                 0.0, 
                 0.5,
@@ -276,15 +398,15 @@ mod tests {
 
     #[test]
     fn ray_misses_bounding_box() {
-        let sphere_1 = Rc::new(Sphere::new(
+        let sphere_1 = Arc::new(Sphere::new(
             Point3::new(4.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         ));
-        let sphere_2 = Rc::new(Sphere::new(
+        let sphere_2 = Arc::new(Sphere::new(
             Point3::new(7.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         ));
         let bounding_box = BoundingBox::new(
             Point3::new(3.0, -1.0, -1.0),
@@ -297,7 +419,7 @@ mod tests {
             Vector3::new(0.0, 1.0, 0.0),
         );
 
-        let hit_record = bvh_node.hit(&ray, 0.0, 10.0);
+        let hit_record = bvh_node.hit(&ray, 0.0, 10.0, &mut Sampler::new(0));
 
         assert_eq!(
             hit_record,
@@ -307,25 +429,25 @@ mod tests {
 
     #[test]
     fn bvh_node_from_single_element() {
-        let mut elements: Vec<Rc<dyn Element>> = Vec::new();
-        elements.push(Rc::new(Sphere::new(
+        let mut elements: Vec<Arc<dyn Element>> = Vec::new();
+        elements.push(Arc::new(Sphere::new(
             Point3::new(4.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         )));
 
         let bvh_node = BvhNode::from_elements(elements, 0.0, 1.0);
 
         let expected = BvhNode::new(
-            Rc::new(Sphere::new(
+            Arc::new(Sphere::new(
                 Point3::new(4.0, 0.0, 0.0),
                 1.0,
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
             )),
-            Rc::new(Sphere::new(
+            Arc::new(Sphere::new(
                 Point3::new(4.0, 0.0, 0.0),
                 1.0,
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
             )),
             BoundingBox::new(
                 Point3::new(3.0, -1.0, -1.0),
@@ -338,4 +460,74 @@ mod tests {
             expected,
         );
     }
+
+    fn sphere_grid() -> Vec<Arc<dyn Element>> {
+        let mut elements: Vec<Arc<dyn Element>> = Vec::new();
+        for i in 0..40 {
+            elements.push(Arc::new(Sphere::new(
+                Point3::new((i % 8) as f32, (i % 3) as f32, (i / 8) as f32),
+                0.3,
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            )));
+        }
+
+        elements
+    }
+
+    #[test]
+    fn bvh_node_from_elements_reproducible() {
+        assert_eq!(
+            BvhNode::from_elements(sphere_grid(), 0.0, 1.0),
+            BvhNode::from_elements(sphere_grid(), 0.0, 1.0),
+        );
+        assert_eq!(
+            BvhNode::from_elements_median(sphere_grid(), 0.0, 1.0, &mut Sampler::new(9)),
+            BvhNode::from_elements_median(sphere_grid(), 0.0, 1.0, &mut Sampler::new(9)),
+        );
+    }
+
+    #[test]
+    fn bvh_node_from_elements_shares_leaves() {
+        // Two spheres a long way apart are split, but three on top of each other
+        // stay together in one leaf.
+        let sphere = |x: f32, z: f32| -> Arc<dyn Element> {
+            Arc::new(Sphere::new(
+                Point3::new(x, 0.0, z),
+                1.0,
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            ))
+        };
+        let elements = vec![sphere(0.0, 0.0), sphere(0.0, 0.1), sphere(100.0, 0.0), sphere(0.0, 0.2)];
+
+        let bvh_node = BvhNode::from_elements(elements, 0.0, 1.0);
+
+        let leaf = bvh_node.left.as_any().downcast_ref::<BvhLeaf>().unwrap();
+        assert_eq!(leaf.elements.len(), 3);
+        assert_eq!(*bvh_node.right, *sphere(100.0, 0.0));
+    }
+
+    #[test]
+    fn bvh_node_hits_match_list() {
+        let mut list = ElementList::new();
+        for element in sphere_grid() {
+            list.add(Box::new(Sphere::new(
+                element.bounding_box(0.0, 0.0).unwrap().centroid(),
+                0.3,
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            )));
+        }
+        let sah = BvhNode::from_elements(sphere_grid(), 0.0, 1.0);
+        let median = BvhNode::from_elements_median(sphere_grid(), 0.0, 1.0, &mut Sampler::new(3));
+
+        let mut sampler = Sampler::new(5);
+        for _ in 0..500 {
+            let origin = Point3::new(sampler.gen_range(-2.0..10.0), sampler.gen_range(-2.0..4.0), -3.0);
+            let target = Point3::new(sampler.gen_range(0.0..8.0), sampler.gen_range(0.0..3.0), sampler.gen_range(0.0..5.0));
+            let ray = Ray::new(origin, target - origin);
+
+            let expected = list.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)).map(|h| h.t);
+            assert_eq!(sah.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)).map(|h| h.t), expected);
+            assert_eq!(median.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)).map(|h| h.t), expected);
+        }
+    }
 }
@@ -0,0 +1,192 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::ray::Ray;
+use crate::util::sampler::Sampler;
+
+use super::super::element::Element;
+use super::super::hit_record::HitRecord;
+use super::super::material::Material;
+
+/// Smoke, fog or mist of even density filling the inside of the boundary, which
+/// must be convex: a ray is taken to be inside between where it first enters
+/// and next leaves it. Along the way, the ray scatters off the medium with
+/// density as the chance per unit length, so the fraction of light getting
+/// through a distance d is exp(-density * d). Where it scatters, the phase
+/// function, usually isotropic, takes the place of a surface's material.
+#[derive(Debug)]
+pub struct ConstantMedium {
+    pub boundary: Arc<dyn Element>,
+    pub density: f32,
+    pub phase_function: Arc<dyn Material>,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Arc<dyn Element>, density: f32, phase_function: Arc<dyn Material>) -> Self {
+        ConstantMedium {
+            boundary: boundary,
+            density: density,
+            phase_function: phase_function,
+        }
+    }
+}
+
+impl Element for ConstantMedium {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
+        // The boundary is found along the whole line, so a ray starting inside
+        // still has an entry point, behind it.
+        let entry = self.boundary.hit(ray, f32::NEG_INFINITY, f32::INFINITY, sampler)?;
+        let exit = self.boundary.hit(ray, entry.t + 0.0001, f32::INFINITY, sampler)?;
+
+        let t_enter = entry.t.max(t_min);
+        let t_exit = exit.t.min(t_max);
+        if t_enter >= t_exit {
+            return None;
+        }
+
+        let distance = -(1.0 - sampler.gen::<f32>()).ln() / self.density;
+        let t = t_enter + distance / ray.direction.length();
+        if t >= t_exit {
+            return None;
+        }
+
+        Some(HitRecord::new_in_medium(ray.at(t), self.phase_function.clone(), t))
+    }
+
+    fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<BoundingBox> {
+        self.boundary.bounding_box(time_0, time_1)
+    }
+
+    fn eq(&self, other: &dyn Element) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PartialEq for ConstantMedium {
+    fn eq(&self, other: &Self) -> bool {
+        (self.boundary.as_ref() == other.boundary.as_ref()) &&
+        (self.density == other.density) &&
+        (*self.phase_function == *other.phase_function)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::point::Point3;
+    use crate::geometry::vector::Vector3;
+    use crate::graphics::colour::Colour;
+    use crate::render::elements::axis_aligned_box::AxisAlignedBox;
+    use crate::render::elements::sphere::Sphere;
+    use crate::render::materials::isotropic::Isotropic;
+    use crate::render::materials::lambertian::Lambertian;
+
+    use super::*;
+
+    fn unit_cube_of_fog(density: f32) -> ConstantMedium {
+        let white = Arc::new(Lambertian::new_with_colour(Colour::new(0.5, 0.5, 0.5)));
+
+        ConstantMedium::new(
+            Arc::new(AxisAlignedBox::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0), white)),
+            density,
+            Arc::new(Isotropic::new_with_colour(Colour::new(0.8, 0.8, 0.8))),
+        )
+    }
+
+    /// Rays along z through the cube at a spread of points.
+    fn rays(origin_z: f32, count: usize) -> impl Iterator<Item = Ray> {
+        (0..count).map(move |i| Ray::new(
+            Point3::new((i % 100) as f32 * 0.01 + 0.005, (i / 100) as f32 * 0.001 + 0.005, origin_z),
+            Vector3::new(0.0, 0.0, 2.0),
+        ))
+    }
+
+    #[test]
+    fn transmittance_falls_off_exponentially() {
+        for &density in [0.5, 1.0, 3.0].iter() {
+            let medium = unit_cube_of_fog(density);
+            let count = 20_000;
+            let mut sampler = Sampler::new(1);
+            let through = rays(-1.0, count)
+                .filter(|ray| medium.hit(ray, 0.001, f32::INFINITY, &mut sampler).is_none())
+                .count();
+
+            let transmittance = through as f32 / count as f32;
+            let expected = (-density).exp();
+            assert!((transmittance - expected).abs() < 0.015, "{} is not near {}", transmittance, expected);
+        }
+    }
+
+    #[test]
+    fn same_ray_scatters_independently() {
+        let medium = unit_cube_of_fog(1.0);
+        let ray = Ray::new(Point3::new(0.5, 0.5, -1.0), Vector3::new(0.0, 0.0, 1.0));
+        let mut sampler = Sampler::new(3);
+
+        let count = 20_000;
+        let through = (0..count).filter(|_| medium.hit(&ray, 0.001, f32::INFINITY, &mut sampler).is_none()).count();
+
+        let transmittance = through as f32 / count as f32;
+        let expected = (-1.0f32).exp();
+        assert!((transmittance - expected).abs() < 0.015, "{} is not near {}", transmittance, expected);
+    }
+
+    #[test]
+    fn scatters_inside_boundary() {
+        let medium = unit_cube_of_fog(2.0);
+        let mut sampler = Sampler::new(2);
+
+        for ray in rays(-1.0, 1_000).chain(rays(0.5, 1_000)) {
+            if let Some(hit_record) = medium.hit(&ray, 0.001, f32::INFINITY, &mut sampler) {
+                assert!(hit_record.medium);
+                assert!(hit_record.point.z >= ray.origin.z.max(0.0) && hit_record.point.z <= 1.0);
+                assert_eq!(ray.at(hit_record.t), hit_record.point);
+                assert_eq!(*hit_record.material, *medium.phase_function);
+            }
+        }
+    }
+
+    #[test]
+    fn hit_same_whatever_range() {
+        let medium = unit_cube_of_fog(2.0);
+
+        for (i, ray) in rays(-1.0, 1_000).enumerate() {
+            let sampler = Sampler::new(i as u64);
+
+            if let Some(hit_record) = medium.hit(&ray, 0.001, f32::INFINITY, &mut sampler.clone()) {
+                let t = hit_record.t;
+                assert_eq!(medium.hit(&ray, 0.001, t + 0.01, &mut sampler.clone()).map(|h| h.t), Some(t));
+                assert!(medium.hit(&ray, 0.001, t, &mut sampler.clone()).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn misses_outside_boundary() {
+        let medium = unit_cube_of_fog(100.0);
+        let ray = Ray::new(Point3::new(2.0, 0.5, -1.0), Vector3::new(0.0, 0.0, 1.0));
+        let behind = Ray::new(Point3::new(0.5, 0.5, 2.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(medium.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)).is_none());
+        assert!(medium.hit(&behind, 0.001, f32::INFINITY, &mut Sampler::new(0)).is_none());
+    }
+
+    #[test]
+    fn bounding_box_of_boundary() {
+        let glass = Arc::new(Lambertian::new_with_colour(Colour::new(0.5, 0.5, 0.5)));
+        let sphere = Arc::new(Sphere::new(Point3::new(1.0, 2.0, 3.0), 1.0, glass));
+        let medium = ConstantMedium::new(
+            sphere.clone(),
+            1.0,
+            Arc::new(Isotropic::new_with_colour(Colour::new(1.0, 1.0, 1.0))),
+        );
+
+        assert_eq!(medium.bounding_box(0.0, 1.0), sphere.bounding_box(0.0, 1.0));
+    }
+}
@@ -3,6 +3,7 @@ use std::fmt::Debug;
 
 use crate::geometry::bounding_box::BoundingBox;
 use crate::geometry::ray::Ray;
+use crate::util::sampler::Sampler;
 
 use super::super::element::Element;
 use super::super::hit_record::HitRecord;
@@ -12,6 +13,7 @@ pub struct ElementList {
     pub elements: Vec<Box<dyn Element>>,
 }
 
+#[allow(clippy::new_without_default)]
 impl ElementList {
     pub fn new() -> Self {
         ElementList {
@@ -25,17 +27,14 @@ impl ElementList {
 }
 
 impl Element for ElementList {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
         let mut closest_t = t_max;
         let mut closest_hit_record = None;
 
         for element in self.elements.iter() {
-            match element.hit(ray, t_min, closest_t) {
-                Some(hit_record) => {
-                    closest_hit_record = Some(hit_record.clone());
-                    closest_t = hit_record.t;
-                },
-                None => {},
+            if let Some(hit_record) = element.hit(ray, t_min, closest_t, sampler) {
+                closest_t = hit_record.t;
+                closest_hit_record = Some(hit_record);
             }
         }
 
@@ -68,9 +67,11 @@ impl Element for ElementList {
 }
 
 #[cfg(test)]
+#[allow(clippy::vec_init_then_push)]
 mod tests {
-    use std::rc::Rc;
+    use std::sync::Arc;
 
+    use crate::geometry::normal::Normal3;
     use crate::geometry::point::Point3;
     use crate::geometry::vector::Vector3;
     use crate::graphics::colour::Colour;
@@ -93,7 +94,7 @@ mod tests {
         list.add(Box::new(Sphere::new(
             Point3::new(1.0, 2.0, 3.0),
             5.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         )));
 
         let mut expected_elements: Vec<Box<dyn Element>> = Vec::new();
@@ -101,7 +102,7 @@ mod tests {
             Box::new(Sphere::new(
                 Point3::new(1.0, 2.0, 3.0),
                 5.0,
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
             )) as Box<dyn Element>
         );
         let expected = ElementList {
@@ -120,24 +121,24 @@ mod tests {
         list.add(Box::new(Sphere::new(
             Point3::new(4.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         )));
         list.add(Box::new(Sphere::new(
             Point3::new(7.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         )));
 
         let ray = Ray::new(Point3::zero(), Vector3::new(1.0, 0.0, 0.0));
 
-        let record = list.hit(&ray, 0.0, 10.0);
+        let record = list.hit(&ray, 0.0, 10.0, &mut Sampler::new(0));
 
         assert_eq!(
             record,
             Some(HitRecord::new(
                 Point3::new(3.0, 0.0, 0.0),
-                Vector3::new(-1.0, 0.0, 0.0),
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Normal3::new(-1.0, 0.0, 0.0),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 3.0,
                 0.0,
                 0.5,
@@ -152,24 +153,24 @@ mod tests {
         list.add(Box::new(Sphere::new(
             Point3::new(7.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         )));
         list.add(Box::new(Sphere::new(
             Point3::new(4.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         )));
 
         let ray = Ray::new(Point3::zero(), Vector3::new(1.0, 0.0, 0.0));
 
-        let record = list.hit(&ray, 0.0, 10.0);
+        let record = list.hit(&ray, 0.0, 10.0, &mut Sampler::new(0));
 
         assert_eq!(
             record,
             Some(HitRecord::new(
                 Point3::new(3.0, 0.0, 0.0),
-                Vector3::new(-1.0, 0.0, 0.0),
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Normal3::new(-1.0, 0.0, 0.0),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 3.0,
                 0.0,
                 0.5,
@@ -184,12 +185,12 @@ mod tests {
         list.add(Box::new(Sphere::new(
             Point3::new(7.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         )));
         list.add(Box::new(Sphere::new(
             Point3::new(4.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         )));
 
         assert_eq!(
@@ -0,0 +1,107 @@
+use std::any::Any;
+
+use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::normal::Normal3;
+use crate::geometry::point::Point3;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::Vector3;
+use crate::util::sampler::Sampler;
+
+use super::super::element::Element;
+use super::super::hit_record::HitRecord;
+
+/// The element with its outward normal turned around, so what was its back face
+/// is its front face. Rectangles face along their positive axis, so this gives
+/// ones facing the other way.
+#[derive(Debug)]
+pub struct FlipFace {
+    pub element: Box<dyn Element>,
+}
+
+impl FlipFace {
+    pub fn new(element: Box<dyn Element>) -> Self {
+        FlipFace {
+            element: element,
+        }
+    }
+}
+
+impl Element for FlipFace {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
+        let mut hit_record = self.element.hit(ray, t_min, t_max, sampler)?;
+        hit_record.front_face = !hit_record.front_face;
+
+        Some(hit_record)
+    }
+
+    fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<BoundingBox> {
+        self.element.bounding_box(time_0, time_1)
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vector3, time: f32) -> f32 {
+        self.element.pdf_value(origin, direction, time)
+    }
+
+    fn sample_direction(&self, origin: &Point3, time: f32, sampler: &mut Sampler) -> Option<Vector3> {
+        self.element.sample_direction(origin, time, sampler)
+    }
+
+    fn sample_surface(&self, time: f32, sampler: &mut Sampler) -> Option<(HitRecord, f32)> {
+        let (mut hit_record, pdf) = self.element.sample_surface(time, sampler)?;
+        hit_record.normal = -hit_record.normal;
+
+        Some((hit_record, pdf))
+    }
+
+    fn surface_pdf(&self, point: &Point3, normal: &Normal3, time: f32) -> f32 {
+        self.element.surface_pdf(point, normal, time)
+    }
+
+    fn eq(&self, other: &dyn Element) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PartialEq for FlipFace {
+    fn eq(&self, other: &Self) -> bool {
+        self.element.as_ref() == other.element.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::geometry::point::Point3;
+    use crate::geometry::vector::Vector3;
+    use crate::graphics::colour::Colour;
+
+    use super::*;
+    use super::super::rect::XyRect;
+    use super::super::super::materials::lambertian::Lambertian;
+
+    #[test]
+    fn flip_face_turns_front_face() {
+        let rect = XyRect::new(
+            0.0,
+            1.0,
+            0.0,
+            1.0,
+            0.0,
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+        );
+        let ray = Ray::new(Point3::new(0.5, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+
+        let hit = rect.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+        let flipped_hit = FlipFace::new(Box::new(rect)).hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+
+        assert!(hit.front_face);
+        assert!(!flipped_hit.front_face);
+        // The normal still points against the ray.
+        assert_eq!(flipped_hit.normal, hit.normal);
+    }
+}
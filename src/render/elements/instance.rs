@@ -0,0 +1,249 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::normal::Normal3;
+use crate::geometry::point::Point3;
+use crate::geometry::ray::Ray;
+use crate::geometry::transform::Transform;
+use crate::geometry::vector::Vector3;
+use crate::util::sampler::Sampler;
+
+use super::super::element::Element;
+use super::super::hit_record::HitRecord;
+
+/// An element placed in the world by an affine transform. The element is shared,
+/// so the same one can be placed any number of times.
+///
+/// Rays are taken into the element's own space by the inverse transform, and the
+/// hit brought back out. The ray direction isn't normalised on the way in, so t
+/// is the same in both spaces.
+#[derive(Debug)]
+pub struct Instance {
+    pub element: Arc<dyn Element>,
+    transform: Transform,
+    inverse: Transform,
+}
+
+impl Instance {
+    pub fn new(element: Arc<dyn Element>, transform: Transform) -> Self {
+        Instance {
+            element: element,
+            transform: transform,
+            inverse: transform.inverse(),
+        }
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// How much the transform stretches area at a point of the element where it
+    /// has the unit normal given, in world space. For the linear part A, that is
+    /// |det A| / |A^T n|.
+    fn area_scale(&self, normal: &Normal3) -> f32 {
+        let stretched = self.transform.matrix().transpose().transform_vector(&normal.as_vector3());
+
+        self.transform.matrix().determinant().abs() / stretched.length()
+    }
+}
+
+impl Element for Instance {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
+        let object_ray = self.inverse.ray(ray);
+
+        let mut hit_record = self.element.hit(&object_ray, t_min, t_max, sampler)?;
+
+        // The normal faces against the object space ray, and so still faces
+        // against the world ray afterwards, so front_face is unchanged.
+        hit_record.point = self.transform.point(&hit_record.point);
+        hit_record.normal = self.transform.normal(&hit_record.normal);
+
+        Some(hit_record)
+    }
+
+    fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<BoundingBox> {
+        let b = self.element.bounding_box(time_0, time_1)?;
+
+        Some(self.transform.bounding_box(&b))
+    }
+
+    /// The element's density for the direction taken into its space, scaled by
+    /// how the transform stretches solid angle. For a unit direction d going to
+    /// A d, that is |det A| / |A d|^3.
+    fn pdf_value(&self, origin: &Point3, direction: &Vector3, time: f32) -> f32 {
+        let object_direction = self.inverse.vector(&direction.normalise());
+        let pdf = self.element.pdf_value(&self.inverse.point(origin), &object_direction, time);
+        if pdf == 0.0 {
+            return 0.0;
+        }
+
+        let stretch = self.inverse.matrix().determinant().abs() / object_direction.length().powi(3);
+
+        pdf * stretch
+    }
+
+    fn sample_direction(&self, origin: &Point3, time: f32, sampler: &mut Sampler) -> Option<Vector3> {
+        let direction = self.element.sample_direction(&self.inverse.point(origin), time, sampler)?;
+
+        Some(self.transform.vector(&direction))
+    }
+
+    /// Points are picked on the element in its own space and brought out, so
+    /// the density is spread over the stretched area.
+    fn sample_surface(&self, time: f32, sampler: &mut Sampler) -> Option<(HitRecord, f32)> {
+        let (mut hit_record, pdf) = self.element.sample_surface(time, sampler)?;
+        hit_record.point = self.transform.point(&hit_record.point);
+        hit_record.normal = self.transform.normal(&hit_record.normal);
+        let pdf = pdf / self.area_scale(&hit_record.normal);
+
+        Some((hit_record, pdf))
+    }
+
+    fn surface_pdf(&self, point: &Point3, normal: &Normal3, time: f32) -> f32 {
+        let object_normal = self.inverse.normal(normal);
+        let pdf = self.element.surface_pdf(&self.inverse.point(point), &object_normal, time);
+        if pdf == 0.0 {
+            return 0.0;
+        }
+
+        pdf / self.area_scale(&normal.normalise())
+    }
+
+    fn eq(&self, other: &dyn Element) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        (*self.element == *other.element) &&
+        (self.transform == other.transform)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::normal::Normal3;
+    use crate::geometry::point::Point3;
+    use crate::geometry::vector::Vector3;
+    use crate::graphics::colour::Colour;
+    use crate::util::testing::assertions::{assert_direction_pdf_normalised, assert_surface_pdf_uniform};
+
+    use super::*;
+    use super::super::axis_aligned_box::AxisAlignedBox;
+    use super::super::rect::XzRect;
+    use super::super::sphere::Sphere;
+    use super::super::super::materials::lambertian::Lambertian;
+
+    fn unit_cube() -> Arc<dyn Element> {
+        Arc::new(AxisAlignedBox::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 1.0),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+        ))
+    }
+
+    fn assert_near(a: &Point3, b: &Point3) {
+        assert!((*a - *b).length() < 1e-5, "{:?} is not near {:?}", a, b);
+    }
+
+    #[test]
+    fn hit_translated_instance() {
+        let instance = Instance::new(unit_cube(), Transform::translate(Vector3::new(5.0, 0.0, 0.0)));
+        let ray = Ray::new(Point3::new(5.5, 0.5, 5.0), Vector3::new(0.0, 0.0, -1.0));
+
+        let hit = instance.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+
+        assert_eq!(hit.point, Point3::new(5.5, 0.5, 1.0));
+        assert_eq!(hit.normal, Normal3::new(0.0, 0.0, 1.0));
+        assert_eq!(hit.t, 4.0);
+        assert!(hit.front_face);
+
+        // The cube itself is still at the origin.
+        assert!(unit_cube().hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).is_none());
+    }
+
+    #[test]
+    fn hit_rotated_instance() {
+        // Turned 90 degrees about y, the cube's +x face faces -z.
+        let instance = Instance::new(unit_cube(), Transform::rotate(Vector3::new(0.0, 1.0, 0.0), 90.0));
+        let ray = Ray::new(Point3::new(0.5, 0.5, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        let hit = instance.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+
+        assert_near(&hit.point, &Point3::new(0.5, 0.5, -1.0));
+        assert!((hit.normal.as_vector3() + Vector3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+        assert!((hit.t - 4.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn hit_scaled_instance_normal() {
+        // Squashing a sphere flat makes its normals point more along the squashed
+        // axis, not less.
+        let sphere = Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+        ));
+        let instance = Instance::new(sphere, Transform::scale(1.0, 0.5, 1.0));
+        let s = 0.5_f32.sqrt();
+        let ray = Ray::new(Point3::new(s, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        let hit = instance.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+
+        assert_near(&hit.point, &Point3::new(s, 0.5 * s, 0.0));
+        let expected = Vector3::new(1.0, 2.0, 0.0).normalise();
+        assert!((hit.normal.as_vector3() - expected).length() < 1e-5, "{:?}", hit.normal);
+    }
+
+    #[test]
+    fn instance_bounding_box() {
+        let instance = Instance::new(
+            unit_cube(),
+            Transform::translate(Vector3::new(0.0, 2.0, 0.0)) * Transform::rotate(Vector3::new(0.0, 0.0, 1.0), 45.0),
+        );
+
+        let b = instance.bounding_box(0.0, 1.0).unwrap();
+        let s = 0.5_f32.sqrt();
+
+        assert_near(&b.min, &Point3::new(-s, 2.0, 0.0));
+        assert_near(&b.max, &Point3::new(s, 2.0 + (2.0 * s), 1.0));
+    }
+
+    #[test]
+    fn sample_instance_directions() {
+        let sphere: Arc<dyn Element> = Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+        ));
+        let instance = Instance::new(
+            sphere,
+            Transform::translate(Vector3::new(2.0, 0.0, 1.0))
+                * Transform::rotate(Vector3::new(0.0, 0.0, 1.0), 30.0)
+                * Transform::scale(1.5, 0.5, 1.0),
+        );
+
+        assert_direction_pdf_normalised(&instance, &Point3::new(0.0, 0.5, 0.0));
+    }
+
+    #[test]
+    fn sample_instance_surfaces() {
+        let material = Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3)));
+        let rotate = Transform::rotate(Vector3::new(1.0, 1.0, 0.0), 30.0);
+
+        // Stretching a 2 by 3 rectangle in its plane by 3 and across it by 2.
+        let rect: Arc<dyn Element> = Arc::new(XzRect::new(-1.0, 1.0, 0.0, 3.0, 0.5, material.clone()));
+        let instance = Instance::new(rect, rotate * Transform::scale(3.0, 2.0, 1.0));
+        assert_surface_pdf_uniform(&instance, 18.0);
+
+        let sphere: Arc<dyn Element> = Arc::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, material));
+        let instance = Instance::new(sphere, rotate * Transform::scale(2.0, 2.0, 2.0));
+        assert_surface_pdf_uniform(&instance, 16.0 * std::f32::consts::PI);
+    }
+}
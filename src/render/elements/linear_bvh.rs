@@ -0,0 +1,390 @@
+use arrayvec::ArrayVec;
+
+use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::point::Point3;
+use crate::geometry::ray::Ray;
+
+use super::super::hit_record::HitRecord;
+use super::sah::{self, Split};
+use super::triangle::BOUNDING_BOX_PADDING;
+
+/// A BVH over some primitives, flattened into an array of nodes and walked
+/// without recursion. The BVH only knows the primitives by index, so it serves
+/// the triangles of a mesh as well as the elements of a scene.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LinearBvh {
+    nodes: Vec<LinearNode>,
+}
+
+/// A node of the BVH, stored depth first so the first child of an interior node
+/// directly follows it.
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum LinearNode {
+    Leaf {
+        bounding_box: BoundingBox,
+        start: usize,
+        count: usize,
+    },
+    Interior {
+        bounding_box: BoundingBox,
+        second_child: usize,
+        axis: usize,
+    },
+}
+
+impl LinearNode {
+    fn bounding_box(&self) -> &BoundingBox {
+        match self {
+            LinearNode::Leaf { bounding_box, .. } => bounding_box,
+            LinearNode::Interior { bounding_box, .. } => bounding_box,
+        }
+    }
+
+    fn bounding_box_mut(&mut self) -> &mut BoundingBox {
+        match self {
+            LinearNode::Leaf { bounding_box, .. } => bounding_box,
+            LinearNode::Interior { bounding_box, .. } => bounding_box,
+        }
+    }
+}
+
+impl LinearBvh {
+    /// Builds the BVH over primitives with the given boxes by the surface area
+    /// heuristic, with at most max_leaf primitives in most leaves.
+    ///
+    /// Leaves refer to contiguous runs of primitives, so they must be stored in
+    /// the order returned alongside the BVH: its ith entry is the index of the
+    /// primitive to store ith. Panics if there are no boxes.
+    pub fn build(boxes: &[BoundingBox], max_leaf: usize) -> (Self, Vec<usize>) {
+        if boxes.is_empty() {
+            panic!("BVH has nothing to hold");
+        }
+
+        let centroids: Vec<Point3> = boxes.iter().map(|b| b.centroid()).collect();
+        let mut order: Vec<usize> = (0..boxes.len()).collect();
+        let mut nodes = Vec::with_capacity(2 * boxes.len() / max_leaf);
+
+        build_node(&mut nodes, &mut order, 0, boxes, &centroids, max_leaf, 0);
+
+        (LinearBvh { nodes: nodes }, order)
+    }
+
+    pub fn bounding_box(&self) -> &BoundingBox {
+        self.nodes[0].bounding_box()
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Fits the node boxes to new primitive boxes, in the stored order, keeping
+    /// the shape of the tree. This is much quicker than building again, but the
+    /// tree gets worse the further the primitives move from where they were
+    /// built; see cost.
+    pub fn refit(&mut self, boxes: &[BoundingBox]) {
+        // Children come after their parents, so going backwards fits each node
+        // after its children. Boxes are padded only once fitted, as when built.
+        let mut fitted: Vec<BoundingBox> = self.nodes.iter().map(|node| *node.bounding_box()).collect();
+
+        for index in (0..self.nodes.len()).rev() {
+            let bounding_box = match self.nodes[index] {
+                LinearNode::Leaf { start, count, .. } => boxes[(start + 1)..(start + count)]
+                    .iter()
+                    .fold(boxes[start], |b, other| b.union(other)),
+                LinearNode::Interior { second_child, .. } => fitted[index + 1].union(&fitted[second_child]),
+            };
+            fitted[index] = bounding_box;
+            *self.nodes[index].bounding_box_mut() = bounding_box.padded(BOUNDING_BOX_PADDING);
+        }
+    }
+
+    /// The expected cost of tracing a ray that hits the root through the tree,
+    /// by the surface area heuristic, in units of primitive tests. It doesn't
+    /// change with the scale of the scene, so compares trees before and after a
+    /// refit.
+    pub fn cost(&self) -> f32 {
+        let root_area = self.bounding_box().surface_area();
+
+        self.nodes
+            .iter()
+            .map(|node| match node {
+                LinearNode::Leaf { bounding_box, count, .. } => bounding_box.surface_area() * (*count as f32),
+                LinearNode::Interior { bounding_box, .. } => bounding_box.surface_area() * sah::TRAVERSAL_COST,
+            })
+            .sum::<f32>() / root_area
+    }
+
+    /// The closest hit between t_min and t_max. hit_primitive(i, ray, t_min,
+    /// t_max) tests the ray against the ith primitive in the stored order.
+    ///
+    /// The nodes are walked with a stack, visiting the child nearer the ray
+    /// origin first and shrinking t_max with each hit so farther nodes are
+    /// skipped.
+    pub fn hit<F>(&self, ray: &Ray, t_min: f32, t_max: f32, mut hit_primitive: F) -> Option<HitRecord>
+    where
+        F: FnMut(usize, &Ray, f32, f32) -> Option<HitRecord>,
+    {
+        let mut closest = t_max;
+        let mut hit = None;
+
+        // Splits fall back to the median deep in the tree, halving the primitives
+        // at each level, so the depth and the stack stay under 64.
+        let mut stack = ArrayVec::<usize, 64>::new();
+        stack.push(0);
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.bounding_box().hit(ray, t_min, closest) {
+                continue;
+            }
+
+            match *node {
+                LinearNode::Leaf { start, count, .. } => {
+                    for primitive in start..(start + count) {
+                        if let Some(hit_record) = hit_primitive(primitive, ray, t_min, closest) {
+                            closest = hit_record.t;
+                            hit = Some(hit_record);
+                        }
+                    }
+                },
+                LinearNode::Interior { second_child, axis, .. } => {
+                    // The child popped last is visited first.
+                    if ray.direction[axis] < 0.0 {
+                        stack.push(index + 1);
+                        stack.push(second_child);
+                    } else {
+                        stack.push(second_child);
+                        stack.push(index + 1);
+                    }
+                },
+            }
+        }
+
+        hit
+    }
+}
+
+/// Builds the node over order[..], whose first primitive is at offset in the
+/// final order, returning its index.
+fn build_node(
+    nodes: &mut Vec<LinearNode>,
+    order: &mut [usize],
+    offset: usize,
+    boxes: &[BoundingBox],
+    centroids: &[Point3],
+    max_leaf: usize,
+    depth: usize,
+) -> usize {
+    let bounding_box = order[1..]
+        .iter()
+        .fold(boxes[order[0]], |b, &i| b.union(&boxes[i]))
+        .padded(BOUNDING_BOX_PADDING);
+
+    let index = nodes.len();
+
+    let (axis, mid) = match sah::split(order, boxes, centroids, max_leaf, depth) {
+        Split::Leaf => {
+            nodes.push(LinearNode::Leaf {
+                bounding_box: bounding_box,
+                start: offset,
+                count: order.len(),
+            });
+            return index;
+        },
+        Split::At { axis, mid } => (axis, mid),
+    };
+
+    nodes.push(LinearNode::Interior {
+        bounding_box: bounding_box,
+        second_child: 0,
+        axis: axis,
+    });
+
+    let (left, right) = order.split_at_mut(mid);
+    build_node(nodes, left, offset, boxes, centroids, max_leaf, depth + 1);
+    let right_index = build_node(nodes, right, offset + mid, boxes, centroids, max_leaf, depth + 1);
+
+    if let LinearNode::Interior { ref mut second_child, .. } = nodes[index] {
+        *second_child = right_index;
+    }
+
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::sync::Arc;
+
+    use crate::geometry::normal::Normal3;
+    use crate::geometry::vector::Vector3;
+    use crate::graphics::colour::Colour;
+    use crate::render::materials::lambertian::Lambertian;
+
+    use super::*;
+
+    /// Unit cubes along the x axis, at x = 0, 2, 4 and so on.
+    fn cubes(count: usize) -> Vec<BoundingBox> {
+        (0..count)
+            .map(|i| BoundingBox::new(
+                Point3::new(2.0 * (i as f32), 0.0, 0.0),
+                Point3::new(2.0 * (i as f32) + 1.0, 1.0, 1.0),
+            ))
+            .collect()
+    }
+
+    /// Hits the front face of cube i, at t = the distance from x = -1.
+    fn hit_cube(boxes: &[BoundingBox], order: &[usize], i: usize, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let b = boxes[order[i]];
+        if !b.hit(ray, t_min, t_max) {
+            return None;
+        }
+        let t = (b.min.x - ray.origin.x) / ray.direction.x;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        Some(HitRecord::new(
+            ray.at(t),
+            Normal3::new(-1.0, 0.0, 0.0),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            t,
+            0.0,
+            0.0,
+            true,
+        ))
+    }
+
+    #[test]
+    fn build_orders_every_primitive() {
+        let boxes = cubes(50);
+
+        let (bvh, order) = LinearBvh::build(&boxes, 4);
+
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..50).collect::<Vec<usize>>());
+        assert_eq!(bvh.bounding_box().min, Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(bvh.bounding_box().max, Point3::new(99.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn build_single_leaf() {
+        let (bvh, order) = LinearBvh::build(&cubes(1), 4);
+
+        assert_eq!(bvh.node_count(), 1);
+        assert_eq!(order, vec![0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "BVH has nothing to hold")]
+    fn build_empty_panics() {
+        LinearBvh::build(&[], 4);
+    }
+
+    #[test]
+    fn hit_finds_closest() {
+        let boxes = cubes(50);
+        let (bvh, order) = LinearBvh::build(&boxes, 4);
+        let ray = Ray::new(Point3::new(-1.0, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0));
+
+        let hit = bvh.hit(&ray, 0.0, f32::INFINITY, |i, ray, t_min, t_max| hit_cube(&boxes, &order, i, ray, t_min, t_max));
+
+        assert_eq!(hit.map(|h| h.t), Some(1.0));
+
+        let hit = bvh.hit(&ray, 10.0, f32::INFINITY, |i, ray, t_min, t_max| hit_cube(&boxes, &order, i, ray, t_min, t_max));
+
+        assert_eq!(hit.map(|h| h.t), Some(11.0));
+    }
+
+    #[test]
+    fn hit_visits_near_side_first() {
+        // Going along the row either way, only the first cube is ever hit, as the
+        // shrinking t_max rules out the rest.
+        let boxes = cubes(64);
+        let (bvh, order) = LinearBvh::build(&boxes, 1);
+
+        for &(x, direction) in [(-1.0, 1.0), (200.0, -1.0)].iter() {
+            let ray = Ray::new(Point3::new(x, 0.5, 0.5), Vector3::new(direction, 0.0, 0.0));
+            let hits = RefCell::new(0);
+
+            bvh.hit(&ray, 0.0, f32::INFINITY, |i, ray, t_min, t_max| {
+                let hit = hit_cube(&boxes, &order, i, ray, t_min, t_max);
+                if hit.is_some() {
+                    *hits.borrow_mut() += 1;
+                }
+                hit
+            });
+
+            assert_eq!(*hits.borrow(), 1);
+        }
+    }
+
+    #[test]
+    fn refit_unmoved_is_unchanged() {
+        let boxes = cubes(50);
+        let (mut bvh, order) = LinearBvh::build(&boxes, 4);
+        let built = bvh.clone();
+
+        let stored: Vec<BoundingBox> = order.iter().map(|&i| boxes[i]).collect();
+        bvh.refit(&stored);
+
+        assert_eq!(bvh, built);
+    }
+
+    #[test]
+    fn refit_follows_primitives() {
+        let boxes = cubes(50);
+        let (mut bvh, order) = LinearBvh::build(&boxes, 4);
+
+        // Every cube moves up by 10, and the last also out along z.
+        let mut moved: Vec<BoundingBox> = boxes
+            .iter()
+            .map(|b| BoundingBox::new(b.min + Vector3::new(0.0, 10.0, 0.0), b.max + Vector3::new(0.0, 10.0, 0.0)))
+            .collect();
+        moved[49] = BoundingBox::new(Point3::new(98.0, 10.0, 20.0), Point3::new(99.0, 11.0, 21.0));
+        let stored: Vec<BoundingBox> = order.iter().map(|&i| moved[i]).collect();
+        bvh.refit(&stored);
+
+        assert_eq!(bvh.bounding_box().min, Point3::new(0.0, 10.0, 0.0));
+        assert_eq!(bvh.bounding_box().max, Point3::new(99.0, 11.0, 21.0));
+
+        // The boxes are already in the stored order.
+        let unchanged: Vec<usize> = (0..50).collect();
+        let ray = Ray::new(Point3::new(-1.0, 10.5, 0.5), Vector3::new(1.0, 0.0, 0.0));
+        let hit = bvh.hit(&ray, 0.0, f32::INFINITY, |i, ray, t_min, t_max| hit_cube(&stored, &unchanged, i, ray, t_min, t_max));
+        assert_eq!(hit.map(|h| h.t), Some(1.0));
+    }
+
+    #[test]
+    fn cost_grows_as_primitives_cross() {
+        let boxes = cubes(64);
+        let (mut bvh, order) = LinearBvh::build(&boxes, 4);
+        let built_cost = bvh.cost();
+
+        // Moving the whole row keeps the cost, but swapping alternate cubes end
+        // for end stretches every node along the row.
+        let shifted: Vec<BoundingBox> = order
+            .iter()
+            .map(|&i| BoundingBox::new(boxes[i].min + Vector3::new(5.0, 5.0, 5.0), boxes[i].max + Vector3::new(5.0, 5.0, 5.0)))
+            .collect();
+        bvh.refit(&shifted);
+        assert!((bvh.cost() - built_cost).abs() < 1e-3);
+
+        let swapped: Vec<BoundingBox> = order
+            .iter()
+            .map(|&i| if i % 2 == 0 { boxes[i] } else { boxes[63 - i] })
+            .collect();
+        bvh.refit(&swapped);
+        assert!(bvh.cost() > 2.0 * built_cost, "{} against {}", bvh.cost(), built_cost);
+    }
+
+    #[test]
+    fn miss_all() {
+        let boxes = cubes(10);
+        let (bvh, order) = LinearBvh::build(&boxes, 4);
+        let ray = Ray::new(Point3::new(-1.0, 5.0, 0.5), Vector3::new(1.0, 0.0, 0.0));
+
+        assert!(bvh.hit(&ray, 0.0, f32::INFINITY, |i, ray, t_min, t_max| hit_cube(&boxes, &order, i, ray, t_min, t_max)).is_none());
+    }
+}
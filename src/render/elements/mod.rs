@@ -1,4 +1,15 @@
+pub mod atmosphere;
+pub mod axis_aligned_box;
+pub mod bvh;
 pub mod bvh_node;
+pub mod constant_medium;
 pub mod element_list;
+pub mod flip_face;
+pub mod instance;
+pub mod linear_bvh;
 pub mod moving_sphere;
+pub mod rect;
+pub mod sah;
 pub mod sphere;
+pub mod triangle;
+pub mod triangle_mesh;
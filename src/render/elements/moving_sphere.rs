@@ -1,11 +1,12 @@
 use std::any::Any;
 use std::fmt::Debug;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::geometry::bounding_box::BoundingBox;
 use crate::geometry::point::Point3;
 use crate::geometry::ray::Ray;
 use crate::geometry::vector::Vector3;
+use crate::util::sampler::Sampler;
 
 use super::sphere::sphere_uv;
 use super::super::element::Element;
@@ -19,7 +20,7 @@ pub struct MovingSphere {
     pub time_0: f32,
     pub time_1: f32,
     pub radius: f32,
-    pub material: Rc<dyn Material>,
+    pub material: Arc<dyn Material>,
 }
 
 impl MovingSphere {
@@ -29,7 +30,7 @@ impl MovingSphere {
         time_0: f32,
         time_1: f32,
         radius: f32,
-        material: Rc<dyn Material>,
+        material: Arc<dyn Material>,
     ) -> Self {
         MovingSphere {
             centre_0: centre_0,
@@ -57,7 +58,7 @@ impl Element for MovingSphere {
     ///     (t^2)(b . b) + 2t(b . (A - C)) + ((A - C) . A - C) - r^2 = 0,
     /// a quadratic in t. We can then look at the discriminant ot see whether there are
     /// any solutions.
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut Sampler) -> Option<HitRecord> {
         let oc = ray.origin - self.centre_at(ray.time);
         let a = ray.direction.length_squared();
         let half_b = oc.dot(&ray.direction);
@@ -70,9 +71,9 @@ impl Element for MovingSphere {
         }
 
         let sqrt_discriminant = discriminant.sqrt();
-        let mut root = ((half_b * -1.0) - sqrt_discriminant) / a;
+        let mut root = (-half_b - sqrt_discriminant) / a;
         if root < t_min || root > t_max {
-            root = ((half_b * -1.0) + sqrt_discriminant) / a;
+            root = (-half_b + sqrt_discriminant) / a;
             if root < t_min || root > t_max {
                 return None
             }
@@ -85,11 +86,11 @@ impl Element for MovingSphere {
 
         Some(HitRecord::new_from_incident_ray(
             p,
-            normal,
+            normal.as_normal3(),
             root,
             u,
             v,
-            &ray,
+            ray,
             self.material.clone(),
         ))
     }
@@ -129,6 +130,7 @@ impl PartialEq for MovingSphere {
 
 #[cfg(test)]
 mod tests {
+    use crate::geometry::normal::Normal3;
     use crate::geometry::vector::Vector3;
     use crate::graphics::colour::Colour;
 
@@ -144,7 +146,7 @@ mod tests {
                 0.0,
                 1.0,
                 5.0,
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
             ),
             MovingSphere {
                 centre_0: Point3 { x: 1.0, y: 2.0, z: 3.0 },
@@ -152,7 +154,7 @@ mod tests {
                 time_0: 0.0,
                 time_1: 1.0,
                 radius: 5.0,
-                material: Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                material: Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
             },
         )
     }
@@ -166,17 +168,17 @@ mod tests {
             0.0,
             1.0,
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         );
 
-        let record = sphere.hit(&ray, 0.0, f32::INFINITY);
+        let record = sphere.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0));
 
         assert_eq!(
             record,
             Some(HitRecord::new(
                 Point3::new(2.0, 0.0, 0.0),
-                Vector3::new(-1.0, 0.0, 0.0),
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Normal3::new(-1.0, 0.0, 0.0),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 2.0,
                 0.0,
                 0.5,
@@ -194,10 +196,10 @@ mod tests {
             0.0,
             1.0,
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         );
 
-        let record = sphere.hit(&ray, 5.0, f32::INFINITY);
+        let record = sphere.hit(&ray, 5.0, f32::INFINITY, &mut Sampler::new(0));
 
         let expected: Option<HitRecord> = None;
         assert_eq!(
@@ -215,10 +217,10 @@ mod tests {
             0.0,
             1.0,
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         );
 
-        let record = sphere.hit(&ray, 0.0, 1.0);
+        let record = sphere.hit(&ray, 0.0, 1.0, &mut Sampler::new(0));
 
         let expected: Option<HitRecord> = None;
         assert_eq!(
@@ -236,17 +238,17 @@ mod tests {
             0.0,
             1.0,
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         );
 
-        let record = sphere.hit(&ray, 3.0, f32::INFINITY);
+        let record = sphere.hit(&ray, 3.0, f32::INFINITY, &mut Sampler::new(0));
 
         assert_eq!(
             record,
             Some(HitRecord::new(
                 Point3::new(4.0, 0.0, 0.0),
-                Vector3::new(-1.0, 0.0, 0.0),
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Normal3::new(-1.0, 0.0, 0.0),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 4.0,
                 0.5,
                 0.5,
@@ -263,7 +265,7 @@ mod tests {
             1.0,
             3.0,
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         );
 
         assert_eq!(
@@ -0,0 +1,519 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::point::Point3;
+use crate::geometry::ray::Ray;
+use crate::geometry::normal::Normal3;
+use crate::geometry::vector::Vector3;
+use crate::util::sampler::Sampler;
+
+use super::super::element::{Element, SURFACE_TOLERANCE};
+use super::super::hit_record::HitRecord;
+use super::super::material::Material;
+use super::triangle::BOUNDING_BOX_PADDING;
+
+/// A rectangle in the plane z = k, from x0 to x1 and y0 to y1. Its outward normal
+/// is +z, and u and v run from 0 to 1 along x and y.
+#[derive(Debug, Clone)]
+pub struct XyRect {
+    pub x0: f32,
+    pub x1: f32,
+    pub y0: f32,
+    pub y1: f32,
+    pub k: f32,
+    pub material: Arc<dyn Material>,
+}
+
+/// A rectangle in the plane y = k, from x0 to x1 and z0 to z1. Its outward normal
+/// is +y, and u and v run from 0 to 1 along x and z.
+#[derive(Debug, Clone)]
+pub struct XzRect {
+    pub x0: f32,
+    pub x1: f32,
+    pub z0: f32,
+    pub z1: f32,
+    pub k: f32,
+    pub material: Arc<dyn Material>,
+}
+
+/// A rectangle in the plane x = k, from y0 to y1 and z0 to z1. Its outward normal
+/// is +x, and u and v run from 0 to 1 along y and z.
+#[derive(Debug, Clone)]
+pub struct YzRect {
+    pub y0: f32,
+    pub y1: f32,
+    pub z0: f32,
+    pub z1: f32,
+    pub k: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl XyRect {
+    pub fn new(x0: f32, x1: f32, y0: f32, y1: f32, k: f32, material: Arc<dyn Material>) -> Self {
+        XyRect {
+            x0: x0,
+            x1: x1,
+            y0: y0,
+            y1: y1,
+            k: k,
+            material: material,
+        }
+    }
+
+    pub fn area(&self) -> f32 {
+        (self.x1 - self.x0) * (self.y1 - self.y0)
+    }
+
+    /// Where the ray meets the rectangle, between t_min and t_max.
+    pub fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let t = plane_t(self.k, ray.origin.z, ray.direction.z, t_min, t_max)?;
+        let p = ray.at(t);
+        let (u, v) = rect_uv(p.x, self.x0, self.x1, p.y, self.y0, self.y1)?;
+
+        Some(HitRecord::new_from_incident_ray(
+            Point3::new(p.x, p.y, self.k),
+            Normal3::new(0.0, 0.0, 1.0),
+            t,
+            u,
+            v,
+            ray,
+            self.material.clone(),
+        ))
+    }
+}
+
+impl XzRect {
+    pub fn new(x0: f32, x1: f32, z0: f32, z1: f32, k: f32, material: Arc<dyn Material>) -> Self {
+        XzRect {
+            x0: x0,
+            x1: x1,
+            z0: z0,
+            z1: z1,
+            k: k,
+            material: material,
+        }
+    }
+
+    pub fn area(&self) -> f32 {
+        (self.x1 - self.x0) * (self.z1 - self.z0)
+    }
+
+    /// Where the ray meets the rectangle, between t_min and t_max.
+    pub fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let t = plane_t(self.k, ray.origin.y, ray.direction.y, t_min, t_max)?;
+        let p = ray.at(t);
+        let (u, v) = rect_uv(p.x, self.x0, self.x1, p.z, self.z0, self.z1)?;
+
+        Some(HitRecord::new_from_incident_ray(
+            Point3::new(p.x, self.k, p.z),
+            Normal3::new(0.0, 1.0, 0.0),
+            t,
+            u,
+            v,
+            ray,
+            self.material.clone(),
+        ))
+    }
+}
+
+impl YzRect {
+    pub fn new(y0: f32, y1: f32, z0: f32, z1: f32, k: f32, material: Arc<dyn Material>) -> Self {
+        YzRect {
+            y0: y0,
+            y1: y1,
+            z0: z0,
+            z1: z1,
+            k: k,
+            material: material,
+        }
+    }
+
+    pub fn area(&self) -> f32 {
+        (self.y1 - self.y0) * (self.z1 - self.z0)
+    }
+
+    /// Where the ray meets the rectangle, between t_min and t_max.
+    pub fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let t = plane_t(self.k, ray.origin.x, ray.direction.x, t_min, t_max)?;
+        let p = ray.at(t);
+        let (u, v) = rect_uv(p.y, self.y0, self.y1, p.z, self.z0, self.z1)?;
+
+        Some(HitRecord::new_from_incident_ray(
+            Point3::new(self.k, p.y, p.z),
+            Normal3::new(1.0, 0.0, 0.0),
+            t,
+            u,
+            v,
+            ray,
+            self.material.clone(),
+        ))
+    }
+}
+
+impl Element for XyRect {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut Sampler) -> Option<HitRecord> {
+        self.intersect(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _time_0: f32, _time_1: f32) -> Option<BoundingBox> {
+        Some(BoundingBox::new(
+            Point3::new(self.x0, self.y0, self.k),
+            Point3::new(self.x1, self.y1, self.k),
+        ).padded(BOUNDING_BOX_PADDING))
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vector3, time: f32) -> f32 {
+        let hit_record = self.intersect(&Ray::new_at_time(*origin, *direction, time), 0.001, f32::INFINITY);
+
+        area_pdf(hit_record, direction, self.area())
+    }
+
+    fn sample_direction(&self, origin: &Point3, _time: f32, sampler: &mut Sampler) -> Option<Vector3> {
+        Some(Point3::new(between(self.x0, self.x1, sampler), between(self.y0, self.y1, sampler), self.k) - *origin)
+    }
+
+    fn sample_surface(&self, _time: f32, sampler: &mut Sampler) -> Option<(HitRecord, f32)> {
+        let (a, b) = (between(self.x0, self.x1, sampler), between(self.y0, self.y1, sampler));
+        let (u, v) = rect_uv(a, self.x0, self.x1, b, self.y0, self.y1)?;
+
+        Some((
+            HitRecord::new(Point3::new(a, b, self.k), Normal3::new(0.0, 0.0, 1.0), self.material.clone(), 0.0, u, v, true),
+            1.0 / self.area(),
+        ))
+    }
+
+    fn surface_pdf(&self, point: &Point3, _normal: &Normal3, _time: f32) -> f32 {
+        let uv = rect_uv(point.x, self.x0, self.x1, point.y, self.y0, self.y1);
+
+        rect_surface_pdf(uv, point.z - self.k, self.area())
+    }
+
+    fn eq(&self, other: &dyn Element) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Element for XzRect {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut Sampler) -> Option<HitRecord> {
+        self.intersect(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _time_0: f32, _time_1: f32) -> Option<BoundingBox> {
+        Some(BoundingBox::new(
+            Point3::new(self.x0, self.k, self.z0),
+            Point3::new(self.x1, self.k, self.z1),
+        ).padded(BOUNDING_BOX_PADDING))
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vector3, time: f32) -> f32 {
+        let hit_record = self.intersect(&Ray::new_at_time(*origin, *direction, time), 0.001, f32::INFINITY);
+
+        area_pdf(hit_record, direction, self.area())
+    }
+
+    fn sample_direction(&self, origin: &Point3, _time: f32, sampler: &mut Sampler) -> Option<Vector3> {
+        Some(Point3::new(between(self.x0, self.x1, sampler), self.k, between(self.z0, self.z1, sampler)) - *origin)
+    }
+
+    fn sample_surface(&self, _time: f32, sampler: &mut Sampler) -> Option<(HitRecord, f32)> {
+        let (a, b) = (between(self.x0, self.x1, sampler), between(self.z0, self.z1, sampler));
+        let (u, v) = rect_uv(a, self.x0, self.x1, b, self.z0, self.z1)?;
+
+        Some((
+            HitRecord::new(Point3::new(a, self.k, b), Normal3::new(0.0, 1.0, 0.0), self.material.clone(), 0.0, u, v, true),
+            1.0 / self.area(),
+        ))
+    }
+
+    fn surface_pdf(&self, point: &Point3, _normal: &Normal3, _time: f32) -> f32 {
+        let uv = rect_uv(point.x, self.x0, self.x1, point.z, self.z0, self.z1);
+
+        rect_surface_pdf(uv, point.y - self.k, self.area())
+    }
+
+    fn eq(&self, other: &dyn Element) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Element for YzRect {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut Sampler) -> Option<HitRecord> {
+        self.intersect(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _time_0: f32, _time_1: f32) -> Option<BoundingBox> {
+        Some(BoundingBox::new(
+            Point3::new(self.k, self.y0, self.z0),
+            Point3::new(self.k, self.y1, self.z1),
+        ).padded(BOUNDING_BOX_PADDING))
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vector3, time: f32) -> f32 {
+        let hit_record = self.intersect(&Ray::new_at_time(*origin, *direction, time), 0.001, f32::INFINITY);
+
+        area_pdf(hit_record, direction, self.area())
+    }
+
+    fn sample_direction(&self, origin: &Point3, _time: f32, sampler: &mut Sampler) -> Option<Vector3> {
+        Some(Point3::new(self.k, between(self.y0, self.y1, sampler), between(self.z0, self.z1, sampler)) - *origin)
+    }
+
+    fn sample_surface(&self, _time: f32, sampler: &mut Sampler) -> Option<(HitRecord, f32)> {
+        let (a, b) = (between(self.y0, self.y1, sampler), between(self.z0, self.z1, sampler));
+        let (u, v) = rect_uv(a, self.y0, self.y1, b, self.z0, self.z1)?;
+
+        Some((
+            HitRecord::new(Point3::new(self.k, a, b), Normal3::new(1.0, 0.0, 0.0), self.material.clone(), 0.0, u, v, true),
+            1.0 / self.area(),
+        ))
+    }
+
+    fn surface_pdf(&self, point: &Point3, _normal: &Normal3, _time: f32) -> f32 {
+        let uv = rect_uv(point.y, self.y0, self.y1, point.z, self.z0, self.z1);
+
+        rect_surface_pdf(uv, point.x - self.k, self.area())
+    }
+
+    fn eq(&self, other: &dyn Element) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PartialEq for XyRect {
+    fn eq(&self, other: &Self) -> bool {
+        (self.x0 == other.x0) &&
+        (self.x1 == other.x1) &&
+        (self.y0 == other.y0) &&
+        (self.y1 == other.y1) &&
+        (self.k == other.k) &&
+        (*self.material == *other.material)
+    }
+}
+
+impl PartialEq for XzRect {
+    fn eq(&self, other: &Self) -> bool {
+        (self.x0 == other.x0) &&
+        (self.x1 == other.x1) &&
+        (self.z0 == other.z0) &&
+        (self.z1 == other.z1) &&
+        (self.k == other.k) &&
+        (*self.material == *other.material)
+    }
+}
+
+impl PartialEq for YzRect {
+    fn eq(&self, other: &Self) -> bool {
+        (self.y0 == other.y0) &&
+        (self.y1 == other.y1) &&
+        (self.z0 == other.z0) &&
+        (self.z1 == other.z1) &&
+        (self.k == other.k) &&
+        (*self.material == *other.material)
+    }
+}
+
+/// Where a ray with the given origin and direction along one axis reaches k on
+/// that axis, if it does within [t_min, t_max]. A ray parallel to the plane
+/// gives an infinite or NaN t, and so misses.
+fn plane_t(k: f32, origin: f32, direction: f32, t_min: f32, t_max: f32) -> Option<f32> {
+    let t = (k - origin) / direction;
+
+    if t >= t_min && t <= t_max {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+/// The density, per unit solid angle, of the direction to a point picked
+/// uniformly over the rectangle's area, given where a ray in the direction
+/// meets it: the density by area scaled by the square of the distance, over the
+/// cosine of the angle at which it is seen.
+fn area_pdf(hit_record: Option<HitRecord>, direction: &Vector3, area: f32) -> f32 {
+    let hit_record = match hit_record {
+        Some(hit_record) => hit_record,
+        None => return 0.0,
+    };
+
+    let distance_squared = hit_record.t * hit_record.t * direction.length_squared();
+    let cosine = (hit_record.normal.dot(direction) / direction.length()).abs();
+
+    distance_squared / (cosine * area)
+}
+
+/// The density per unit area of a point picked uniformly over a rectangle of
+/// the given area, for a point offset from the rectangle's plane by offset,
+/// which has texture coordinates uv if it is within the edges.
+fn rect_surface_pdf(uv: Option<(f32, f32)>, offset: f32, area: f32) -> f32 {
+    if uv.is_some() && (offset.abs() <= SURFACE_TOLERANCE * area.sqrt()) {
+        1.0 / area
+    } else {
+        0.0
+    }
+}
+
+/// A uniformly random value from a to b.
+fn between(a: f32, b: f32, sampler: &mut Sampler) -> f32 {
+    a + (sampler.gen::<f32>() * (b - a))
+}
+
+/// The texture coordinates of the point (a, b) in the rectangle [a0, a1] x [b0, b1],
+/// or None if it is outside.
+fn rect_uv(a: f32, a0: f32, a1: f32, b: f32, b0: f32, b1: f32) -> Option<(f32, f32)> {
+    if a < a0 || a > a1 || b < b0 || b > b1 {
+        return None;
+    }
+
+    Some(((a - a0) / (a1 - a0), (b - b0) / (b1 - b0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphics::colour::Colour;
+    use crate::util::testing::assertions::{assert_direction_pdf_normalised, assert_surface_pdf_uniform};
+
+    use super::*;
+    use super::super::super::materials::lambertian::Lambertian;
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3)))
+    }
+
+    #[test]
+    fn hit_xy_rect() {
+        let rect = XyRect::new(0.0, 2.0, 0.0, 4.0, -1.0, material());
+        let ray = Ray::new(Point3::new(0.5, 1.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert_eq!(
+            rect.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)),
+            Some(HitRecord::new(
+                Point3::new(0.5, 1.0, -1.0),
+                Normal3::new(0.0, 0.0, 1.0),
+                material(),
+                2.0,
+                0.25,
+                0.25,
+                true,
+            )),
+        );
+    }
+
+    #[test]
+    fn hit_xz_rect_from_below() {
+        let rect = XzRect::new(-1.0, 1.0, -1.0, 1.0, 3.0, material());
+        let ray = Ray::new(Point3::new(0.5, 0.0, -0.5), Vector3::new(0.0, 1.0, 0.0));
+
+        assert_eq!(
+            rect.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)),
+            Some(HitRecord::new(
+                Point3::new(0.5, 3.0, -0.5),
+                Normal3::new(0.0, -1.0, 0.0),
+                material(),
+                3.0,
+                0.75,
+                0.25,
+                false,
+            )),
+        );
+    }
+
+    #[test]
+    fn hit_yz_rect() {
+        let rect = YzRect::new(0.0, 1.0, 0.0, 1.0, 2.0, material());
+        let ray = Ray::new(Point3::new(0.0, 0.5, 0.5), Vector3::new(2.0, 0.0, 0.0));
+
+        let hit = rect.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+
+        assert_eq!(hit.point, Point3::new(2.0, 0.5, 0.5));
+        assert_eq!(hit.t, 1.0);
+        assert_eq!((hit.u, hit.v), (0.5, 0.5));
+    }
+
+    #[test]
+    fn miss_rect() {
+        let rect = XyRect::new(0.0, 1.0, 0.0, 1.0, 0.0, material());
+
+        // Outside the edges.
+        let ray = Ray::new(Point3::new(1.5, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(rect.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).is_none());
+
+        // Outside the range of t.
+        let ray = Ray::new(Point3::new(0.5, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(rect.hit(&ray, 0.0, 0.5, &mut Sampler::new(0)).is_none());
+
+        // Parallel to the plane, both in it and beside it.
+        let ray = Ray::new(Point3::new(-1.0, 0.5, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(rect.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).is_none());
+        let ray = Ray::new(Point3::new(-1.0, 0.5, 1.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(rect.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).is_none());
+    }
+
+    #[test]
+    fn rect_bounding_box_is_padded() {
+        let rect = XzRect::new(0.0, 1.0, 2.0, 3.0, 5.0, material());
+
+        assert_eq!(
+            rect.bounding_box(0.0, 1.0),
+            Some(BoundingBox::new(
+                Point3::new(0.0, 5.0 - (BOUNDING_BOX_PADDING / 2.0), 2.0),
+                Point3::new(1.0, 5.0 + (BOUNDING_BOX_PADDING / 2.0), 3.0),
+            )),
+        );
+    }
+
+    #[test]
+    fn hit_rect_through_bounding_box() {
+        // A ray grazing the padded box still finds the rectangle inside it.
+        let rect = YzRect::new(0.0, 1.0, 0.0, 1.0, 0.0, material());
+        let ray = Ray::new(Point3::new(-1.0, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0));
+
+        assert!(rect.bounding_box(0.0, 1.0).unwrap().hit(&ray, 0.0, f32::INFINITY));
+        assert!(rect.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).is_some());
+    }
+
+    #[test]
+    fn sample_rect_directions() {
+        let origin = Point3::new(0.5, 0.3, 0.2);
+
+        assert_direction_pdf_normalised(&XyRect::new(-1.0, 2.0, -1.0, 1.0, 1.5, material()), &origin);
+        assert_direction_pdf_normalised(&XzRect::new(-1.0, 2.0, -2.0, 1.0, -0.5, material()), &origin);
+        assert_direction_pdf_normalised(&YzRect::new(-1.0, 2.0, -1.0, 1.0, 1.5, material()), &origin);
+    }
+
+    #[test]
+    fn sample_rect_surfaces() {
+        assert_surface_pdf_uniform(&XyRect::new(-1.0, 2.0, -1.0, 1.0, 1.5, material()), 6.0);
+        assert_surface_pdf_uniform(&XzRect::new(-1.0, 2.0, -2.0, 1.0, -0.5, material()), 9.0);
+        assert_surface_pdf_uniform(&YzRect::new(0.0, 0.5, -1.0, 1.0, 1.5, material()), 1.0);
+    }
+
+    #[test]
+    fn rect_pdf_away_from_rect() {
+        let rect = XzRect::new(0.0, 1.0, 0.0, 1.0, 1.0, material());
+
+        assert_eq!(
+            rect.pdf_value(&Point3::new(0.5, 0.0, 0.5), &Vector3::new(0.0, -1.0, 0.0), 0.0),
+            0.0,
+        );
+        // Straight up at a distance of 2, the density is 2^2 / 1.
+        assert_eq!(
+            rect.pdf_value(&Point3::new(0.5, -1.0, 0.5), &Vector3::new(0.0, 1.0, 0.0), 0.0),
+            4.0,
+        );
+    }
+}
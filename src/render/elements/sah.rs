@@ -0,0 +1,230 @@
+use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::point::Point3;
+
+/// Buckets the centroids are sorted into along the split axis.
+const BINS: usize = 12;
+
+/// The cost of testing a node's box, relative to hitting one element.
+pub const TRAVERSAL_COST: f32 = 0.125;
+
+/// Depth past which splits fall back to the median. Median splits halve the
+/// elements, so trees stay shallow enough for a fixed traversal stack.
+const MAX_SAH_DEPTH: usize = 32;
+
+/// How to divide the elements under a BVH node.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Split {
+    /// Keep them together in a leaf.
+    Leaf,
+    /// Give order[..mid] to the first child and order[mid..] to the second,
+    /// divided along the axis.
+    At { axis: usize, mid: usize },
+}
+
+/// Chooses where to split the elements order[..], with the given boxes and box
+/// centroids, by the surface area heuristic, and reorders them to match.
+///
+/// The heuristic takes the chance of a ray that hits a node hitting a child as
+/// the ratio of their surface areas. The centroids are binned along their
+/// longest axis, and each boundary between bins costed as the elements on each
+/// side weighted by their boxes' areas. The elements stay in a leaf if there are
+/// at most max_leaf of them and no split is cheaper than testing them all.
+pub fn split(
+    order: &mut [usize],
+    boxes: &[BoundingBox],
+    centroids: &[Point3],
+    max_leaf: usize,
+    depth: usize,
+) -> Split {
+    let count = order.len();
+    if count <= 1 {
+        return Split::Leaf;
+    }
+
+    let centroid_bounds = order[1..].iter().fold(
+        BoundingBox::new(centroids[order[0]], centroids[order[0]]),
+        |b, &i| b.union(&BoundingBox::new(centroids[i], centroids[i])),
+    );
+    let extent = centroid_bounds.max - centroid_bounds.min;
+    let axis = extent.max_dimension();
+
+    // All the centroids coincide, so no split separates them. Halving still
+    // keeps leaves small.
+    if extent[axis] == 0.0 {
+        return if count <= max_leaf { Split::Leaf } else { Split::At { axis: axis, mid: count / 2 } };
+    }
+
+    if depth >= MAX_SAH_DEPTH {
+        let mid = count / 2;
+        order.select_nth_unstable_by(mid, |&a, &b| centroids[a][axis].partial_cmp(&centroids[b][axis]).unwrap());
+        return Split::At { axis: axis, mid: mid };
+    }
+
+    let min = centroid_bounds.min[axis];
+    let bin = |i: usize| ((((centroids[i][axis] - min) / extent[axis]) * (BINS as f32)) as usize).min(BINS - 1);
+
+    let mut counts = [0; BINS];
+    let mut bounds: [Option<BoundingBox>; BINS] = [None; BINS];
+    for &i in order.iter() {
+        let b = bin(i);
+        counts[b] += 1;
+        bounds[b] = Some(bounds[b].map_or(boxes[i], |bounds| bounds.union(&boxes[i])));
+    }
+
+    // The count and area on the far side of each boundary, sweeping in from the
+    // end. The lowest centroid is in the first bin and the highest in the last,
+    // so no side is ever empty.
+    let mut above = [(0, 0.0); BINS - 1];
+    let mut running: (usize, Option<BoundingBox>) = (0, None);
+    for boundary in (0..(BINS - 1)).rev() {
+        running = accumulate(running, counts[boundary + 1], bounds[boundary + 1]);
+        above[boundary] = (running.0, running.1.map_or(0.0, |b| b.surface_area()));
+    }
+
+    let mut best = (f32::INFINITY, 0);
+    let mut running: (usize, Option<BoundingBox>) = (0, None);
+    for (boundary, &(count_above, area_above)) in above.iter().enumerate() {
+        running = accumulate(running, counts[boundary], bounds[boundary]);
+        let area_below = running.1.map_or(0.0, |b| b.surface_area());
+        let cost = ((running.0 as f32) * area_below) + ((count_above as f32) * area_above);
+        if cost < best.0 {
+            best = (cost, boundary);
+        }
+    }
+
+    // Both costs are left unscaled by the area of the node, the same for both.
+    let area = order[1..].iter().fold(boxes[order[0]], |b, &i| b.union(&boxes[i])).surface_area();
+    let split_cost = (TRAVERSAL_COST * area) + best.0;
+    let leaf_cost = (count as f32) * area;
+    if count <= max_leaf && leaf_cost <= split_cost {
+        return Split::Leaf;
+    }
+
+    let mid = partition(order, |i| bin(i) <= best.1);
+
+    Split::At { axis: axis, mid: mid }
+}
+
+fn accumulate(
+    (count, bounds): (usize, Option<BoundingBox>),
+    bin_count: usize,
+    bin_bounds: Option<BoundingBox>,
+) -> (usize, Option<BoundingBox>) {
+    let bounds = match (bounds, bin_bounds) {
+        (Some(a), Some(b)) => Some(a.union(&b)),
+        (a, b) => a.or(b),
+    };
+
+    (count + bin_count, bounds)
+}
+
+/// Moves the elements for which below holds to the front, returning how many
+/// there are.
+fn partition<F: Fn(usize) -> bool>(order: &mut [usize], below: F) -> usize {
+    let mut mid = 0;
+    for i in 0..order.len() {
+        if below(order[i]) {
+            order.swap(i, mid);
+            mid += 1;
+        }
+    }
+
+    mid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_boxes(centres: &[Point3]) -> (Vec<BoundingBox>, Vec<Point3>) {
+        let boxes = centres
+            .iter()
+            .map(|c| BoundingBox::new(
+                Point3::new(c.x - 0.5, c.y - 0.5, c.z - 0.5),
+                Point3::new(c.x + 0.5, c.y + 0.5, c.z + 0.5),
+            ))
+            .collect();
+
+        (boxes, centres.to_vec())
+    }
+
+    #[test]
+    fn single_element_is_a_leaf() {
+        let (boxes, centroids) = unit_boxes(&[Point3::new(0.0, 0.0, 0.0)]);
+
+        assert_eq!(split(&mut [0], &boxes, &centroids, 4, 0), Split::Leaf);
+    }
+
+    #[test]
+    fn split_separates_clusters() {
+        // Two clusters far apart on y, given interleaved.
+        let mut centres = Vec::new();
+        for i in 0..4 {
+            centres.push(Point3::new(i as f32, 0.0, 0.0));
+            centres.push(Point3::new(i as f32, 100.0, 0.0));
+        }
+        let (boxes, centroids) = unit_boxes(&centres);
+        let mut order: Vec<usize> = (0..8).collect();
+
+        let result = split(&mut order, &boxes, &centroids, 4, 0);
+
+        assert_eq!(result, Split::At { axis: 1, mid: 4 });
+        assert!(order[..4].iter().all(|&i| centroids[i].y == 0.0));
+        assert!(order[4..].iter().all(|&i| centroids[i].y == 100.0));
+    }
+
+    #[test]
+    fn split_picks_cheaper_side() {
+        // Eight boxes in a row, and one far off. The lone box goes on its own.
+        let mut centres: Vec<Point3> = (0..8).map(|i| Point3::new(i as f32, 0.0, 0.0)).collect();
+        centres.push(Point3::new(1000.0, 0.0, 0.0));
+        let (boxes, centroids) = unit_boxes(&centres);
+        let mut order: Vec<usize> = (0..9).collect();
+
+        let result = split(&mut order, &boxes, &centroids, 4, 0);
+
+        assert_eq!(result, Split::At { axis: 0, mid: 8 });
+        assert_eq!(order[8], 8);
+    }
+
+    #[test]
+    fn overlapping_elements_stay_in_leaf() {
+        // Boxes on top of each other gain nothing from splitting.
+        let centres: Vec<Point3> = (0..3).map(|i| Point3::new(0.0, 0.0, (i as f32) * 0.01)).collect();
+        let (boxes, centroids) = unit_boxes(&centres);
+
+        assert_eq!(split(&mut [0, 1, 2], &boxes, &centroids, 4, 0), Split::Leaf);
+    }
+
+    #[test]
+    fn too_many_for_leaf_are_split() {
+        let centres: Vec<Point3> = (0..3).map(|i| Point3::new(0.0, 0.0, (i as f32) * 0.01)).collect();
+        let (boxes, centroids) = unit_boxes(&centres);
+
+        match split(&mut [0, 1, 2], &boxes, &centroids, 2, 0) {
+            Split::At { axis, mid } => assert_eq!((axis, mid > 0 && mid < 3), (2, true)),
+            Split::Leaf => panic!("expected a split"),
+        }
+    }
+
+    #[test]
+    fn coincident_centroids_are_halved() {
+        let (boxes, centroids) = unit_boxes(&[Point3::new(1.0, 1.0, 1.0); 6]);
+        let mut order: Vec<usize> = (0..6).collect();
+
+        assert_eq!(split(&mut order, &boxes, &centroids, 4, 0), Split::At { axis: 2, mid: 3 });
+        assert_eq!(split(&mut order[..3], &boxes, &centroids, 4, 0), Split::Leaf);
+    }
+
+    #[test]
+    fn deep_splits_use_median() {
+        let centres: Vec<Point3> = (0..6).map(|i| Point3::new((i * i) as f32, 0.0, 0.0)).collect();
+        let (boxes, centroids) = unit_boxes(&centres);
+        let mut order: Vec<usize> = vec![5, 3, 1, 0, 2, 4];
+
+        assert_eq!(split(&mut order, &boxes, &centroids, 1, MAX_SAH_DEPTH), Split::At { axis: 0, mid: 3 });
+        let mut below = order[..3].to_vec();
+        below.sort_unstable();
+        assert_eq!(below, vec![0, 1, 2]);
+    }
+}
@@ -1,14 +1,20 @@
 use std::any::Any;
 use std::f32::consts::PI;
 use std::fmt::Debug;
-use std::rc::Rc;
+use std::sync::Arc;
+
+use rand::Rng;
 
 use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::normal::Normal3;
+use crate::geometry::orthonormal_basis::OrthonormalBasis;
 use crate::geometry::point::Point3;
 use crate::geometry::ray::Ray;
 use crate::geometry::vector::Vector3;
+use crate::util::random::random_unit_vector;
+use crate::util::sampler::Sampler;
 
-use super::super::element::Element;
+use super::super::element::{Element, SURFACE_TOLERANCE};
 use super::super::hit_record::HitRecord;
 use super::super::material::Material;
 
@@ -16,21 +22,20 @@ use super::super::material::Material;
 pub struct Sphere {
     pub centre: Point3,
     pub radius: f32,
-    pub material: Rc<dyn Material>,
+    pub material: Arc<dyn Material>,
 }
 
 impl Sphere {
-    pub fn new(centre: Point3, radius: f32, material: Rc<dyn Material>) -> Self {
+    pub fn new(centre: Point3, radius: f32, material: Arc<dyn Material>) -> Self {
         Sphere {
             centre: centre,
             radius: radius,
             material: material,
         }
     }
-}
-
-impl Element for Sphere {
 
+    /// Where the ray meets the sphere, between t_min and t_max.
+    ///
     /// We have a ray R(t) = A + tb and sphere of radius r centred at C. We have an intersection
     /// if there is t such that
     ///     (R(t) - C) . (R(t) - C) = r^2.
@@ -38,8 +43,8 @@ impl Element for Sphere {
     ///     (t^2)(b . b) + 2t(b . (A - C)) + ((A - C) . A - C) - r^2 = 0,
     /// a quadratic in t. We can then look at the discriminant ot see whether there are
     /// any solutions.
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let oc = ray.origin - self.centre.clone();
+    pub fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let oc = ray.origin - self.centre;
         let a = ray.direction.length_squared();
         let half_b = oc.dot(&ray.direction);
         let c = oc.length_squared() - (self.radius * self.radius);
@@ -51,9 +56,9 @@ impl Element for Sphere {
         }
 
         let sqrt_discriminant = discriminant.sqrt();
-        let mut root = ((half_b * -1.0) - sqrt_discriminant) / a;
+        let mut root = (-half_b - sqrt_discriminant) / a;
         if root < t_min || root > t_max {
-            root = ((half_b * -1.0) + sqrt_discriminant) / a;
+            root = (-half_b + sqrt_discriminant) / a;
             if root < t_min || root > t_max {
                 return None
             }
@@ -66,14 +71,20 @@ impl Element for Sphere {
 
         Some(HitRecord::new_from_incident_ray(
             p,
-            normal,
+            normal.as_normal3(),
             root,
             u,
             v,
-            &ray,
+            ray,
             self.material.clone(),
         ))
     }
+}
+
+impl Element for Sphere {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut Sampler) -> Option<HitRecord> {
+        self.intersect(ray, t_min, t_max)
+    }
 
     fn bounding_box(&self, _time_0: f32, _time_1: f32) -> Option<BoundingBox> {
         Some(BoundingBox::new(
@@ -82,6 +93,59 @@ impl Element for Sphere {
         ))
     }
 
+    /// Directions are sampled uniformly from the cone the sphere fills, so the
+    /// density is one over the cone's solid angle.
+    fn pdf_value(&self, origin: &Point3, direction: &Vector3, time: f32) -> f32 {
+        if self.intersect(&Ray::new_at_time(*origin, *direction, time), 0.001, f32::INFINITY).is_none() {
+            return 0.0;
+        }
+
+        match self.cone_one_minus_cos_theta_max(origin) {
+            Some(one_minus_cos_theta_max) => 1.0 / (2.0 * PI * one_minus_cos_theta_max),
+            None => 0.0,
+        }
+    }
+
+    fn sample_direction(&self, origin: &Point3, _time: f32, sampler: &mut Sampler) -> Option<Vector3> {
+        let one_minus_cos_theta_max = self.cone_one_minus_cos_theta_max(origin)?;
+
+        // The cosine of the angle from the cone's axis is uniform between 1 and
+        // cos_theta_max for a uniform solid angle.
+        let z = 1.0 - (sampler.gen::<f32>() * one_minus_cos_theta_max);
+        let phi = 2.0 * PI * sampler.gen::<f32>();
+        let r = (1.0 - (z * z)).max(0.0).sqrt();
+
+        let basis = OrthonormalBasis::new_from_w(&(self.centre - *origin));
+        Some(basis.local(&Vector3::new(r * phi.cos(), r * phi.sin(), z)))
+    }
+
+    fn sample_surface(&self, _time: f32, sampler: &mut Sampler) -> Option<(HitRecord, f32)> {
+        let normal = random_unit_vector(sampler);
+        let (u, v) = sphere_uv(&normal.as_point3());
+
+        let hit_record = HitRecord::new(
+            self.centre + (normal * self.radius),
+            normal.as_normal3(),
+            self.material.clone(),
+            0.0,
+            u,
+            v,
+            true,
+        );
+
+        Some((hit_record, 1.0 / self.area()))
+    }
+
+    fn surface_pdf(&self, point: &Point3, _normal: &Normal3, _time: f32) -> f32 {
+        let distance = (*point - self.centre).length();
+
+        if (distance - self.radius).abs() <= SURFACE_TOLERANCE * self.radius {
+            1.0 / self.area()
+        } else {
+            0.0
+        }
+    }
+
     fn eq(&self, other: &dyn Element) -> bool {
         other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
     }
@@ -91,6 +155,26 @@ impl Element for Sphere {
     }
 }
 
+impl Sphere {
+    pub fn area(&self) -> f32 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    /// One minus the cosine of the half angle of the cone the sphere fills seen
+    /// from origin, or None from inside the sphere. This is worked out from the
+    /// sine squared, as the cosine of the tiny cone of a far sphere rounds to one.
+    fn cone_one_minus_cos_theta_max(&self, origin: &Point3) -> Option<f32> {
+        let distance_squared = (self.centre - *origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return None;
+        }
+
+        let sin_squared = radius_squared / distance_squared;
+        Some(sin_squared / (1.0 + (1.0 - sin_squared).sqrt()))
+    }
+}
+
 impl PartialEq for Sphere {
     fn eq(&self, other: &Self) -> bool {
         (self.centre == other.centre) &&
@@ -126,6 +210,7 @@ pub fn sphere_uv(p: &Point3) -> (f32, f32) {
 mod tests {
     use crate::geometry::vector::Vector3;
     use crate::graphics::colour::Colour;
+    use crate::util::testing::assertions::{assert_direction_pdf_normalised, assert_surface_pdf_uniform};
 
     use super::*;
     use super::super::super::materials::lambertian::Lambertian;
@@ -136,12 +221,12 @@ mod tests {
             Sphere::new(
                 Point3::new(1.0, 2.0, 3.0),
                 5.0,
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
             ),
             Sphere {
                 centre: Point3 { x: 1.0, y: 2.0, z: 3.0 },
                 radius: 5.0,
-                material: Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                material: Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
             },
         )
     }
@@ -152,17 +237,17 @@ mod tests {
         let sphere = Sphere::new(
             Point3::new(3.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         );
 
-        let record = sphere.hit(&ray, 0.0, f32::INFINITY);
+        let record = sphere.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0));
 
         assert_eq!(
             record,
             Some(HitRecord::new(
                 Point3::new(2.0, 0.0, 0.0),
-                Vector3::new(-1.0, 0.0, 0.0),
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Normal3::new(-1.0, 0.0, 0.0),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 2.0,
                 0.0,
                 0.5,
@@ -177,10 +262,10 @@ mod tests {
         let sphere = Sphere::new(
             Point3::new(3.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         );
 
-        let record = sphere.hit(&ray, 5.0, f32::INFINITY);
+        let record = sphere.hit(&ray, 5.0, f32::INFINITY, &mut Sampler::new(0));
 
         let expected: Option<HitRecord> = None;
         assert_eq!(
@@ -195,10 +280,10 @@ mod tests {
         let sphere = Sphere::new(
             Point3::new(3.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         );
 
-        let record = sphere.hit(&ray, 0.0, 1.0);
+        let record = sphere.hit(&ray, 0.0, 1.0, &mut Sampler::new(0));
 
         let expected: Option<HitRecord> = None;
         assert_eq!(
@@ -213,17 +298,17 @@ mod tests {
         let sphere = Sphere::new(
             Point3::new(3.0, 0.0, 0.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         );
 
-        let record = sphere.hit(&ray, 3.0, f32::INFINITY);
+        let record = sphere.hit(&ray, 3.0, f32::INFINITY, &mut Sampler::new(0));
 
         assert_eq!(
             record,
             Some(HitRecord::new(
                 Point3::new(4.0, 0.0, 0.0),
-                Vector3::new(-1.0, 0.0, 0.0),
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Normal3::new(-1.0, 0.0, 0.0),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 4.0,
                 0.5,
                 0.5,
@@ -232,12 +317,65 @@ mod tests {
         );
     }
 
+    #[test]
+    fn sample_sphere_directions() {
+        let sphere = Sphere::new(
+            Point3::new(3.0, 1.0, 0.0),
+            2.0,
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+        );
+
+        assert_direction_pdf_normalised(&sphere, &Point3::new(0.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn sample_sphere_surface() {
+        let sphere = Sphere::new(
+            Point3::new(1.0, -2.0, 0.5),
+            2.0,
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+        );
+
+        assert_surface_pdf_uniform(&sphere, 16.0 * PI);
+    }
+
+    #[test]
+    fn sample_sphere_from_inside() {
+        let sphere = Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            2.0,
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+        );
+        let origin = Point3::new(0.5, 0.0, 0.0);
+
+        assert!(sphere.sample_direction(&origin, 0.0, &mut Sampler::new(1)).is_none());
+        assert_eq!(sphere.pdf_value(&origin, &Vector3::new(1.0, 0.0, 0.0), 0.0), 0.0);
+    }
+
+    #[test]
+    fn pdf_of_far_sphere_is_finite() {
+        let sphere = Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            0.25,
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+        );
+        let origin = Point3::new(0.0, -2000.0, 0.0);
+
+        // The cone is 2 pi (1 - cos) = pi sin^2 across, to first order.
+        let pdf = sphere.pdf_value(&origin, &Vector3::new(0.0, 1.0, 0.0), 0.0);
+        let expected = 1.0 / (PI * (0.25f32 / 2000.0).powi(2));
+        assert!((pdf / expected - 1.0).abs() < 1e-3, "{} is not near {}", pdf, expected);
+
+        let direction = sphere.sample_direction(&origin, 0.0, &mut Sampler::new(1)).unwrap();
+        assert!(direction.x.is_finite() && direction.y > 0.0);
+    }
+
     #[test]
     fn sphere_bounding_box() {
         let s = Sphere::new(
             Point3::new(1.0, 2.0, 3.0),
             1.0,
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
         );
 
         assert_eq!(
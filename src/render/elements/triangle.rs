@@ -0,0 +1,341 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::point::Point3;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::Vector3;
+use crate::util::sampler::Sampler;
+
+use super::super::element::Element;
+use super::super::hit_record::HitRecord;
+use super::super::material::Material;
+
+/// Thickness given to the bounding box of a triangle lying in an axis plane.
+pub const BOUNDING_BOX_PADDING: f32 = 1e-4;
+
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    pub vertices: [Point3; 3],
+    pub normals: Option<[Vector3; 3]>,
+    pub uvs: [(f32, f32); 3],
+    pub material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    /// A flat shaded triangle with texture coordinates (0, 0), (1, 0) and (0, 1)
+    /// at its vertices.
+    pub fn new(vertices: [Point3; 3], material: Arc<dyn Material>) -> Self {
+        Triangle {
+            vertices: vertices,
+            normals: None,
+            uvs: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            material: material,
+        }
+    }
+
+    /// A triangle with normals and texture coordinates at its vertices, which are
+    /// interpolated across it.
+    pub fn new_with_attributes(
+        vertices: [Point3; 3],
+        normals: Option<[Vector3; 3]>,
+        uvs: [(f32, f32); 3],
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Triangle {
+            vertices: vertices,
+            normals: normals,
+            uvs: uvs,
+            material: material,
+        }
+    }
+}
+
+impl Element for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut Sampler) -> Option<HitRecord> {
+        let (t, barycentrics) = intersect(ray, &self.vertices, t_min, t_max)?;
+
+        Some(hit_record(
+            ray,
+            t,
+            &barycentrics,
+            &self.vertices,
+            self.normals.as_ref(),
+            &self.uvs,
+            self.material.clone(),
+        ))
+    }
+
+    fn bounding_box(&self, _time_0: f32, _time_1: f32) -> Option<BoundingBox> {
+        Some(BoundingBox::from_points(&self.vertices).padded(BOUNDING_BOX_PADDING))
+    }
+
+    fn eq(&self, other: &dyn Element) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PartialEq for Triangle {
+    fn eq(&self, other: &Self) -> bool {
+        (self.vertices == other.vertices) &&
+        (self.normals == other.normals) &&
+        (self.uvs == other.uvs) &&
+        (*self.material == *other.material)
+    }
+}
+
+/// The watertight ray triangle test of Woop, Benthin and Wald (2013). Returns the
+/// ray parameter and the barycentric weights of the three vertices.
+///
+/// We translate the vertices so the ray starts at the origin, permute the axes so
+/// the ray is mostly along z, and shear so it points exactly along z. The test is
+/// then in 2D, with the edge functions computed the same way for a shared edge
+/// whichever triangle it belongs to, so a ray can't slip between neighbours.
+pub fn intersect(ray: &Ray, vertices: &[Point3; 3], t_min: f32, t_max: f32) -> Option<(f32, [f32; 3])> {
+    let direction = [ray.direction.x, ray.direction.y, ray.direction.z];
+
+    // z is the axis along which the ray is longest.
+    let kz = largest_axis(&direction);
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    // Swap to keep the winding, and so the sign of the edge functions.
+    if direction[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    let shear_x = direction[kx] / direction[kz];
+    let shear_y = direction[ky] / direction[kz];
+    let shear_z = 1.0 / direction[kz];
+
+    let relative = |p: &Point3| {
+        let v = *p - ray.origin;
+        [v.x, v.y, v.z]
+    };
+    let a = relative(&vertices[0]);
+    let b = relative(&vertices[1]);
+    let c = relative(&vertices[2]);
+
+    let a_x = a[kx] - (shear_x * a[kz]);
+    let a_y = a[ky] - (shear_y * a[kz]);
+    let b_x = b[kx] - (shear_x * b[kz]);
+    let b_y = b[ky] - (shear_y * b[kz]);
+    let c_x = c[kx] - (shear_x * c[kz]);
+    let c_y = c[ky] - (shear_y * c[kz]);
+
+    let mut u = (c_x * b_y) - (c_y * b_x);
+    let mut v = (a_x * c_y) - (a_y * c_x);
+    let mut w = (b_x * a_y) - (b_y * a_x);
+
+    // On an edge, fall back to double precision to decide which side we are on.
+    if u == 0.0 || v == 0.0 || w == 0.0 {
+        u = ((c_x as f64 * b_y as f64) - (c_y as f64 * b_x as f64)) as f32;
+        v = ((a_x as f64 * c_y as f64) - (a_y as f64 * c_x as f64)) as f32;
+        w = ((b_x as f64 * a_y as f64) - (b_y as f64 * a_x as f64)) as f32;
+    }
+
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+
+    let determinant = u + v + w;
+    if determinant == 0.0 {
+        return None;
+    }
+
+    let a_z = shear_z * a[kz];
+    let b_z = shear_z * b[kz];
+    let c_z = shear_z * c[kz];
+
+    let t = ((u * a_z) + (v * b_z) + (w * c_z)) / determinant;
+    if t < t_min || t > t_max {
+        return None;
+    }
+
+    Some((t, [u / determinant, v / determinant, w / determinant]))
+}
+
+/// Builds the hit record for a hit at t with the given barycentric weights,
+/// interpolating the normals, if any, and the texture coordinates.
+///
+/// The normal faces the same side as the interpolated normal, or follows the
+/// counterclockwise winding of the vertices if there are none.
+pub fn hit_record(
+    ray: &Ray,
+    t: f32,
+    barycentrics: &[f32; 3],
+    vertices: &[Point3; 3],
+    normals: Option<&[Vector3; 3]>,
+    uvs: &[(f32, f32); 3],
+    material: Arc<dyn Material>,
+) -> HitRecord {
+    let [b_0, b_1, b_2] = *barycentrics;
+
+    let mut geometric_normal = (vertices[1] - vertices[0]).cross(&(vertices[2] - vertices[0])).normalise();
+    let shading_normal = match normals {
+        Some(normals) => {
+            let normal = ((normals[0] * b_0) + (normals[1] * b_1) + (normals[2] * b_2)).normalise();
+            if normal.dot(&geometric_normal) < 0.0 {
+                geometric_normal = -geometric_normal;
+            }
+            normal
+        },
+        None => geometric_normal,
+    };
+
+    let front_face = ray.direction.dot(&geometric_normal) < 0.0;
+    let normal = if front_face { shading_normal } else { -shading_normal };
+
+    let u = (uvs[0].0 * b_0) + (uvs[1].0 * b_1) + (uvs[2].0 * b_2);
+    let v = (uvs[0].1 * b_0) + (uvs[1].1 * b_1) + (uvs[2].1 * b_2);
+
+    HitRecord::new(
+        ray.at(t),
+        normal.as_normal3(),
+        material,
+        t,
+        u,
+        v,
+        front_face,
+    )
+}
+
+fn largest_axis(v: &[f32; 3]) -> usize {
+    let (x, y, z) = (v[0].abs(), v[1].abs(), v[2].abs());
+
+    if x > y && x > z {
+        0
+    } else if y > z {
+        1
+    } else {
+        2
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use crate::geometry::normal::Normal3;
+    use crate::graphics::colour::Colour;
+
+    use super::*;
+    use super::super::super::materials::lambertian::Lambertian;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            [Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)],
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+        )
+    }
+
+    #[test]
+    fn hit_triangle() {
+        let ray = Ray::new(Point3::new(0.25, 0.5, 2.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert_eq!(
+            triangle().hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)),
+            Some(HitRecord::new(
+                Point3::new(0.25, 0.5, 0.0),
+                Normal3::new(0.0, 0.0, 1.0),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                2.0,
+                0.25,
+                0.5,
+                true,
+            )),
+        );
+    }
+
+    #[test]
+    fn hit_triangle_back_face() {
+        let ray = Ray::new(Point3::new(0.25, 0.25, -1.0), Vector3::new(0.0, 0.0, 2.0));
+
+        let record = triangle().hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+
+        assert_eq!(record.t, 0.5);
+        assert_eq!(record.normal, Normal3::new(0.0, 0.0, -1.0));
+        assert_eq!(record.front_face, false);
+    }
+
+    #[test]
+    fn miss_triangle() {
+        let ray = Ray::new(Point3::new(0.75, 0.75, 2.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert!(triangle().hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).is_none());
+    }
+
+    #[test]
+    fn miss_triangle_outside_t_range() {
+        let ray = Ray::new(Point3::new(0.25, 0.25, 2.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert!(triangle().hit(&ray, 0.0, 1.0, &mut Sampler::new(0)).is_none());
+        assert!(triangle().hit(&ray, 3.0, f32::INFINITY, &mut Sampler::new(0)).is_none());
+    }
+
+    #[test]
+    fn miss_triangle_edge_on() {
+        let ray = Ray::new(Point3::new(-1.0, 0.25, 0.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert!(triangle().hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).is_none());
+    }
+
+    #[test]
+    fn hit_shared_edge_watertight() {
+        // Two triangles making a quad. A ray exactly along the diagonal must hit
+        // at least one of them.
+        let material: Arc<dyn Material> = Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3)));
+        let first = Triangle::new(
+            [Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(1.0, 1.0, 0.0)],
+            material.clone(),
+        );
+        let second = Triangle::new(
+            [Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 0.0), Point3::new(0.0, 1.0, 0.0)],
+            material,
+        );
+
+        for i in 1..100 {
+            let x = (i as f32) / 100.0;
+            let direction = Vector3::new(0.3, -0.1, -1.0);
+            let ray = Ray::new(Point3::new(x, x, 0.0) - (direction * 2.0), direction);
+
+            assert!(
+                first.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).is_some() ||
+                second.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).is_some()
+            );
+        }
+    }
+
+    #[test]
+    fn hit_triangle_interpolates_attributes() {
+        let triangle = Triangle::new_with_attributes(
+            [Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)],
+            Some([
+                Vector3::new(0.0, 0.0, 1.0),
+                Vector3::new(1.0, 0.0, 1.0).normalise(),
+                Vector3::new(0.0, 0.0, 1.0),
+            ]),
+            [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)],
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+        );
+        let ray = Ray::new(Point3::new(0.5, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+
+        let record = triangle.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+
+        assert_eq!(record.u, 0.5);
+        assert_eq!(record.v, 0.0);
+        assert!(record.normal.x > 0.3 && record.normal.z > 0.9);
+        assert!((record.normal.length() - 1.0).abs() < 1e-6);
+        assert_eq!(record.front_face, true);
+    }
+
+    #[test]
+    fn triangle_bounding_box_is_padded() {
+        let b = triangle().bounding_box(0.0, 1.0).unwrap();
+
+        assert_eq!(b.min, Point3::new(0.0, 0.0, -BOUNDING_BOX_PADDING / 2.0));
+        assert_eq!(b.max, Point3::new(1.0, 1.0, BOUNDING_BOX_PADDING / 2.0));
+    }
+}
@@ -0,0 +1,315 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use crate::geometry::bounding_box::BoundingBox;
+use crate::geometry::point::Point3;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::Vector3;
+use crate::graphics::colour::Colour;
+use crate::util::sampler::Sampler;
+
+use super::super::element::Element;
+use super::super::hit_record::HitRecord;
+use super::super::material::Material;
+use super::linear_bvh::LinearBvh;
+use super::triangle::{hit_record, intersect};
+
+/// Most triangles in a leaf of the mesh BVH.
+const MAX_LEAF_TRIANGLES: usize = 4;
+
+/// A mesh of triangles sharing one material, with vertices shared between
+/// triangles. Normals and texture coordinates, when given, are per vertex and
+/// interpolated across each triangle.
+///
+/// The mesh keeps its own BVH over its triangles, so it is a single element to
+/// the rest of the scene however many triangles it has.
+#[derive(Debug, Clone)]
+pub struct TriangleMesh {
+    positions: Vec<Point3>,
+    normals: Option<Vec<Vector3>>,
+    uvs: Option<Vec<(f32, f32)>>,
+    colours: Option<Vec<Colour>>,
+    triangles: Vec<[usize; 3]>,
+    material: Arc<dyn Material>,
+    bvh: LinearBvh,
+}
+
+impl TriangleMesh {
+    /// A flat shaded mesh. Each triangle is three indices into positions.
+    pub fn new(positions: Vec<Point3>, triangles: Vec<[usize; 3]>, material: Arc<dyn Material>) -> Self {
+        Self::new_with_attributes(positions, None, None, triangles, material)
+    }
+
+    /// A mesh with optional per vertex normals and texture coordinates, each the
+    /// same length as positions.
+    ///
+    /// Panics if there are no triangles, an index is out of range or the
+    /// attributes don't match the positions.
+    pub fn new_with_attributes(
+        positions: Vec<Point3>,
+        normals: Option<Vec<Vector3>>,
+        uvs: Option<Vec<(f32, f32)>>,
+        triangles: Vec<[usize; 3]>,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        if triangles.is_empty() {
+            panic!("mesh has no triangles");
+        }
+        if triangles.iter().flatten().any(|&i| i >= positions.len()) {
+            panic!("triangle index out of range");
+        }
+        if normals.as_ref().map_or(false, |n| n.len() != positions.len()) {
+            panic!("normals don't match positions");
+        }
+        if uvs.as_ref().map_or(false, |uv| uv.len() != positions.len()) {
+            panic!("texture coordinates don't match positions");
+        }
+
+        // Each leaf of the BVH covers a contiguous run of triangles, so they are
+        // stored in the order it gives.
+        let boxes: Vec<BoundingBox> = triangles
+            .iter()
+            .map(|&[a, b, c]| BoundingBox::from_points(&[positions[a], positions[b], positions[c]]))
+            .collect();
+        let (bvh, order) = LinearBvh::build(&boxes, MAX_LEAF_TRIANGLES);
+
+        TriangleMesh {
+            positions: positions,
+            normals: normals,
+            uvs: uvs,
+            colours: None,
+            triangles: order.iter().map(|&i| triangles[i]).collect(),
+            material: material,
+            bvh: bvh,
+        }
+    }
+
+    /// Adds per vertex colours, interpolated into the hit record for the
+    /// VertexColour texture. Panics if they don't match the positions.
+    pub fn with_colours(mut self, colours: Vec<Colour>) -> Self {
+        if colours.len() != self.positions.len() {
+            panic!("colours don't match positions");
+        }
+        self.colours = Some(colours);
+
+        self
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    fn vertices(&self, triangle: usize) -> [Point3; 3] {
+        let [a, b, c] = self.triangles[triangle];
+
+        [self.positions[a], self.positions[b], self.positions[c]]
+    }
+
+    fn hit_triangle(&self, triangle: usize, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let vertices = self.vertices(triangle);
+        let (t, barycentrics) = intersect(ray, &vertices, t_min, t_max)?;
+
+        let [a, b, c] = self.triangles[triangle];
+        let normals = self.normals.as_ref().map(|n| [n[a], n[b], n[c]]);
+        let uvs = match self.uvs {
+            Some(ref uvs) => [uvs[a], uvs[b], uvs[c]],
+            None => [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+        };
+
+        let mut hit_record = hit_record(
+            ray,
+            t,
+            &barycentrics,
+            &vertices,
+            normals.as_ref(),
+            &uvs,
+            self.material.clone(),
+        );
+
+        if let Some(ref colours) = self.colours {
+            let [b_0, b_1, b_2] = barycentrics;
+            hit_record.vertex_colour = Some((colours[a] * b_0) + (colours[b] * b_1) + (colours[c] * b_2));
+        }
+
+        Some(hit_record)
+    }
+}
+
+impl Element for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut Sampler) -> Option<HitRecord> {
+        self.bvh.hit(ray, t_min, t_max, |triangle, ray, t_min, t_max| self.hit_triangle(triangle, ray, t_min, t_max))
+    }
+
+    fn bounding_box(&self, _time_0: f32, _time_1: f32) -> Option<BoundingBox> {
+        Some(*self.bvh.bounding_box())
+    }
+
+    fn eq(&self, other: &dyn Element) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PartialEq for TriangleMesh {
+    fn eq(&self, other: &Self) -> bool {
+        (self.positions == other.positions) &&
+        (self.normals == other.normals) &&
+        (self.uvs == other.uvs) &&
+        (self.colours == other.colours) &&
+        (self.triangles == other.triangles) &&
+        (*self.material == *other.material)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::normal::Normal3;
+    use crate::graphics::colour::Colour;
+
+    use super::*;
+    use super::super::super::materials::lambertian::Lambertian;
+    use super::super::triangle::Triangle;
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3)))
+    }
+
+    /// A bumpy n by n grid of quads in the xz plane, two triangles each.
+    fn grid(n: usize) -> (Vec<Point3>, Vec<[usize; 3]>) {
+        let mut positions = Vec::new();
+        for z in 0..=n {
+            for x in 0..=n {
+                let height = ((x * 7 + z * 13) % 5) as f32 * 0.1;
+                positions.push(Point3::new(x as f32, height, z as f32));
+            }
+        }
+
+        let mut triangles = Vec::new();
+        for z in 0..n {
+            for x in 0..n {
+                let i = (z * (n + 1)) + x;
+                triangles.push([i, i + n + 1, i + 1]);
+                triangles.push([i + 1, i + n + 1, i + n + 2]);
+            }
+        }
+
+        (positions, triangles)
+    }
+
+    #[test]
+    fn mesh_counts() {
+        let (positions, triangles) = grid(3);
+        let mesh = TriangleMesh::new(positions, triangles, material());
+
+        assert_eq!(mesh.vertex_count(), 16);
+        assert_eq!(mesh.triangle_count(), 18);
+    }
+
+    #[test]
+    #[should_panic(expected = "triangle index out of range")]
+    fn mesh_index_out_of_range() {
+        TriangleMesh::new(
+            vec![Point3::zero(), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)],
+            vec![[0, 1, 3]],
+            material(),
+        );
+    }
+
+    #[test]
+    fn mesh_bounding_box() {
+        let (positions, triangles) = grid(4);
+        let mesh = TriangleMesh::new(positions, triangles, material());
+
+        let b = mesh.bounding_box(0.0, 1.0).unwrap();
+        assert_eq!(b.min, Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(b.max, Point3::new(4.0, 0.4, 4.0));
+    }
+
+    #[test]
+    fn mesh_matches_triangles() {
+        let (positions, triangles) = grid(20);
+        let mesh = TriangleMesh::new(positions.clone(), triangles.clone(), material());
+        let separate: Vec<Triangle> = triangles
+            .iter()
+            .map(|t| Triangle::new([positions[t[0]], positions[t[1]], positions[t[2]]], material()))
+            .collect();
+
+        for i in 0..200 {
+            let origin = Point3::new((i % 23) as f32 - 1.5, 3.0, (i % 19) as f32 - 0.5);
+            let direction = Vector3::new(0.05 * ((i % 7) as f32 - 3.0), -1.0, 0.03 * ((i % 11) as f32 - 5.0));
+            let ray = Ray::new(origin, direction);
+
+            let expected = separate
+                .iter()
+                .filter_map(|t| t.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)))
+                .min_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+
+            assert_eq!(
+                mesh.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)).map(|r| (r.point, r.t)),
+                expected.map(|r| (r.point, r.t)),
+            );
+        }
+    }
+
+    #[test]
+    fn mesh_watertight() {
+        let (positions, triangles) = grid(8);
+        let mesh = TriangleMesh::new(positions, triangles, material());
+
+        // Rays through vertices and along edges of the grid.
+        for z in 1..8 {
+            for x in 1..8 {
+                let ray = Ray::new(Point3::new(x as f32, 5.0, z as f32), Vector3::new(0.0, -1.0, 0.0));
+                assert!(mesh.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)).is_some());
+
+                let ray = Ray::new(Point3::new(x as f32 + 0.5, 5.0, z as f32), Vector3::new(0.0, -1.0, 0.0));
+                assert!(mesh.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn mesh_interpolates_attributes() {
+        let mesh = TriangleMesh::new_with_attributes(
+            vec![Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 0.0, 0.0), Point3::new(0.0, 2.0, 0.0)],
+            Some(vec![Vector3::new(0.0, 0.0, 1.0); 3]),
+            Some(vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]),
+            vec![[0, 1, 2]],
+            material(),
+        );
+        let ray = Ray::new(Point3::new(1.0, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+
+        let record = mesh.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+
+        assert_eq!(record.u, 0.5);
+        assert_eq!(record.v, 0.25);
+        assert_eq!(record.normal, Normal3::new(0.0, 0.0, 1.0));
+        assert_eq!(record.t, 1.0);
+        assert_eq!(record.vertex_colour, None);
+    }
+
+    #[test]
+    fn mesh_interpolates_colours() {
+        let mesh = TriangleMesh::new(
+            vec![Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 0.0, 0.0), Point3::new(0.0, 2.0, 0.0)],
+            vec![[0, 1, 2]],
+            material(),
+        ).with_colours(vec![
+            Colour::new(1.0, 0.0, 0.0),
+            Colour::new(0.0, 1.0, 0.0),
+            Colour::new(0.0, 0.0, 1.0),
+        ]);
+        let ray = Ray::new(Point3::new(1.0, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+
+        let record = mesh.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+
+        assert_eq!(record.vertex_colour, Some(Colour::new(0.25, 0.5, 0.25)));
+    }
+}
@@ -1,8 +1,9 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
+use crate::geometry::normal::Normal3;
 use crate::geometry::point::Point3;
 use crate::geometry::ray::Ray;
-use crate::geometry::vector::Vector3;
+use crate::graphics::colour::Colour;
 
 use super::material::Material;
 
@@ -11,19 +12,24 @@ use super::material::Material;
 // The front_face flag tells us whether we hit the outside or inside.
 pub struct HitRecord {
     pub point: Point3,
-    pub normal: Vector3,
-    pub material: Rc<dyn Material>,
+    pub normal: Normal3,
+    pub material: Arc<dyn Material>,
     pub t: f32,
     pub u: f32,
     pub v: f32,
     pub front_face: bool,
+    /// The colour interpolated from the vertices, for meshes that have them.
+    pub vertex_colour: Option<Colour>,
+    /// Whether the ray scattered inside a medium rather than met a surface, in
+    /// which case the normal means nothing.
+    pub medium: bool,
 }
 
 impl HitRecord {
     pub fn new(
         point: Point3,
-        normal: Vector3,
-        material: Rc<dyn Material>,
+        normal: Normal3,
+        material: Arc<dyn Material>,
         t: f32,
         u: f32,
         v: f32,
@@ -37,20 +43,22 @@ impl HitRecord {
             u: u,
             v: v,
             front_face: front_face,
+            vertex_colour: None,
+            medium: false,
         }
     }
 
     pub fn new_from_incident_ray(
         point: Point3,
-        outward_normal: Vector3,
+        outward_normal: Normal3,
         t: f32,
         u: f32,
         v: f32,
         ray: &Ray,
-        material: Rc<dyn Material>,
+        material: Arc<dyn Material>,
     ) -> Self {
-        let front_face = ray.direction.dot(&outward_normal) < 0.0;
-        let normal = if front_face { outward_normal } else { outward_normal * -1.0 };
+        let front_face = outward_normal.dot(&ray.direction) < 0.0;
+        let normal = if front_face { outward_normal } else { -outward_normal };
 
         HitRecord {
             point: point,
@@ -60,6 +68,23 @@ impl HitRecord {
             u: u,
             v: v,
             front_face: front_face,
+            vertex_colour: None,
+            medium: false,
+        }
+    }
+
+    /// Where a ray scattered at t inside a medium made of the material.
+    pub fn new_in_medium(point: Point3, material: Arc<dyn Material>, t: f32) -> Self {
+        HitRecord {
+            point: point,
+            normal: Normal3::new(1.0, 0.0, 0.0),
+            material: material,
+            t: t,
+            u: 0.0,
+            v: 0.0,
+            front_face: true,
+            vertex_colour: None,
+            medium: true,
         }
     }
 }
@@ -72,13 +97,15 @@ impl PartialEq for HitRecord {
         (self.t == other.t) &&
         (self.u == other.u) &&
         (self.v == other.v) &&
-        (self.front_face == other.front_face)
+        (self.front_face == other.front_face) &&
+        (self.vertex_colour == other.vertex_colour) &&
+        (self.medium == other.medium)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::graphics::colour::Colour;
+    use crate::geometry::vector::Vector3;
 
     use super::*;
     use super::super::materials::lambertian::Lambertian;
@@ -88,8 +115,8 @@ mod tests {
         assert_eq!(
             HitRecord::new(
                 Point3::new(1.0, 2.0, 3.0),
-                Vector3::new(4.0, 5.0, 6.0),
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Normal3::new(4.0, 5.0, 6.0),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 7.0,
                 0.1,
                 0.2,
@@ -97,12 +124,14 @@ mod tests {
             ),
             HitRecord {
                 point: Point3::new(1.0, 2.0, 3.0),
-                normal: Vector3::new(4.0, 5.0, 6.0),
-                material: Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                normal: Normal3::new(4.0, 5.0, 6.0),
+                material: Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 t: 7.0,
                 u: 0.1,
                 v: 0.2,
                 front_face: true,
+                vertex_colour: None,
+                medium: false,
             },
         )
     }
@@ -112,7 +141,7 @@ mod tests {
         assert_eq!(
             HitRecord::new_from_incident_ray(
                 Point3::new(1.0, 2.0, 3.0),
-                Vector3::new(4.0, 5.0, 6.0),
+                Normal3::new(4.0, 5.0, 6.0),
                 7.0,
                 0.1,
                 0.2,
@@ -120,16 +149,18 @@ mod tests {
                     Point3::zero(),
                     Vector3::new(1.0, 0.0, 0.0),
                 ),
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
             ),
             HitRecord {
                 point: Point3::new(1.0, 2.0, 3.0),
-                normal: Vector3::new(-4.0, -5.0, -6.0),
-                material: Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                normal: Normal3::new(-4.0, -5.0, -6.0),
+                material: Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 t: 7.0,
                 u: 0.1,
                 v: 0.2,
                 front_face: false,
+                vertex_colour: None,
+                medium: false,
             },
         )
     }
@@ -139,7 +170,7 @@ mod tests {
         assert_eq!(
             HitRecord::new_from_incident_ray(
                 Point3::new(1.0, 2.0, 3.0),
-                Vector3::new(4.0, 5.0, 6.0),
+                Normal3::new(4.0, 5.0, 6.0),
                 7.0,
                 0.1,
                 0.2,
@@ -147,16 +178,18 @@ mod tests {
                     Point3::zero(),
                     Vector3::new(-1.0, 0.0, 0.0),
                 ),
-                Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
             ),
             HitRecord {
                 point: Point3::new(1.0, 2.0, 3.0),
-                normal: Vector3::new(4.0, 5.0, 6.0),
-                material: Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+                normal: Normal3::new(4.0, 5.0, 6.0),
+                material: Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
                 t: 7.0,
                 u: 0.1,
                 v: 0.2,
                 front_face: true,
+                vertex_colour: None,
+                medium: false,
             },
         );
     }
@@ -0,0 +1,142 @@
+use std::fmt::Debug;
+
+use crate::geometry::ray::Ray;
+use crate::graphics::colour::Colour;
+use crate::util::sampler::Sampler;
+
+use super::camera::Camera;
+use super::element::Element;
+use super::hit_record::HitRecord;
+use super::lights::Lights;
+
+/// A way of working out the light carried back along a ray.
+pub trait Integrator : Debug + Send + Sync {
+    /// The light arriving back along the ray from the world, which is lit by
+    /// the background where rays escape it, and can sample the lights directly.
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+        sampler: &mut Sampler,
+    ) -> Colour;
+
+    /// The light arriving back along the ray from the camera, as radiance finds
+    /// it. Integrators that trace light from the lights to the camera may also
+    /// find light reaching other parts of the image, which they add to splats.
+    #[allow(clippy::too_many_arguments)]
+    fn radiance_and_splats(
+        &self,
+        ray: &Ray,
+        _camera: &Camera,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+        sampler: &mut Sampler,
+        _splats: &mut Vec<Splat>,
+    ) -> Colour {
+        self.radiance(ray, world, lights, background, sampler)
+    }
+
+    /// Prepares for a pass of a render with the given seed, before any light is
+    /// asked for in it. Integrators that trace photons from the lights trace
+    /// those for the pass here; others have nothing to do.
+    fn begin_pass(&mut self, _pass: usize, _camera: &Camera, _world: &dyn Element, _lights: &Lights, _seed: u64) {}
+}
+
+/// Light found reaching the camera at the image coordinates (s, t), as the
+/// camera's get_ray takes them, rather than along the ray being traced. The
+/// renderer adds up the splats from every sample over the image.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Splat {
+    pub s: f32,
+    pub t: f32,
+    pub colour: Colour,
+}
+
+/// The integrators that can be chosen in the render settings.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum IntegratorKind {
+    /// Unidirectional path tracing, with light sampling.
+    Path,
+    /// Only light arriving straight from emitters, after any mirrors and glass.
+    Direct,
+    /// The fraction of the hemisphere left open within the distance.
+    AmbientOcclusion { distance: f32 },
+    /// Light sampled from emitters and traced through mirrors and glass, but
+    /// never bounced off diffuse surfaces.
+    Whitted,
+    /// Paths traced from both the camera and the lights, and joined.
+    Bidirectional,
+    /// Light gathered from photons traced from the lights, at the first surface
+    /// past any mirrors and glass, and sampled from the lights directly there.
+    Photon { photons: usize, radius: f32 },
+    /// Unidirectional path tracing, with the light focused through mirrors and
+    /// glass onto surfaces gathered from photons instead.
+    PathWithCaustics { photons: usize, radius: f32 },
+}
+
+/// The light reaching the hit along a direction sampled towards the lights and
+/// scattered back along the ray, divided by the density of the sample. A shadow
+/// ray finds what is actually seen that way, which may not be the light.
+///
+/// If mis is set, the result is weighted against the chance of the material
+/// having scattered that way itself, for integrators that also follow the
+/// material's own samples to lights.
+pub fn sample_light(
+    ray: &Ray,
+    hit_record: &HitRecord,
+    background: &Colour,
+    world: &dyn Element,
+    lights: &Lights,
+    mis: bool,
+    sampler: &mut Sampler,
+) -> Colour {
+    let black = Colour::new(0.0, 0.0, 0.0);
+
+    let direction = match lights.sample_direction(&hit_record.point, ray.time, sampler) {
+        Some(direction) => direction,
+        None => return black,
+    };
+    let light_pdf = lights.pdf_value(&hit_record.point, &direction, ray.time);
+    let scattering = hit_record.material.evaluate(ray, hit_record, &direction);
+    if light_pdf <= 0.0 || scattering == black {
+        return black;
+    }
+
+    let shadow_ray = Ray::new_at_time(hit_record.point, direction, ray.time);
+    let incoming = match world.hit(&shadow_ray, 0.001, f32::INFINITY, sampler) {
+        Some(light_hit) => light_hit.material.emit(light_hit.u, light_hit.v, &light_hit.point),
+        None => *background,
+    };
+
+    let weight = if mis {
+        power_heuristic(light_pdf, hit_record.material.pdf(ray, hit_record, &direction))
+    } else {
+        1.0
+    };
+
+    incoming * scattering * (weight / light_pdf)
+}
+
+/// The multiple importance sampling weight for a sample taken with density
+/// pdf, when another strategy could have taken it with density other_pdf.
+pub fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+
+    a / (a + b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_heuristic_favours_larger_pdf() {
+        assert_eq!(power_heuristic(1.0, 0.0), 1.0);
+        assert_eq!(power_heuristic(0.0, 1.0), 0.0);
+        assert_eq!(power_heuristic(2.0, 2.0), 0.5);
+        assert_eq!(power_heuristic(3.0, 1.0) + power_heuristic(1.0, 3.0), 1.0);
+    }
+}
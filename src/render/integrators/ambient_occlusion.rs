@@ -0,0 +1,133 @@
+use crate::geometry::orthonormal_basis::OrthonormalBasis;
+use crate::geometry::ray::Ray;
+use crate::graphics::colour::Colour;
+use crate::util::random::random_cosine_direction;
+use crate::util::sampler::Sampler;
+
+use super::super::element::Element;
+use super::super::integrator::Integrator;
+use super::super::lights::Lights;
+
+/// Ambient occlusion: how open the first surface seen is, as the fraction of
+/// directions around it, weighted by the cosine to the normal, that reach no
+/// other surface within distance. Materials and lights are ignored, and rays
+/// that miss everything see black.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct AmbientOcclusionIntegrator {
+    pub distance: f32,
+}
+
+impl AmbientOcclusionIntegrator {
+    pub fn new(distance: f32) -> Self {
+        AmbientOcclusionIntegrator {
+            distance: distance,
+        }
+    }
+}
+
+impl Integrator for AmbientOcclusionIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Element,
+        _lights: &Lights,
+        _background: &Colour,
+        sampler: &mut Sampler,
+    ) -> Colour {
+        let hit_record = match world.hit(ray, 0.001, f32::INFINITY, sampler) {
+            Some(hit_record) => hit_record,
+            None => return Colour::new(0.0, 0.0, 0.0),
+        };
+
+        let basis = OrthonormalBasis::new_from_w(&hit_record.normal.as_vector3());
+        let direction = basis.local(&random_cosine_direction(sampler));
+        let occlusion_ray = Ray::new_at_time(hit_record.point, direction, ray.time);
+
+        // The direction is a unit vector, so t is the distance along it.
+        match world.hit(&occlusion_ray, 0.001, self.distance, sampler) {
+            Some(_) => Colour::new(0.0, 0.0, 0.0),
+            None => Colour::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::geometry::point::Point3;
+    use crate::geometry::vector::Vector3;
+    use crate::render::elements::element_list::ElementList;
+    use crate::render::elements::rect::XzRect;
+    use crate::render::materials::lambertian::Lambertian;
+
+    use super::*;
+
+    /// The mean occlusion looking down at a floor, under a ceiling one unit up
+    /// that spans ceiling_width around the origin.
+    fn mean_occlusion(distance: f32, ceiling_width: f32) -> f32 {
+        let white = Arc::new(Lambertian::new_with_colour(Colour::new(1.0, 1.0, 1.0)));
+        let half = ceiling_width / 2.0;
+        let mut world = ElementList::new();
+        world.add(Box::new(XzRect::new(-100.0, 100.0, -100.0, 100.0, 0.0, white.clone())));
+        world.add(Box::new(XzRect::new(-half, half, -half, half, 1.0, white)));
+
+        let integrator = AmbientOcclusionIntegrator::new(distance);
+        let ray = Ray::new(Point3::new(0.0, 0.5, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let mut sampler = Sampler::new(0);
+
+        let n = 10_000;
+        let total: f32 = (0..n)
+            .map(|_| integrator.radiance(&ray, &world, &Lights::none(), &Colour::new(0.0, 0.0, 0.0), &mut sampler).r)
+            .sum();
+
+        total / (n as f32)
+    }
+
+    #[test]
+    fn ceiling_occludes_within_distance() {
+        let open = mean_occlusion(1000.0, 400.0);
+
+        assert!(open < 0.01, "{}", open);
+    }
+
+    #[test]
+    fn ceiling_occludes_only_within_distance() {
+        // The ceiling is further than 2 along directions with a cosine under
+        // 1/2, which are a quarter of the cosine weighted hemisphere.
+        let open = mean_occlusion(2.0, 400.0);
+
+        assert!((open - 0.25).abs() < 0.02, "{}", open);
+    }
+
+    #[test]
+    fn ceiling_beyond_distance_is_ignored() {
+        assert_eq!(mean_occlusion(0.5, 400.0), 1.0);
+    }
+
+    #[test]
+    fn small_ceiling_partly_occludes() {
+        // A square of side 2 one unit up covers the directions within 45
+        // degrees along each axis; a disc of radius 1 would hide half the
+        // cosine weighted hemisphere, and the square hides a little more.
+        let open = mean_occlusion(2.0, 2.0);
+
+        assert!(open > 0.3 && open < 0.5, "{}", open);
+    }
+
+    #[test]
+    fn miss_is_black() {
+        let ray = Ray::new(Point3::new(0.0, 0.5, 0.0), Vector3::new(0.0, 1.0, 0.0));
+
+        assert_eq!(
+            AmbientOcclusionIntegrator::new(1.0).radiance(
+                &ray,
+                &ElementList::new(),
+                &Lights::none(),
+                &Colour::new(1.0, 1.0, 1.0),
+                &mut Sampler::new(0),
+            ),
+            Colour::new(0.0, 0.0, 0.0),
+        );
+    }
+}
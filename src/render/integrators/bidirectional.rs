@@ -0,0 +1,696 @@
+use std::f32::consts::PI;
+
+use rand::Rng;
+
+use crate::geometry::orthonormal_basis::OrthonormalBasis;
+use crate::geometry::point::Point3;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::Vector3;
+use crate::graphics::colour::Colour;
+use crate::util::random::random_cosine_direction;
+use crate::util::sampler::Sampler;
+
+use super::super::camera::Camera;
+use super::super::element::Element;
+use super::super::hit_record::HitRecord;
+use super::super::integrator::{Integrator, Splat};
+use super::super::lights::Lights;
+
+/// Bidirectional path tracing. Each sample traces a path from the camera and
+/// another from a point on a light, then joins every prefix of one to every
+/// prefix of the other, giving many ways of making each path from the lights
+/// to the camera. Light that only reaches the camera through glass, or through
+/// small openings, is found far more often by paths that start at the lights
+/// than by camera paths that have to stumble on the lights.
+///
+/// Where s vertices of the light path are joined to t of the camera path:
+/// - with s = 0, the camera path has hit an emitter itself;
+/// - with s = 1, a point on the lights is sampled directly, as in path tracing;
+/// - with t = 1, the light path is joined to a point on the lens, and so
+///   reaches the camera at some other part of the image, as a splat.
+///
+/// Each way is weighted against all the others that could have made the same
+/// path by multiple importance sampling, with the power heuristic. Paths have
+/// at most max_depth bounces, and from roulette_depth bounces on, each path
+/// is ended at random as its throughput fades, as in path tracing.
+///
+/// Only the lights can start light paths, so emitters that aren't among them
+/// are only found by camera paths, as is the background.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct BidirectionalIntegrator {
+    pub max_depth: usize,
+    pub roulette_depth: usize,
+}
+
+impl BidirectionalIntegrator {
+    pub fn new(max_depth: usize, roulette_depth: usize) -> Self {
+        BidirectionalIntegrator {
+            max_depth: max_depth,
+            roulette_depth: roulette_depth,
+        }
+    }
+}
+
+impl Integrator for BidirectionalIntegrator {
+    /// Without a camera to join light paths to, light paths only reach the
+    /// camera path's vertices.
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+        sampler: &mut Sampler,
+    ) -> Colour {
+        self.trace(ray, None, world, lights, background, sampler, &mut Vec::new())
+    }
+
+    fn radiance_and_splats(
+        &self,
+        ray: &Ray,
+        camera: &Camera,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+        sampler: &mut Sampler,
+        splats: &mut Vec<Splat>,
+    ) -> Colour {
+        self.trace(ray, Some(camera), world, lights, background, sampler, splats)
+    }
+}
+
+impl BidirectionalIntegrator {
+    #[allow(clippy::too_many_arguments)]
+    fn trace(
+        &self,
+        ray: &Ray,
+        camera: Option<&Camera>,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+        sampler: &mut Sampler,
+        splats: &mut Vec<Splat>,
+    ) -> Colour {
+        let (camera_path, mut colour) = self.camera_path(ray, camera, world, background, sampler);
+        let light_path = self.light_path(ray.time, world, lights, sampler);
+
+        for t in 1..=camera_path.len() {
+            if t == 1 && camera.is_none() {
+                continue;
+            }
+
+            for s in 0..=light_path.len() {
+                // A light path can't reach the lens on its own, and a point on
+                // the lights seen from the lens is left to camera paths.
+                if (s + t < 2) || (s + t - 2 > self.max_depth) || (s == 1 && t == 1) {
+                    continue;
+                }
+
+                let (contribution, coordinates) = self.connect(
+                    &light_path,
+                    &camera_path,
+                    s,
+                    t,
+                    camera,
+                    world,
+                    lights,
+                    sampler,
+                );
+
+                match coordinates {
+                    Some((s, t)) => splats.push(Splat { s: s, t: t, colour: contribution }),
+                    None => colour += contribution,
+                }
+            }
+        }
+
+        colour
+    }
+
+    /// The path from the camera along ray, and the light it finds escaping to
+    /// the background.
+    fn camera_path(
+        &self,
+        ray: &Ray,
+        camera: Option<&Camera>,
+        world: &dyn Element,
+        background: &Colour,
+        sampler: &mut Sampler,
+    ) -> (Vec<Vertex>, Colour) {
+        let mut path = vec![Vertex::camera(*ray)];
+
+        // The density of the direction only matters when the camera can be
+        // reached by light paths.
+        let pdf = camera.map_or(1.0, |c| c.importance(&ray.direction));
+
+        let escaped = self.random_walk(
+            &mut path,
+            *ray,
+            Colour::new(1.0, 1.0, 1.0),
+            pdf,
+            self.max_depth + 2,
+            false,
+            world,
+            sampler,
+        );
+        let colour = match escaped {
+            Some(beta) => beta * *background,
+            None => Colour::new(0.0, 0.0, 0.0),
+        };
+
+        (path, colour)
+    }
+
+    /// A path from a point picked on the lights, leaving in a direction picked
+    /// in proportion to its cosine on either side, as light leaves both sides of
+    /// emitters. Empty if there are no lights to pick from.
+    fn light_path(&self, time: f32, world: &dyn Element, lights: &Lights, sampler: &mut Sampler) -> Vec<Vertex> {
+        let mut path = Vec::new();
+
+        let (hit_record, pdf_position) = match lights.sample_surface(time, sampler) {
+            Some(sample) => sample,
+            None => return path,
+        };
+        let emitted = hit_record.material.emit(hit_record.u, hit_record.v, &hit_record.point);
+
+        let side = if sampler.gen::<bool>() { hit_record.normal } else { -hit_record.normal };
+        let direction = OrthonormalBasis::new_from_w(&side.as_vector3()).local(&random_cosine_direction(sampler));
+        let cosine = side.dot(&direction);
+        let pdf_direction = cosine / (2.0 * PI);
+
+        if pdf_position <= 0.0 || pdf_direction <= 0.0 || emitted == Colour::new(0.0, 0.0, 0.0) {
+            return path;
+        }
+
+        let ray = Ray::new_at_time(hit_record.point, direction, time);
+        let beta = emitted * (cosine / (pdf_position * pdf_direction));
+        path.push(Vertex::light(hit_record, ray, emitted * (1.0 / pdf_position), pdf_position));
+
+        self.random_walk(&mut path, ray, beta, pdf_direction, self.max_depth + 1, true, world, sampler);
+
+        path
+    }
+
+    /// Extends the path from its last vertex along ray, which was picked with
+    /// density pdf per unit solid angle and carries beta, until the path is
+    /// absorbed, escapes the world or has max_vertices vertices. Each vertex is
+    /// given its densities of being picked either way along the path.
+    ///
+    /// Paths from_light carry light rather than gather it, so are scaled on
+    /// passing through surfaces where radiance isn't.
+    ///
+    /// Returns what the path carries if it escapes the world.
+    #[allow(clippy::too_many_arguments)]
+    fn random_walk(
+        &self,
+        path: &mut Vec<Vertex>,
+        ray: Ray,
+        beta: Colour,
+        pdf: f32,
+        max_vertices: usize,
+        from_light: bool,
+        world: &dyn Element,
+        sampler: &mut Sampler,
+    ) -> Option<Colour> {
+        let (mut ray, mut beta, mut pdf_fwd) = (ray, beta, pdf);
+        let mut throughput = Colour::new(1.0, 1.0, 1.0);
+
+        for bounces in 1.. {
+            if path.len() >= max_vertices {
+                break;
+            }
+
+            let hit_record = match world.hit(&ray, 0.001, f32::INFINITY, sampler) {
+                Some(hit_record) => hit_record,
+                None => return Some(beta),
+            };
+
+            let mut vertex = Vertex::surface(hit_record, ray, beta);
+            vertex.pdf_fwd = path[path.len() - 1].convert_density(pdf_fwd, &vertex);
+            path.push(vertex);
+
+            if path.len() >= max_vertices {
+                break;
+            }
+
+            let vertex = &path[path.len() - 1];
+            let hit_record = vertex.hit_record.as_ref().unwrap();
+            let sample = match hit_record.material.sample(&ray, hit_record, sampler) {
+                Some(sample) => sample,
+                None => break,
+            };
+
+            // Specular samples have no density either way, and are passed over
+            // by the weights.
+            let delta = sample.is_specular();
+            let (pdf, pdf_rev) = if delta {
+                (0.0, 0.0)
+            } else {
+                let reverse = Ray::new_at_time(hit_record.point + sample.direction, -sample.direction, ray.time);
+                let reverse_hit_record = facing(hit_record, &reverse.direction);
+                (sample.pdf, hit_record.material.pdf(&reverse, &reverse_hit_record, &-ray.direction))
+            };
+            pdf_fwd = pdf;
+
+            let previous_pdf_rev = vertex.convert_density(pdf_rev, &path[path.len() - 2]);
+            let point = vertex.point;
+            let index = path.len() - 1;
+            path[index].delta = delta;
+            path[index - 1].pdf_rev = previous_pdf_rev;
+
+            let attenuation = if from_light {
+                sample.attenuation * (1.0 / (sample.eta * sample.eta))
+            } else {
+                sample.attenuation
+            };
+            beta = beta * attenuation;
+            throughput = throughput * attenuation;
+
+            if bounces >= self.roulette_depth {
+                let survival = throughput.max_component();
+                if survival < 1.0 {
+                    if sampler.gen::<f32>() >= survival {
+                        break;
+                    }
+                    beta = beta * (1.0 / survival);
+                    throughput = throughput * (1.0 / survival);
+                }
+            }
+
+            ray = Ray::new_at_time(point, sample.direction, ray.time);
+        }
+
+        None
+    }
+
+    /// The light carried by the path made of the first s vertices of the light
+    /// path and the first t of the camera path, weighted against the other ways
+    /// of making it. Light joined to the lens, with t = 1, is returned with the
+    /// image coordinates it reaches.
+    #[allow(clippy::too_many_arguments)]
+    fn connect(
+        &self,
+        light_path: &[Vertex],
+        camera_path: &[Vertex],
+        s: usize,
+        t: usize,
+        camera: Option<&Camera>,
+        world: &dyn Element,
+        lights: &Lights,
+        sampler: &mut Sampler,
+    ) -> (Colour, Option<(f32, f32)>) {
+        let black = Colour::new(0.0, 0.0, 0.0);
+        let time = camera_path[0].ray.time;
+
+        // The vertices at the join, which for s = 1 and t = 1 are sampled afresh
+        // rather than taken from the paths.
+        let (sampled_qs, sampled_pt);
+        let (colour, qs, pt, coordinates) = if s == 0 {
+            let pt = &camera_path[t - 1];
+
+            (pt.beta * pt.emitted(), None, pt, None)
+        } else if t == 1 {
+            let qs = &light_path[s - 1];
+            let (lens_point, image_s, image_t) = match camera.and_then(|c| c.connect(&qs.point, sampler)) {
+                Some(connection) => connection,
+                None => return (black, None),
+            };
+            if qs.delta {
+                return (black, None);
+            }
+
+            let direction = qs.point - lens_point;
+            let importance = camera.map_or(0.0, |c| c.importance(&direction));
+            sampled_pt = Vertex::camera(Ray::new_at_time(lens_point, direction, time));
+            let colour = qs.beta * qs.evaluate(&sampled_pt) * (importance / direction.length_squared());
+
+            if colour == black || !visible(world, &lens_point, &qs.point, time, sampler) {
+                return (black, None);
+            }
+
+            (colour, Some(qs), &sampled_pt, Some((image_s, image_t)))
+        } else if s == 1 {
+            let pt = &camera_path[t - 1];
+            if pt.delta {
+                return (black, None);
+            }
+
+            // The point on the lights is picked as light paths pick their first
+            // vertex, so its density per unit area is the one weighed against
+            // the other ways of making the path.
+            let (light_hit, pdf_position) = match lights.sample_surface(time, sampler) {
+                Some(sample) => sample,
+                None => return (black, None),
+            };
+            if pdf_position <= 0.0 {
+                return (black, None);
+            }
+
+            let emitted = light_hit.material.emit(light_hit.u, light_hit.v, &light_hit.point);
+            let direction = light_hit.point - pt.point;
+            let distance_squared = direction.length_squared();
+            let cosine = light_hit.normal.dot(&direction).abs() / distance_squared.sqrt();
+            let light_ray = Ray::new_at_time(light_hit.point, -direction, time);
+            sampled_qs = Vertex::light(light_hit, light_ray, emitted * (1.0 / pdf_position), pdf_position);
+
+            let colour = pt.beta * pt.evaluate(&sampled_qs) * sampled_qs.beta * (cosine / distance_squared);
+            if colour == black || !visible(world, &pt.point, &sampled_qs.point, time, sampler) {
+                return (black, None);
+            }
+
+            (colour, Some(&sampled_qs), pt, None)
+        } else {
+            let (qs, pt) = (&light_path[s - 1], &camera_path[t - 1]);
+            if qs.delta || pt.delta {
+                return (black, None);
+            }
+
+            let distance_squared = (qs.point - pt.point).length_squared();
+            let colour = qs.beta * qs.evaluate(pt) * pt.evaluate(qs) * pt.beta * (1.0 / distance_squared);
+
+            if colour == black || !visible(world, &pt.point, &qs.point, time, sampler) {
+                return (black, None);
+            }
+
+            (colour, Some(qs), pt, None)
+        };
+
+        if colour == black {
+            return (black, None);
+        }
+
+        let weight = mis_weight(light_path, camera_path, qs, pt, s, t, camera, lights);
+
+        (colour * weight, coordinates)
+    }
+}
+
+/// What a vertex of a path is.
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum VertexKind {
+    /// The point on the lens a camera path starts from.
+    Camera,
+    /// The point on a light a light path starts from.
+    Light,
+    /// Where a path met a surface.
+    Surface,
+}
+
+/// A point along a camera or light path.
+#[derive(Debug, Clone)]
+struct Vertex {
+    kind: VertexKind,
+    point: Point3,
+    /// The surface at the vertex, for all but the camera.
+    hit_record: Option<HitRecord>,
+    /// The ray that arrived at a surface, or left the camera or light. It gives
+    /// the time, and the direction materials are evaluated from.
+    ray: Ray,
+    /// What the path carries to the vertex, divided by the density of the path.
+    beta: Colour,
+    /// Whether the path scattered at the vertex in an exact direction.
+    delta: bool,
+    /// The density, per unit area, of the vertex being picked by its path.
+    pdf_fwd: f32,
+    /// The density, per unit area, of the vertex being picked by a path going
+    /// the other way, through the same vertices.
+    pdf_rev: f32,
+}
+
+impl Vertex {
+    fn camera(ray: Ray) -> Self {
+        Vertex {
+            kind: VertexKind::Camera,
+            point: ray.origin,
+            hit_record: None,
+            ray: ray,
+            beta: Colour::new(1.0, 1.0, 1.0),
+            delta: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+        }
+    }
+
+    fn light(hit_record: HitRecord, ray: Ray, beta: Colour, pdf_fwd: f32) -> Self {
+        Vertex {
+            kind: VertexKind::Light,
+            point: hit_record.point,
+            hit_record: Some(hit_record),
+            ray: ray,
+            beta: beta,
+            delta: false,
+            pdf_fwd: pdf_fwd,
+            pdf_rev: 0.0,
+        }
+    }
+
+    fn surface(hit_record: HitRecord, ray: Ray, beta: Colour) -> Self {
+        Vertex {
+            kind: VertexKind::Surface,
+            point: hit_record.point,
+            hit_record: Some(hit_record),
+            ray: ray,
+            beta: beta,
+            delta: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+        }
+    }
+
+    fn emitted(&self) -> Colour {
+        match &self.hit_record {
+            Some(hit_record) => hit_record.material.emit(hit_record.u, hit_record.v, &hit_record.point),
+            None => Colour::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// The light arriving from next that the surface scatters back along the
+    /// ray that reached it: the BSDF times the cosine towards next.
+    fn evaluate(&self, next: &Vertex) -> Colour {
+        match &self.hit_record {
+            Some(hit_record) => hit_record.material.evaluate(&self.ray, hit_record, &(next.point - self.point)),
+            None => Colour::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// The density per unit area at next of picking the direction to it from
+    /// here with density pdf per unit solid angle. Surfaces seen at a slant
+    /// take up less solid angle. Points in a medium have no surface, and are
+    /// measured per unit volume instead, which has no slant.
+    fn convert_density(&self, pdf: f32, next: &Vertex) -> f32 {
+        let direction = next.point - self.point;
+        let distance_squared = direction.length_squared();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+
+        let pdf = pdf / distance_squared;
+
+        match &next.hit_record {
+            Some(hit_record) if !hit_record.medium => {
+                pdf * (hit_record.normal.dot(&direction).abs() / distance_squared.sqrt())
+            },
+            _ => pdf,
+        }
+    }
+
+    /// The density per unit area with which a path that reached this vertex
+    /// from prev goes on to next. With no prev, the vertex emits the path.
+    fn pdf(&self, prev: Option<&Vertex>, next: &Vertex, camera: Option<&Camera>) -> f32 {
+        let direction = next.point - self.point;
+
+        let pdf = match (self.kind, prev, &self.hit_record) {
+            (VertexKind::Camera, _, _) => camera.map_or(0.0, |c| c.importance(&direction)),
+            (VertexKind::Surface, Some(prev), Some(hit_record)) => {
+                let ray = Ray::new_at_time(prev.point, self.point - prev.point, self.ray.time);
+                let hit_record = facing(hit_record, &ray.direction);
+                hit_record.material.pdf(&ray, &hit_record, &direction)
+            },
+            _ => return self.emission_pdf(next),
+        };
+
+        self.convert_density(pdf, next)
+    }
+
+    /// The density per unit area with which light emitted here goes to next,
+    /// picked in proportion to the cosine on either side of the surface.
+    fn emission_pdf(&self, next: &Vertex) -> f32 {
+        let direction = (next.point - self.point).normalise();
+        let cosine = match &self.hit_record {
+            Some(hit_record) => hit_record.normal.dot(&direction).abs(),
+            None => 0.0,
+        };
+
+        self.convert_density(cosine / (2.0 * PI), next)
+    }
+
+    fn densities(&self) -> Densities {
+        Densities {
+            forward: self.pdf_fwd,
+            reverse: self.pdf_rev,
+            delta: self.delta,
+        }
+    }
+}
+
+/// The densities of a vertex being picked either way along a path, and whether
+/// the path scatters there in an exact direction.
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct Densities {
+    forward: f32,
+    reverse: f32,
+    delta: bool,
+}
+
+/// The weight for the path made of the first s vertices of the light path and
+/// the first t of the camera path, joined at qs and pt, which are the last of
+/// each unless they were sampled afresh.
+///
+/// Every way of making the same path would join it at a different vertex.
+/// Walking along the path from the join, the density of each way follows from
+/// the last by swapping a vertex's forward density for its reverse one, so only
+/// the ratios to this way's density are needed. Ways that would join at a
+/// vertex scattering in an exact direction can't happen, and densities of zero
+/// are taken as one so that the ratios through such vertices are unaffected.
+#[allow(clippy::too_many_arguments)]
+fn mis_weight(
+    light_path: &[Vertex],
+    camera_path: &[Vertex],
+    qs: Option<&Vertex>,
+    pt: &Vertex,
+    s: usize,
+    t: usize,
+    camera: Option<&Camera>,
+    lights: &Lights,
+) -> f32 {
+    if s + t == 2 {
+        return 1.0;
+    }
+
+    let pt_minus = if t >= 2 { Some(&camera_path[t - 2]) } else { None };
+    let qs_minus = if s >= 2 { Some(&light_path[s - 2]) } else { None };
+
+    let mut camera_densities: Vec<Densities> = camera_path[..(t - 1)].iter()
+        .chain(Some(pt))
+        .map(Vertex::densities)
+        .collect();
+    let mut light_densities: Vec<Densities> = light_path[..s.saturating_sub(1)].iter()
+        .chain(qs)
+        .map(Vertex::densities)
+        .collect();
+
+    // The reverse densities near the join are those of the joined path.
+    camera_densities[t - 1].reverse = match qs {
+        Some(qs) => qs.pdf(qs_minus, pt, camera),
+        None => lights.surface_pdf(&pt.point, &pt.hit_record.as_ref().unwrap().normal, pt.ray.time),
+    };
+    camera_densities[t - 1].delta = false;
+
+    // An emitter that isn't among the lights can only be found this way.
+    if s == 0 && camera_densities[t - 1].reverse == 0.0 {
+        return 1.0;
+    }
+
+    if let Some(pt_minus) = pt_minus {
+        camera_densities[t - 2].reverse = pt.pdf(qs, pt_minus, camera);
+    }
+    if let Some(qs) = qs {
+        light_densities[s - 1].reverse = pt.pdf(pt_minus, qs, camera);
+        light_densities[s - 1].delta = false;
+    }
+    if let (Some(qs), Some(qs_minus)) = (qs, qs_minus) {
+        light_densities[s - 2].reverse = qs.pdf(Some(pt), qs_minus, camera);
+    }
+
+    let remap = |pdf: f32| if pdf == 0.0 { 1.0 } else { pdf };
+    let mut sum = 0.0;
+
+    // Ways with fewer camera vertices, down to just the lens if there is a
+    // camera to join to.
+    let mut ratio = 1.0;
+    for i in (1..t).rev() {
+        ratio *= remap(camera_densities[i].reverse) / remap(camera_densities[i].forward);
+        if !camera_densities[i].delta && !camera_densities[i - 1].delta && (i > 1 || camera.is_some()) {
+            sum += ratio * ratio;
+        }
+    }
+
+    // Ways with fewer light vertices, down to none.
+    let mut ratio = 1.0;
+    for i in (0..s).rev() {
+        ratio *= remap(light_densities[i].reverse) / remap(light_densities[i].forward);
+        let delta_before = (i > 0) && light_densities[i - 1].delta;
+        if !light_densities[i].delta && !delta_before {
+            sum += ratio * ratio;
+        }
+    }
+
+    1.0 / (1.0 + sum)
+}
+
+/// The hit record as met by a ray in the direction given, with the normal
+/// against the ray.
+fn facing(hit_record: &HitRecord, direction: &Vector3) -> HitRecord {
+    let mut facing = hit_record.clone();
+    if facing.normal.dot(direction) > 0.0 {
+        facing.normal = -facing.normal;
+        facing.front_face = !facing.front_face;
+    }
+
+    facing
+}
+
+/// Whether nothing in the world lies between the two points. Light can scatter
+/// off media in the way, so this is decided at random by the sampler.
+fn visible(world: &dyn Element, from: &Point3, to: &Point3, time: f32, sampler: &mut Sampler) -> bool {
+    let direction = *to - *from;
+    let distance = direction.length();
+    let ray = Ray::new_at_time(*from, direction / distance, time);
+
+    world.hit(&ray, 0.001, distance - 0.001, sampler).is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::integrator::IntegratorKind;
+    use crate::render::integrators::path::PathIntegrator;
+    use crate::util::testing::fixtures::{floor_radiance, floor_samples, foggy_room_mean, glass_lamp_mean_and_noise, room_mean};
+
+    use super::*;
+
+    #[test]
+    fn bidirectional_matches_one_bounce() {
+        let expected = floor_radiance();
+
+        for &use_lights in [false, true].iter() {
+            let (mean, _) = floor_samples(&BidirectionalIntegrator::new(1, 10), use_lights);
+
+            assert!((mean - expected).abs() < 0.01, "{} is not near {}", mean, expected);
+        }
+    }
+
+    #[test]
+    fn bidirectional_matches_path_tracing() {
+        let path = room_mean(&PathIntegrator::new(5, 5, 5, 5, 3));
+        let bidirectional = room_mean(&BidirectionalIntegrator::new(5, 3));
+
+        assert!((bidirectional / path - 1.0).abs() < 0.03, "{} is not near {}", bidirectional, path);
+    }
+
+    #[test]
+    fn bidirectional_matches_path_tracing_in_fog() {
+        let path = foggy_room_mean(&PathIntegrator::new(5, 5, 5, 5, 3));
+        let bidirectional = foggy_room_mean(&BidirectionalIntegrator::new(5, 3));
+
+        assert!((bidirectional / path - 1.0).abs() < 0.03, "{} is not near {}", bidirectional, path);
+    }
+
+    #[test]
+    fn bidirectional_lights_through_glass() {
+        let (path_mean, path_noise) = glass_lamp_mean_and_noise(IntegratorKind::Path, 1);
+        let (mean, noise) = glass_lamp_mean_and_noise(IntegratorKind::Bidirectional, 1);
+
+        assert!((mean / path_mean - 1.0).abs() < 0.1, "{} is not near {}", mean, path_mean);
+        assert!(noise * 2.0 < path_noise, "{} against {}", noise, path_noise);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::geometry::ray::Ray;
+use crate::graphics::colour::Colour;
+use crate::util::sampler::Sampler;
+
+use super::super::element::Element;
+use super::super::hit_record::HitRecord;
+use super::super::integrator::{power_heuristic, sample_light, Integrator};
+use super::super::lights::Lights;
+
+/// Direct lighting only: the light reaching the first diffuse surface straight
+/// from emitters or the background, without bouncing between surfaces. Mirrors
+/// and glass on the way there are followed, up to max_depth of them.
+///
+/// As in path tracing, light at the diffuse surface is gathered both by sampling
+/// the lights and by following one scattered ray, weighted by multiple
+/// importance sampling.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct DirectIntegrator {
+    pub max_depth: usize,
+}
+
+impl DirectIntegrator {
+    pub fn new(max_depth: usize) -> Self {
+        DirectIntegrator {
+            max_depth: max_depth,
+        }
+    }
+
+    /// The direct lighting along the ray, with gathered adding any other light
+    /// found at the diffuse surface, given the ray that reached it and the hit.
+    pub fn radiance_with<F>(
+        &self,
+        ray: &Ray,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+        sampler: &mut Sampler,
+        gathered: F,
+    ) -> Colour
+    where
+        F: Fn(&Ray, &HitRecord) -> Colour,
+    {
+        let mut colour = Colour::new(0.0, 0.0, 0.0);
+        let mut throughput = Colour::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+
+        for depth in 0.. {
+            let hit_record = match world.hit(&ray, 0.001, f32::INFINITY, sampler) {
+                Some(hit_record) => hit_record,
+                None => {
+                    colour += throughput * *background;
+                    break;
+                },
+            };
+
+            colour += throughput * hit_record.material.emit(hit_record.u, hit_record.v, &hit_record.point);
+
+            if depth >= self.max_depth {
+                break;
+            }
+
+            let sample = match hit_record.material.sample(&ray, &hit_record, sampler) {
+                Some(sample) => sample,
+                None => break,
+            };
+            let scattered = Ray::new_at_time(hit_record.point, sample.direction, ray.time);
+
+            if sample.is_specular() {
+                throughput = throughput * sample.attenuation;
+                ray = scattered;
+                continue;
+            }
+
+            colour += throughput * gathered(&ray, &hit_record);
+
+            let weight = if lights.is_empty() {
+                1.0
+            } else {
+                colour += throughput * sample_light(&ray, &hit_record, background, world, lights, true, sampler);
+
+                power_heuristic(sample.pdf, lights.pdf_value(&hit_record.point, &sample.direction, ray.time))
+            };
+
+            let incoming = match world.hit(&scattered, 0.001, f32::INFINITY, sampler) {
+                Some(light_hit) => light_hit.material.emit(light_hit.u, light_hit.v, &light_hit.point),
+                None => *background,
+            };
+            colour += throughput * sample.attenuation * incoming * weight;
+
+            break;
+        }
+
+        colour
+    }
+}
+
+impl Integrator for DirectIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+        sampler: &mut Sampler,
+    ) -> Colour {
+        self.radiance_with(ray, world, lights, background, sampler, |_, _| Colour::new(0.0, 0.0, 0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::geometry::point::Point3;
+    use crate::geometry::vector::Vector3;
+    use crate::render::elements::element_list::ElementList;
+    use crate::render::elements::rect::XzRect;
+    use crate::render::materials::lambertian::Lambertian;
+    use crate::util::testing::fixtures::{floor_radiance, floor_samples};
+
+    use super::*;
+
+    #[test]
+    fn direct_matches_one_bounce() {
+        let expected = floor_radiance();
+
+        for &use_lights in [false, true].iter() {
+            let (mean, _) = floor_samples(&DirectIntegrator::new(5), use_lights);
+
+            assert!((mean - expected).abs() < 0.01, "{} is not near {}", mean, expected);
+        }
+    }
+
+    #[test]
+    fn direct_ignores_light_between_surfaces() {
+        // Between a floor and a ceiling, under a white sky that only reaches
+        // the floor by bouncing off the ceiling.
+        let white = Arc::new(Lambertian::new_with_colour(Colour::new(1.0, 1.0, 1.0)));
+        let mut world = ElementList::new();
+        world.add(Box::new(XzRect::new(-100.0, 100.0, -100.0, 100.0, 0.0, white.clone())));
+        world.add(Box::new(XzRect::new(-100.0, 100.0, -100.0, 100.0, 1.0, white)));
+
+        let ray = Ray::new(Point3::new(0.0, 0.5, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let colour = DirectIntegrator::new(5).radiance(
+            &ray,
+            &world,
+            &Lights::none(),
+            &Colour::new(1.0, 1.0, 1.0),
+            &mut Sampler::new(0),
+        );
+
+        assert_eq!(colour, Colour::new(0.0, 0.0, 0.0));
+    }
+}
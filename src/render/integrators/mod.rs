@@ -0,0 +1,6 @@
+pub mod ambient_occlusion;
+pub mod bidirectional;
+pub mod direct;
+pub mod path;
+pub mod photon;
+pub mod whitted;
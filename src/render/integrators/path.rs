@@ -0,0 +1,236 @@
+use rand::Rng;
+
+use crate::geometry::ray::Ray;
+use crate::graphics::colour::Colour;
+use crate::util::sampler::Sampler;
+
+use super::super::bsdf_sample::Lobe;
+use super::super::camera::Camera;
+use super::super::element::Element;
+use super::super::integrator::{power_heuristic, sample_light, Integrator};
+use super::super::lights::Lights;
+use super::super::photon_map::ProgressivePhotonMap;
+
+/// Unidirectional path tracing, following each path one bounce at a time so
+/// that long paths don't grow the stack.
+///
+/// At surfaces that scatter over a range of directions, light is gathered in
+/// two ways: by sampling a direction towards a light, and by following the
+/// scattered ray. Either can find the same light, so each is weighted by
+/// multiple importance sampling, which favours whichever was more likely to
+/// pick the direction. The weight for the scattered ray applies to the light
+/// emitted by whatever it hits, as emission_weight. Specular samples, from
+/// mirrors and glass, are exact directions a light sample can never match, so
+/// light found through them always counts in full.
+///
+/// The path ends when it reaches the limit on bounces of the kind it would
+/// take next, or on bounces overall. From roulette_depth bounces on, it is
+/// also ended at random with a chance that grows as its throughput fades, and
+/// the paths that survive are brightened to make up for the rest.
+///
+/// Light from the lights focused through mirrors and glass onto a surface is
+/// hard to find by following rays back from it. With caustics, that light is
+/// gathered from photons at each surface instead, and paths that find the
+/// lights that way after scattering over a range of directions off a surface
+/// leave it out.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PathIntegrator {
+    pub max_depth: usize,
+    pub max_diffuse_depth: usize,
+    pub max_specular_depth: usize,
+    pub max_transmission_depth: usize,
+    pub roulette_depth: usize,
+    pub caustics: Option<ProgressivePhotonMap>,
+}
+
+impl PathIntegrator {
+    pub fn new(
+        max_depth: usize,
+        max_diffuse_depth: usize,
+        max_specular_depth: usize,
+        max_transmission_depth: usize,
+        roulette_depth: usize,
+    ) -> Self {
+        PathIntegrator {
+            max_depth: max_depth,
+            max_diffuse_depth: max_diffuse_depth,
+            max_specular_depth: max_specular_depth,
+            max_transmission_depth: max_transmission_depth,
+            roulette_depth: roulette_depth,
+            caustics: None,
+        }
+    }
+}
+
+impl Integrator for PathIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+        sampler: &mut Sampler,
+    ) -> Colour {
+        let mut colour = Colour::new(0.0, 0.0, 0.0);
+        let mut throughput = Colour::new(1.0, 1.0, 1.0);
+        let mut emission_weight = 1.0;
+        let mut ray = *ray;
+        let (mut diffuse_depth, mut specular_depth, mut transmission_depth) = (0, 0, 0);
+        // Whether the path has scattered over a range of directions, and only
+        // through mirrors and glass since then, which the caustics cover.
+        let (mut scattered, mut caustic) = (false, false);
+
+        for depth in 0.. {
+            let hit_record = match world.hit(&ray, 0.001, f32::INFINITY, sampler) {
+                Some(hit_record) => hit_record,
+                None => {
+                    colour += throughput * *background * emission_weight;
+                    break;
+                },
+            };
+
+            let emitted = hit_record.material.emit(hit_record.u, hit_record.v, &hit_record.point);
+            if !caustic || lights.surface_pdf(&hit_record.point, &hit_record.normal, ray.time) <= 0.0 {
+                colour += throughput * emitted * emission_weight;
+            }
+
+            if depth >= self.max_depth {
+                break;
+            }
+
+            let sample = match hit_record.material.sample(&ray, &hit_record, sampler) {
+                Some(sample) => sample,
+                None => break,
+            };
+
+            let (lobe_depth, max_lobe_depth) = match sample.lobe {
+                Lobe::Diffuse => (&mut diffuse_depth, self.max_diffuse_depth),
+                Lobe::Specular => (&mut specular_depth, self.max_specular_depth),
+                Lobe::Transmission => (&mut transmission_depth, self.max_transmission_depth),
+            };
+            if *lobe_depth >= max_lobe_depth {
+                break;
+            }
+            *lobe_depth += 1;
+
+            if sample.is_specular() || lights.is_empty() {
+                emission_weight = 1.0;
+            } else {
+                colour += throughput * sample_light(&ray, &hit_record, background, world, lights, true, sampler);
+                if let Some(caustics) = &self.caustics {
+                    colour += throughput * caustics.estimate(&ray, &hit_record);
+                }
+
+                let light_pdf = lights.pdf_value(&hit_record.point, &sample.direction, ray.time);
+                emission_weight = power_heuristic(sample.pdf, light_pdf);
+            }
+
+            // Photons aren't kept in media, so light found through mirrors and
+            // glass from there is left to the path.
+            if self.caustics.is_some() {
+                caustic = sample.is_specular() && scattered;
+                scattered = (scattered || !sample.is_specular()) && !hit_record.medium;
+            }
+
+            throughput = throughput * sample.attenuation;
+
+            if depth + 1 >= self.roulette_depth {
+                let survival = throughput.max_component();
+                if survival < 1.0 {
+                    if sampler.gen::<f32>() >= survival {
+                        break;
+                    }
+                    throughput = throughput * (1.0 / survival);
+                }
+            }
+
+            ray = Ray::new_at_time(hit_record.point, sample.direction, ray.time);
+        }
+
+        colour
+    }
+
+    fn begin_pass(&mut self, pass: usize, camera: &Camera, world: &dyn Element, lights: &Lights, seed: u64) {
+        if let Some(caustics) = &mut self.caustics {
+            caustics.begin_pass(pass, camera, world, lights, seed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::geometry::point::Point3;
+    use crate::geometry::vector::Vector3;
+    use crate::render::elements::element_list::ElementList;
+    use crate::render::elements::rect::XzRect;
+    use crate::render::integrator::IntegratorKind;
+    use crate::render::materials::metal::Metal;
+    use crate::util::testing::fixtures::{floor_radiance, floor_samples, glass_lamp_mean_and_noise};
+
+    use super::*;
+
+    /// A single bounce, with no roulette.
+    fn one_bounce() -> PathIntegrator {
+        PathIntegrator::new(1, 5, 5, 5, 10)
+    }
+
+    #[test]
+    fn sampling_lights_reduces_noise() {
+        let expected = floor_radiance();
+
+        let (mean_without, variance_without) = floor_samples(&one_bounce(), false);
+        let (mean_with, variance_with) = floor_samples(&one_bounce(), true);
+
+        assert!((mean_without - expected).abs() < 0.01, "{} is not near {}", mean_without, expected);
+        assert!((mean_with - expected).abs() < 0.01, "{} is not near {}", mean_with, expected);
+        assert!(variance_with * 10.0 < variance_without, "{} against {}", variance_with, variance_without);
+    }
+
+    #[test]
+    fn roulette_keeps_mean() {
+        let expected = floor_radiance();
+
+        let mut integrator = one_bounce();
+        integrator.roulette_depth = 0;
+        let (mean, _) = floor_samples(&integrator, true);
+
+        assert!((mean - expected).abs() < 0.01, "{} is not near {}", mean, expected);
+    }
+
+    #[test]
+    fn diffuse_depth_limits_bounces() {
+        let mut integrator = one_bounce();
+        integrator.max_diffuse_depth = 0;
+
+        assert_eq!(floor_samples(&integrator, true), (0.0, 0.0));
+    }
+
+    #[test]
+    fn deep_paths_dont_grow_stack() {
+        // A ray caught between two perfect mirrors bounces until the limit.
+        let mirror = Arc::new(Metal::new(Colour::new(1.0, 1.0, 1.0)));
+        let mut world = ElementList::new();
+        world.add(Box::new(XzRect::new(-1.0, 1.0, -1.0, 1.0, 0.0, mirror.clone())));
+        world.add(Box::new(XzRect::new(-1.0, 1.0, -1.0, 1.0, 1.0, mirror)));
+
+        let integrator = PathIntegrator::new(200_000, 5, 200_000, 5, 200_000);
+        let ray = Ray::new(Point3::new(0.0, 0.5, 0.0), Vector3::new(0.0, 1.0, 0.0));
+
+        assert_eq!(
+            integrator.radiance(&ray, &world, &Lights::none(), &Colour::new(1.0, 1.0, 1.0), &mut Sampler::new(0)),
+            Colour::new(0.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn caustics_from_photons() {
+        // All the light reaching the floor is focused through the glass.
+        let (path_mean, path_noise) = glass_lamp_mean_and_noise(IntegratorKind::Path, 4);
+        let (mean, noise) = glass_lamp_mean_and_noise(IntegratorKind::PathWithCaustics { photons: 20_000, radius: 0.2 }, 4);
+
+        assert!((mean / path_mean - 1.0).abs() < 0.1, "{} is not near {}", mean, path_mean);
+        assert!(noise * 2.0 < path_noise, "{} against {}", noise, path_noise);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::geometry::ray::Ray;
+use crate::graphics::colour::Colour;
+use crate::util::sampler::Sampler;
+
+use super::super::camera::Camera;
+use super::super::element::Element;
+use super::super::integrator::Integrator;
+use super::super::lights::Lights;
+use super::super::photon_map::ProgressivePhotonMap;
+use super::direct::DirectIntegrator;
+
+/// Photon mapping: rays from the camera are followed through mirrors and glass,
+/// up to max_depth of them, to the first surface that scatters over a range of
+/// directions. There, light straight from emitters and the background is found
+/// as the direct lighting integrator finds it, and the rest is gathered from
+/// photons traced from the lights before each pass.
+///
+/// Photons only start from the lights, so light from the background, or from
+/// emitters that aren't among the lights, only counts where it arrives directly.
+/// No photons are kept in media, so rays that scatter in one there gather only
+/// the light arriving directly.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PhotonIntegrator {
+    pub max_depth: usize,
+    pub photons: ProgressivePhotonMap,
+}
+
+impl PhotonIntegrator {
+    pub fn new(max_depth: usize, photons: ProgressivePhotonMap) -> Self {
+        PhotonIntegrator {
+            max_depth: max_depth,
+            photons: photons,
+        }
+    }
+}
+
+impl Integrator for PhotonIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+        sampler: &mut Sampler,
+    ) -> Colour {
+        DirectIntegrator::new(self.max_depth).radiance_with(ray, world, lights, background, sampler, |ray, hit_record| {
+            self.photons.estimate(ray, hit_record)
+        })
+    }
+
+    fn begin_pass(&mut self, pass: usize, camera: &Camera, world: &dyn Element, lights: &Lights, seed: u64) {
+        self.photons.begin_pass(pass, camera, world, lights, seed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::point::Point3;
+    use crate::geometry::vector::Vector3;
+    use crate::render::integrators::path::PathIntegrator;
+    use crate::util::testing::fixtures::{room, room_mean};
+
+    use super::*;
+
+    #[test]
+    fn photon_matches_path_tracing() {
+        let (world, lights) = room();
+        let camera = Camera::new(
+            Point3::new(0.0, 1.0, 0.9),
+            Point3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            90.0,
+            1.0,
+            0.0,
+            1.0,
+            0.0,
+            1.0,
+        );
+
+        // The view is of a single point, so the photons gathered there are
+        // averaged over several passes.
+        let passes = 8;
+        let mut photon = PhotonIntegrator::new(5, ProgressivePhotonMap::new(100_000, 0.05, false, 5, 3, 4));
+        let photon = (0..passes).map(|pass| {
+            photon.begin_pass(pass, &camera, &world, &lights, 0);
+            room_mean(&photon)
+        }).sum::<f32>() / (passes as f32);
+
+        let path = room_mean(&PathIntegrator::new(5, 5, 5, 5, 3));
+
+        assert!((photon / path - 1.0).abs() < 0.05, "{} is not near {}", photon, path);
+    }
+}
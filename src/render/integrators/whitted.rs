@@ -0,0 +1,120 @@
+use crate::geometry::ray::Ray;
+use crate::graphics::colour::Colour;
+use crate::util::sampler::Sampler;
+
+use super::super::element::Element;
+use super::super::integrator::{sample_light, Integrator};
+use super::super::lights::Lights;
+
+/// Classic Whitted ray tracing: diffuse surfaces are lit only by sampling the
+/// lights, while mirrors and glass pass on what is seen in the direction they
+/// send the ray, up to max_depth of them. Light never bounces off a diffuse
+/// surface onto another, and emitters that aren't among the lights only show
+/// where they are seen directly or through mirrors and glass.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct WhittedIntegrator {
+    pub max_depth: usize,
+}
+
+impl WhittedIntegrator {
+    pub fn new(max_depth: usize) -> Self {
+        WhittedIntegrator {
+            max_depth: max_depth,
+        }
+    }
+}
+
+impl Integrator for WhittedIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+        sampler: &mut Sampler,
+    ) -> Colour {
+        let mut colour = Colour::new(0.0, 0.0, 0.0);
+        let mut throughput = Colour::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+
+        for depth in 0.. {
+            let hit_record = match world.hit(&ray, 0.001, f32::INFINITY, sampler) {
+                Some(hit_record) => hit_record,
+                None => {
+                    colour += throughput * *background;
+                    break;
+                },
+            };
+
+            colour += throughput * hit_record.material.emit(hit_record.u, hit_record.v, &hit_record.point);
+
+            if depth >= self.max_depth {
+                break;
+            }
+
+            let sample = match hit_record.material.sample(&ray, &hit_record, sampler) {
+                Some(sample) => sample,
+                None => break,
+            };
+
+            if !sample.is_specular() {
+                colour += throughput * sample_light(&ray, &hit_record, background, world, lights, false, sampler);
+                break;
+            }
+
+            throughput = throughput * sample.attenuation;
+            ray = Ray::new_at_time(hit_record.point, sample.direction, ray.time);
+        }
+
+        colour
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::geometry::point::Point3;
+    use crate::geometry::vector::Vector3;
+    use crate::render::elements::element_list::ElementList;
+    use crate::render::elements::rect::XzRect;
+    use crate::render::materials::metal::Metal;
+    use crate::util::testing::fixtures::{floor_radiance, floor_samples};
+
+    use super::*;
+
+    #[test]
+    fn whitted_samples_lights() {
+        let expected = floor_radiance();
+
+        let (mean, _) = floor_samples(&WhittedIntegrator::new(5), true);
+
+        assert!((mean - expected).abs() < 0.01, "{} is not near {}", mean, expected);
+    }
+
+    #[test]
+    fn whitted_needs_lights() {
+        // Without sampling the lights, the floor never finds the light.
+        assert_eq!(floor_samples(&WhittedIntegrator::new(5), false), (0.0, 0.0));
+    }
+
+    #[test]
+    fn whitted_follows_mirrors() {
+        let mirror = Arc::new(Metal::new(Colour::new(0.5, 0.5, 0.5)));
+        let mut world = ElementList::new();
+        world.add(Box::new(XzRect::new(-1.0, 1.0, -1.0, 1.0, 0.0, mirror)));
+
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        assert_eq!(
+            WhittedIntegrator::new(5).radiance(
+                &ray,
+                &world,
+                &Lights::none(),
+                &Colour::new(0.2, 0.4, 0.8),
+                &mut Sampler::new(0),
+            ),
+            Colour::new(0.1, 0.2, 0.4),
+        );
+    }
+}
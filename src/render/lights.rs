@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::geometry::normal::Normal3;
+use crate::geometry::point::Point3;
+use crate::geometry::vector::Vector3;
+use crate::util::sampler::Sampler;
+
+use super::element::Element;
+use super::hit_record::HitRecord;
+
+/// The lights of a scene that can be sampled, to light surfaces directly rather
+/// than waiting for scattered rays to find them. The lights are also part of
+/// the world, which is what rays actually hit.
+#[derive(Debug)]
+pub struct Lights {
+    pub elements: Vec<Arc<dyn Element>>,
+}
+
+impl Lights {
+    pub fn new(elements: Vec<Arc<dyn Element>>) -> Self {
+        Lights {
+            elements: elements,
+        }
+    }
+
+    pub fn none() -> Self {
+        Lights::new(Vec::new())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// The density, per unit solid angle, with which sample_direction picks the
+    /// direction: the average of the lights' densities, as each is equally likely
+    /// to be picked.
+    pub fn pdf_value(&self, origin: &Point3, direction: &Vector3, time: f32) -> f32 {
+        if self.elements.is_empty() {
+            return 0.0;
+        }
+
+        let total: f32 = self.elements.iter().map(|e| e.pdf_value(origin, direction, time)).sum();
+
+        total / (self.elements.len() as f32)
+    }
+
+    /// A direction from origin towards a random point on a random light, or None
+    /// if there are no lights or the one picked can't be sampled from origin.
+    pub fn sample_direction(&self, origin: &Point3, time: f32, sampler: &mut Sampler) -> Option<Vector3> {
+        if self.elements.is_empty() {
+            return None;
+        }
+
+        let light = &self.elements[sampler.gen_range(0..self.elements.len())];
+
+        light.sample_direction(origin, time, sampler)
+    }
+
+    /// A point picked on a random light, with the density per unit area with
+    /// which it was picked, or None if there are no lights or the one picked
+    /// can't be sampled. Light paths start from these.
+    pub fn sample_surface(&self, time: f32, sampler: &mut Sampler) -> Option<(HitRecord, f32)> {
+        if self.elements.is_empty() {
+            return None;
+        }
+
+        let light = &self.elements[sampler.gen_range(0..self.elements.len())];
+        let (hit_record, pdf) = light.sample_surface(time, sampler)?;
+
+        Some((hit_record, pdf / (self.elements.len() as f32)))
+    }
+
+    /// The density, per unit area, with which sample_surface picks the point: the
+    /// average of the lights' densities, which is zero away from the lights.
+    pub fn surface_pdf(&self, point: &Point3, normal: &Normal3, time: f32) -> f32 {
+        if self.elements.is_empty() {
+            return 0.0;
+        }
+
+        let total: f32 = self.elements.iter().map(|e| e.surface_pdf(point, normal, time)).sum();
+
+        total / (self.elements.len() as f32)
+    }
+}
+
+impl PartialEq for Lights {
+    fn eq(&self, other: &Self) -> bool {
+        (self.elements.len() == other.elements.len()) &&
+        self.elements.iter().zip(other.elements.iter()).all(|(a, b)| **a == **b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphics::colour::Colour;
+    use crate::render::elements::rect::XzRect;
+    use crate::render::elements::sphere::Sphere;
+    use crate::render::materials::diffuse_light::DiffuseLight;
+    use crate::render::textures::solid_colour::SolidColour;
+    use crate::util::testing::assertions::assert_pdf_normalised;
+
+    use super::*;
+
+    fn light() -> Arc<DiffuseLight> {
+        Arc::new(DiffuseLight::new(Arc::new(SolidColour::new(Colour::new(4.0, 4.0, 4.0)))))
+    }
+
+    fn lights() -> Lights {
+        Lights::new(vec![
+            Arc::new(Sphere::new(Point3::new(0.0, 3.0, 0.0), 1.0, light())),
+            Arc::new(XzRect::new(-1.0, 1.0, -1.0, 1.0, -2.0, light())),
+        ])
+    }
+
+    #[test]
+    fn pdf_averages_lights() {
+        let lights = lights();
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let up = Vector3::new(0.0, 1.0, 0.0);
+
+        assert_eq!(
+            lights.pdf_value(&origin, &up, 0.0),
+            lights.elements[0].pdf_value(&origin, &up, 0.0) / 2.0,
+        );
+    }
+
+    #[test]
+    fn sample_lights_directions() {
+        let lights = lights();
+        let origin = Point3::new(0.0, 0.0, 0.0);
+
+        assert_pdf_normalised(
+            |sampler| lights.sample_direction(&origin, 0.0, sampler),
+            |direction| lights.pdf_value(&origin, direction, 0.0),
+        );
+    }
+
+    #[test]
+    fn sample_lights_surfaces() {
+        let lights = lights();
+        let mut sampler = Sampler::new(0);
+
+        for _ in 0..100 {
+            let (hit_record, pdf) = lights.sample_surface(0.0, &mut sampler).unwrap();
+            let area = if hit_record.point.y > 0.0 { 4.0 * std::f32::consts::PI } else { 4.0 };
+
+            assert!((pdf * 2.0 * area - 1.0).abs() < 1e-5);
+            assert_eq!(lights.surface_pdf(&hit_record.point, &hit_record.normal, 0.0), pdf);
+        }
+    }
+
+    #[test]
+    fn no_lights() {
+        let lights = Lights::none();
+
+        assert!(lights.is_empty());
+        assert!(lights.sample_direction(&Point3::zero(), 0.0, &mut Sampler::new(0)).is_none());
+        assert_eq!(lights.pdf_value(&Point3::zero(), &Vector3::new(0.0, 1.0, 0.0), 0.0), 0.0);
+        assert!(lights.sample_surface(0.0, &mut Sampler::new(0)).is_none());
+        assert_eq!(lights.surface_pdf(&Point3::zero(), &Normal3::new(0.0, 1.0, 0.0), 0.0), 0.0);
+    }
+}
@@ -3,17 +3,38 @@ use std::fmt::Debug;
 
 use crate::geometry::point::Point3;
 use crate::geometry::ray::Ray;
+use crate::geometry::vector::Vector3;
 use crate::graphics::colour::Colour;
+use crate::util::sampler::Sampler;
 
+use super::bsdf_sample::BsdfSample;
 use super::hit_record::HitRecord;
 
-pub trait Material : Debug {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)>;
+/// How a surface gives off and scatters light. Scattering is described by the
+/// BSDF: it can be sampled for a direction to follow, evaluated for any given
+/// direction, and asked the density with which sample picks a direction.
+pub trait Material : Debug + Send + Sync {
+    /// A direction to scatter the ray in, or None if the light is absorbed.
+    fn sample(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample>;
 
     fn emit(&self, _u: f32, _v: f32, _p: &Point3) -> Colour {
         Colour::new(0.0, 0.0, 0.0)
     }
 
+    /// The fraction of light arriving from the direction that is scattered back
+    /// along the ray: the BSDF times the cosine of the angle to the normal.
+    /// Black for materials whose samples are specular, which sampled lights
+    /// can't reach.
+    fn evaluate(&self, _ray: &Ray, _hit_record: &HitRecord, _direction: &Vector3) -> Colour {
+        Colour::new(0.0, 0.0, 0.0)
+    }
+
+    /// The density, per unit solid angle, with which sample picks the direction.
+    /// Zero for materials whose samples are specular.
+    fn pdf(&self, _ray: &Ray, _hit_record: &HitRecord, _direction: &Vector3) -> f32 {
+        0.0
+    }
+
     fn eq(&self, other: &dyn Material) -> bool;
     fn as_any(&self) -> &dyn Any;
 }
@@ -1,14 +1,14 @@
 use std::any::Any;
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
-use crate::geometry::vector::Vector3;
 use crate::geometry::ray::Ray;
 use crate::graphics::colour::Colour;
 use crate::render::hit_record::HitRecord;
+use crate::util::sampler::Sampler;
 
+use super::super::bsdf_sample::BsdfSample;
 use super::super::material::Material;
-use super::super::materials::metal::reflect;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Dielectric {
@@ -24,7 +24,7 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)> {
+    fn sample(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
         let refraction_ratio = if hit_record.front_face {
             1.0 / self.index_of_refraction
         } else {
@@ -33,22 +33,18 @@ impl Material for Dielectric {
 
         let unit_direction = ray.direction.normalise();
 
-        let cos_theta = (unit_direction * -1.0).dot(&hit_record.normal).min(1.0);
+        let cos_theta = hit_record.normal.dot(&-unit_direction).min(1.0);
         let sin_theta = (1.0 - (cos_theta * cos_theta)).sqrt();
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
 
-        let mut rng = thread_rng();
-
-        let direction = if cannot_refract || (reflectance(cos_theta, refraction_ratio) > rng.gen()) {
-            reflect(unit_direction, hit_record.normal)
-        } else {
-            refract(unit_direction, hit_record.normal, refraction_ratio)
-        };
-
         let attenuation = Colour::new(1.0, 1.0, 1.0);
-        let scattered = Ray::new_at_time(hit_record.point, direction, ray.time);
 
-        Some((attenuation, scattered))
+        if cannot_refract || (reflectance(cos_theta, refraction_ratio) > sampler.gen()) {
+            Some(BsdfSample::new_specular(unit_direction.reflect(&hit_record.normal), attenuation))
+        } else {
+            let direction = unit_direction.refract(&hit_record.normal, refraction_ratio);
+            Some(BsdfSample::new_transmission(direction, attenuation, 1.0 / refraction_ratio))
+        }
     }
 
     fn eq(&self, other: &dyn Material) -> bool {
@@ -60,14 +56,6 @@ impl Material for Dielectric {
     }
 }
 
-fn refract(v: Vector3, n: Vector3, eta_i_over_eta_t: f32) -> Vector3 {
-    let cos_theta = (v * -1.0).dot(&n).min(1.0);
-    let r_perpendicular = (v + (n * cos_theta)) * eta_i_over_eta_t;
-    let r_parallel = n * -(1.0 - r_perpendicular.length_squared()).abs().sqrt();
-
-    r_perpendicular + r_parallel
-}
-
 /// Schlick approximation
 fn reflectance(cos_theta: f32, refraction_ratio: f32) -> f32 {
     let sqrt_r0 = (1.0 - refraction_ratio) / (1.0 + refraction_ratio);
@@ -78,6 +66,13 @@ fn reflectance(cos_theta: f32, refraction_ratio: f32) -> f32 {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use crate::geometry::normal::Normal3;
+    use crate::geometry::point::Point3;
+    use crate::geometry::vector::Vector3;
+    use crate::render::bsdf_sample::Lobe;
+
     use super::*;
 
     #[test]
@@ -87,4 +82,52 @@ mod tests {
             Dielectric { index_of_refraction: 1.0 },
         );
     }
+
+    #[test]
+    fn sample_reflects_beyond_critical_angle() {
+        let glass = Dielectric::new(1.5);
+        // Leaving the glass at 60 degrees, past the critical angle of about 42.
+        let ray = Ray::new(Point3::new(-3.0f32.sqrt(), 1.0, 0.0), Vector3::new(3.0f32.sqrt(), -1.0, 0.0));
+        let hit_record = HitRecord::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Normal3::new(0.0, 1.0, 0.0),
+            Arc::new(glass),
+            1.0,
+            0.0,
+            0.0,
+            false,
+        );
+
+        let sample = glass.sample(&ray, &hit_record, &mut Sampler::new(0)).unwrap();
+
+        assert_eq!(sample.lobe, Lobe::Specular);
+        assert_eq!(sample.attenuation, Colour::new(1.0, 1.0, 1.0));
+        assert!((sample.direction - Vector3::new(3.0f32.sqrt(), 1.0, 0.0).normalise()).length() < 1e-6);
+    }
+
+    #[test]
+    fn sample_refracts_head_on() {
+        let glass = Dielectric::new(1.5);
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit_record = HitRecord::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Normal3::new(0.0, 1.0, 0.0),
+            Arc::new(glass),
+            1.0,
+            0.0,
+            0.0,
+            true,
+        );
+
+        // Only 4% is reflected head on, so most samples pass straight through.
+        let mut sampler = Sampler::new(0);
+        let transmitted = (0..1000)
+            .filter_map(|_| glass.sample(&ray, &hit_record, &mut sampler))
+            .filter(|sample| sample.lobe == Lobe::Transmission)
+            .inspect(|sample| assert!((sample.direction - Vector3::new(0.0, -1.0, 0.0)).length() < 1e-6))
+            .inspect(|sample| assert_eq!(sample.eta, 1.5))
+            .count();
+
+        assert!(transmitted > 900, "{}", transmitted);
+    }
 }
\ No newline at end of file
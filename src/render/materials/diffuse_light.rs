@@ -1,21 +1,23 @@
 use std::any::Any;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::geometry::point::Point3;
 use crate::geometry::ray::Ray;
 use crate::graphics::colour::Colour;
+use crate::util::sampler::Sampler;
 
+use super::super::bsdf_sample::BsdfSample;
 use super::super::hit_record::HitRecord;
 use super::super::material::Material;
 use super::super::texture::Texture;
 
 #[derive(Debug)]
 pub struct DiffuseLight {
-    emit: Rc<dyn Texture>,
+    emit: Arc<dyn Texture>,
 }
 
 impl DiffuseLight {
-    pub fn new(emit: Rc<dyn Texture>) -> Self {
+    pub fn new(emit: Arc<dyn Texture>) -> Self {
         DiffuseLight {
             emit: emit,
         }
@@ -23,7 +25,7 @@ impl DiffuseLight {
 }
 
 impl Material for DiffuseLight {
-    fn scatter(&self, _ray: &Ray, _hit_record: &HitRecord) -> Option<(Colour, Ray)> {
+    fn sample(&self, _ray: &Ray, _hit_record: &HitRecord, _sampler: &mut Sampler) -> Option<BsdfSample> {
         None
     }
 
@@ -48,6 +50,7 @@ impl PartialEq for DiffuseLight {
 
 #[cfg(test)]
 mod tests {
+    use crate::geometry::normal::Normal3;
     use crate::geometry::vector::Vector3;
     use crate::render::materials::lambertian::Lambertian;
     use crate::render::textures::solid_colour::SolidColour;
@@ -58,10 +61,10 @@ mod tests {
     fn new_diffuse_light() {
         assert_eq!(
             DiffuseLight::new(
-                Rc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
+                Arc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
             ),
             DiffuseLight {
-                emit: Rc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
+                emit: Arc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
             },
         );
     }
@@ -69,7 +72,7 @@ mod tests {
     #[test]
     fn doesnt_scatter() {
         let light = DiffuseLight::new(
-            Rc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
         );
         let ray = Ray::new(
             Point3::new(0.0, 0.0, 0.0),
@@ -77,8 +80,8 @@ mod tests {
         );
         let hit_record = HitRecord::new(
             Point3::new(3.0, 0.0, 0.0),
-            Vector3::new(-1.0, 0.0, 0.0),
-            Rc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
+            Normal3::new(-1.0, 0.0, 0.0),
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3))),
             3.0,
             0.0,
             0.5,
@@ -86,7 +89,7 @@ mod tests {
         );
 
         assert_eq!(
-            light.scatter(&ray, &hit_record),
+            light.sample(&ray, &hit_record, &mut Sampler::new(0)),
             None,
         );
     }
@@ -94,7 +97,7 @@ mod tests {
     #[test]
     fn diffuse_light_emit() {
         let light = DiffuseLight::new(
-            Rc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
+            Arc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
         );
 
         assert_eq!(
@@ -0,0 +1,104 @@
+use std::any::Any;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::Vector3;
+use crate::graphics::colour::Colour;
+use crate::util::random::random_unit_vector;
+use crate::util::sampler::Sampler;
+
+use super::super::bsdf_sample::BsdfSample;
+use super::super::hit_record::HitRecord;
+use super::super::material::Material;
+use super::super::texture::Texture;
+use super::super::textures::solid_colour::SolidColour;
+
+/// The phase function of a medium that scatters light equally in every
+/// direction, the albedo being the fraction scattered rather than absorbed.
+/// Points in a medium have no surface, so there is no cosine to the normal.
+#[derive(Debug, Clone)]
+pub struct Isotropic {
+    pub albedo: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Arc<dyn Texture>) -> Self {
+        Isotropic {
+            albedo: albedo,
+        }
+    }
+
+    pub fn new_with_colour(albedo: Colour) -> Self {
+        Isotropic {
+            albedo: Arc::new(SolidColour::new(albedo)),
+        }
+    }
+}
+
+impl Material for Isotropic {
+    fn sample(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let direction = random_unit_vector(sampler);
+
+        Some(BsdfSample::new(direction, self.albedo.value_at(hit_record), self.pdf(ray, hit_record, &direction)))
+    }
+
+    fn evaluate(&self, ray: &Ray, hit_record: &HitRecord, direction: &Vector3) -> Colour {
+        self.albedo.value_at(hit_record) * self.pdf(ray, hit_record, direction)
+    }
+
+    fn pdf(&self, _ray: &Ray, _hit_record: &HitRecord, _direction: &Vector3) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn eq(&self, other: &dyn Material) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PartialEq for Isotropic {
+    fn eq(&self, other: &Self) -> bool {
+        *self.albedo == *other.albedo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::point::Point3;
+    use crate::util::testing::assertions::assert_pdf_normalised;
+
+    use super::*;
+
+    #[test]
+    fn sample_follows_pdf() {
+        let isotropic = Isotropic::new_with_colour(Colour::new(0.5, 0.5, 0.5));
+        let hit_record = HitRecord::new_in_medium(Point3::new(0.0, 0.0, 0.0), Arc::new(isotropic.clone()), 1.0);
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        assert_pdf_normalised(
+            |sampler| isotropic.sample(&ray, &hit_record, sampler).map(|sample| sample.direction),
+            |direction| isotropic.pdf(&ray, &hit_record, direction),
+        );
+    }
+
+    #[test]
+    fn sample_agrees_with_evaluate() {
+        let isotropic = Isotropic::new_with_colour(Colour::new(0.5, 0.25, 0.5));
+        let hit_record = HitRecord::new_in_medium(Point3::new(0.0, 0.0, 0.0), Arc::new(isotropic.clone()), 1.0);
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let mut sampler = Sampler::new(2);
+
+        for _ in 0..100 {
+            let sample = isotropic.sample(&ray, &hit_record, &mut sampler).unwrap();
+            let expected = isotropic.evaluate(&ray, &hit_record, &sample.direction) * (1.0 / sample.pdf);
+
+            assert!(!sample.is_specular());
+            assert_eq!(sample.attenuation, Colour::new(0.5, 0.25, 0.5));
+            assert!((expected.g - 0.25).abs() < 1e-4, "{:?}", expected);
+        }
+    }
+}
@@ -1,23 +1,30 @@
 use std::any::Any;
-use std::rc::Rc;
+use std::f32::consts::PI;
+use std::sync::Arc;
 
+use crate::geometry::orthonormal_basis::OrthonormalBasis;
 use crate::geometry::ray::Ray;
+use crate::geometry::vector::Vector3;
 use crate::graphics::colour::Colour;
-use crate::util::random::random_in_unit_sphere;
+use crate::util::random::random_cosine_direction;
+use crate::util::sampler::Sampler;
 
+use super::super::bsdf_sample::BsdfSample;
 use super::super::hit_record::HitRecord;
 use super::super::material::Material;
 use super::super::texture::Texture;
 use super::super::textures::solid_colour::SolidColour;
 
-// Not a true Lambertian.
+/// A perfectly matte material, scattering light equally in every direction.
+/// Directions are sampled in proportion to the cosine of their angle to the
+/// normal, which cancels the cosine in the scattering.
 #[derive(Debug, Clone)]
 pub struct Lambertian {
-    pub albedo: Rc<dyn Texture>,
+    pub albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
-    pub fn new(albedo: Rc<dyn Texture>) -> Self {
+    pub fn new(albedo: Arc<dyn Texture>) -> Self {
         Lambertian {
             albedo: albedo,
         }
@@ -25,23 +32,34 @@ impl Lambertian {
 
     pub fn new_with_colour(albedo: Colour) -> Self {
         Lambertian {
-            albedo: Rc::new(SolidColour::new(albedo)),
+            albedo: Arc::new(SolidColour::new(albedo)),
         }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)> {
-        let mut scatter_direction = hit_record.normal + random_in_unit_sphere();
-        
-        if scatter_direction.near_zero() {
-            scatter_direction = hit_record.normal;
+    fn sample(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let basis = OrthonormalBasis::new_from_w(&hit_record.normal.as_vector3());
+        let direction = basis.local(&random_cosine_direction(sampler));
+        let pdf = self.pdf(ray, hit_record, &direction);
+
+        // The cosine and the density cancel, leaving just the albedo, except for
+        // directions grazing the surface so closely they have no density.
+        if pdf <= 0.0 {
+            return None;
         }
 
-        Some((
-            self.albedo.value(hit_record.u, hit_record.v, &hit_record.point),
-            Ray::new_at_time(hit_record.point, scatter_direction, ray.time),
-        ))
+        Some(BsdfSample::new(direction, self.albedo.value_at(hit_record), pdf))
+    }
+
+    fn evaluate(&self, ray: &Ray, hit_record: &HitRecord, direction: &Vector3) -> Colour {
+        self.albedo.value_at(hit_record) * self.pdf(ray, hit_record, direction)
+    }
+
+    fn pdf(&self, _ray: &Ray, hit_record: &HitRecord, direction: &Vector3) -> f32 {
+        let cosine = hit_record.normal.dot(&direction.normalise());
+
+        if cosine > 0.0 { cosine / PI } else { 0.0 }
     }
 
     fn eq(&self, other: &dyn Material) -> bool {
@@ -61,15 +79,79 @@ impl PartialEq for Lambertian {
 
 #[cfg(test)]
 mod tests {
+    use crate::geometry::normal::Normal3;
+    use crate::geometry::point::Point3;
+    use crate::util::testing::assertions::assert_pdf_normalised;
+
     use super::*;
 
+    fn facing_up_hit(lambertian: &Lambertian) -> HitRecord {
+        HitRecord::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Normal3::new(0.0, 1.0, 0.0),
+            Arc::new(lambertian.clone()),
+            1.0,
+            0.0,
+            0.0,
+            true,
+        )
+    }
+
     #[test]
     fn new_lambertian_with_colour() {
         assert_eq!(
             Lambertian::new_with_colour(Colour::new(0.1, 0.2, 0.3)),
             Lambertian {
-                albedo: Rc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
+                albedo: Arc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
             },
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn evaluate_matches_pdf() {
+        let lambertian = Lambertian::new_with_colour(Colour::new(0.5, 0.5, 0.5));
+        let hit_record = facing_up_hit(&lambertian);
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        let up = Vector3::new(0.0, 1.0, 0.0);
+        let slanted = Vector3::new(1.0, 1.0, 0.0);
+        let below = Vector3::new(0.0, -1.0, 0.0);
+
+        assert!((lambertian.pdf(&ray, &hit_record, &up) - 1.0 / PI).abs() < 1e-6);
+        assert!((lambertian.pdf(&ray, &hit_record, &slanted) - 0.5f32.sqrt() / PI).abs() < 1e-6);
+        assert_eq!(lambertian.pdf(&ray, &hit_record, &below), 0.0);
+        assert_eq!(
+            lambertian.evaluate(&ray, &hit_record, &up),
+            Colour::new(0.5, 0.5, 0.5) * (1.0 / PI),
+        );
+    }
+
+    #[test]
+    fn sample_follows_pdf() {
+        let lambertian = Lambertian::new_with_colour(Colour::new(0.5, 0.5, 0.5));
+        let hit_record = facing_up_hit(&lambertian);
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        assert_pdf_normalised(
+            |sampler| lambertian.sample(&ray, &hit_record, sampler).map(|sample| sample.direction),
+            |direction| lambertian.pdf(&ray, &hit_record, direction),
+        );
+    }
+
+    #[test]
+    fn sample_agrees_with_evaluate() {
+        let lambertian = Lambertian::new_with_colour(Colour::new(0.5, 0.5, 0.5));
+        let hit_record = facing_up_hit(&lambertian);
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let mut sampler = Sampler::new(2);
+
+        for _ in 0..100 {
+            let sample = lambertian.sample(&ray, &hit_record, &mut sampler).unwrap();
+            let expected = lambertian.evaluate(&ray, &hit_record, &sample.direction) * (1.0 / sample.pdf);
+
+            assert!(!sample.is_specular());
+            assert!((sample.attenuation.r - expected.r).abs() < 1e-4, "{:?} against {:?}", sample, expected);
+            assert!(sample.direction.y > 0.0);
+        }
+    }
+}
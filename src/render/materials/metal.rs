@@ -1,13 +1,16 @@
 use std::any::Any;
 
-use crate::geometry::vector::Vector3;
 use crate::geometry::ray::Ray;
 use crate::graphics::colour::Colour;
 use crate::util::random::random_in_unit_sphere;
+use crate::util::sampler::Sampler;
 
+use super::super::bsdf_sample::BsdfSample;
 use super::super::hit_record::HitRecord;
 use super::super::material::Material;
 
+/// A mirror, blurred by fuzz. Its samples count as specular even when fuzzy,
+/// as the spread of directions has no density we can evaluate.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Metal {
     pub albedo: Colour,
@@ -31,16 +34,12 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)> {
-        let reflected = reflect(ray.direction.normalise(), hit_record.normal);
-        let fuzz_vector = random_in_unit_sphere() * self.fuzz;
-        let scattered =  Ray::new_at_time(hit_record.point, reflected + fuzz_vector, ray.time);
-
-        if scattered.direction.dot(&hit_record.normal) > 0.0 {
-            Some((
-                self.albedo,
-                scattered,
-            ))
+    fn sample(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let reflected = ray.direction.normalise().reflect(&hit_record.normal);
+        let direction = reflected + (random_in_unit_sphere(sampler) * self.fuzz);
+
+        if hit_record.normal.dot(&direction) > 0.0 {
+            Some(BsdfSample::new_specular(direction, self.albedo))
         } else {
             None
         }
@@ -55,12 +54,14 @@ impl Material for Metal {
     }
 }
 
-pub fn reflect(v: Vector3, n: Vector3) -> Vector3 {
-    v + (n * (-2.0 * v.dot(&n)))
-}
-
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use crate::geometry::normal::Normal3;
+    use crate::geometry::point::Point3;
+    use crate::geometry::vector::Vector3;
+
     use super::*;
 
     #[test]
@@ -84,4 +85,26 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn sample_reflects() {
+        let metal = Metal::new(Colour::new(0.8, 0.6, 0.2));
+        let ray = Ray::new(Point3::new(-1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        let hit_record = HitRecord::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Normal3::new(0.0, 1.0, 0.0),
+            Arc::new(metal.clone()),
+            1.0,
+            0.0,
+            0.0,
+            true,
+        );
+
+        let sample = metal.sample(&ray, &hit_record, &mut Sampler::new(0)).unwrap();
+
+        assert!(sample.is_specular());
+        assert_eq!(sample.attenuation, Colour::new(0.8, 0.6, 0.2));
+        assert!((sample.direction - Vector3::new(1.0, 1.0, 0.0).normalise()).length() < 1e-6);
+        assert_eq!(metal.pdf(&ray, &hit_record, &sample.direction), 0.0);
+    }
 }
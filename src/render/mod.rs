@@ -1,9 +1,20 @@
+// Elements, materials and textures compare themselves through
+// `downcast_ref::<Self>().map_or(false, ..)`.
+#[allow(clippy::unnecessary_map_or)]
 pub mod elements;
+pub mod integrators;
+#[allow(clippy::unnecessary_map_or)]
 pub mod materials;
+#[allow(clippy::unnecessary_map_or)]
 pub mod textures;
 
+pub mod bsdf_sample;
 pub mod camera;
 pub mod element;
 pub mod hit_record;
+pub mod integrator;
+pub mod lights;
 pub mod material;
+pub mod photon_map;
+pub mod renderer;
 pub mod texture;
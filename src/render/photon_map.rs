@@ -0,0 +1,487 @@
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use rand::Rng;
+
+use crate::geometry::normal::Normal3;
+use crate::geometry::orthonormal_basis::OrthonormalBasis;
+use crate::geometry::point::Point3;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::Vector3;
+use crate::graphics::colour::Colour;
+use crate::util::random::random_cosine_direction;
+use crate::util::sampler::Sampler;
+
+use super::camera::Camera;
+use super::element::Element;
+use super::hit_record::HitRecord;
+use super::lights::Lights;
+
+/// How quickly the radius of a progressive photon map shrinks: each pass
+/// keeps this share of the photons the one before would have gathered.
+const RADIUS_ALPHA: f32 = 2.0 / 3.0;
+
+/// The number of photons traced with each sampler. Batches are handed out to
+/// the threads in turn, so the photons don't depend on how many there are.
+const PHOTON_BATCH: usize = 4096;
+
+/// Light carried from the lights to a point on a surface that scatters it over
+/// a range of directions.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Photon {
+    pub point: Point3,
+    /// The normal at the point, on the side the photon arrived at.
+    pub normal: Normal3,
+    /// The direction back the way the photon came.
+    pub direction: Vector3,
+    pub power: Colour,
+}
+
+/// Photons kept as a balanced kd-tree, so those near a point can be found
+/// quickly. The tree is the order of the photons itself: each range of them
+/// has the median along its split axis in the middle, with those below it
+/// before and those above it after.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PhotonMap {
+    photons: Vec<Photon>,
+    axes: Vec<u8>,
+}
+
+impl PhotonMap {
+    pub fn new(photons: Vec<Photon>) -> Self {
+        let mut photons = photons;
+        let mut axes = vec![0; photons.len()];
+        build(&mut photons, &mut axes);
+
+        PhotonMap {
+            photons: photons,
+            axes: axes,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    /// Calls f with every photon within radius of point.
+    pub fn within<F: FnMut(&Photon)>(&self, point: &Point3, radius: f32, mut f: F) {
+        self.within_range(0, self.photons.len(), point, radius * radius, &mut f);
+    }
+
+    fn within_range<F: FnMut(&Photon)>(&self, start: usize, end: usize, point: &Point3, radius_squared: f32, f: &mut F) {
+        if start >= end {
+            return;
+        }
+
+        let middle = start + ((end - start) / 2);
+        let photon = &self.photons[middle];
+        if (photon.point - *point).length_squared() <= radius_squared {
+            f(photon);
+        }
+
+        let axis = self.axes[middle] as usize;
+        let offset = point[axis] - photon.point[axis];
+        let (near, far) = if offset < 0.0 {
+            ((start, middle), (middle + 1, end))
+        } else {
+            ((middle + 1, end), (start, middle))
+        };
+
+        self.within_range(near.0, near.1, point, radius_squared, f);
+        if offset * offset <= radius_squared {
+            self.within_range(far.0, far.1, point, radius_squared, f);
+        }
+    }
+
+    /// The light the photons within radius of the hit scatter back along the
+    /// ray, taking them to be spread evenly over the disc the radius covers.
+    /// Photons that arrived on the other side of the surface are left out, and
+    /// points in a medium, where none are kept, gather nothing.
+    pub fn estimate(&self, ray: &Ray, hit_record: &HitRecord, radius: f32) -> Colour {
+        let mut total = Colour::new(0.0, 0.0, 0.0);
+        if hit_record.medium {
+            return total;
+        }
+
+        self.within(&hit_record.point, radius, |photon| {
+            let cosine = hit_record.normal.dot(&photon.direction);
+            if cosine <= 0.0 || photon.normal.dot(&hit_record.normal.as_vector3()) <= 0.0 {
+                return;
+            }
+
+            // The photon's power already counts the cosine where it landed.
+            let scattering = hit_record.material.evaluate(ray, hit_record, &photon.direction);
+            total += scattering * photon.power * (1.0 / cosine);
+        });
+
+        total * (1.0 / (PI * radius * radius))
+    }
+}
+
+/// Puts the photons in kd-tree order, with the axis each is split on in axes.
+fn build(photons: &mut [Photon], axes: &mut [u8]) {
+    if photons.len() <= 1 {
+        return;
+    }
+
+    let (min, max) = photons.iter().fold(
+        (photons[0].point.as_vector3(), photons[0].point.as_vector3()),
+        |(min, max), photon| (min.min(&photon.point.as_vector3()), max.max(&photon.point.as_vector3())),
+    );
+    let axis = (max - min).max_dimension();
+
+    let middle = photons.len() / 2;
+    photons.select_nth_unstable_by(middle, |a, b| a.point[axis].total_cmp(&b.point[axis]));
+    axes[middle] = axis as u8;
+
+    let (below, above) = photons.split_at_mut(middle);
+    let (axes_below, axes_above) = axes.split_at_mut(middle);
+    build(below, axes_below);
+    build(&mut above[1..], &mut axes_above[1..]);
+}
+
+/// A photon map traced afresh for each pass of a render, gathered from a radius
+/// that shrinks from one pass to the next. The average over the passes loses
+/// the blur of gathering from a wide radius as they go on, while the noise of
+/// the narrower ones is averaged away.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProgressivePhotonMap {
+    /// The number of photons traced for each pass.
+    pub photons: usize,
+    /// The radius photons are gathered from in the first pass.
+    pub initial_radius: f32,
+    pub caustics_only: bool,
+    pub max_depth: usize,
+    pub roulette_depth: usize,
+    /// The number of threads the photons are traced across.
+    pub threads: usize,
+    pub radius: f32,
+    pub map: PhotonMap,
+}
+
+impl ProgressivePhotonMap {
+    /// Panics unless initial_radius is positive and finite, so callers taking
+    /// it from users should check it first.
+    pub fn new(
+        photons: usize,
+        initial_radius: f32,
+        caustics_only: bool,
+        max_depth: usize,
+        roulette_depth: usize,
+        threads: usize,
+    ) -> Self {
+        assert!(initial_radius.is_finite() && initial_radius > 0.0, "photon radius not positive");
+
+        ProgressivePhotonMap {
+            photons: photons,
+            initial_radius: initial_radius,
+            caustics_only: caustics_only,
+            max_depth: max_depth,
+            roulette_depth: roulette_depth,
+            threads: threads,
+            radius: initial_radius,
+            map: PhotonMap::new(Vec::new()),
+        }
+    }
+
+    /// The radius photons are gathered from in the pass.
+    pub fn radius_for_pass(&self, pass: usize) -> f32 {
+        let shrink: f32 = (1..=pass).map(|i| ((i as f32) + RADIUS_ALPHA) / ((i as f32) + 1.0)).product();
+
+        self.initial_radius * shrink.sqrt()
+    }
+
+    /// Traces the photons for the pass of a render with the given seed.
+    pub fn begin_pass(&mut self, pass: usize, camera: &Camera, world: &dyn Element, lights: &Lights, seed: u64) {
+        self.radius = self.radius_for_pass(pass);
+        self.map = self.trace(pass, camera, world, lights, seed);
+    }
+
+    /// Traces the photons for the pass from the lights, at times while the
+    /// camera's shutter is open, and keeps those that land on surfaces that
+    /// scatter over a range of directions after at least one bounce. Light
+    /// landing straight from the lights is left to be sampled directly. Photons
+    /// scatter off media along the way, but none are kept there.
+    ///
+    /// If caustics_only is set, only photons that reached the surface through
+    /// mirrors and glass alone are kept, and none are followed further.
+    ///
+    /// Photons are followed for at most max_depth bounces, and from
+    /// roulette_depth bounces on are ended at random as they fade.
+    ///
+    /// The photons are traced in batches across the threads, each batch drawing
+    /// from its own sampler seeded by Sampler::for_photons, and are put
+    /// together in the order of the batches before the kd-tree is built.
+    pub fn trace(&self, pass: usize, camera: &Camera, world: &dyn Element, lights: &Lights, seed: u64) -> PhotonMap {
+        let batches = self.photons.div_ceil(PHOTON_BATCH);
+        let next_batch = AtomicUsize::new(0);
+
+        let mut batch_photons: Vec<Vec<Photon>> = vec![Vec::new(); batches];
+
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+
+            for _thread in 0..self.threads.max(1) {
+                let sender = sender.clone();
+                let next_batch = &next_batch;
+
+                scope.spawn(move || loop {
+                    let batch = next_batch.fetch_add(1, Ordering::Relaxed);
+                    if batch >= batches {
+                        break;
+                    }
+
+                    let count = PHOTON_BATCH.min(self.photons - (batch * PHOTON_BATCH));
+                    let mut sampler = Sampler::for_photons(seed, pass, batch);
+                    sender.send((batch, self.trace_batch(count, camera, world, lights, &mut sampler))).unwrap();
+                });
+            }
+
+            // Only the workers hold senders now, so the loop ends when they are done.
+            drop(sender);
+
+            for (batch, photons) in receiver.iter() {
+                batch_photons[batch] = photons;
+            }
+        });
+
+        PhotonMap::new(batch_photons.concat())
+    }
+
+    /// Traces count of the photons of a pass, drawing from sampler.
+    fn trace_batch(&self, count: usize, camera: &Camera, world: &dyn Element, lights: &Lights, sampler: &mut Sampler) -> Vec<Photon> {
+        let mut stored = Vec::new();
+
+        for _photon in 0..count {
+            let time = camera.time(sampler);
+
+            let (hit_record, pdf_position) = match lights.sample_surface(time, sampler) {
+                Some(sample) => sample,
+                None => continue,
+            };
+            let emitted = hit_record.material.emit(hit_record.u, hit_record.v, &hit_record.point);
+
+            let side = if sampler.gen::<bool>() { hit_record.normal } else { -hit_record.normal };
+            let direction = OrthonormalBasis::new_from_w(&side.as_vector3()).local(&random_cosine_direction(sampler));
+            let cosine = side.dot(&direction);
+            let pdf_direction = cosine / (2.0 * PI);
+
+            if pdf_position <= 0.0 || pdf_direction <= 0.0 || emitted == Colour::new(0.0, 0.0, 0.0) {
+                continue;
+            }
+
+            let mut power = emitted * (cosine / (pdf_position * pdf_direction * (self.photons as f32)));
+            let mut ray = Ray::new_at_time(hit_record.point, direction, time);
+            let mut specular_only = true;
+
+            for bounces in 0..self.max_depth {
+                let hit_record = match world.hit(&ray, 0.001, f32::INFINITY, sampler) {
+                    Some(hit_record) => hit_record,
+                    None => break,
+                };
+                let sample = match hit_record.material.sample(&ray, &hit_record, sampler) {
+                    Some(sample) => sample,
+                    None => break,
+                };
+
+                if !sample.is_specular() {
+                    if bounces > 0 && !hit_record.medium && (specular_only || !self.caustics_only) {
+                        stored.push(Photon {
+                            point: hit_record.point,
+                            normal: hit_record.normal,
+                            direction: -ray.direction.normalise(),
+                            power: power,
+                        });
+                    }
+
+                    if self.caustics_only {
+                        break;
+                    }
+                    specular_only = false;
+                }
+
+                // Photons carry light rather than gather it, so are scaled on
+                // passing through surfaces where radiance isn't.
+                let attenuation = sample.attenuation * (1.0 / (sample.eta * sample.eta));
+                power = power * attenuation;
+
+                if bounces + 1 >= self.roulette_depth {
+                    let survival = attenuation.max_component();
+                    if survival < 1.0 {
+                        if sampler.gen::<f32>() >= survival {
+                            break;
+                        }
+                        power = power * (1.0 / survival);
+                    }
+                }
+
+                ray = Ray::new_at_time(hit_record.point, sample.direction, time);
+            }
+        }
+
+        stored
+    }
+
+    /// The light the photons of this pass scatter back along the ray.
+    pub fn estimate(&self, ray: &Ray, hit_record: &HitRecord) -> Colour {
+        self.map.estimate(ray, hit_record, self.radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::render::elements::element_list::ElementList;
+    use crate::render::elements::rect::XzRect;
+    use crate::render::elements::sphere::Sphere;
+    use crate::render::materials::dielectric::Dielectric;
+    use crate::render::materials::diffuse_light::DiffuseLight;
+    use crate::render::materials::lambertian::Lambertian;
+    use crate::render::materials::metal::Metal;
+    use crate::render::textures::solid_colour::SolidColour;
+    use crate::util::random::random_in_unit_cube;
+
+    use super::*;
+
+    fn camera() -> Camera {
+        Camera::new(
+            Point3::new(0.0, 1.0, 5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            5.0,
+            0.0,
+            1.0,
+        )
+    }
+
+    /// A grey floor lit by a small sphere of the given radiance at (1, 1, 0),
+    /// with something above both at height 2 made of top.
+    fn lamp_over_floor(radiance: f32, top: Option<Arc<dyn crate::render::material::Material>>) -> (ElementList, Lights) {
+        let lamp = Sphere::new(
+            Point3::new(1.0, 1.0, 0.0),
+            0.1,
+            Arc::new(DiffuseLight::new(Arc::new(SolidColour::new(Colour::new(radiance, radiance, radiance))))),
+        );
+
+        let mut world = ElementList::new();
+        world.add(Box::new(XzRect::new(
+            -100.0,
+            100.0,
+            -100.0,
+            100.0,
+            0.0,
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.5, 0.5, 0.5))),
+        )));
+        if let Some(top) = top {
+            world.add(Box::new(XzRect::new(-100.0, 100.0, -100.0, 100.0, 2.0, top)));
+        }
+        world.add(Box::new(lamp.clone()));
+
+        (world, Lights::new(vec![Arc::new(lamp)]))
+    }
+
+    #[test]
+    fn within_finds_photons_in_radius() {
+        let mut sampler = Sampler::new(1);
+        let photons: Vec<Photon> = (0..1000)
+            .map(|i| Photon {
+                point: random_in_unit_cube(&mut sampler).as_point3(),
+                normal: Normal3::new(0.0, 1.0, 0.0),
+                direction: Vector3::new(0.0, 1.0, 0.0),
+                power: Colour::new(i as f32, 0.0, 0.0),
+            })
+            .collect();
+        let map = PhotonMap::new(photons.clone());
+
+        assert_eq!(map.len(), 1000);
+
+        for &radius in [0.0, 0.1, 0.3, 2.0].iter() {
+            for _query in 0..10 {
+                let point = random_in_unit_cube(&mut sampler).as_point3();
+
+                let mut found = Vec::new();
+                map.within(&point, radius, |photon| found.push(photon.power.r));
+                found.sort_by(f32::total_cmp);
+
+                let expected: Vec<f32> = photons.iter()
+                    .filter(|photon| (photon.point - point).length() <= radius)
+                    .map(|photon| photon.power.r)
+                    .collect();
+
+                assert_eq!(found, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn caustics_need_mirrors_or_glass() {
+        let caustics = ProgressivePhotonMap::new(1000, 1.0, true, 5, 5, 2);
+
+        let (world, lights) = lamp_over_floor(1.0, None);
+        let plain = caustics.trace(0, &camera(), &world, &lights, 2);
+
+        let (world, lights) = lamp_over_floor(1.0, Some(Arc::new(Dielectric::new(1.5))));
+        let glass = caustics.trace(0, &camera(), &world, &lights, 2);
+
+        assert!(plain.is_empty());
+        assert!(!glass.is_empty());
+        assert!(glass.photons.iter().all(|photon| photon.point.y.abs() < 0.001));
+    }
+
+    #[test]
+    fn photons_reflected_by_mirror_light_floor() {
+        let (world, lights) = lamp_over_floor(1000.0, Some(Arc::new(Metal::new(Colour::new(1.0, 1.0, 1.0)))));
+        let map = ProgressivePhotonMap::new(200_000, 1.0, true, 5, 5, 4).trace(0, &camera(), &world, &lights, 3);
+
+        let ray = Ray::new(Point3::new(0.0, 0.5, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit_record = world.hit(&ray, 0.001, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+        let radiance = map.estimate(&ray, &hit_record, 0.3).r;
+
+        // The lamp's image in the mirror is at (1, 3, 0). A small sphere of
+        // radius R and radiance L at distance d lights a surface turned by
+        // theta from it with pi L R^2 cos(theta) / d^2, of which the floor
+        // sends back 0.5 / pi.
+        let distance_squared: f32 = 10.0;
+        let cosine = 3.0 / distance_squared.sqrt();
+        let expected = 0.5 * 1000.0 * 0.01 * cosine / distance_squared;
+
+        assert!((radiance / expected - 1.0).abs() < 0.15, "{} is not near {}", radiance, expected);
+    }
+
+    #[test]
+    fn trace_independent_of_thread_count() {
+        let (world, lights) = lamp_over_floor(1.0, Some(Arc::new(Metal::new(Colour::new(1.0, 1.0, 1.0)))));
+
+        let single = ProgressivePhotonMap::new(10_000, 1.0, false, 5, 5, 1).trace(1, &camera(), &world, &lights, 4);
+        let multiple = ProgressivePhotonMap::new(10_000, 1.0, false, 5, 5, 3).trace(1, &camera(), &world, &lights, 4);
+
+        assert!(!single.is_empty());
+        assert_eq!(single, multiple);
+    }
+
+    #[test]
+    #[should_panic(expected = "photon radius not positive")]
+    fn radius_must_be_positive() {
+        ProgressivePhotonMap::new(1_000, 0.0, false, 5, 3, 1);
+    }
+
+    #[test]
+    fn radius_shrinks_with_passes() {
+        let map = ProgressivePhotonMap::new(10, 1.0, false, 5, 5, 1);
+
+        assert_eq!(map.radius_for_pass(0), 1.0);
+        assert!((map.radius_for_pass(1) - ((1.0 + RADIUS_ALPHA) / 2.0).sqrt()).abs() < 1e-6);
+        assert!(map.radius_for_pass(10) < map.radius_for_pass(9));
+        assert!(map.radius_for_pass(100) > 0.0);
+    }
+}
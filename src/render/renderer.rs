@@ -0,0 +1,742 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use rand::Rng;
+
+use crate::graphics::colour::Colour;
+use crate::graphics::framebuffer::Framebuffer;
+use crate::util::sampler::Sampler;
+
+use super::camera::Camera;
+use super::element::Element;
+use super::integrator::{Integrator, IntegratorKind, Splat};
+use super::integrators::ambient_occlusion::AmbientOcclusionIntegrator;
+use super::integrators::bidirectional::BidirectionalIntegrator;
+use super::integrators::direct::DirectIntegrator;
+use super::integrators::path::PathIntegrator;
+use super::integrators::photon::PhotonIntegrator;
+use super::integrators::whitted::WhittedIntegrator;
+use super::lights::Lights;
+use super::photon_map::ProgressivePhotonMap;
+
+#[derive(Debug, Copy, Clone)]
+pub struct RenderSettings {
+    pub image_width: usize,
+    pub image_height: usize,
+    pub samples_per_pixel: usize,
+    /// The number of passes the samples of each pixel are split between, at
+    /// most one for each sample. Integrators that trace photons trace a fresh
+    /// set for each pass, gathered from a smaller radius than the last.
+    pub passes: usize,
+    pub integrator: IntegratorKind,
+    /// The most bounces a path may take, of any kind. The direct lighting and
+    /// Whitted integrators follow this many mirror and glass bounces.
+    pub max_depth: usize,
+    /// The most bounces of each kind a path may take.
+    pub max_diffuse_depth: usize,
+    pub max_specular_depth: usize,
+    pub max_transmission_depth: usize,
+    /// The number of bounces after which paths may be ended by Russian roulette.
+    pub roulette_depth: usize,
+    pub tile_size: usize,
+    pub threads: usize,
+    pub seed: u64,
+    /// Called as the render goes on, to report how far it has got.
+    pub progress: Option<fn(Progress)>,
+}
+
+impl RenderSettings {
+    /// The integrator chosen, with the limits it takes from the settings.
+    /// Panics if a photon radius isn't positive; see ProgressivePhotonMap::new.
+    pub fn integrator(&self) -> Box<dyn Integrator> {
+        match self.integrator {
+            IntegratorKind::Path => Box::new(PathIntegrator::new(
+                self.max_depth,
+                self.max_diffuse_depth,
+                self.max_specular_depth,
+                self.max_transmission_depth,
+                self.roulette_depth,
+            )),
+            IntegratorKind::Direct => Box::new(DirectIntegrator::new(self.max_depth)),
+            IntegratorKind::AmbientOcclusion { distance } => Box::new(AmbientOcclusionIntegrator::new(distance)),
+            IntegratorKind::Whitted => Box::new(WhittedIntegrator::new(self.max_depth)),
+            IntegratorKind::Bidirectional => Box::new(BidirectionalIntegrator::new(self.max_depth, self.roulette_depth)),
+            IntegratorKind::Photon { photons, radius } => Box::new(PhotonIntegrator::new(
+                self.max_depth,
+                ProgressivePhotonMap::new(
+                    photons,
+                    radius,
+                    false,
+                    self.max_depth,
+                    self.roulette_depth,
+                    self.threads,
+                ),
+            )),
+            IntegratorKind::PathWithCaustics { photons, radius } => {
+                let mut integrator = PathIntegrator::new(
+                    self.max_depth,
+                    self.max_diffuse_depth,
+                    self.max_specular_depth,
+                    self.max_transmission_depth,
+                    self.roulette_depth,
+                );
+                integrator.caustics = Some(ProgressivePhotonMap::new(
+                    photons,
+                    radius,
+                    true,
+                    self.max_depth,
+                    self.roulette_depth,
+                    self.threads,
+                ));
+                Box::new(integrator)
+            },
+        }
+    }
+}
+
+/// How far a render has got, as reported to the progress callback.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Progress {
+    /// The tile has been rendered, counting from one, out of the tiles in the image.
+    Tile { tile: usize, tiles: usize },
+    /// The pass, counting from one, out of the passes of the render, is starting.
+    /// Only reported when there is more than one.
+    Pass { pass: usize, passes: usize },
+}
+
+/// A rectangle of pixels, in image coordinates with the origin top left.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Splits the image into tiles of at most tile_size by tile_size, in reading order.
+/// Tiles on the right and bottom edges are cropped to the image.
+pub fn tiles(image_width: usize, image_height: usize, tile_size: usize) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+
+    for y in (0..image_height).step_by(tile_size) {
+        for x in (0..image_width).step_by(tile_size) {
+            tiles.push(Tile {
+                x: x,
+                y: y,
+                width: tile_size.min(image_width - x),
+                height: tile_size.min(image_height - y),
+            });
+        }
+    }
+
+    tiles
+}
+
+/// The rendered image along with auxiliary passes for compositing.
+///
+/// The normal pass holds the surface normal, facing the camera, as x, y and z
+/// in the red, green and blue channels. The depth pass holds the distance from
+/// the camera in every channel. Both are taken from a single ray through the
+/// centre of each pixel.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RenderPasses {
+    pub beauty: Framebuffer,
+    pub normal: Framebuffer,
+    pub depth: Framebuffer,
+}
+
+struct TileSamples {
+    beauty: Vec<Colour>,
+    splats: Vec<Splat>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Renderer {
+    pub settings: RenderSettings,
+}
+
+impl Renderer {
+    pub fn new(settings: RenderSettings) -> Self {
+        Renderer {
+            settings: settings,
+        }
+    }
+
+    /// Renders the image across settings.threads worker threads, which take tiles
+    /// from a shared queue until it is empty. Each pixel is the average of its samples.
+    ///
+    /// Every sample draws from its own sampler seeded by Sampler::for_sample, so the
+    /// output doesn't depend on the number of threads or the tile order.
+    ///
+    /// The light for each sample is found by the integrator chosen in the settings.
+    /// Light it finds reaching other pixels is added over the whole image, with
+    /// the splats of each tile added in turn so the sum doesn't depend on the
+    /// order tiles finish in.
+    ///
+    /// The samples are taken over settings.passes passes, each of which the
+    /// integrator is prepared for in turn before its tiles are handed out.
+    pub fn render(&self, camera: &Camera, world: &dyn Element, lights: &Lights, background: &Colour) -> Framebuffer {
+        self.render_passes(camera, world, lights, background).beauty
+    }
+
+    /// Renders the image as render does, along with the auxiliary passes.
+    pub fn render_passes(&self, camera: &Camera, world: &dyn Element, lights: &Lights, background: &Colour) -> RenderPasses {
+        let width = self.settings.image_width;
+        let height = self.settings.image_height;
+
+        let mut integrator = self.settings.integrator();
+
+        let mut sums = Framebuffer::new(width, height);
+        let mut splatted = Framebuffer::new(width, height);
+
+        let pass_count = self.pass_count();
+        for pass in 0..pass_count {
+            match self.settings.progress {
+                Some(progress) if pass_count > 1 => progress(Progress::Pass { pass: pass + 1, passes: pass_count }),
+                _ => {},
+            }
+
+            integrator.begin_pass(pass, camera, world, lights, self.settings.seed);
+            self.render_pass(
+                self.pass_samples(pass),
+                camera,
+                integrator.as_ref(),
+                world,
+                lights,
+                background,
+                &mut sums,
+                &mut splatted,
+            );
+        }
+
+        // Each pass adds up its samples, so the total is divided once at the end.
+        let mut beauty = Framebuffer::from_sums(width, height, sums.pixels, self.settings.samples_per_pixel);
+
+        // Each sample traces one path from the lights, and a pixel takes up
+        // 1 / ((width - 1) * (height - 1)) of the camera's coordinates.
+        let samples = width * height * self.settings.samples_per_pixel;
+        let scale = (((width - 1) * (height - 1)) as f32) / (samples as f32);
+        for (pixel, splat) in beauty.pixels.iter_mut().zip(splatted.pixels.iter()) {
+            *pixel += *splat * scale;
+        }
+
+        // The surface passes don't change from one pass to the next, so are
+        // found once for the whole render.
+        let mut normal = Framebuffer::new(width, height);
+        let mut depth = Framebuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                // Our coordinates have the origin bottom left.
+                let (pixel_normal, pixel_depth) = self.surface_pixel(x, height - 1 - y, camera, world);
+                normal.pixels[(y * width) + x] = pixel_normal;
+                depth.pixels[(y * width) + x] = pixel_depth;
+            }
+        }
+
+        RenderPasses {
+            beauty: beauty,
+            normal: normal,
+            depth: depth,
+        }
+    }
+
+    /// The number of passes the render takes, from one to one for each sample.
+    fn pass_count(&self) -> usize {
+        self.settings.passes.max(1).min(self.settings.samples_per_pixel)
+    }
+
+    /// The samples of each pixel taken in the pass, which share them out as
+    /// evenly as they can.
+    fn pass_samples(&self, pass: usize) -> Range<usize> {
+        let (samples, pass_count) = (self.settings.samples_per_pixel, self.pass_count());
+
+        ((pass * samples) / pass_count)..(((pass + 1) * samples) / pass_count)
+    }
+
+    /// Takes the samples of every pixel in the range given, across the worker
+    /// threads, adding their sum to beauty and their splats to splatted.
+    #[allow(clippy::too_many_arguments)]
+    fn render_pass(
+        &self,
+        samples: Range<usize>,
+        camera: &Camera,
+        integrator: &dyn Integrator,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+        beauty: &mut Framebuffer,
+        splatted: &mut Framebuffer,
+    ) {
+        let width = self.settings.image_width;
+
+        let tiles = tiles(width, self.settings.image_height, self.settings.tile_size);
+        let next_tile = AtomicUsize::new(0);
+
+        let mut tile_splats: Vec<Option<Vec<Splat>>> = vec![None; tiles.len()];
+        let mut next_splats = 0;
+
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+
+            for _thread in 0..self.settings.threads.max(1) {
+                let sender = sender.clone();
+                let tiles = &tiles;
+                let next_tile = &next_tile;
+                let samples = samples.clone();
+
+                scope.spawn(move || loop {
+                    let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                    if index >= tiles.len() {
+                        break;
+                    }
+
+                    let tile_samples = self.render_tile(
+                        &tiles[index],
+                        samples.clone(),
+                        camera,
+                        integrator,
+                        world,
+                        lights,
+                        background,
+                    );
+                    sender.send((index, tile_samples)).unwrap();
+                });
+            }
+
+            // Only the workers hold senders now, so the loop ends when they are done.
+            drop(sender);
+
+            for (count, (index, tile_samples)) in receiver.iter().enumerate() {
+                let tile = &tiles[index];
+                for row in 0..tile.height {
+                    let start = ((tile.y + row) * width) + tile.x;
+                    let tile_row = (row * tile.width)..((row + 1) * tile.width);
+
+                    let pixels = beauty.pixels[start..(start + tile.width)].iter_mut();
+                    for (pixel, sum) in pixels.zip(tile_samples.beauty[tile_row].iter()) {
+                        *pixel += *sum;
+                    }
+                }
+
+                tile_splats[index] = Some(tile_samples.splats);
+                while next_splats < tiles.len() {
+                    match tile_splats[next_splats].take() {
+                        Some(splats) => splats.iter().for_each(|splat| self.add_splat(splatted, splat)),
+                        None => break,
+                    }
+                    next_splats += 1;
+                }
+
+                if let Some(progress) = self.settings.progress {
+                    progress(Progress::Tile { tile: count + 1, tiles: tiles.len() });
+                }
+            }
+        });
+    }
+
+    /// Adds the splat to the pixel it falls in, if it is in the image.
+    fn add_splat(&self, framebuffer: &mut Framebuffer, splat: &Splat) {
+        let width = self.settings.image_width;
+        let height = self.settings.image_height;
+
+        let i = (splat.s * ((width - 1) as f32)).floor();
+        let j = (splat.t * ((height - 1) as f32)).floor();
+        if i < 0.0 || j < 0.0 || i >= (width as f32) || j >= (height as f32) {
+            return;
+        }
+
+        // Our coordinates have the origin bottom left.
+        let index = ((height - 1 - (j as usize)) * width) + (i as usize);
+        framebuffer.pixels[index] += splat.colour;
+    }
+
+    /// Renders the average of the samples of pixel (i, j), with the origin bottom left.
+    /// This gives exactly the value the pixel has in the full render, except for
+    /// light splatted onto it from other pixels' samples.
+    pub fn render_pixel(
+        &self,
+        i: usize,
+        j: usize,
+        camera: &Camera,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+    ) -> Colour {
+        let mut integrator = self.settings.integrator();
+        let mut pixel_colour = Colour::new(0.0, 0.0, 0.0);
+
+        for pass in 0..self.pass_count() {
+            integrator.begin_pass(pass, camera, world, lights, self.settings.seed);
+            pixel_colour += self.pixel(
+                i,
+                j,
+                self.pass_samples(pass),
+                camera,
+                integrator.as_ref(),
+                world,
+                lights,
+                background,
+                &mut Vec::new(),
+            );
+        }
+
+        pixel_colour * (1.0 / (self.settings.samples_per_pixel as f32))
+    }
+
+    /// The sum of the given samples of pixel (i, j), with the origin bottom left.
+    #[allow(clippy::too_many_arguments)]
+    fn pixel(
+        &self,
+        i: usize,
+        j: usize,
+        samples: Range<usize>,
+        camera: &Camera,
+        integrator: &dyn Integrator,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+        splats: &mut Vec<Splat>,
+    ) -> Colour {
+        let width = self.settings.image_width;
+        let height = self.settings.image_height;
+
+        let mut pixel_colour = Colour::new(0.0, 0.0, 0.0);
+
+        for s in samples {
+            let mut sampler = Sampler::for_sample(self.settings.seed, i, j, s);
+
+            let u_offset: f32 = sampler.gen();
+            let v_offset: f32 = sampler.gen();
+
+            let u = ((i as f32) + u_offset) / ((width - 1) as f32);
+            let v = ((j as f32) + v_offset) / ((height - 1) as f32);
+
+            let ray = camera.get_ray(u, v, &mut sampler);
+
+            pixel_colour += integrator.radiance_and_splats(&ray, camera, world, lights, background, &mut sampler, splats);
+        }
+
+        pixel_colour
+    }
+
+    /// The normal and depth of the first surface seen through the centre of pixel
+    /// (i, j), with the origin bottom left. A miss has a zero normal and infinite depth.
+    /// Media along the way are sampled as if by one sample more than the pixel takes.
+    pub fn surface_pixel(&self, i: usize, j: usize, camera: &Camera, world: &dyn Element) -> (Colour, Colour) {
+        let u = ((i as f32) + 0.5) / ((self.settings.image_width - 1) as f32);
+        let v = ((j as f32) + 0.5) / ((self.settings.image_height - 1) as f32);
+
+        let ray = camera.get_pinhole_ray(u, v);
+        let mut sampler = Sampler::for_sample(self.settings.seed, i, j, self.settings.samples_per_pixel);
+
+        match world.hit(&ray, 0.001, f32::INFINITY, &mut sampler) {
+            Some(hit_record) => {
+                let normal = hit_record.normal;
+                let depth = hit_record.t * ray.direction.length();
+                (Colour::new(normal.x, normal.y, normal.z), Colour::new(depth, depth, depth))
+            },
+            None => (Colour::new(0.0, 0.0, 0.0), Colour::new(f32::INFINITY, f32::INFINITY, f32::INFINITY)),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_tile(
+        &self,
+        tile: &Tile,
+        samples: Range<usize>,
+        camera: &Camera,
+        integrator: &dyn Integrator,
+        world: &dyn Element,
+        lights: &Lights,
+        background: &Colour,
+    ) -> TileSamples {
+        let height = self.settings.image_height;
+
+        let mut tile_samples = TileSamples {
+            beauty: Vec::with_capacity(tile.width * tile.height),
+            splats: Vec::new(),
+        };
+
+        for y in tile.y..(tile.y + tile.height) {
+            // Our coordinates have the origin bottom left.
+            let j = height - 1 - y;
+
+            for i in tile.x..(tile.x + tile.width) {
+                tile_samples.beauty.push(self.pixel(
+                    i,
+                    j,
+                    samples.clone(),
+                    camera,
+                    integrator,
+                    world,
+                    lights,
+                    background,
+                    &mut tile_samples.splats,
+                ));
+            }
+        }
+
+        tile_samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::geometry::point::Point3;
+    use crate::geometry::vector::Vector3;
+    use crate::render::elements::element_list::ElementList;
+    use crate::render::elements::sphere::Sphere;
+    use crate::render::materials::dielectric::Dielectric;
+    use crate::render::materials::diffuse_light::DiffuseLight;
+    use crate::render::materials::lambertian::Lambertian;
+    use crate::render::materials::metal::Metal;
+    use crate::render::textures::solid_colour::SolidColour;
+
+    use super::*;
+
+    fn settings(threads: usize) -> RenderSettings {
+        RenderSettings {
+            image_width: 16,
+            image_height: 12,
+            samples_per_pixel: 4,
+            passes: 1,
+            integrator: IntegratorKind::Path,
+            max_depth: 5,
+            max_diffuse_depth: 5,
+            max_specular_depth: 5,
+            max_transmission_depth: 5,
+            roulette_depth: 3,
+            tile_size: 5,
+            threads: threads,
+            seed: 42,
+            progress: None,
+        }
+    }
+
+    #[test]
+    fn tiles_cover_image() {
+        let tiles = tiles(10, 7, 4);
+
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(
+            tiles[0],
+            Tile { x: 0, y: 0, width: 4, height: 4 },
+        );
+        assert_eq!(
+            tiles[5],
+            Tile { x: 8, y: 4, width: 2, height: 3 },
+        );
+        assert_eq!(
+            tiles.iter().map(|t| t.width * t.height).sum::<usize>(),
+            70,
+        );
+    }
+
+    fn scene() -> (Camera, ElementList) {
+        let mut world = ElementList::new();
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.0, -100.5, -1.0),
+            100.0,
+            Arc::new(Lambertian::new_with_colour(Colour::new(0.6, 0.6, 0.0))),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point3::new(-0.5, 0.0, -1.0),
+            0.5,
+            Arc::new(Dielectric::new(1.5)),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.5, 0.0, -1.0),
+            0.5,
+            Arc::new(Metal::new_with_fuzz(Colour::new(0.8, 0.6, 0.2), 0.3)),
+        )));
+
+        let camera = Camera::new(
+            Point3::new(0.0, 0.0, 1.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            60.0,
+            4.0 / 3.0,
+            0.1,
+            2.0,
+            0.0,
+            1.0,
+        );
+
+        (camera, world)
+    }
+
+    #[test]
+    fn render_independent_of_thread_count() {
+        let (camera, world) = scene();
+        let background = Colour::new(0.7, 0.8, 1.0);
+
+        let single = Renderer::new(settings(1)).render(&camera, &world, &Lights::none(), &background);
+        let multiple = Renderer::new(settings(4)).render(&camera, &world, &Lights::none(), &background);
+
+        assert_eq!(single.pixels.len(), 16 * 12);
+        assert_eq!(single, multiple);
+    }
+
+    #[test]
+    fn bidirectional_render_independent_of_thread_count() {
+        let (camera, mut world) = scene();
+        let light = Sphere::new(
+            Point3::new(0.0, 2.0, -1.0),
+            0.5,
+            Arc::new(DiffuseLight::new(Arc::new(SolidColour::new(Colour::new(4.0, 4.0, 4.0))))),
+        );
+        world.add(Box::new(light.clone()));
+        let lights = Lights::new(vec![Arc::new(light)]);
+        let background = Colour::new(0.0, 0.0, 0.0);
+
+        let mut single_settings = settings(1);
+        single_settings.integrator = IntegratorKind::Bidirectional;
+        let mut multiple_settings = settings(4);
+        multiple_settings.integrator = IntegratorKind::Bidirectional;
+
+        assert_eq!(
+            Renderer::new(single_settings).render(&camera, &world, &lights, &background),
+            Renderer::new(multiple_settings).render(&camera, &world, &lights, &background),
+        );
+    }
+
+    #[test]
+    fn passes_share_out_samples() {
+        let (camera, world) = scene();
+        let background = Colour::new(0.7, 0.8, 1.0);
+
+        let mut passes_settings = settings(2);
+        passes_settings.passes = 3;
+        let renderer = Renderer::new(passes_settings);
+
+        assert_eq!(renderer.pass_count(), 3);
+        assert_eq!(
+            (0..3).map(|pass| renderer.pass_samples(pass)).collect::<Vec<_>>(),
+            vec![0..1, 1..2, 2..4],
+        );
+
+        // The same samples are taken, only added up in another order.
+        let single = Renderer::new(settings(2)).render(&camera, &world, &Lights::none(), &background);
+        let split = renderer.render(&camera, &world, &Lights::none(), &background);
+        for (a, b) in single.pixels.iter().zip(split.pixels.iter()) {
+            assert!((a.r - b.r).abs() < 1e-5 && (a.g - b.g).abs() < 1e-5 && (a.b - b.b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn photon_render_independent_of_thread_count() {
+        let (camera, mut world) = scene();
+        let light = Sphere::new(
+            Point3::new(0.0, 2.0, -1.0),
+            0.5,
+            Arc::new(DiffuseLight::new(Arc::new(SolidColour::new(Colour::new(4.0, 4.0, 4.0))))),
+        );
+        world.add(Box::new(light.clone()));
+        let lights = Lights::new(vec![Arc::new(light)]);
+        let background = Colour::new(0.0, 0.0, 0.0);
+
+        let mut single_settings = settings(1);
+        single_settings.integrator = IntegratorKind::Photon { photons: 1000, radius: 0.1 };
+        single_settings.passes = 2;
+        let mut multiple_settings = single_settings;
+        multiple_settings.threads = 4;
+
+        let single = Renderer::new(single_settings).render(&camera, &world, &lights, &background);
+
+        assert_eq!(single, Renderer::new(multiple_settings).render(&camera, &world, &lights, &background));
+        assert_eq!(
+            Renderer::new(single_settings).render_pixel(3, 4, &camera, &world, &lights, &background),
+            single.get(3, 7),
+        );
+    }
+
+    #[test]
+    fn render_pixel_matches_render() {
+        let (camera, world) = scene();
+        let background = Colour::new(0.7, 0.8, 1.0);
+        let renderer = Renderer::new(settings(2));
+
+        let framebuffer = renderer.render(&camera, &world, &Lights::none(), &background);
+
+        // Pixel (3, 4) from the bottom left is in row 12 - 1 - 4 from the top.
+        assert_eq!(
+            renderer.render_pixel(3, 4, &camera, &world, &Lights::none(), &background),
+            framebuffer.get(3, 7),
+        );
+    }
+
+    #[test]
+    fn render_passes() {
+        let (camera, world) = scene();
+        let background = Colour::new(0.7, 0.8, 1.0);
+        let renderer = Renderer::new(settings(3));
+
+        let passes = renderer.render_passes(&camera, &world, &Lights::none(), &background);
+
+        assert_eq!(passes.beauty, renderer.render(&camera, &world, &Lights::none(), &background));
+
+        // The top row sees only sky.
+        assert_eq!(passes.normal.get(0, 0), Colour::new(0.0, 0.0, 0.0));
+        assert_eq!(passes.depth.get(0, 0).r, f32::INFINITY);
+
+        // The bottom row sees the ground, which faces up.
+        let normal = passes.normal.get(8, 11);
+        assert!((normal.g - 1.0).abs() < 0.01);
+        let depth = passes.depth.get(8, 11).r;
+        assert!(depth > 0.0 && depth.is_finite());
+
+        let mut split_settings = settings(3);
+        split_settings.passes = 2;
+        let split = Renderer::new(split_settings).render_passes(&camera, &world, &Lights::none(), &background);
+        assert_eq!(split.normal, passes.normal);
+        assert_eq!(split.depth, passes.depth);
+    }
+
+    #[test]
+    fn render_depends_on_seed() {
+        let (camera, world) = scene();
+        let background = Colour::new(0.7, 0.8, 1.0);
+
+        let mut other_settings = settings(1);
+        other_settings.seed = 43;
+
+        assert_ne!(
+            Renderer::new(settings(1)).render(&camera, &world, &Lights::none(), &background),
+            Renderer::new(other_settings).render(&camera, &world, &Lights::none(), &background),
+        );
+    }
+
+    static TILES_REPORTED: AtomicUsize = AtomicUsize::new(0);
+    static PASSES_REPORTED: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_progress(progress: Progress) {
+        match progress {
+            Progress::Tile { tile, tiles } => {
+                assert!(tile >= 1 && tile <= tiles && tiles == 12, "{:?}", progress);
+                TILES_REPORTED.fetch_add(1, Ordering::Relaxed);
+            },
+            Progress::Pass { pass, passes } => {
+                assert!(pass >= 1 && pass <= passes && passes == 2, "{:?}", progress);
+                PASSES_REPORTED.fetch_add(1, Ordering::Relaxed);
+            },
+        }
+    }
+
+    #[test]
+    fn reports_progress() {
+        let (camera, world) = scene();
+        let background = Colour::new(0.7, 0.8, 1.0);
+
+        let mut reporting = settings(4);
+        reporting.passes = 2;
+        reporting.progress = Some(count_progress);
+        Renderer::new(reporting).render(&camera, &world, &Lights::none(), &background);
+
+        assert_eq!(PASSES_REPORTED.load(Ordering::Relaxed), 2);
+        assert_eq!(TILES_REPORTED.load(Ordering::Relaxed), 2 * 12);
+    }
+}
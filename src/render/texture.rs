@@ -4,9 +4,17 @@ use std::fmt::Debug;
 use crate::geometry::point::Point3;
 use crate::graphics::colour::Colour;
 
-pub trait Texture : Debug {
+use super::hit_record::HitRecord;
+
+pub trait Texture : Debug + Send + Sync {
     fn value(&self, u: f32, v: f32, p: &Point3) -> Colour;
 
+    /// The value at a hit. Textures needing more of the hit than u, v and the
+    /// point, such as vertex colours, override this.
+    fn value_at(&self, hit_record: &HitRecord) -> Colour {
+        self.value(hit_record.u, hit_record.v, &hit_record.point)
+    }
+
     fn eq(&self, other: &dyn Texture) -> bool;
     fn as_any(&self) -> &dyn Any;
 }
@@ -1,5 +1,5 @@
 use std::any::Any;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::geometry::point::Point3;
 use crate::graphics::colour::Colour;
@@ -8,12 +8,12 @@ use super::super::texture::Texture;
 
 #[derive(Debug)]
 pub struct Checker {
-    odd: Rc<dyn Texture>,
-    even: Rc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+    even: Arc<dyn Texture>,
 }
 
 impl Checker {
-    pub fn new(odd: Rc<dyn Texture>, even: Rc<dyn Texture>) -> Self {
+    pub fn new(odd: Arc<dyn Texture>, even: Arc<dyn Texture>) -> Self {
         Checker {
             odd: odd,
             even: even,
@@ -55,12 +55,12 @@ mod tests {
     fn new_checker() {
         assert_eq!(
             Checker::new(
-                Rc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
-                Rc::new(SolidColour::new(Colour::new(0.4, 0.5, 0.6))),
+                Arc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
+                Arc::new(SolidColour::new(Colour::new(0.4, 0.5, 0.6))),
             ),
             Checker {
-                odd: Rc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
-                even: Rc::new(SolidColour::new(Colour::new(0.4, 0.5, 0.6))),
+                odd: Arc::new(SolidColour::new(Colour::new(0.1, 0.2, 0.3))),
+                even: Arc::new(SolidColour::new(Colour::new(0.4, 0.5, 0.6))),
             }
         );
     }
@@ -33,12 +33,12 @@ impl ImageTexture {
 impl Texture for ImageTexture {
     fn value(&self, u: f32, v: f32, _p: &Point3) -> Colour {
         let (width, height) = self.image.dimensions();
-        let i = ((clamp(u, 0.0, 1.0) * width as f32) as u32).min(width);
+        let i = ((clamp(u, 0.0, 1.0) * width as f32) as u32).min(width - 1);
         let j = ((clamp(v, 0.0, 1.0) * height as f32) as u32).min(height);
 
         // Origin is bottom left in our coordinates, but the image coordinates
         // have it top left. So need to flip vertically.
-        let pixel = self.image.get_pixel(i, height - j);
+        let pixel = self.image.get_pixel(i, (height - j).min(height - 1));
 
         let scale = 255.0_f32.recip();
 
@@ -86,4 +86,17 @@ mod tests {
             Colour::new(1.0, 0.0, 0.0),
         );
     }
+
+    #[test]
+    fn image_texture_edges() {
+        let mut image = RgbImage::new(10, 10);
+        image.put_pixel(9, 9, Rgb([0, 255, 0]));
+        image.put_pixel(0, 0, Rgb([0, 0, 255]));
+
+        let image_texture = ImageTexture::new(image);
+        let p = Point3::new(0.0, 0.0, 0.0);
+
+        assert_eq!(image_texture.value(1.0, 0.0, &p), Colour::new(0.0, 1.0, 0.0));
+        assert_eq!(image_texture.value(0.0, 1.0, &p), Colour::new(0.0, 0.0, 1.0));
+    }
 }
@@ -38,16 +38,18 @@ impl Texture for Marble {
 
 #[cfg(test)]
 mod tests {
+    use crate::util::sampler::Sampler;
+
     use super::*;
 
     #[test]
     fn new_marble() {
-        let _marble = Marble::new(Perlin::new(), 1.0);
+        let _marble = Marble::new(Perlin::new(&mut Sampler::new(0)), 1.0);
     }
 
     #[test]
     fn marble_value() {
-        let marble = Marble::new(Perlin::new(), 1.0);
+        let marble = Marble::new(Perlin::new(&mut Sampler::new(0)), 1.0);
         let c = marble.value(0.5, 0.5, &Point3::new(1.0, 2.0, 3.0));
 
         assert!((0.0 <= c.r) && (c.b <= 1.0));
@@ -3,3 +3,4 @@ pub mod image_texture;
 pub mod marble;
 pub mod noise;
 pub mod solid_colour;
+pub mod vertex_colour;
@@ -37,16 +37,18 @@ impl Texture for Noise {
 
 #[cfg(test)]
 mod tests {
+    use crate::util::sampler::Sampler;
+
     use super::*;
 
     #[test]
     fn new_noise() {
-        let _noise = Noise::new(Perlin::new(), 1.0);
+        let _noise = Noise::new(Perlin::new(&mut Sampler::new(0)), 1.0);
     }
 
     #[test]
     fn noise_value() {
-        let noise = Noise::new(Perlin::new(), 1.0);
+        let noise = Noise::new(Perlin::new(&mut Sampler::new(0)), 1.0);
         let c = noise.value(0.5, 0.5, &Point3::new(1.0, 2.0, 3.0));
 
         assert!((0.0 <= c.r) && (c.b <= 1.0));
@@ -0,0 +1,81 @@
+use std::any::Any;
+
+use crate::geometry::point::Point3;
+use crate::graphics::colour::Colour;
+
+use super::super::hit_record::HitRecord;
+use super::super::texture::Texture;
+
+/// The colour interpolated from the vertices of a mesh, for meshes that have
+/// vertex colours. Anything else gets the fallback colour.
+#[derive(Debug, PartialEq)]
+pub struct VertexColour {
+    pub fallback: Colour,
+}
+
+impl VertexColour {
+    pub fn new(fallback: Colour) -> Self {
+        VertexColour {
+            fallback: fallback,
+        }
+    }
+}
+
+impl Texture for VertexColour {
+    fn value(&self, _u: f32, _v: f32, _p: &Point3) -> Colour {
+        self.fallback
+    }
+
+    fn value_at(&self, hit_record: &HitRecord) -> Colour {
+        hit_record.vertex_colour.unwrap_or(self.fallback)
+    }
+
+    fn eq(&self, other: &dyn Texture) -> bool {
+        other.as_any().downcast_ref::<Self>().map_or(false, |x| x == self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::geometry::normal::Normal3;
+    use crate::render::materials::lambertian::Lambertian;
+
+    use super::*;
+
+    fn hit_record() -> HitRecord {
+        HitRecord::new(
+            Point3::new(1.0, 2.0, 3.0),
+            Normal3::new(0.0, 1.0, 0.0),
+            Arc::new(Lambertian::new(Arc::new(VertexColour::new(Colour::new(0.5, 0.5, 0.5))))),
+            1.0,
+            0.0,
+            0.0,
+            true,
+        )
+    }
+
+    #[test]
+    fn vertex_colour_value_at() {
+        let mut hit_record = hit_record();
+        hit_record.vertex_colour = Some(Colour::new(0.1, 0.2, 0.3));
+
+        assert_eq!(
+            VertexColour::new(Colour::new(0.5, 0.5, 0.5)).value_at(&hit_record),
+            Colour::new(0.1, 0.2, 0.3),
+        );
+    }
+
+    #[test]
+    fn vertex_colour_fallback() {
+        assert_eq!(
+            VertexColour::new(Colour::new(0.5, 0.5, 0.5)).value_at(&hit_record()),
+            Colour::new(0.5, 0.5, 0.5),
+        );
+    }
+}
@@ -1,14 +1,23 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::geometry::point::Point3;
+use crate::geometry::transform::Transform;
+use crate::geometry::vector::Vector3;
 use crate::graphics::colour::Colour;
+use crate::render::camera::CameraSettings;
 use crate::render::element::Element;
-use crate::render::elements::bvh_node::BvhNode;
+use crate::render::elements::axis_aligned_box::AxisAlignedBox;
+use crate::render::elements::bvh::Bvh;
+use crate::render::elements::constant_medium::ConstantMedium;
 use crate::render::elements::element_list::ElementList;
+use crate::render::elements::instance::Instance;
 use crate::render::elements::moving_sphere::MovingSphere;
+use crate::render::elements::rect::{XyRect, XzRect, YzRect};
 use crate::render::elements::sphere::Sphere;
+use crate::render::lights::Lights;
 use crate::render::materials::dielectric::Dielectric;
 use crate::render::materials::diffuse_light::DiffuseLight;
+use crate::render::materials::isotropic::Isotropic;
 use crate::render::materials::lambertian::Lambertian;
 use crate::render::materials::metal::Metal;
 use crate::render::textures::checker::Checker;
@@ -17,30 +26,94 @@ use crate::render::textures::marble::Marble;
 use crate::render::textures::noise::Noise;
 use crate::render::textures::solid_colour::SolidColour;
 use crate::util::perlin::Perlin;
+use crate::util::sampler::Sampler;
 
-pub fn create_basic_spheres() -> Box<dyn Element> {
-    let material_ground = Rc::new(Lambertian::new_with_colour(Colour::new(0.6, 0.6, 0.0)));
-    let material_checker = Rc::new(
+/// A world to render, along with where to view it from and the colour seen by
+/// rays that escape it. The lights are those elements of the world that give
+/// off light and can be sampled directly.
+#[derive(Debug)]
+pub struct Scene {
+    pub world: Box<dyn Element>,
+    pub lights: Lights,
+    pub camera: CameraSettings,
+    pub background: Colour,
+}
+
+/// A scene compiled into the renderer, which can be chosen by name.
+#[derive(Debug, Copy, Clone)]
+pub struct BuiltinScene {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub create: fn(&mut Sampler) -> Scene,
+}
+
+pub const BUILTIN_SCENES: [BuiltinScene; 7] = [
+    BuiltinScene {
+        name: "basic-spheres",
+        description: "Glass, metal and checked spheres on a plain ground",
+        create: create_basic_spheres,
+    },
+    BuiltinScene {
+        name: "lit-spheres",
+        description: "The basic spheres lit by a small light, which the glass focuses on the ground",
+        create: create_lit_spheres,
+    },
+    BuiltinScene {
+        name: "noise-spheres",
+        description: "A Perlin noise sphere on a marble ground",
+        create: create_noise_spheres,
+    },
+    BuiltinScene {
+        name: "globe",
+        description: "An image textured globe under a sky",
+        create: create_globe,
+    },
+    BuiltinScene {
+        name: "lit-globe",
+        description: "The globe lit by a single small light",
+        create: create_lit_globe,
+    },
+    BuiltinScene {
+        name: "cornell-box",
+        description: "Two boxes in a red and green walled room lit from above",
+        create: create_cornell_box,
+    },
+    BuiltinScene {
+        name: "cornell-smoke",
+        description: "The Cornell box with boxes of dark and pale smoke",
+        create: create_cornell_smoke,
+    },
+];
+
+pub fn builtin_scene(name: &str) -> Option<&'static BuiltinScene> {
+    BUILTIN_SCENES.iter().find(|scene| scene.name == name)
+}
+
+/// The glass, metal and checked spheres on the ground of the basic scene.
+#[allow(clippy::vec_init_then_push)]
+fn basic_spheres() -> Vec<Arc<dyn Element>> {
+    let material_ground = Arc::new(Lambertian::new_with_colour(Colour::new(0.6, 0.6, 0.0)));
+    let material_checker = Arc::new(
         Lambertian::new(
-            Rc::new(Checker::new(
-                Rc::new(SolidColour::new(Colour::new(0.1, 0.1, 0.1))),
-                Rc::new(SolidColour::new(Colour::new(0.7, 0.7, 0.7))),
+            Arc::new(Checker::new(
+                Arc::new(SolidColour::new(Colour::new(0.1, 0.1, 0.1))),
+                Arc::new(SolidColour::new(Colour::new(0.7, 0.7, 0.7))),
             )),
         ),
     );
-    let material_glass = Rc::new(Dielectric::new(1.5));
-    let material_metal = Rc::new(Metal::new(Colour::new(0.8, 0.6, 0.2)));
+    let material_glass = Arc::new(Dielectric::new(1.5));
+    let material_metal = Arc::new(Metal::new(Colour::new(0.8, 0.6, 0.2)));
     
-    let mut elements: Vec<Rc<dyn Element>> = Vec::new();
+    let mut elements: Vec<Arc<dyn Element>> = Vec::new();
 
-    elements.push(Rc::new(
+    elements.push(Arc::new(
         Sphere::new(
             Point3::new(0.0, -1000.5, -1.0),
             1000.0,
             material_ground.clone(),
         )
     ));
-    elements.push(Rc::new(
+    elements.push(Arc::new(
         MovingSphere::new(
             Point3::new(0.0, 0.0, -1.5),
             Point3::new(0.0, 0.0, -1.5),
@@ -50,48 +123,94 @@ pub fn create_basic_spheres() -> Box<dyn Element> {
             material_checker.clone(),
         )
     ));
-    elements.push(Rc::new(
+    elements.push(Arc::new(
         Sphere::new(
             Point3::new(-1.0, 0.0, -1.0),
             0.5,
             material_glass.clone(),
         )
     ));
-    elements.push(Rc::new(
+    elements.push(Arc::new(
         Sphere::new(
             Point3::new(1.0, 0.0, -1.0),
             0.5,
             material_metal.clone(),
         )
-    )); 
+    ));
+
+    elements
+}
+
+pub fn create_basic_spheres(_sampler: &mut Sampler) -> Scene {
+    let elements = basic_spheres();
+
+    let world = Bvh::new(elements, 0.0, 1.0);
+
+    Scene {
+        world: Box::new(world),
+        lights: Lights::none(),
+        camera: CameraSettings::new(
+            Point3::new(-2.5, 1.5, 1.0),
+            Point3::new(0.0, 0.0, -1.0),
+            45.0,
+        ),
+        background: Colour::new(0.7, 0.8, 1.0),
+    }
+}
+
+/// The basic spheres in the dark, lit by a small light up and behind the glass
+/// sphere, which focuses it on the ground in front.
+pub fn create_lit_spheres(_sampler: &mut Sampler) -> Scene {
+    let material_light = Arc::new(DiffuseLight::new(
+        Arc::new(SolidColour::new(Colour::new(80.0, 80.0, 80.0)))
+    ));
+
+    let light = Sphere::new(
+        Point3::new(-3.0, 2.5, -3.0),
+        0.25,
+        material_light,
+    );
+
+    let mut elements = basic_spheres();
+    elements.push(Arc::new(light.clone()));
 
-    let world = BvhNode::from_elements(elements, 0.0, 1.0);
+    let world = Bvh::new(elements, 0.0, 1.0);
 
-    Box::new(world)
+    Scene {
+        world: Box::new(world),
+        lights: Lights::new(vec![Arc::new(light)]),
+        camera: CameraSettings::new(
+            Point3::new(-2.5, 1.5, 1.0),
+            Point3::new(0.0, 0.0, -1.0),
+            45.0,
+        ),
+        background: Colour::new(0.0, 0.0, 0.0),
+    }
 }
 
-pub fn create_noise_spheres() -> Box<dyn Element> {
-    let material_noise = Rc::new(
+#[allow(clippy::vec_init_then_push)]
+pub fn create_noise_spheres(sampler: &mut Sampler) -> Scene {
+    let material_noise = Arc::new(
         Lambertian::new(
-            Rc::new(Noise::new(Perlin::new(), 4.0))
+            Arc::new(Noise::new(Perlin::new(sampler), 4.0))
         )
     );
-    let material_marble = Rc::new(
+    let material_marble = Arc::new(
         Lambertian::new(
-            Rc::new(Marble::new(Perlin::new(), 4.0))
+            Arc::new(Marble::new(Perlin::new(sampler), 4.0))
         )
     );
 
-    let mut elements: Vec<Rc<dyn Element>> = Vec::new();
+    let mut elements: Vec<Arc<dyn Element>> = Vec::new();
 
-    elements.push(Rc::new(
+    elements.push(Arc::new(
         Sphere::new(
             Point3::new(0.0, -1000.0, 0.0),
             1000.0,
             material_marble.clone(),
         )
     ));
-    elements.push(Rc::new(
+    elements.push(Arc::new(
         Sphere::new(
             Point3::new(0.0, 2.0, 0.0),
             2.0,
@@ -99,14 +218,23 @@ pub fn create_noise_spheres() -> Box<dyn Element> {
         )
     ));
 
-    let world = BvhNode::from_elements(elements, 0.0, 1.0);
+    let world = Bvh::new(elements, 0.0, 1.0);
 
-    Box::new(world)
+    Scene {
+        world: Box::new(world),
+        lights: Lights::none(),
+        camera: CameraSettings::new(
+            Point3::new(13.0, 2.0, 3.0),
+            Point3::new(0.0, 0.0, 0.0),
+            20.0,
+        ),
+        background: Colour::new(0.7, 0.8, 1.0),
+    }
 }
 
-pub fn create_globe() -> Box<dyn Element> {
-    let material_earth = Rc::new(Lambertian::new(
-        Rc::new(ImageTexture::new_from_filename("earth.jpg"))
+pub fn create_globe(_sampler: &mut Sampler) -> Scene {
+    let material_earth = Arc::new(Lambertian::new(
+        Arc::new(ImageTexture::new_from_filename("earth.jpg"))
     ));
 
     let mut world = ElementList::new();
@@ -119,18 +247,27 @@ pub fn create_globe() -> Box<dyn Element> {
         )),
     );
 
-    Box::new(world)
+    Scene {
+        world: Box::new(world),
+        lights: Lights::none(),
+        camera: CameraSettings::new(
+            Point3::new(13.0, 2.0, 3.0),
+            Point3::new(0.0, 0.0, 0.0),
+            20.0,
+        ),
+        background: Colour::new(0.7, 0.8, 1.0),
+    }
 }
 
-pub fn create_lit_globe() -> Box<dyn Element> {
-    let material_earth = Rc::new(Lambertian::new(
-        Rc::new(ImageTexture::new_from_filename("earth.jpg"))
+pub fn create_lit_globe(_sampler: &mut Sampler) -> Scene {
+    let material_earth = Arc::new(Lambertian::new(
+        Arc::new(ImageTexture::new_from_filename("earth.jpg"))
     ));
-    let material_ground = Rc::new(Lambertian::new(
-        Rc::new(SolidColour::new(Colour::new(1.0, 0.0, 0.0)))
+    let material_ground = Arc::new(Lambertian::new(
+        Arc::new(SolidColour::new(Colour::new(1.0, 0.0, 0.0)))
     ));
-    let material_light = Rc::new(DiffuseLight::new(
-        Rc::new(SolidColour::new(Colour::new(4.0, 4.0, 4.0)))
+    let material_light = Arc::new(DiffuseLight::new(
+        Arc::new(SolidColour::new(Colour::new(4.0, 4.0, 4.0)))
     ));
 
     let mut world = ElementList::new();
@@ -150,13 +287,159 @@ pub fn create_lit_globe() -> Box<dyn Element> {
         ))
     );
 
-    world.add(
-        Box::new(Sphere::new(
-            Point3::new(5.0, 5.0, 5.0),
-            1.0,
-            material_light,
-        ))
+    let light = Sphere::new(
+        Point3::new(5.0, 5.0, 5.0),
+        1.0,
+        material_light,
     );
+    world.add(Box::new(light.clone()));
+
+    Scene {
+        world: Box::new(world),
+        lights: Lights::new(vec![Arc::new(light)]),
+        camera: CameraSettings::new(
+            Point3::new(26.0, 3.0, 6.0),
+            Point3::new(0.0, 2.0, 0.0),
+            20.0,
+        ),
+        background: Colour::new(0.0, 0.0, 0.0),
+    }
+}
+
+/// The Cornell box, a 555 unit cube open at the front, with the light in a
+/// hole in the ceiling.
+pub fn create_cornell_box(_sampler: &mut Sampler) -> Scene {
+    let white = Arc::new(Lambertian::new_with_colour(Colour::new(0.73, 0.73, 0.73)));
+    let light = XzRect::new(
+        213.0,
+        343.0,
+        227.0,
+        332.0,
+        554.0,
+        Arc::new(DiffuseLight::new(Arc::new(SolidColour::new(Colour::new(15.0, 15.0, 15.0))))),
+    );
+
+    let mut world = cornell_room(&light);
+    let [tall_box, short_box] = cornell_boxes(white);
+    world.add(Box::new(tall_box));
+    world.add(Box::new(short_box));
+
+    cornell_scene(world, light)
+}
+
+/// The Cornell box with its boxes made of smoke, one dark and one pale, lit by
+/// a larger, dimmer light.
+pub fn create_cornell_smoke(_sampler: &mut Sampler) -> Scene {
+    let white = Arc::new(Lambertian::new_with_colour(Colour::new(0.73, 0.73, 0.73)));
+    let light = XzRect::new(
+        113.0,
+        443.0,
+        127.0,
+        432.0,
+        554.0,
+        Arc::new(DiffuseLight::new(Arc::new(SolidColour::new(Colour::new(7.0, 7.0, 7.0))))),
+    );
+
+    let mut world = cornell_room(&light);
+    let [tall_box, short_box] = cornell_boxes(white);
+    world.add(Box::new(ConstantMedium::new(
+        Arc::new(tall_box),
+        0.01,
+        Arc::new(Isotropic::new_with_colour(Colour::new(0.0, 0.0, 0.0))),
+    )));
+    world.add(Box::new(ConstantMedium::new(
+        Arc::new(short_box),
+        0.01,
+        Arc::new(Isotropic::new_with_colour(Colour::new(1.0, 1.0, 1.0))),
+    )));
+
+    cornell_scene(world, light)
+}
+
+/// The red and green walled room of the Cornell box, with the light.
+fn cornell_room(light: &XzRect) -> ElementList {
+    let red = Arc::new(Lambertian::new_with_colour(Colour::new(0.65, 0.05, 0.05)));
+    let white = Arc::new(Lambertian::new_with_colour(Colour::new(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::new_with_colour(Colour::new(0.12, 0.45, 0.15)));
+
+    let mut world = ElementList::new();
+
+    world.add(Box::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)));
+    world.add(Box::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)));
+    world.add(Box::new(light.clone()));
+    world.add(Box::new(XzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, white.clone())));
+    world.add(Box::new(XzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white.clone())));
+    world.add(Box::new(XyRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white)));
+
+    world
+}
+
+/// The tall and short boxes of the Cornell box, made of the material.
+fn cornell_boxes(material: Arc<Lambertian>) -> [Instance; 2] {
+    // The boxes are built at the origin and turned into place.
+    let tall_box = Arc::new(AxisAlignedBox::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 330.0, 165.0),
+        material.clone(),
+    ));
+    let short_box = Arc::new(AxisAlignedBox::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 165.0, 165.0),
+        material,
+    ));
+
+    [
+        Instance::new(
+            tall_box,
+            Transform::translate(Vector3::new(265.0, 0.0, 295.0)) * Transform::rotate(Vector3::new(0.0, 1.0, 0.0), 15.0),
+        ),
+        Instance::new(
+            short_box,
+            Transform::translate(Vector3::new(130.0, 0.0, 65.0)) * Transform::rotate(Vector3::new(0.0, 1.0, 0.0), -18.0),
+        ),
+    ]
+}
+
+/// The Cornell box scene with the world built, viewed from in front of the room.
+fn cornell_scene(world: ElementList, light: XzRect) -> Scene {
+    let mut camera = CameraSettings::new(
+        Point3::new(278.0, 278.0, -800.0),
+        Point3::new(278.0, 278.0, 0.0),
+        40.0,
+    );
+    camera.aperture = 0.0;
+
+    Scene {
+        world: Box::new(world),
+        lights: Lights::new(vec![Arc::new(light)]),
+        camera: camera,
+        background: Colour::new(0.0, 0.0, 0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_builtin_scene() {
+        assert_eq!(
+            builtin_scene("globe").map(|scene| scene.name),
+            Some("globe"),
+        );
+    }
+
+    #[test]
+    fn unknown_builtin_scene() {
+        assert!(builtin_scene("teapot").is_none());
+    }
 
-    Box::new(world)
+    #[test]
+    fn builtin_scene_names_unique() {
+        for (i, a) in BUILTIN_SCENES.iter().enumerate() {
+            for b in BUILTIN_SCENES[(i + 1)..].iter() {
+                assert_ne!(a.name, b.name);
+            }
+        }
+    }
 }
@@ -3,3 +3,4 @@ pub mod testing;
 
 pub mod perlin;
 pub mod random;
+pub mod sampler;
@@ -1,10 +1,11 @@
-use rand::{Rng, thread_rng};
+use rand::Rng;
 
 use arrayvec::ArrayVec;
 
 use crate::geometry::point::Point3;
 use crate::geometry::vector::Vector3;
 use crate::util::random::random_in_unit_cube;
+use crate::util::sampler::Sampler;
 
 const POINT_COUNT: usize = 256;
 
@@ -17,17 +18,17 @@ pub struct Perlin {
 }
 
 impl Perlin {
-    pub fn new() -> Self {
+    pub fn new(sampler: &mut Sampler) -> Self {
         let mut random_vectors = ArrayVec::<Vector3, POINT_COUNT>::new();
         for _i in 0..POINT_COUNT {
-            random_vectors.push(random_in_unit_cube());
+            random_vectors.push(random_in_unit_cube(sampler));
         }
 
         Perlin {
             random_vectors: random_vectors,
-            permutation_x: Self::make_permutation(),
-            permutation_y: Self::make_permutation(),
-            permutation_z: Self::make_permutation(),
+            permutation_x: Self::make_permutation(sampler),
+            permutation_y: Self::make_permutation(sampler),
+            permutation_z: Self::make_permutation(sampler),
         }
     }
 
@@ -66,23 +67,19 @@ impl Perlin {
             weighted_p = weighted_p * 2.0;
         }
 
-        return acc.abs()
+        acc.abs()
     }
 
-    fn make_permutation() -> [usize; POINT_COUNT] {
-        let mut rng = thread_rng();
-        
+    fn make_permutation(sampler: &mut Sampler) -> [usize; POINT_COUNT] {
         let mut p = [0; POINT_COUNT];
 
-        for i in 0..POINT_COUNT {
-            p[i] = i;
+        for (i, x) in p.iter_mut().enumerate() {
+            *x = i;
         }
 
         for i in (1..POINT_COUNT).rev() {
-            let target = rng.gen_range(0..i);
-            let tmp = p[i];
-            p[i] = p[target];
-            p[target] = tmp;
+            let target = sampler.gen_range(0..i);
+            p.swap(i, target);
         }
 
         p
@@ -131,22 +128,31 @@ fn perlin_interpolate(c: ArrayVec<ArrayVec<ArrayVec<Vector3, 2>, 2>, 2>, u: f32,
 }
 
 #[cfg(test)]
+#[allow(clippy::manual_range_contains)]
 mod tests {
     use super::*;
 
     #[test]
     fn new_perlin() {
-        let _perlin = Perlin::new();
+        let _perlin = Perlin::new(&mut Sampler::new(0));
     }
 
     #[test]
     fn perlin_noise() {
-        let perlin = Perlin::new();
+        let perlin = Perlin::new(&mut Sampler::new(0));
         let x = perlin.noise(&Point3::new(1.0, 2.0, 3.0));
 
         assert!((0.0 <= x) && (x <= 1.0));
     }
 
+    #[test]
+    fn perlin_reproducible() {
+        assert_eq!(
+            Perlin::new(&mut Sampler::new(3)),
+            Perlin::new(&mut Sampler::new(3)),
+        );
+    }
+
     // #[test]
     // fn trilinear_interpolate_top_left() {
     //     let c = [
@@ -1,23 +1,45 @@
-use rand::{Rng, thread_rng};
+use std::f32::consts::PI;
+
+use rand::Rng;
 
 use crate::geometry::vector::Vector3;
 
-pub fn random_in_unit_sphere() -> Vector3 {
+use super::sampler::Sampler;
+
+pub fn random_in_unit_sphere(sampler: &mut Sampler) -> Vector3 {
     loop {
-        let p = random_in_unit_cube();
+        let p = random_in_unit_cube(sampler);
         if p.length_squared() < 1.0 {
             return p;
         }
     }
 }
 
-pub fn random_in_unit_disk() -> Vector3 {
-    let mut rng = thread_rng();
+/// A point on the unit sphere, uniformly distributed over its surface.
+pub fn random_unit_vector(sampler: &mut Sampler) -> Vector3 {
+    let z: f32 = sampler.gen_range(-1.0..1.0);
+    let phi = 2.0 * PI * sampler.gen::<f32>();
+    let r = (1.0 - (z * z)).max(0.0).sqrt();
+
+    Vector3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// A direction on the hemisphere about the z axis, with density proportional to
+/// the cosine of its angle to the axis: cos(theta) / pi per unit solid angle.
+pub fn random_cosine_direction(sampler: &mut Sampler) -> Vector3 {
+    let r1: f32 = sampler.gen();
+    let r2: f32 = sampler.gen();
+    let phi = 2.0 * PI * r1;
+    let r = r2.sqrt();
+
+    Vector3::new(r * phi.cos(), r * phi.sin(), (1.0 - r2).sqrt())
+}
 
+pub fn random_in_unit_disk(sampler: &mut Sampler) -> Vector3 {
     loop {
         let p = Vector3::new(
-            rng.gen_range(-1.0..1.0),
-            rng.gen_range(-1.0..1.0),
+            sampler.gen_range(-1.0..1.0),
+            sampler.gen_range(-1.0..1.0),
             0.0,
         );
         if p.length_squared() < 1.0 {
@@ -26,13 +48,11 @@ pub fn random_in_unit_disk() -> Vector3 {
     }
 }
 
-pub fn random_in_unit_cube() -> Vector3 {
-    let mut rng = thread_rng();
-
+pub fn random_in_unit_cube(sampler: &mut Sampler) -> Vector3 {
     Vector3::new(
-        rng.gen_range(-1.0..1.0),
-        rng.gen_range(-1.0..1.0),
-        rng.gen_range(-1.0..1.0),
+        sampler.gen_range(-1.0..1.0),
+        sampler.gen_range(-1.0..1.0),
+        sampler.gen_range(-1.0..1.0),
     )
 }
 
@@ -42,14 +62,43 @@ mod tests {
 
     #[test]
     fn test_random_in_unit_sphere() {
-        let p = random_in_unit_sphere();
+        let p = random_in_unit_sphere(&mut Sampler::new(1));
 
         assert!(p.length_squared() <= 1.0);
     }
 
+    #[test]
+    fn test_random_unit_vector() {
+        let mut sampler = Sampler::new(1);
+        let mut mean = Vector3::zero();
+        for _ in 0..10_000 {
+            let v = random_unit_vector(&mut sampler);
+            assert!((v.length() - 1.0).abs() < 1e-5);
+            mean += v / 10_000.0;
+        }
+
+        // Spread evenly, so no direction is favoured.
+        assert!(mean.length() < 0.05, "{:?}", mean);
+    }
+
+    #[test]
+    fn test_random_cosine_direction() {
+        let mut sampler = Sampler::new(1);
+        let mut mean_z = 0.0;
+        for _ in 0..10_000 {
+            let v = random_cosine_direction(&mut sampler);
+            assert!((v.length() - 1.0).abs() < 1e-5);
+            assert!(v.z >= 0.0);
+            mean_z += v.z / 10_000.0;
+        }
+
+        // The mean cosine under a cosine density is 2/3.
+        assert!((mean_z - 2.0 / 3.0).abs() < 0.01, "{}", mean_z);
+    }
+
     #[test]
     fn test_random_in_unit_disk() {
-        let p = random_in_unit_disk();
+        let p = random_in_unit_disk(&mut Sampler::new(1));
 
         assert!(p.length_squared() <= 1.0);
         assert_eq!(p.z, 0.0);
@@ -57,10 +106,18 @@ mod tests {
 
     #[test]
     fn test_random_in_unit_cube() {
-        let p = random_in_unit_cube();
+        let p = random_in_unit_cube(&mut Sampler::new(1));
 
         assert!((-1.0 <= p.x) && (p.x <= 1.0));
         assert!((-1.0 <= p.y) && (p.y <= 1.0));
         assert!((-1.0 <= p.z) && (p.z <= 1.0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn random_in_unit_sphere_reproducible() {
+        assert_eq!(
+            random_in_unit_sphere(&mut Sampler::new(5)),
+            random_in_unit_sphere(&mut Sampler::new(5)),
+        );
+    }
+}
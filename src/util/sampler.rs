@@ -0,0 +1,108 @@
+use rand::{Error, RngCore, SeedableRng};
+use rand::rngs::StdRng;
+
+/// The source of every random number drawn while building or rendering a scene.
+/// Samplers are always created from an explicit seed, so a render can be repeated
+/// exactly, and sample_seed lets any single sample be reproduced on its own.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    rng: StdRng,
+}
+
+impl Sampler {
+    pub fn new(seed: u64) -> Self {
+        Sampler {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// The sampler for sample s of pixel (i, j) in a render with the given seed.
+    pub fn for_sample(seed: u64, i: usize, j: usize, s: usize) -> Self {
+        Self::new(sample_seed(seed, i, j, s))
+    }
+
+    /// The sampler for a batch of the light traced from the lights before pass of
+    /// a render with the given seed. No pixel has the coordinates used, so it is
+    /// never that of a sample.
+    pub fn for_photons(seed: u64, pass: usize, batch: usize) -> Self {
+        Self::new(sample_seed(seed, usize::MAX, pass, batch))
+    }
+}
+
+impl RngCore for Sampler {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+/// Derives the seed for a single sample from the seed of the whole render, so
+/// a sample doesn't depend on which thread or tile rendered it, or on how many
+/// random numbers the samples before it used.
+pub fn sample_seed(seed: u64, i: usize, j: usize, s: usize) -> u64 {
+    let mut x = splitmix64(seed);
+    x = splitmix64(x ^ (i as u64));
+    x = splitmix64(x ^ (j as u64));
+
+    splitmix64(x ^ (s as u64))
+}
+
+/// The SplitMix64 finaliser, used to scatter nearby seeds across the whole range.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut a = Sampler::new(3);
+        let mut b = Sampler::new(3);
+
+        for _i in 0..10 {
+            assert_eq!(a.gen::<f32>(), b.gen::<f32>());
+        }
+    }
+
+    #[test]
+    fn different_seed_different_numbers() {
+        let mut a = Sampler::new(3);
+        let mut b = Sampler::new(4);
+
+        assert_ne!(a.gen::<u64>(), b.gen::<u64>());
+    }
+
+    #[test]
+    fn sample_seed_is_stable() {
+        assert_eq!(
+            sample_seed(7, 3, 4, 5),
+            sample_seed(7, 3, 4, 5),
+        );
+    }
+
+    #[test]
+    fn sample_seed_differs_between_samples() {
+        assert_ne!(sample_seed(7, 3, 4, 5), sample_seed(7, 4, 3, 5));
+        assert_ne!(sample_seed(7, 3, 4, 5), sample_seed(7, 3, 4, 6));
+        assert_ne!(sample_seed(7, 3, 4, 5), sample_seed(8, 3, 4, 5));
+    }
+}
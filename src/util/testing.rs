@@ -4,3 +4,294 @@ macro_rules! assert_eq_delta {
         if !($x - $y < $d || $y - $x < $d) { panic!(); }
     }
 }
+
+/// Checks of the densities elements and materials sample with.
+#[cfg(test)]
+pub mod assertions {
+    use std::f32::consts::PI;
+
+    use crate::geometry::point::Point3;
+    use crate::geometry::ray::Ray;
+    use crate::geometry::vector::Vector3;
+    use crate::render::element::Element;
+    use crate::util::random::random_unit_vector;
+    use crate::util::sampler::Sampler;
+
+    /// Checks that an element's sample_direction and pdf_value agree when seen
+    /// from origin; see assert_pdf_normalised.
+    pub fn assert_direction_pdf_normalised(element: &dyn Element, origin: &Point3) {
+        assert_pdf_normalised(
+            |sampler| element.sample_direction(origin, 0.0, sampler),
+            |direction| element.pdf_value(origin, direction, 0.0),
+        );
+    }
+
+    /// Checks that a way of sampling directions and its density agree: every
+    /// sampled direction has a density, and the density integrates to one over
+    /// all directions. The integral is estimated from uniformly random
+    /// directions, so the directions sampled should cover a fair part of the
+    /// sphere.
+    pub fn assert_pdf_normalised<S, P>(mut sample: S, pdf: P)
+    where
+        S: FnMut(&mut Sampler) -> Option<Vector3>,
+        P: Fn(&Vector3) -> f32,
+    {
+        let mut sampler = Sampler::new(11);
+        for _ in 0..1_000 {
+            let direction = sample(&mut sampler).unwrap();
+            assert!(pdf(&direction) > 0.0, "sampled {:?} has no density", direction);
+        }
+
+        let count = 200_000;
+        let total: f32 = (0..count).map(|_| pdf(&random_unit_vector(&mut sampler))).sum();
+        let integral = total * 4.0 * PI / (count as f32);
+        assert!((integral - 1.0).abs() < 0.03, "density integrates to {}", integral);
+    }
+
+    /// Checks that the points an element's sample_surface picks are uniform
+    /// over its area: each is on the element, with the outward normal, and is
+    /// picked with density one over the area, which surface_pdf agrees with.
+    /// Points just off the surface have no density.
+    pub fn assert_surface_pdf_uniform(element: &dyn Element, area: f32) {
+        let mut sampler = Sampler::new(13);
+        for _ in 0..1_000 {
+            let (hit_record, pdf) = element.sample_surface(0.0, &mut sampler).unwrap();
+            let (point, normal) = (hit_record.point, hit_record.normal);
+            assert!((pdf * area - 1.0).abs() < 1e-3, "density {} over area {}", pdf, area);
+
+            let surface_pdf = element.surface_pdf(&point, &normal, 0.0);
+            assert!((surface_pdf - pdf).abs() < pdf * 1e-3, "{} is not {} at {:?}", surface_pdf, pdf, point);
+
+            // The point is the first seen looking back along the normal from outside.
+            let ray = Ray::new(point + (normal.as_vector3() * 0.1), -normal.as_vector3());
+            let seen = element.hit(&ray, 0.0, f32::INFINITY, &mut Sampler::new(0)).unwrap();
+            assert!((seen.point - point).length() < 1e-3, "{:?} is not {:?}", seen.point, point);
+
+            let outside = point + (normal.as_vector3() * 0.05);
+            assert_eq!(element.surface_pdf(&outside, &normal, 0.0), 0.0);
+        }
+    }
+}
+
+/// Small scenes lit by a lamp, and the light integrators find in them.
+#[cfg(test)]
+pub mod fixtures {
+    use std::f32::consts::PI;
+    use std::sync::Arc;
+
+    use crate::geometry::point::Point3;
+    use crate::geometry::ray::Ray;
+    use crate::geometry::vector::Vector3;
+    use crate::graphics::colour::Colour;
+    use crate::render::camera::Camera;
+    use crate::render::element::Element;
+    use crate::render::elements::atmosphere::Atmosphere;
+    use crate::render::elements::constant_medium::ConstantMedium;
+    use crate::render::elements::element_list::ElementList;
+    use crate::render::elements::rect::{XyRect, XzRect, YzRect};
+    use crate::render::elements::sphere::Sphere;
+    use crate::render::integrator::{Integrator, IntegratorKind};
+    use crate::render::lights::Lights;
+    use crate::render::materials::dielectric::Dielectric;
+    use crate::render::materials::diffuse_light::DiffuseLight;
+    use crate::render::materials::isotropic::Isotropic;
+    use crate::render::materials::lambertian::Lambertian;
+    use crate::render::renderer::{RenderSettings, Renderer};
+    use crate::render::textures::solid_colour::SolidColour;
+    use crate::util::sampler::Sampler;
+
+    /// A world of the elements given and a lamp, which is the only light.
+    fn lit_scene<L: Element + Clone + 'static>(elements: Vec<Box<dyn Element>>, lamp: L) -> (ElementList, Lights) {
+        let mut world = ElementList::new();
+        for element in elements {
+            world.add(element);
+        }
+        world.add(Box::new(lamp.clone()));
+
+        (world, Lights::new(vec![Arc::new(lamp)]))
+    }
+
+    fn grey(albedo: f32) -> Arc<Lambertian> {
+        Arc::new(Lambertian::new_with_colour(Colour::new(albedo, albedo, albedo)))
+    }
+
+    fn light(radiance: f32) -> Arc<DiffuseLight> {
+        Arc::new(DiffuseLight::new(Arc::new(SolidColour::new(Colour::new(radiance, radiance, radiance)))))
+    }
+
+    /// The mean and variance of the red channel of many samples of the light
+    /// an integrator finds along a ray, against a black background.
+    pub fn mean_and_variance(
+        integrator: &dyn Integrator,
+        world: &dyn Element,
+        lights: &Lights,
+        ray: &Ray,
+        seed: u64,
+    ) -> (f32, f32) {
+        let background = Colour::new(0.0, 0.0, 0.0);
+        let mut sampler = Sampler::new(seed);
+
+        let n = 20_000;
+        let samples: Vec<f32> = (0..n)
+            .map(|_| integrator.radiance(ray, world, lights, &background, &mut sampler).r)
+            .collect();
+        let mean = samples.iter().sum::<f32>() / (n as f32);
+        let variance = samples.iter().map(|s| (s - mean) * (s - mean)).sum::<f32>() / (n as f32);
+
+        (mean, variance)
+    }
+
+    /// The mean of the pixels of two renders with different seeds, and the
+    /// mean difference between each pixel of one and that of the other.
+    pub fn mean_and_noise<F: Fn(u64) -> Vec<f32>>(image: F) -> (f32, f32) {
+        let (a, b) = (image(1), image(2));
+        let n = a.len() as f32;
+
+        let mean = a.iter().chain(b.iter()).sum::<f32>() / (2.0 * n);
+        let noise = a.iter().zip(b.iter()).map(|(x, y)| (x - y).abs()).sum::<f32>() / n;
+
+        (mean, noise)
+    }
+
+    /// A grey floor lit by a small sphere above.
+    pub fn floor() -> (ElementList, Lights) {
+        lit_scene(
+            vec![Box::new(XzRect::new(-100.0, 100.0, -100.0, 100.0, 0.0, grey(0.5)))],
+            Sphere::new(Point3::new(0.0, 4.0, 0.0), 1.0, light(4.0)),
+        )
+    }
+
+    /// The light reflected straight up by the floor seen in floor_samples,
+    /// after one bounce: albedo / pi of the light's radiance over the solid
+    /// angle the light fills, as it is nearly overhead.
+    pub fn floor_radiance() -> f32 {
+        let solid_angle = 2.0 * PI * (1.0 - (1.0f32 - 1.0 / 16.0).sqrt());
+
+        0.5 / PI * 4.0 * solid_angle
+    }
+
+    /// The mean and variance of the light an integrator finds looking straight
+    /// down at the floor. The sphere is only sampled directly if use_lights is
+    /// set.
+    pub fn floor_samples(integrator: &dyn Integrator, use_lights: bool) -> (f32, f32) {
+        let (world, lights) = floor();
+        let lights = if use_lights { lights } else { Lights::none() };
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        mean_and_variance(integrator, &world, &lights, &ray, 7)
+    }
+
+    /// A closed white room two units across, with a red wall, lit by a small
+    /// square in the ceiling.
+    pub fn room() -> (ElementList, Lights) {
+        let white = grey(0.7);
+        let red = Arc::new(Lambertian::new_with_colour(Colour::new(0.7, 0.1, 0.1)));
+
+        lit_scene(
+            vec![
+                Box::new(XzRect::new(-1.0, 1.0, -1.0, 1.0, 0.0, white.clone())),
+                Box::new(XzRect::new(-1.0, 1.0, -1.0, 1.0, 2.0, white.clone())),
+                Box::new(YzRect::new(0.0, 2.0, -1.0, 1.0, -1.0, red)),
+                Box::new(YzRect::new(0.0, 2.0, -1.0, 1.0, 1.0, white.clone())),
+                Box::new(XyRect::new(-1.0, 1.0, 0.0, 2.0, -1.0, white.clone())),
+                Box::new(XyRect::new(-1.0, 1.0, 0.0, 2.0, 1.0, white)),
+            ],
+            XzRect::new(-0.3, 0.3, -0.3, 0.3, 1.99, light(10.0)),
+        )
+    }
+
+    /// The room filled with thin haze, with a ball of thicker smoke on the floor.
+    pub fn foggy_room() -> (Atmosphere, Lights) {
+        let (mut world, lights) = room();
+        let smoke = Arc::new(Isotropic::new_with_colour(Colour::new(0.9, 0.9, 0.9)));
+        world.add(Box::new(ConstantMedium::new(
+            Arc::new(Sphere::new(Point3::new(-0.4, 0.4, -0.4), 0.4, smoke.clone())),
+            4.0,
+            smoke,
+        )));
+
+        let haze = Arc::new(Isotropic::new_with_colour(Colour::new(0.8, 0.8, 0.8)));
+
+        (Atmosphere::new(Box::new(world), 0.3, haze), lights)
+    }
+
+    /// The mean of the light an integrator finds along a ray from near the
+    /// open side of the room, down towards the red wall.
+    pub fn room_mean(integrator: &dyn Integrator) -> f32 {
+        let (world, lights) = room();
+
+        view_mean(integrator, &world, &lights)
+    }
+
+    /// As room_mean, looking into the foggy room.
+    pub fn foggy_room_mean(integrator: &dyn Integrator) -> f32 {
+        let (world, lights) = foggy_room();
+
+        view_mean(integrator, &world, &lights)
+    }
+
+    fn view_mean(integrator: &dyn Integrator, world: &dyn Element, lights: &Lights) -> f32 {
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.9), Vector3::new(-0.5, -0.5, -1.0));
+
+        mean_and_variance(integrator, world, lights, &ray, 3).0
+    }
+
+    /// A lamp inside a glass ball over a floor, in front of a wall.
+    pub fn glass_lamp() -> (ElementList, Lights) {
+        let white = grey(0.7);
+
+        lit_scene(
+            vec![
+                Box::new(XzRect::new(-4.0, 4.0, -4.0, 4.0, 0.0, white.clone())),
+                Box::new(XyRect::new(-4.0, 4.0, 0.0, 4.0, -2.0, white)),
+                Box::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 0.8, Arc::new(Dielectric::new(1.5)))),
+            ],
+            Sphere::new(Point3::new(0.0, 1.0, 0.0), 0.2, light(20.0)),
+        )
+    }
+
+    /// The red channel of the image of the glass lamp, rendered over the given
+    /// number of passes with the integrator and seed given.
+    pub fn glass_lamp_image(integrator: IntegratorKind, passes: usize, seed: u64) -> Vec<f32> {
+        let (world, lights) = glass_lamp();
+        let camera = Camera::new(
+            Point3::new(0.0, 2.0, 5.0),
+            Point3::new(0.0, 0.5, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            50.0,
+            4.0 / 3.0,
+            0.0,
+            5.0,
+            0.0,
+            1.0,
+        );
+        let settings = RenderSettings {
+            image_width: 16,
+            image_height: 12,
+            samples_per_pixel: 64,
+            passes: passes,
+            integrator: integrator,
+            max_depth: 8,
+            max_diffuse_depth: 8,
+            max_specular_depth: 8,
+            max_transmission_depth: 8,
+            roulette_depth: 3,
+            tile_size: 8,
+            threads: 4,
+            seed: seed,
+            progress: None,
+        };
+
+        Renderer::new(settings)
+            .render(&camera, &world, &lights, &Colour::new(0.0, 0.0, 0.0))
+            .pixels
+            .iter()
+            .map(|p| p.r)
+            .collect()
+    }
+
+    /// The mean and noise of glass_lamp_image; see mean_and_noise.
+    pub fn glass_lamp_mean_and_noise(integrator: IntegratorKind, passes: usize) -> (f32, f32) {
+        mean_and_noise(|seed| glass_lamp_image(integrator, passes, seed))
+    }
+}